@@ -1,13 +1,16 @@
 use byteorder::{
 	LE,
-	ReadBytesExt,
-	WriteBytesExt
+	ReadBytesExt
 };
 
+#[cfg(feature = "export")]
+use byteorder::WriteBytesExt;
+
 use std::io::Result;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct CPTArchive {
+	/// Number of offsets, counting the end of file pointer if there is one
 	pub num_objs: u32,
 	pub offsets: Vec<u32>,
 	pub objects: Vec<Vec<u8>>,
@@ -37,10 +40,8 @@ impl CPTArchive {
 		}
 
 		CPTArchive {
-			header: Header {
-				num_objs: objs.len() as u32,
-				offsets: offsets,
-			},
+			num_objs: offsets.len() as u32,
+			offsets: offsets,
 			objects: objs,
 			has_eof_ptr: has_eof_ptr,
 		}
@@ -76,15 +77,15 @@ impl CPTArchive {
 		}
 
 		let length = if has_eof_ptr {
-			(header.num_objs - 1) as usize
+			(nobjs - 1) as usize
 		} else {
-			header.num_objs as usize
+			nobjs as usize
 		};
 
 		let mut objs = vec![];
 		for i in 0..length {
 			if i < (length - 1) {
-				let size = (header.offsets[i + 1] - header.offsets[i]) as usize;
+				let size = (offsets[i + 1] - offsets[i]) as usize;
 				let mut data = vec![0; size];
 				buf.read_exact(data.as_mut_slice())?;
 
@@ -108,21 +109,17 @@ impl CPTArchive {
 
 #[cfg(test)]
 mod tests {
-	#[cfg(feature = "export")]
+	#[cfg(all(feature = "import", feature = "export"))]
 	#[test]
 	fn test_write() {
-		use std::fs::{
-			File,
-			read
-		};
+		let data = vec![b"first".to_vec(), b"second".to_vec(), b"3".to_vec()];
 
-		let data = vec![read("test_data/Screenshot_20211206_222120.png").unwrap(),
-			read("test_data/Screenshot_20211206_230126.png").unwrap(),
-			read("test_data/Screenshot_20211206_232554.png").unwrap()];
+		let cpt = super::CPTArchive::new(data.clone(), true);
+		assert_eq!(cpt.offsets, vec![20, 25, 31, 32]);
 
-		let cpt = super::CPTArchive::new(data, true);
-		let mut f = File::create("test_data/test.cpt").unwrap();
-		println!("{:?}", cpt.header);
-		cpt.write(&mut f).unwrap();
+		let mut buf = vec![];
+		cpt.write(&mut buf).unwrap();
+		assert_eq!(buf.len(), 32);
+		assert_eq!(super::CPTArchive::read(true, &mut &buf[..]).unwrap(), cpt);
 	}
 }
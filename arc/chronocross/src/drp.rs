@@ -1,5 +1,26 @@
+use byteorder::{
+	BE,
+	LE,
+	ReadBytesExt
+};
+
+use std::io::{
+	self,
+	Read
+};
+use thiserror::Error;
+
 use rgk_core::tag4;
 
+#[cfg(feature = "import")]
+use rgk_core::format::{
+	self,
+	Entry,
+	Extractor,
+	FormatError,
+	FormatInfo
+};
+
 pub const MAGIC: u32 = tag4!(b"drp\x00");
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -27,6 +48,25 @@ pub enum ResType {
 	Unknown = 255,
 }
 
+impl From<u8> for ResType {
+	fn from(v: u8) -> ResType {
+		match v {
+			1 => ResType::NestedDRP,
+			2 => ResType::GenericMesh,
+			3 => ResType::SpriteInfo,
+			4 => ResType::Texture,
+			5 => ResType::MusicInstrument,
+			11 => ResType::ModelPack,
+			18 => ResType::BattleFieldMesh,
+			21 => ResType::LightInfo,
+			22 => ResType::MusicSequence,
+			25 => ResType::Animation,
+			37 => ResType::Compressed,
+			_ => ResType::Unknown,
+		}
+	}
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct ResHeader {
 	reserved: u32,
@@ -46,3 +86,148 @@ pub struct DynResPack {
 	pub header: Header,
 	pub resources: Vec<Resource>,
 }
+
+impl Header {
+	#[cfg(feature = "import")]
+	pub fn read<R>(buf: &mut R) -> Result<Header, DRPImportError>
+	where
+		R: ReadBytesExt,
+	{
+		let magic = buf.read_u32::<BE>()?;
+		if magic != MAGIC {
+			return Err(DRPImportError::Magic(magic));
+		}
+
+		Ok(Header {
+			magic,
+			reserved_4: buf.read_u32::<LE>()?,
+			num_res: buf.read_u16::<LE>()?,
+			reserved_a: buf.read_u16::<LE>()?,
+		})
+	}
+}
+
+impl ResHeader {
+	/// Reads a 16 byte resource header, whose type byte is padded to a word
+	#[cfg(feature = "import")]
+	pub fn read<R>(buf: &mut R) -> Result<ResHeader, DRPImportError>
+	where
+		R: ReadBytesExt,
+	{
+		let reserved = buf.read_u32::<LE>()?;
+		let mut name = [0; 4];
+		buf.read_exact(&mut name)?;
+		let kind = ResType::from(buf.read_u32::<LE>()? as u8);
+
+		Ok(ResHeader {
+			reserved,
+			name,
+			kind,
+			size: buf.read_u32::<LE>()?,
+		})
+	}
+
+	/// Returns the name with anything but ASCII letters and digits replaced, so it's safe as a file name
+	pub fn name(&self) -> String {
+		self.name.iter().take_while(|c| **c != 0)
+			.map(|c| if c.is_ascii_alphanumeric() { *c as char } else { '_' })
+			.collect()
+	}
+}
+
+impl DynResPack {
+	/// Reads an archive, where each resource's header is followed by its data
+	#[cfg(feature = "import")]
+	pub fn read<R>(buf: &mut R) -> Result<DynResPack, DRPImportError>
+	where
+		R: ReadBytesExt,
+	{
+		let header = Header::read(buf)?;
+		let mut resources = Vec::with_capacity(header.num_res.min(256) as usize);
+
+		for _ in 0..header.num_res {
+			let res = ResHeader::read(buf)?;
+
+			// the size isn't trusted for preallocation
+			let mut data = vec![];
+			buf.take(res.size as u64).read_to_end(&mut data)?;
+			if data.len() != res.size as usize {
+				return Err(DRPImportError::Truncated(res.name()));
+			}
+
+			resources.push(Resource {
+				header: res,
+				data,
+			});
+		}
+
+		Ok(DynResPack {
+			header,
+			resources,
+		})
+	}
+}
+
+#[cfg(feature = "import")]
+#[derive(Error, Debug)]
+pub enum DRPImportError {
+	#[error("I/O error")]
+	IO {
+		#[from]
+		source: io::Error,
+	},
+	#[error("Not a DRP file: {0:X}")]
+	Magic(u32),
+	#[error("Resource {0} is truncated")]
+	Truncated(String),
+}
+
+/// [`Extractor`] unpacking each resource as `<index>_<name>.bin`, or `.drp` for nested archives
+#[cfg(feature = "import")]
+pub struct DRPExtractor;
+
+#[cfg(feature = "import")]
+impl Extractor for DRPExtractor {
+	fn format(&self) -> &FormatInfo {
+		format::builtin("DRP").expect("DRP is a builtin format")
+	}
+
+	fn extract(&self, data: &[u8]) -> Result<Vec<Entry>, FormatError> {
+		let drp = DynResPack::read(&mut &data[..]).map_err(|e| FormatError::format("DRP", e))?;
+
+		Ok(drp.resources.into_iter().enumerate().map(|(i, res)| Entry {
+			name: format!("{:03}_{}.{}", i, res.header.name(), match res.header.kind {
+				ResType::NestedDRP => "drp",
+				_ => "bin",
+			}),
+			data: res.data,
+		}).collect())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_extract() {
+		let mut data = b"drp\x00\x00\x00\x00\x00\x02\x00\x00\x00".to_vec();
+		data.extend_from_slice(b"\x00\x00\x00\x00mesh\x02\x00\x00\x00\x03\x00\x00\x00abc");
+		data.extend_from_slice(b"\x00\x00\x00\x00a/b\x00\x01\x00\x00\x00\x00\x00\x00\x00");
+
+		let entries = DRPExtractor.extract(&data).unwrap();
+		assert_eq!(entries, vec![
+			Entry {
+				name: "000_mesh.bin".to_string(),
+				data: b"abc".to_vec(),
+			},
+			Entry {
+				name: "001_a_b.drp".to_string(),
+				data: vec![],
+			},
+		]);
+
+		assert!(DRPExtractor.extract(&data[..data.len() - 17]).is_err());
+		assert!(DRPExtractor.extract(b"pak\x00").is_err());
+	}
+}
@@ -1 +1,2 @@
 pub mod cpt;
+pub mod drp;
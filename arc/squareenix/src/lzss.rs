@@ -5,7 +5,10 @@ use byteorder::{
 	LE
 };
 
-use std::io;
+use std::io::{
+	self,
+	Read
+};
 use thiserror::Error;
 
 use rgk_core::{
@@ -13,6 +16,15 @@ use rgk_core::{
 	rtag4
 };
 
+#[cfg(feature = "import")]
+use rgk_core::format::{
+	self,
+	Entry,
+	Extractor,
+	FormatError,
+	FormatInfo
+};
+
 pub const MAGIC: u32 = rtag4!(b"sszl");
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -48,25 +60,43 @@ pub struct LZSSArchive {
 }
 
 impl LZSSArchive {
+	/// Decompresses an archive. The stream is read in 512 byte chunks, each consumed from its last bit, and a
+	/// token split across chunks carries over into the next. Each token is a flag bit followed by either a
+	/// literal byte or a 12 bit ring buffer offset and a 4 bit length, to which `padding` is added.
 	#[cfg(feature = "import")]
 	pub fn read<R>(padding: usize, buf: &mut R) -> Result<LZSSArchive, LZSSImportError>
 	where
 		R: ReadBytesExt,
 	{
 		let header = Header::read(buf)?;
-		let mut buffer = [0; 512];
 		let mut ringbuffer = [0; 4096];
 		let mut rb_index = 0;
-		let mut slopover_bits = BitVec::new();
+		let mut slopover_bits = BitVec::<Lsb0, u8>::new();
 		let mut data = vec![];
+		let size = header.dcmp_size as usize;
 
-		while let Ok(()) = buf.read_exact(&mut buffer) {
-			let mut vob = buffer.view_bits_mut::<Lsb0>().to_bitvec();
+		loop {
+			let mut buffer = Vec::with_capacity(512);
+			buf.take(512).read_to_end(&mut buffer)?;
+			if buffer.is_empty() {
+				break;
+			}
+
+			let mut vob = buffer.view_bits::<Lsb0>().to_bitvec();
 			vob.append(&mut slopover_bits);
-			println!("{}", vob.len());
 
-			while let Some(is_value) = vob.pop() {
-				if is_value {
+			while data.len() < size {
+				// keep an incomplete token for the next chunk
+				let needed = match vob.last().map(|b| *b) {
+					Some(true) => 9,
+					Some(false) => 17,
+					None => break,
+				};
+				if vob.len() < needed {
+					break;
+				}
+
+				if vob.pop() == Some(true) {
 					// copy the byte into both ring buffer and output data
 					let value = vec_pop_n(&mut vob, 8) as u8;
 					data.push(value);
@@ -75,10 +105,10 @@ impl LZSSArchive {
 				} else {
 					// grab the offset and how many bytes to copy from ring buffer
 					let offset = vec_pop_n(&mut vob, 12);
-					let size = vec_pop_n(&mut vob, 4) + padding;
+					let count = vec_pop_n(&mut vob, 4) + padding;
 
-					for i in (0..size).rev() {
-						let value = ringbuffer[offset + i];
+					for i in (0..count).rev() {
+						let value = ringbuffer[(offset + i) % 4096];
 						data.push(value);
 						rb_index = (rb_index + 1) % 4096;
 						ringbuffer[rb_index] = value;
@@ -87,16 +117,40 @@ impl LZSSArchive {
 			}
 
 			slopover_bits = vob;
-			println!("{}", vob.len());
 		}
 
+		if data.len() < size {
+			return Err(LZSSImportError::Truncated(data.len(), size));
+		}
+		data.truncate(size);
+
 		Ok(LZSSArchive {
-			header: header,
-			data: data,
+			header,
+			data,
 		})
 	}
 }
 
+/// [`Extractor`] for LZSS archives, which hold a single file. Matches are 2 bytes longer than their stored length.
+#[cfg(feature = "import")]
+pub struct LZSSExtractor;
+
+#[cfg(feature = "import")]
+impl Extractor for LZSSExtractor {
+	fn format(&self) -> &FormatInfo {
+		format::builtin("LZSS").expect("LZSS is a builtin format")
+	}
+
+	fn extract(&self, data: &[u8]) -> Result<Vec<Entry>, FormatError> {
+		let arc = LZSSArchive::read(2, &mut &data[..]).map_err(|e| FormatError::format("LZSS", e))?;
+
+		Ok(vec![Entry {
+			name: "data.bin".to_string(),
+			data: arc.data,
+		}])
+	}
+}
+
 #[cfg(feature = "import")]
 #[derive(Error, Debug)]
 pub enum LZSSImportError {
@@ -107,23 +161,50 @@ pub enum LZSSImportError {
 	},
 	#[error("Not an LZSS file: {0:X}")]
 	Magic(u32),
+	#[error("Decompressed {0} of {1} bytes before the data ran out")]
+	Truncated(usize, usize),
 }
 
 #[test]
+#[ignore = "needs a file from the game in test_data"]
 fn test_lzss() {
 	use std::{
-		fs::{
-			File,
-			read
-		},
-		io::{
-			Cursor,
-			Write
-		}
+		fs::read,
+		io::Cursor
 	};
 
 	let mut data = Cursor::new(read("test_data/2540.enemy_drop_bonus_list.lzss").unwrap());
 	let arc = LZSSArchive::read(2, &mut data).unwrap();
-	let mut outf = File::create("test_data/2540.scp").unwrap();
-	outf.write_all(arc.data.as_slice()).unwrap();
+	assert_eq!(arc.data.len(), arc.header.dcmp_size as usize);
+}
+
+#[test]
+fn test_extract() {
+	// bits in the order they're read, with values least significant bit first
+	let mut bits = vec![];
+	let mut push = |value: usize, count: usize| (0..count).for_each(|i| bits.push((value >> i) & 1 == 1));
+	push(1, 1);
+	push(b'a' as usize, 8);
+	push(1, 1);
+	push(b'b' as usize, 8);
+
+	// copies the 2 bytes from ring buffer offset 1, last first
+	push(0, 1);
+	push(1, 12);
+	push(0, 4);
+
+	let mut packed = vec![0u8; bits.len().div_ceil(8)];
+	let total = packed.len() * 8;
+	for (k, bit) in bits.iter().enumerate() {
+		let j = total - 1 - k;
+		packed[j / 8] |= (*bit as u8) << (j % 8);
+	}
+
+	let mut data = b"sszl\x04\x00\x00\x00\x00\x00\x00\x00".to_vec();
+	data.extend_from_slice(&packed);
+	let entries = LZSSExtractor.extract(&data).unwrap();
+	assert_eq!(entries[0].data, b"abba");
+
+	data[4] = 5;
+	assert!(LZSSExtractor.extract(&data).is_err());
 }
@@ -0,0 +1,620 @@
+//! Declarative binary layouts. A struct declared with [`bin_struct!`](crate::bin_struct) gets both a
+//! reader and a writer, built from its fields' [`BinRead`] and [`BinWrite`] implementations. Layout
+//! details live in the field types, such as [`Counted`] for count-prefixed arrays and [`Ptr`] for
+//! absolute offsets, which are relative to the start of the stream.
+
+use std::{
+	io::{
+		self,
+		Read,
+		Seek,
+		SeekFrom,
+		Write
+	},
+	marker::PhantomData,
+	mem::size_of
+};
+
+pub use byteorder::ByteOrder;
+
+use thiserror::Error;
+
+use ultraviolet::vec::{
+	Vec2,
+	Vec3,
+	Vec4
+};
+
+#[derive(Debug, Error)]
+pub enum BinError {
+	#[error("Couldn't get the stream position: {0}")]
+	Position(io::Error),
+	#[error("I/O error at offset {offset:#x}: {source}")]
+	Io {
+		offset: u64,
+		source: io::Error,
+	},
+	#[error("Bad magic at offset {offset:#x}: expected {expected:02x?}, found {found:02x?}")]
+	Magic {
+		offset: u64,
+		expected: &'static [u8],
+		found: Vec<u8>,
+	},
+	#[error("Count or offset {value} at offset {offset:#x} doesn't fit its field")]
+	Range {
+		offset: u64,
+		value: u64,
+	},
+	#[error("{name}: {source}")]
+	Field {
+		name: &'static str,
+		source: Box<BinError>,
+	},
+}
+
+impl BinError {
+	/// Returns the byte offset the error occurred at, if the stream position could be found
+	pub fn offset(&self) -> Option<u64> {
+		match self {
+			BinError::Position(_) => None,
+			BinError::Io { offset, .. } | BinError::Magic { offset, .. } | BinError::Range { offset, .. } => {
+				Some(*offset)
+			},
+			BinError::Field { source, .. } => source.offset(),
+		}
+	}
+}
+
+/// A value that can be read from a binary stream, with multi-byte values in `E` order
+pub trait BinRead: Sized {
+	fn read_bin<E, R>(r: &mut R) -> Result<Self, BinError>
+	where
+		E: ByteOrder,
+		R: Read + Seek;
+}
+
+/// A value that can be written to a binary stream, with multi-byte values in `E` order
+pub trait BinWrite {
+	fn write_bin<E, W>(&self, w: &mut W) -> Result<(), BinError>
+	where
+		E: ByteOrder,
+		W: Write + Seek;
+}
+
+/// Returns the stream's current position
+pub fn position<S: Seek>(s: &mut S) -> Result<u64, BinError> {
+	s.stream_position().map_err(BinError::Position)
+}
+
+fn seek<S: Seek>(s: &mut S, offset: u64) -> Result<(), BinError> {
+	s.seek(SeekFrom::Start(offset)).map(|_| ()).map_err(|source| BinError::Io {
+		offset,
+		source,
+	})
+}
+
+fn read_bytes<R: Read + Seek>(r: &mut R, buf: &mut [u8]) -> Result<(), BinError> {
+	let offset = position(r)?;
+	r.read_exact(buf).map_err(|source| BinError::Io {
+		offset,
+		source,
+	})
+}
+
+fn write_bytes<W: Write + Seek>(w: &mut W, buf: &[u8]) -> Result<(), BinError> {
+	let offset = position(w)?;
+	w.write_all(buf).map_err(|source| BinError::Io {
+		offset,
+		source,
+	})
+}
+
+/// Reads bytes that must match `magic`
+pub fn read_magic<R: Read + Seek>(r: &mut R, magic: &'static [u8]) -> Result<(), BinError> {
+	let offset = position(r)?;
+	let mut found = vec![0; magic.len()];
+	read_bytes(r, &mut found)?;
+
+	if found != magic {
+		return Err(BinError::Magic {
+			offset,
+			expected: magic,
+			found,
+		});
+	}
+
+	Ok(())
+}
+
+pub fn write_magic<W: Write + Seek>(w: &mut W, magic: &'static [u8]) -> Result<(), BinError> {
+	write_bytes(w, magic)
+}
+
+/// Wraps a field's error with its name
+pub fn field<T>(name: &'static str, result: Result<T, BinError>) -> Result<T, BinError> {
+	result.map_err(|e| BinError::Field {
+		name,
+		source: Box::new(e),
+	})
+}
+
+macro_rules! impl_byte {
+	($($t:ty),*) => {
+		$(
+			impl BinRead for $t {
+				fn read_bin<E, R>(r: &mut R) -> Result<Self, BinError>
+				where
+					E: ByteOrder,
+					R: Read + Seek,
+				{
+					let mut b = [0; 1];
+					read_bytes(r, &mut b)?;
+					Ok(<$t>::from_ne_bytes(b))
+				}
+			}
+
+			impl BinWrite for $t {
+				fn write_bin<E, W>(&self, w: &mut W) -> Result<(), BinError>
+				where
+					E: ByteOrder,
+					W: Write + Seek,
+				{
+					write_bytes(w, &self.to_ne_bytes())
+				}
+			}
+		)*
+	}
+}
+
+macro_rules! impl_primitive {
+	($($t:ty: $read:ident $write:ident),*) => {
+		$(
+			impl BinRead for $t {
+				fn read_bin<E, R>(r: &mut R) -> Result<Self, BinError>
+				where
+					E: ByteOrder,
+					R: Read + Seek,
+				{
+					let mut b = [0; size_of::<$t>()];
+					read_bytes(r, &mut b)?;
+					Ok(E::$read(&b))
+				}
+			}
+
+			impl BinWrite for $t {
+				fn write_bin<E, W>(&self, w: &mut W) -> Result<(), BinError>
+				where
+					E: ByteOrder,
+					W: Write + Seek,
+				{
+					let mut b = [0; size_of::<$t>()];
+					E::$write(&mut b, *self);
+					write_bytes(w, &b)
+				}
+			}
+		)*
+	}
+}
+
+impl_byte!(u8, i8);
+impl_primitive!(
+	u16: read_u16 write_u16,
+	u32: read_u32 write_u32,
+	u64: read_u64 write_u64,
+	i16: read_i16 write_i16,
+	i32: read_i32 write_i32,
+	i64: read_i64 write_i64,
+	f32: read_f32 write_f32,
+	f64: read_f64 write_f64
+);
+
+impl<T: BinRead, const N: usize> BinRead for [T; N] {
+	fn read_bin<E, R>(r: &mut R) -> Result<Self, BinError>
+	where
+		E: ByteOrder,
+		R: Read + Seek,
+	{
+		let v = (0..N).map(|_| T::read_bin::<E, _>(r)).collect::<Result<Vec<T>, _>>()?;
+		match v.try_into() {
+			Ok(a) => Ok(a),
+			Err(_) => unreachable!(),
+		}
+	}
+}
+
+impl<T: BinWrite, const N: usize> BinWrite for [T; N] {
+	fn write_bin<E, W>(&self, w: &mut W) -> Result<(), BinError>
+	where
+		E: ByteOrder,
+		W: Write + Seek,
+	{
+		self.iter().try_for_each(|v| v.write_bin::<E, _>(w))
+	}
+}
+
+macro_rules! impl_vec {
+	($($t:ident: $n:literal),*) => {
+		$(
+			impl BinRead for $t {
+				fn read_bin<E, R>(r: &mut R) -> Result<Self, BinError>
+				where
+					E: ByteOrder,
+					R: Read + Seek,
+				{
+					Ok($t::from(<[f32; $n]>::read_bin::<E, _>(r)?))
+				}
+			}
+
+			impl BinWrite for $t {
+				fn write_bin<E, W>(&self, w: &mut W) -> Result<(), BinError>
+				where
+					E: ByteOrder,
+					W: Write + Seek,
+				{
+					self.as_array().write_bin::<E, _>(w)
+				}
+			}
+		)*
+	}
+}
+
+impl_vec!(Vec2: 2, Vec3: 3, Vec4: 4);
+
+/// An unsigned integer type used for counts and offsets
+pub trait Index: BinRead + BinWrite + Copy + Into<u64> + TryFrom<u64> {}
+
+impl Index for u8 {}
+impl Index for u16 {}
+impl Index for u32 {}
+impl Index for u64 {}
+
+fn read_index<P: Index, E: ByteOrder, R: Read + Seek>(r: &mut R) -> Result<u64, BinError> {
+	Ok(P::read_bin::<E, _>(r)?.into())
+}
+
+fn write_index<P: Index, E: ByteOrder, W: Write + Seek>(w: &mut W, value: u64) -> Result<(), BinError> {
+	let offset = position(w)?;
+	let index = P::try_from(value).map_err(|_| BinError::Range {
+		offset,
+		value,
+	})?;
+
+	index.write_bin::<E, _>(w)
+}
+
+fn read_items<T: BinRead, E: ByteOrder, R: Read + Seek>(r: &mut R, count: u64) -> Result<Vec<T>, BinError> {
+	// the count isn't trusted for preallocation
+	let mut items = Vec::with_capacity(count.min(1024) as usize);
+	for _ in 0..count {
+		items.push(T::read_bin::<E, _>(r)?);
+	}
+	Ok(items)
+}
+
+/// An array prefixed with its length as a `P`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Counted<P, T> {
+	pub items: Vec<T>,
+	_count: PhantomData<P>,
+}
+
+impl<P, T> Counted<P, T> {
+	pub fn new(items: Vec<T>) -> Counted<P, T> {
+		Counted {
+			items,
+			_count: PhantomData,
+		}
+	}
+}
+
+impl<P: Index, T: BinRead> BinRead for Counted<P, T> {
+	fn read_bin<E, R>(r: &mut R) -> Result<Self, BinError>
+	where
+		E: ByteOrder,
+		R: Read + Seek,
+	{
+		let count = read_index::<P, E, _>(r)?;
+		Ok(Counted::new(read_items::<_, E, _>(r, count)?))
+	}
+}
+
+impl<P: Index, T: BinWrite> BinWrite for Counted<P, T> {
+	fn write_bin<E, W>(&self, w: &mut W) -> Result<(), BinError>
+	where
+		E: ByteOrder,
+		W: Write + Seek,
+	{
+		write_index::<P, E, _>(w, self.items.len() as u64)?;
+		self.items.iter().try_for_each(|v| v.write_bin::<E, _>(w))
+	}
+}
+
+/// A value stored elsewhere, at an absolute offset stored as a `P`. Reading and writing return to just
+/// past the offset afterwards, so the value may be anywhere in the stream.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ptr<P, T> {
+	pub offset: u64,
+	pub value: T,
+	_offset: PhantomData<P>,
+}
+
+impl<P, T> Ptr<P, T> {
+	pub fn new(offset: u64, value: T) -> Ptr<P, T> {
+		Ptr {
+			offset,
+			value,
+			_offset: PhantomData,
+		}
+	}
+}
+
+impl<P: Index, T: BinRead> BinRead for Ptr<P, T> {
+	fn read_bin<E, R>(r: &mut R) -> Result<Self, BinError>
+	where
+		E: ByteOrder,
+		R: Read + Seek,
+	{
+		let offset = read_index::<P, E, _>(r)?;
+		let back = position(r)?;
+
+		seek(r, offset)?;
+		let value = T::read_bin::<E, _>(r)?;
+		seek(r, back)?;
+
+		Ok(Ptr::new(offset, value))
+	}
+}
+
+impl<P: Index, T: BinWrite> BinWrite for Ptr<P, T> {
+	fn write_bin<E, W>(&self, w: &mut W) -> Result<(), BinError>
+	where
+		E: ByteOrder,
+		W: Write + Seek,
+	{
+		write_index::<P, E, _>(w, self.offset)?;
+		let back = position(w)?;
+
+		seek(w, self.offset)?;
+		self.value.write_bin::<E, _>(w)?;
+		seek(w, back)
+	}
+}
+
+/// An array stored elsewhere, described by a count followed by an absolute offset, both stored as `P`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CountedPtr<P, T> {
+	pub offset: u64,
+	pub items: Vec<T>,
+	_index: PhantomData<P>,
+}
+
+impl<P, T> CountedPtr<P, T> {
+	pub fn new(offset: u64, items: Vec<T>) -> CountedPtr<P, T> {
+		CountedPtr {
+			offset,
+			items,
+			_index: PhantomData,
+		}
+	}
+}
+
+impl<P: Index, T: BinRead> BinRead for CountedPtr<P, T> {
+	fn read_bin<E, R>(r: &mut R) -> Result<Self, BinError>
+	where
+		E: ByteOrder,
+		R: Read + Seek,
+	{
+		let count = read_index::<P, E, _>(r)?;
+		let offset = read_index::<P, E, _>(r)?;
+		let back = position(r)?;
+
+		seek(r, offset)?;
+		let items = read_items::<_, E, _>(r, count)?;
+		seek(r, back)?;
+
+		Ok(CountedPtr::new(offset, items))
+	}
+}
+
+impl<P: Index, T: BinWrite> BinWrite for CountedPtr<P, T> {
+	fn write_bin<E, W>(&self, w: &mut W) -> Result<(), BinError>
+	where
+		E: ByteOrder,
+		W: Write + Seek,
+	{
+		write_index::<P, E, _>(w, self.items.len() as u64)?;
+		write_index::<P, E, _>(w, self.offset)?;
+		let back = position(w)?;
+
+		seek(w, self.offset)?;
+		self.items.iter().try_for_each(|v| v.write_bin::<E, _>(w))?;
+		seek(w, back)
+	}
+}
+
+/// Declares a struct along with its [`BinRead`](crate::binary::BinRead) and
+/// [`BinWrite`](crate::binary::BinWrite) implementations, which handle the fields in order. A `magic`
+/// byte string after the name is checked before the first field and isn't stored. Field errors are wrapped with
+/// the struct and field names.
+///
+/// ```
+/// use byteorder::LittleEndian;
+/// use rgk_core::binary::{BinRead, Counted};
+///
+/// rgk_core::bin_struct! {
+///     #[derive(Debug)]
+///     pub struct Chunk magic b"CHNK" {
+///         pub version: u16,
+///         pub values: Counted<u8, u32>,
+///     }
+/// }
+///
+/// let data = b"CHNK\x02\x00\x01\x2a\x00\x00\x00";
+/// let chunk = Chunk::read_bin::<LittleEndian, _>(&mut std::io::Cursor::new(&data[..])).unwrap();
+/// assert_eq!(chunk.values.items, vec![42]);
+/// ```
+#[macro_export]
+macro_rules! bin_struct {
+	(
+		$(#[$meta:meta])*
+		$vis:vis struct $name:ident $(magic $magic:literal)? {
+			$($(#[$fmeta:meta])* $fvis:vis $field:ident: $ty:ty),* $(,)?
+		}
+	) => {
+		$(#[$meta])*
+		$vis struct $name {
+			$($(#[$fmeta])* $fvis $field: $ty,)*
+		}
+
+		impl $crate::binary::BinRead for $name {
+			fn read_bin<E, R>(r: &mut R) -> Result<Self, $crate::binary::BinError>
+			where
+				E: $crate::binary::ByteOrder,
+				R: std::io::Read + std::io::Seek,
+			{
+				$($crate::binary::field(stringify!($name), $crate::binary::read_magic(r, $magic))?;)?
+
+				Ok($name {
+					$($field: $crate::binary::field(
+						concat!(stringify!($name), ".", stringify!($field)),
+						<$ty as $crate::binary::BinRead>::read_bin::<E, _>(r),
+					)?,)*
+				})
+			}
+		}
+
+		impl $crate::binary::BinWrite for $name {
+			fn write_bin<E, W>(&self, w: &mut W) -> Result<(), $crate::binary::BinError>
+			where
+				E: $crate::binary::ByteOrder,
+				W: std::io::Write + std::io::Seek,
+			{
+				$($crate::binary::field(stringify!($name), $crate::binary::write_magic(w, $magic))?;)?
+				$($crate::binary::field(
+					concat!(stringify!($name), ".", stringify!($field)),
+					$crate::binary::BinWrite::write_bin::<E, _>(&self.$field, w),
+				)?;)*
+
+				Ok(())
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+
+	use byteorder::{
+		BigEndian,
+		LittleEndian
+	};
+
+	use super::*;
+
+	crate::bin_struct! {
+		#[derive(Clone, Debug, PartialEq)]
+		struct Entry {
+			id: u16,
+			position: Vec3,
+		}
+	}
+
+	crate::bin_struct! {
+		#[derive(Clone, Debug, PartialEq)]
+		struct Header magic b"TEST" {
+			version: u32,
+			scale: [i8; 3],
+			entries: Counted<u16, Entry>,
+			name: Ptr<u32, [u8; 4]>,
+			indices: CountedPtr<u32, u16>,
+		}
+	}
+
+	fn header() -> Header {
+		Header {
+			version: 2,
+			scale: [1, -1, 2],
+			entries: Counted::new(vec![
+				Entry {
+					id: 7,
+					position: Vec3::new(1.0, 2.0, 3.0),
+				},
+			]),
+			name: Ptr::new(48, *b"abcd"),
+			indices: CountedPtr::new(52, vec![1, 2, 3]),
+		}
+	}
+
+	fn roundtrip<E: ByteOrder>() {
+		let mut buf = Cursor::new(vec![]);
+		header().write_bin::<E, _>(&mut buf).unwrap();
+
+		let data = buf.into_inner();
+		assert_eq!(data.len(), 58);
+		assert_eq!(&data[..4], b"TEST");
+		assert_eq!(&data[48..52], b"abcd");
+
+		let mut r = Cursor::new(&data[..]);
+		assert_eq!(Header::read_bin::<E, _>(&mut r).unwrap(), header());
+
+		// pointers leave the stream just past themselves
+		assert_eq!(r.position(), 39);
+	}
+
+	#[test]
+	fn test_roundtrip() {
+		roundtrip::<LittleEndian>();
+		roundtrip::<BigEndian>();
+	}
+
+	#[test]
+	fn test_errors() {
+		let mut buf = Cursor::new(vec![]);
+		header().write_bin::<LittleEndian, _>(&mut buf).unwrap();
+		let mut data = buf.into_inner();
+
+		let e = Header::read_bin::<LittleEndian, _>(&mut Cursor::new(&data[..20])).unwrap_err();
+		assert_eq!(e.offset(), Some(19));
+		assert!(e.to_string().starts_with("Header.entries: Entry.position: I/O error at offset 0x13"));
+
+		data[2] = b'X';
+		let e = Header::read_bin::<LittleEndian, _>(&mut Cursor::new(&data[..])).unwrap_err();
+		match e {
+			BinError::Field { name, source } => {
+				assert_eq!(name, "Header");
+				assert!(matches!(*source, BinError::Magic { offset: 0, .. }));
+			},
+			_ => panic!("{}", e),
+		}
+
+		let mut counted = Counted::<u8, Entry>::new(vec![header().entries.items[0].clone(); 256]);
+		let e = counted.write_bin::<BigEndian, _>(&mut Cursor::new(vec![])).unwrap_err();
+		assert!(matches!(e, BinError::Range { value: 256, .. }));
+
+		counted.items.truncate(255);
+		assert!(counted.write_bin::<BigEndian, _>(&mut Cursor::new(vec![])).is_ok());
+	}
+
+	/// A stream that can't report its position
+	struct Unseekable;
+
+	impl Read for Unseekable {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			Ok(buf.len())
+		}
+	}
+
+	impl Seek for Unseekable {
+		fn seek(&mut self, _: SeekFrom) -> io::Result<u64> {
+			Err(io::Error::new(io::ErrorKind::Unsupported, "unseekable"))
+		}
+	}
+
+	#[test]
+	fn test_position_error() {
+		let e = u32::read_bin::<BigEndian, _>(&mut Unseekable).unwrap_err();
+		assert!(matches!(e, BinError::Position(_)));
+		assert_eq!(e.offset(), None);
+	}
+}
@@ -0,0 +1,611 @@
+//! Format detection and a common interface for importers and exporters. Format crates implement
+//! [`Importer`] and [`Exporter`] and add themselves to a [`Registry`], which can then open files without
+//! knowing their type in advance.
+
+use std::{
+	error::Error,
+	io::{
+		self,
+		Write
+	},
+	path::Path
+};
+
+use thiserror::Error;
+
+use crate::{
+	scene::Scene,
+	texture::Texture
+};
+
+/// Kinds of data a format holds
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum AssetKind {
+	Scene,
+	Texture,
+	Archive,
+	Patch,
+}
+
+/// Data produced by an [`Importer`] or consumed by an [`Exporter`]
+#[derive(Clone, Debug)]
+pub enum Asset {
+	Scene(Box<Scene>),
+	Texture(Texture),
+}
+
+impl Asset {
+	pub fn kind(&self) -> AssetKind {
+		match self {
+			Asset::Scene(_) => AssetKind::Scene,
+			Asset::Texture(_) => AssetKind::Texture,
+		}
+	}
+}
+
+/// Bytes identifying a format, found at `offset` from the start of a file
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Magic {
+	pub offset: usize,
+	pub bytes: &'static [u8],
+}
+
+impl Magic {
+	pub const fn new(bytes: &'static [u8]) -> Magic {
+		Magic::at(0, bytes)
+	}
+
+	pub const fn at(offset: usize, bytes: &'static [u8]) -> Magic {
+		Magic {
+			offset,
+			bytes,
+		}
+	}
+
+	pub fn matches(&self, data: &[u8]) -> bool {
+		data.get(self.offset..self.offset + self.bytes.len()) == Some(self.bytes)
+	}
+}
+
+/// Describes a file format
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FormatInfo {
+	/// Short unique name, such as `PMX`
+	pub name: &'static str,
+	pub description: &'static str,
+
+	/// Lowercase file extensions without the dot
+	pub extensions: &'static [&'static str],
+
+	/// Any one of these identifies the format. Text formats usually have none.
+	pub magic: &'static [Magic],
+	pub kind: AssetKind,
+}
+
+impl FormatInfo {
+	/// Returns whether `path` has one of the format's extensions, ignoring case
+	pub fn matches_extension(&self, path: &Path) -> bool {
+		match path.extension().and_then(|e| e.to_str()) {
+			Some(ext) => self.extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)),
+			None => false,
+		}
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum FormatError {
+	#[error("I/O error")]
+	IO {
+		#[from]
+		source: io::Error,
+	},
+	#[error("Unknown file format")]
+	Unknown,
+	#[error("No importer for {0}")]
+	NoImporter(&'static str),
+	#[error("No exporter for {0}")]
+	NoExporter(String),
+	#[error("No extractor for {0}")]
+	NoExtractor(&'static str),
+	#[error("No patcher for {0}")]
+	NoPatcher(String),
+	#[error("{format} can't hold a {kind:?}")]
+	Unsupported {
+		format: &'static str,
+		kind: AssetKind,
+	},
+	#[error("Invalid {format} data")]
+	Format {
+		format: &'static str,
+		source: Box<dyn Error + Send + Sync>,
+	},
+}
+
+impl FormatError {
+	/// Wraps a format crate's own error
+	pub fn format<E>(format: &'static str, e: E) -> FormatError
+	where
+		E: Error + Send + Sync + 'static,
+	{
+		FormatError::Format {
+			format,
+			source: Box::new(e),
+		}
+	}
+}
+
+/// Reads a format into an [`Asset`]
+pub trait Importer {
+	fn format(&self) -> &FormatInfo;
+	fn import(&self, data: &[u8]) -> Result<Asset, FormatError>;
+
+	/// Describes the file's headers for inspection, if the importer supports it
+	fn headers(&self, _data: &[u8]) -> Result<Option<String>, FormatError> {
+		Ok(None)
+	}
+}
+
+/// Writes an [`Asset`] in a format
+pub trait Exporter {
+	fn format(&self) -> &FormatInfo;
+	fn export(&self, asset: &Asset, out: &mut dyn Write) -> Result<(), FormatError>;
+}
+
+/// A file stored in an archive
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+	/// Path within the archive, using `/` as the separator
+	pub name: String,
+	pub data: Vec<u8>,
+}
+
+/// Unpacks an archive
+pub trait Extractor {
+	fn format(&self) -> &FormatInfo;
+	fn extract(&self, data: &[u8]) -> Result<Vec<Entry>, FormatError>;
+}
+
+/// Applies and creates patches
+pub trait Patcher {
+	fn format(&self) -> &FormatInfo;
+
+	/// Returns `source` with `patch` applied
+	fn apply(&self, patch: &[u8], source: &[u8]) -> Result<Vec<u8>, FormatError>;
+
+	/// Creates a patch turning `source` into `target`
+	fn diff(&self, source: &[u8], target: &[u8]) -> Result<Vec<u8>, FormatError>;
+}
+
+/// Formats known to RustGamingKit, whether or not their importers are registered
+pub static FORMATS: &[FormatInfo] = &[
+	FormatInfo {
+		name: "ASE",
+		description: "3ds Max ASCII Scene Export",
+		extensions: &["ase"],
+		magic: &[Magic::new(b"*3DSMAX_ASCIIEXPORT")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "ASSBIN",
+		description: "Assimp binary dump",
+		extensions: &["assbin"],
+		magic: &[Magic::new(b"ASSIMP.binary-dump.")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "BMP",
+		description: "Windows/OS/2 bitmap",
+		extensions: &["bmp", "dib"],
+		magic: &[Magic::new(b"BM")],
+		kind: AssetKind::Texture,
+	},
+	FormatInfo {
+		name: "BPS",
+		description: "beat patch",
+		extensions: &["bps"],
+		magic: &[Magic::new(b"BPS1")],
+		kind: AssetKind::Patch,
+	},
+	FormatInfo {
+		name: "DAE",
+		description: "COLLADA",
+		extensions: &["dae"],
+		magic: &[],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "DRP",
+		description: "Chrono Cross archive",
+		extensions: &["drp"],
+		magic: &[Magic::new(b"drp\x00")],
+		kind: AssetKind::Archive,
+	},
+	FormatInfo {
+		name: "FBX",
+		description: "Autodesk FBX, binary",
+		extensions: &["fbx"],
+		magic: &[Magic::new(b"Kaydara FBX Binary  \x00")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "FSPY",
+		description: "fSpy camera project",
+		extensions: &["fspy"],
+		magic: &[Magic::new(b"fspy")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "GLB",
+		description: "glTF binary",
+		extensions: &["glb"],
+		magic: &[Magic::new(b"glTF")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "GLTF",
+		description: "glTF",
+		extensions: &["gltf"],
+		magic: &[],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "IPS",
+		description: "International Patching System patch",
+		extensions: &["ips"],
+		magic: &[Magic::new(b"PATCH")],
+		kind: AssetKind::Patch,
+	},
+	FormatInfo {
+		name: "ISM2",
+		description: "Compile Heart model",
+		extensions: &["ism2"],
+		magic: &[Magic::new(b"ISM2")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "LZSS",
+		description: "Square Enix LZSS archive",
+		extensions: &["lzs"],
+		magic: &[Magic::new(b"sszl")],
+		kind: AssetKind::Archive,
+	},
+	FormatInfo {
+		name: "M2",
+		description: "World of Warcraft model",
+		extensions: &["m2"],
+		magic: &[Magic::new(b"MD20"), Magic::new(b"MD21")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "M3",
+		description: "StarCraft II model",
+		extensions: &["m3"],
+		magic: &[Magic::new(b"33DM"), Magic::new(b"43DM")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "MD2",
+		description: "Quake II model",
+		extensions: &["md2"],
+		magic: &[Magic::new(b"IDP2")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "MDL",
+		description: "Quake model",
+		extensions: &["mdl"],
+		magic: &[Magic::new(b"IDPO")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "MDX",
+		description: "Warcraft III model",
+		extensions: &["mdx"],
+		magic: &[Magic::new(b"MDLX")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "MQO",
+		description: "Metasequoia document",
+		extensions: &["mqo"],
+		magic: &[Magic::new(b"Metasequoia Document")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "OBJ",
+		description: "Wavefront OBJ",
+		extensions: &["obj"],
+		magic: &[],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "PHYRE",
+		description: "PhyreEngine asset",
+		extensions: &["phyre"],
+		magic: &[Magic::new(b"PHYR"), Magic::new(b"RYHP")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "PMD",
+		description: "MikuMikuDance model",
+		extensions: &["pmd"],
+		magic: &[Magic::new(b"Pmd")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "PMX",
+		description: "MikuMikuDance extended model",
+		extensions: &["pmx"],
+		magic: &[Magic::new(b"PMX ")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "RIP",
+		description: "Ninja Ripper mesh",
+		extensions: &["rip"],
+		magic: &[Magic::new(b"\xDE\xC0\xAD\xDE")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "TIM",
+		description: "PlayStation texture",
+		extensions: &["tim"],
+		// the ID word alone is just the number 16, so the flags word must be one of the usual depths too
+		magic: &[
+			Magic::new(b"\x10\x00\x00\x00\x02\x00\x00\x00"),
+			Magic::new(b"\x10\x00\x00\x00\x03\x00\x00\x00"),
+			Magic::new(b"\x10\x00\x00\x00\x08\x00\x00\x00"),
+			Magic::new(b"\x10\x00\x00\x00\x09\x00\x00\x00")
+		],
+		kind: AssetKind::Texture,
+	},
+	FormatInfo {
+		name: "TIM2",
+		description: "PlayStation 2 texture",
+		extensions: &["tm2"],
+		magic: &[Magic::new(b"TIM2")],
+		kind: AssetKind::Texture,
+	},
+	FormatInfo {
+		name: "X",
+		description: "DirectX model",
+		extensions: &["x"],
+		magic: &[Magic::new(b"xof ")],
+		kind: AssetKind::Scene,
+	},
+	FormatInfo {
+		name: "XPS",
+		description: "XNALara/XPS model, binary",
+		extensions: &["xps"],
+		magic: &[Magic::new(b"\xA0\xEE\x04\x00")],
+		kind: AssetKind::Scene,
+	},
+];
+
+/// Looks up a format in [`FORMATS`]
+pub fn builtin(name: &str) -> Option<&'static FormatInfo> {
+	FORMATS.iter().find(|f| f.name == name)
+}
+
+/// Formats and their handlers available to a tool
+pub struct Registry {
+	formats: Vec<FormatInfo>,
+	importers: Vec<Box<dyn Importer>>,
+	exporters: Vec<Box<dyn Exporter>>,
+	extractors: Vec<Box<dyn Extractor>>,
+	patchers: Vec<Box<dyn Patcher>>,
+}
+
+impl Default for Registry {
+	fn default() -> Self {
+		Registry::new()
+	}
+}
+
+impl Registry {
+	/// Creates a registry knowing [`FORMATS`], without any handlers
+	pub fn new() -> Registry {
+		Registry {
+			formats: FORMATS.to_vec(),
+			importers: vec![],
+			exporters: vec![],
+			extractors: vec![],
+			patchers: vec![],
+		}
+	}
+
+	/// Adds a format, replacing any of the same name
+	pub fn add_format(&mut self, format: FormatInfo) {
+		match self.formats.iter_mut().find(|f| f.name == format.name) {
+			Some(f) => *f = format,
+			None => self.formats.push(format),
+		}
+	}
+
+	/// Adds an importer along with its format, replacing any importer for the same format
+	pub fn add_importer(&mut self, importer: Box<dyn Importer>) {
+		let format = *importer.format();
+		self.add_format(format);
+		self.importers.retain(|i| i.format().name != format.name);
+		self.importers.push(importer);
+	}
+
+	/// Adds an exporter along with its format, replacing any exporter for the same format
+	pub fn add_exporter(&mut self, exporter: Box<dyn Exporter>) {
+		let format = *exporter.format();
+		self.add_format(format);
+		self.exporters.retain(|e| e.format().name != format.name);
+		self.exporters.push(exporter);
+	}
+
+	/// Adds an extractor along with its format, replacing any extractor for the same format
+	pub fn add_extractor(&mut self, extractor: Box<dyn Extractor>) {
+		let format = *extractor.format();
+		self.add_format(format);
+		self.extractors.retain(|e| e.format().name != format.name);
+		self.extractors.push(extractor);
+	}
+
+	/// Adds a patcher along with its format, replacing any patcher for the same format
+	pub fn add_patcher(&mut self, patcher: Box<dyn Patcher>) {
+		let format = *patcher.format();
+		self.add_format(format);
+		self.patchers.retain(|p| p.format().name != format.name);
+		self.patchers.push(patcher);
+	}
+
+	pub fn formats(&self) -> &[FormatInfo] {
+		&self.formats
+	}
+
+	pub fn format(&self, name: &str) -> Option<&FormatInfo> {
+		self.formats.iter().find(|f| f.name.eq_ignore_ascii_case(name))
+	}
+
+	pub fn importer(&self, name: &str) -> Option<&dyn Importer> {
+		self.importers.iter().find(|i| i.format().name.eq_ignore_ascii_case(name)).map(|i| i.as_ref())
+	}
+
+	pub fn exporter(&self, name: &str) -> Option<&dyn Exporter> {
+		self.exporters.iter().find(|e| e.format().name.eq_ignore_ascii_case(name)).map(|e| e.as_ref())
+	}
+
+	/// Finds an exporter by the extension of `path`
+	pub fn exporter_for(&self, path: &Path) -> Option<&dyn Exporter> {
+		self.exporters.iter().find(|e| e.format().matches_extension(path)).map(|e| e.as_ref())
+	}
+
+	pub fn extractor(&self, name: &str) -> Option<&dyn Extractor> {
+		self.extractors.iter().find(|e| e.format().name.eq_ignore_ascii_case(name)).map(|e| e.as_ref())
+	}
+
+	pub fn patcher(&self, name: &str) -> Option<&dyn Patcher> {
+		self.patchers.iter().find(|p| p.format().name.eq_ignore_ascii_case(name)).map(|p| p.as_ref())
+	}
+
+	/// Finds a patcher by the extension of `path`
+	pub fn patcher_for(&self, path: &Path) -> Option<&dyn Patcher> {
+		self.patchers.iter().find(|p| p.format().matches_extension(path)).map(|p| p.as_ref())
+	}
+
+	/// Detects the format of `data` from its magic, falling back to the extension of `path`. The longest
+	/// matching magic wins, so short signatures don't shadow more specific ones.
+	pub fn detect(&self, data: &[u8], path: Option<&Path>) -> Option<&FormatInfo> {
+		let by_magic = self.formats.iter()
+			.filter_map(|f| f.magic.iter().filter(|m| m.matches(data)).map(|m| m.bytes.len()).max().map(|l| (f, l)))
+			.max_by_key(|(_, l)| *l)
+			.map(|(f, _)| f);
+
+		by_magic.or_else(|| path.and_then(|p| self.formats.iter().find(|f| f.matches_extension(p))))
+	}
+
+	/// Detects the format of `data` and imports it
+	pub fn import(&self, data: &[u8], path: Option<&Path>) -> Result<Asset, FormatError> {
+		let format = self.detect(data, path).ok_or(FormatError::Unknown)?;
+		let importer = self.importer(format.name).ok_or(FormatError::NoImporter(format.name))?;
+
+		importer.import(data)
+	}
+
+	/// Exports `asset` in the format given by the extension of `path`
+	pub fn export(&self, asset: &Asset, path: &Path, out: &mut dyn Write) -> Result<(), FormatError> {
+		let exporter = self.exporter_for(path).ok_or_else(|| FormatError::NoExporter(path.display().to_string()))?;
+		let format = exporter.format();
+		if format.kind != asset.kind() {
+			return Err(FormatError::Unsupported {
+				format: format.name,
+				kind: asset.kind(),
+			});
+		}
+
+		exporter.export(asset, out)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::texture::PixelFormat;
+
+	static RAW: FormatInfo = FormatInfo {
+		name: "RAW",
+		description: "Raw 8 bit luminance, preceded by its width and height",
+		extensions: &["raw"],
+		magic: &[],
+		kind: AssetKind::Texture,
+	};
+
+	struct Raw;
+
+	impl Importer for Raw {
+		fn format(&self) -> &FormatInfo {
+			&RAW
+		}
+
+		fn import(&self, data: &[u8]) -> Result<Asset, FormatError> {
+			let (w, h) = (data[0] as usize, data[1] as usize);
+			let mut tex = Texture::with_format(w, h, PixelFormat::L8);
+			tex.data = crate::texture::PixelData::L8(data[2..2 + w * h].to_vec());
+			Ok(Asset::Texture(tex))
+		}
+	}
+
+	impl Exporter for Raw {
+		fn format(&self) -> &FormatInfo {
+			&RAW
+		}
+
+		fn export(&self, asset: &Asset, out: &mut dyn Write) -> Result<(), FormatError> {
+			let tex = match asset {
+				Asset::Texture(t) => t.convert(PixelFormat::L8),
+				_ => unreachable!(),
+			};
+
+			out.write_all(&[tex.width as u8, tex.height as u8])?;
+			match tex.data {
+				crate::texture::PixelData::L8(p) => out.write_all(&p)?,
+				_ => unreachable!(),
+			}
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn test_detect() {
+		let reg = Registry::new();
+		let name = |data: &[u8], path: Option<&str>| reg.detect(data, path.map(Path::new)).map(|f| f.name);
+
+		assert_eq!(name(b"PMX \x00\x00\x00\x40", None), Some("PMX"));
+		assert_eq!(name(b"MD21....", None), Some("M2"));
+		assert_eq!(name(b"Kaydara FBX Binary  \x00\x1a\x00", None), Some("FBX"));
+		assert_eq!(name(b"PATCH\x00\x00\x00", Some("a.bps")), Some("IPS"));
+		assert_eq!(name(b"v 0 0 0\n", Some("Model.OBJ")), Some("OBJ"));
+		assert_eq!(name(b"v 0 0 0\n", Some("model")), None);
+		assert_eq!(name(b"", None), None);
+
+		assert_eq!(name(b"\x10\x00\x00\x00\x09\x00\x00\x00", None), Some("TIM"));
+		assert_eq!(name(b"\x10\x00\x00\x00\x20\x00\x00\x00", None), None);
+		assert_eq!(name(b"\x10\x00\x00\x00\x20\x00\x00\x00", Some("a.tim")), Some("TIM"));
+	}
+
+	#[test]
+	fn test_registry() {
+		let mut reg = Registry::new();
+		assert!(matches!(reg.import(b"\x01\x01\x80", Some(Path::new("a.raw"))), Err(FormatError::Unknown)));
+
+		reg.add_importer(Box::new(Raw));
+		reg.add_exporter(Box::new(Raw));
+		assert!(matches!(reg.import(b"TIM2", None), Err(FormatError::NoImporter("TIM2"))));
+
+		let asset = reg.import(b"\x02\x01\x00\xff", Some(Path::new("a.raw"))).unwrap();
+		assert_eq!(asset.kind(), AssetKind::Texture);
+
+		let mut out = vec![];
+		reg.export(&asset, Path::new("b.RAW"), &mut out).unwrap();
+		assert_eq!(out, b"\x02\x01\x00\xff");
+
+		assert!(matches!(reg.export(&asset, Path::new("b.png"), &mut out), Err(FormatError::NoExporter(_))));
+		assert!(reg.extractor("RAW").is_none());
+		assert_eq!(reg.formats().len(), FORMATS.len() + 1);
+	}
+}
@@ -0,0 +1,250 @@
+//! Uniform access to file contents. Parsers take an [`Input`] and borrow from it, so the same code reads from
+//! buffers, streams and, with the `mmap` feature, memory mapped files without copying them.
+
+use std::{
+	fs,
+	io::{
+		self,
+		Cursor,
+		Read,
+		Seek,
+		SeekFrom
+	},
+	ops::Deref,
+	path::{
+		Path,
+		PathBuf
+	}
+};
+
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+
+/// Bytes to parse, dereferencing to `[u8]`
+#[derive(Debug)]
+pub enum Input<'a> {
+	Borrowed(&'a [u8]),
+	Owned(Vec<u8>),
+	#[cfg(feature = "mmap")]
+	Mapped(Mmap),
+}
+
+impl<'a> Input<'a> {
+	/// Opens a file, mapping it into memory rather than reading it
+	#[cfg(feature = "mmap")]
+	pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Input<'static>> {
+		let file = fs::File::open(path)?;
+
+		// mapping an empty file fails on some platforms
+		if file.metadata()?.len() == 0 {
+			return Ok(Input::Owned(vec![]));
+		}
+
+		// Safety: the mapping is read only, but like any memory mapped file, its contents are undefined if another
+		// process truncates it while mapped
+		Ok(Input::Mapped(unsafe { Mmap::map(&file)? }))
+	}
+
+	/// Reads a file into memory. Enable the `mmap` feature to map it instead.
+	#[cfg(not(feature = "mmap"))]
+	pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Input<'static>> {
+		Ok(Input::Owned(fs::read(path)?))
+	}
+
+	/// Reads a stream from its current position to the end
+	pub fn from_reader<R: Read + Seek>(reader: &mut R) -> io::Result<Input<'static>> {
+		let start = reader.stream_position()?;
+		let end = reader.seek(SeekFrom::End(0))?;
+		reader.seek(SeekFrom::Start(start))?;
+
+		let mut data = Vec::with_capacity(end.saturating_sub(start) as usize);
+		reader.read_to_end(&mut data)?;
+		Ok(Input::Owned(data))
+	}
+
+	pub fn as_slice(&self) -> &[u8] {
+		match self {
+			Input::Borrowed(data) => data,
+			Input::Owned(data) => data,
+			#[cfg(feature = "mmap")]
+			Input::Mapped(map) => map,
+		}
+	}
+
+	/// Returns a seekable reader borrowing the input
+	pub fn cursor(&self) -> Cursor<&[u8]> {
+		Cursor::new(self.as_slice())
+	}
+}
+
+impl Deref for Input<'_> {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		self.as_slice()
+	}
+}
+
+impl AsRef<[u8]> for Input<'_> {
+	fn as_ref(&self) -> &[u8] {
+		self.as_slice()
+	}
+}
+
+impl<'a> From<&'a [u8]> for Input<'a> {
+	fn from(data: &'a [u8]) -> Input<'a> {
+		Input::Borrowed(data)
+	}
+}
+
+impl From<Vec<u8>> for Input<'_> {
+	fn from(data: Vec<u8>) -> Self {
+		Input::Owned(data)
+	}
+}
+
+/// A `Read + Seek` stream to be read into an [`Input`]
+pub struct Stream<R>(pub R);
+
+/// Conversion into an [`Input`], letting loaders accept paths, buffers and streams alike
+pub trait IntoInput<'a> {
+	fn into_input(self) -> io::Result<Input<'a>>;
+}
+
+impl<'a> IntoInput<'a> for Input<'a> {
+	fn into_input(self) -> io::Result<Input<'a>> {
+		Ok(self)
+	}
+}
+
+impl<'a> IntoInput<'a> for &'a [u8] {
+	fn into_input(self) -> io::Result<Input<'a>> {
+		Ok(Input::Borrowed(self))
+	}
+}
+
+impl<'a> IntoInput<'a> for &'a Vec<u8> {
+	fn into_input(self) -> io::Result<Input<'a>> {
+		Ok(Input::Borrowed(self))
+	}
+}
+
+impl<'a> IntoInput<'a> for Vec<u8> {
+	fn into_input(self) -> io::Result<Input<'a>> {
+		Ok(Input::Owned(self))
+	}
+}
+
+impl<'a, R: Read + Seek> IntoInput<'a> for Stream<R> {
+	fn into_input(mut self) -> io::Result<Input<'a>> {
+		Input::from_reader(&mut self.0)
+	}
+}
+
+macro_rules! path_into_input {
+	($($t: ty),*) => {
+		$(
+			impl<'a> IntoInput<'a> for $t {
+				fn into_input(self) -> io::Result<Input<'a>> {
+					Input::open(self)
+				}
+			}
+		)*
+	}
+}
+
+path_into_input!(&Path, &PathBuf, PathBuf, &str, &String, String);
+
+/// Borrowing reads from in-memory readers, for parsers that keep slices of their input
+pub trait ReadSliceExt<'a> {
+	/// Borrows the next `len` bytes and advances past them
+	fn read_slice(&mut self, len: usize) -> io::Result<&'a [u8]>;
+
+	/// Borrows everything left to read
+	fn remaining_slice(&self) -> &'a [u8];
+}
+
+fn eof() -> io::Error {
+	io::Error::new(io::ErrorKind::UnexpectedEof, "not enough data for slice")
+}
+
+impl<'a> ReadSliceExt<'a> for &'a [u8] {
+	fn read_slice(&mut self, len: usize) -> io::Result<&'a [u8]> {
+		if len > self.len() {
+			return Err(eof());
+		}
+
+		let (head, tail) = self.split_at(len);
+		*self = tail;
+		Ok(head)
+	}
+
+	fn remaining_slice(&self) -> &'a [u8] {
+		self
+	}
+}
+
+impl<'a> ReadSliceExt<'a> for Cursor<&'a [u8]> {
+	fn read_slice(&mut self, len: usize) -> io::Result<&'a [u8]> {
+		let data: &'a [u8] = self.get_ref();
+		let pos = (self.position() as usize).min(data.len());
+		let end = pos.checked_add(len).filter(|&e| e <= data.len()).ok_or_else(eof)?;
+
+		self.set_position(end as u64);
+		Ok(&data[pos..end])
+	}
+
+	fn remaining_slice(&self) -> &'a [u8] {
+		let data: &'a [u8] = self.get_ref();
+		&data[(self.position() as usize).min(data.len())..]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn load<'a, I: IntoInput<'a>>(input: I) -> Vec<u8> {
+		input.into_input().unwrap().to_vec()
+	}
+
+	#[test]
+	fn test_sources() {
+		let data = b"RGK input test".to_vec();
+		let path = std::env::temp_dir().join(format!("rgk_input_{}.bin", std::process::id()));
+		fs::write(&path, &data).unwrap();
+
+		assert_eq!(load(&path), data);
+		assert_eq!(load(path.to_str().unwrap()), data);
+		assert_eq!(load(&data[..]), data);
+		assert_eq!(load(data.clone()), data);
+
+		let mut stream = Cursor::new(data.clone());
+		stream.set_position(4);
+		assert_eq!(load(Stream(stream)), &data[4..]);
+
+		fs::write(&path, b"").unwrap();
+		assert!(load(&path).is_empty());
+		fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn test_read_slice() {
+		let input = Input::from(&b"\x04\x00\x00\x00TIM2rest"[..]);
+
+		let mut buf = input.as_slice();
+		let len = buf.read_slice(4).unwrap();
+		assert_eq!(len, b"\x04\x00\x00\x00");
+		assert_eq!(buf.read_slice(4).unwrap(), b"TIM2");
+		assert_eq!(buf.remaining_slice(), b"rest");
+		assert!(buf.read_slice(5).is_err());
+
+		let mut cursor = input.cursor();
+		cursor.set_position(4);
+		let magic = cursor.read_slice(4).unwrap();
+		assert_eq!(magic, b"TIM2");
+		assert_eq!(cursor.position(), 8);
+		assert_eq!(cursor.remaining_slice(), b"rest");
+		assert!(cursor.read_slice(usize::MAX).is_err());
+	}
+}
@@ -1,89 +1,251 @@
 use std::io::{
+	Error,
+	ErrorKind,
 	Read,
-	Result
+	Result,
+	Write
 };
 
-use ultraviolet::vec::{
-	Vec2,
-	Vec3,
-	Vec4
+use byteorder::{
+	BigEndian,
+	ByteOrder,
+	LittleEndian,
+	ReadBytesExt,
+	WriteBytesExt
 };
 
+use ultraviolet::{
+	mat::{
+		Mat3,
+		Mat4
+	},
+	rotor::Rotor3,
+	vec::{
+		Vec2,
+		Vec3,
+		Vec4
+	}
+};
+
+/// Text encodings found in game data
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Encoding {
+	Ascii,
+
+	/// ISO 8859-1, where every byte maps to the code point of the same value
+	Latin1,
+
+	/// Windows code page 932
+	ShiftJis,
+	Utf8,
+	Utf16Le,
+	Utf16Be,
+
+	/// Windows code page 949
+	EucKr,
+}
+
+impl Encoding {
+	/// Size of a code unit in bytes, and so of a null terminator
+	pub fn unit_size(&self) -> usize {
+		match self {
+			Encoding::Utf16Le | Encoding::Utf16Be => 2,
+			_ => 1,
+		}
+	}
+
+	/// Decodes text, replacing malformed sequences with U+FFFD
+	pub fn decode(&self, bytes: &[u8]) -> String {
+		match self {
+			Encoding::Ascii => bytes.iter().map(|b| if b.is_ascii() { *b as char } else { '\u{FFFD}' }).collect(),
+			Encoding::Latin1 => bytes.iter().map(|b| *b as char).collect(),
+			Encoding::ShiftJis => encoding_rs::SHIFT_JIS.decode_without_bom_handling(bytes).0.into_owned(),
+			Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+			Encoding::Utf16Le | Encoding::Utf16Be => {
+				let units = bytes.chunks(2).map(|c| match (self, c) {
+					(Encoding::Utf16Le, [lo, hi]) => u16::from_le_bytes([*lo, *hi]),
+					(_, [hi, lo]) => u16::from_be_bytes([*hi, *lo]),
+					_ => 0xFFFD,
+				});
+
+				char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect()
+			},
+			Encoding::EucKr => encoding_rs::EUC_KR.decode_without_bom_handling(bytes).0.into_owned(),
+		}
+	}
+
+	/// Encodes text, failing with [`ErrorKind::InvalidInput`] on characters the encoding can't represent
+	pub fn encode(&self, s: &str) -> Result<Vec<u8>> {
+		let unmappable = |c: char| {
+			Error::new(ErrorKind::InvalidInput, format!("{:?} is not representable in {:?}", c, self))
+		};
+
+		match self {
+			Encoding::Ascii => s.chars().map(|c| if c.is_ascii() { Ok(c as u8) } else { Err(unmappable(c)) }).collect(),
+			Encoding::Latin1 => s.chars().map(|c| u8::try_from(c).map_err(|_| unmappable(c))).collect(),
+			Encoding::ShiftJis | Encoding::EucKr => {
+				let encoding = if *self == Encoding::ShiftJis { encoding_rs::SHIFT_JIS } else { encoding_rs::EUC_KR };
+				let mut encoder = encoding.new_encoder();
+				let mut out = Vec::with_capacity(s.len() * 2);
+				let mut rest = s;
+
+				loop {
+					out.reserve(encoder.max_buffer_length_from_utf8_without_replacement(rest.len()).unwrap_or(0));
+					let (result, read) = encoder.encode_from_utf8_to_vec_without_replacement(rest, &mut out, true);
+					rest = &rest[read..];
+
+					match result {
+						encoding_rs::EncoderResult::InputEmpty => return Ok(out),
+						encoding_rs::EncoderResult::OutputFull => (),
+						encoding_rs::EncoderResult::Unmappable(c) => return Err(unmappable(c)),
+					}
+				}
+			},
+			Encoding::Utf8 => Ok(s.as_bytes().to_vec()),
+			Encoding::Utf16Le => Ok(s.encode_utf16().flat_map(|u| u.to_le_bytes()).collect()),
+			Encoding::Utf16Be => Ok(s.encode_utf16().flat_map(|u| u.to_be_bytes()).collect()),
+		}
+	}
+}
+
 pub trait ReadBinExt: Read {
-	/// Reads a null-terminated string
+	/// Reads a null-terminated Latin-1 string
 	#[inline]
 	fn read_cstr(&mut self) -> Result<String> {
-		let mut s = String::new();
-		let mut buf = [1; 1];
+		self.read_cstr_with(Encoding::Latin1)
+	}
 
-		while buf[0] != 0 {
-			self.read_exact(&mut buf)?;
-			if buf[0] != 0 {
-				s.push(buf[0] as char);
+	/// Reads a string terminated by a null code unit
+	#[inline]
+	fn read_cstr_with(&mut self, encoding: Encoding) -> Result<String> {
+		let mut bytes = vec![];
+		let mut unit = vec![0; encoding.unit_size()];
+
+		loop {
+			self.read_exact(&mut unit)?;
+			if unit.iter().all(|b| *b == 0) {
+				break;
 			}
+			bytes.extend_from_slice(&unit);
 		}
 
-		Ok(s)
+		Ok(encoding.decode(&bytes))
 	}
 
-	/// Reads a Pascal style string
+	/// Reads a Pascal style Latin-1 string
 	#[inline]
 	fn read_pstr(&mut self) -> Result<String> {
-		let mut s = String::new();
-		let mut buf = [0; 1];
+		self.read_pstr_with(Encoding::Latin1)
+	}
+
+	/// Reads a string prefixed by its length in bytes as a `u8`
+	#[inline]
+	fn read_pstr_with(&mut self, encoding: Encoding) -> Result<String> {
+		let length = self.read_u8()? as usize;
+		self.read_str_with(length, encoding)
+	}
+
+	/// Reads a Latin-1 string from a fixed width field, stopping at the first null
+	#[inline]
+	fn read_fixed_str(&mut self, width: usize) -> Result<String> {
+		self.read_fixed_str_with(width, Encoding::Latin1)
+	}
 
+	/// Reads a string from a field of `width` bytes, stopping at the first null code unit
+	#[inline]
+	fn read_fixed_str_with(&mut self, width: usize, encoding: Encoding) -> Result<String> {
+		let mut buf = vec![0; width];
 		self.read_exact(&mut buf)?;
-		let length = buf[0] as usize;
 
-		for _ in 0..length {
-			self.read_exact(&mut buf)?;
-			s.push(buf[0] as char);
+		let unit = encoding.unit_size();
+		let end = buf.chunks(unit).position(|c| c.iter().all(|b| *b == 0)).map_or(width, |i| i * unit);
+		Ok(encoding.decode(&buf[..end]))
+	}
+
+	/// Reads a string of `length` bytes. The length often comes from the file, so the buffer only grows as data
+	/// arrives rather than being allocated up front.
+	#[inline]
+	fn read_str_with(&mut self, length: usize, encoding: Encoding) -> Result<String> {
+		let mut buf = vec![];
+		(&mut *self).take(length as u64).read_to_end(&mut buf)?;
+		if buf.len() != length {
+			let msg = format!("String ends after {} of {} bytes", buf.len(), length);
+			return Err(Error::new(ErrorKind::UnexpectedEof, msg));
 		}
 
-		Ok(s)
+		Ok(encoding.decode(&buf))
 	}
 
-	/// Reads a little endian 2D vector
+	/// Reads a 2D vector
 	#[inline]
-	fn read_vec2_le(&mut self) -> Result<Vec2> {
-		let mut x = [0; 4];
-		let mut y = x;
+	fn read_vec2<B: ByteOrder>(&mut self) -> Result<Vec2> {
+		Ok(Vec2::new(self.read_f32::<B>()?, self.read_f32::<B>()?))
+	}
 
-		self.read_exact(&mut x)?;
-		self.read_exact(&mut y)?;
+	/// Reads a 3D vector
+	#[inline]
+	fn read_vec3<B: ByteOrder>(&mut self) -> Result<Vec3> {
+		Ok(Vec3::new(self.read_f32::<B>()?, self.read_f32::<B>()?, self.read_f32::<B>()?))
+	}
 
-		Ok(Vec2::new(f32::from_le_bytes(x), f32::from_le_bytes(y)))
+	/// Reads a 4D vector
+	#[inline]
+	fn read_vec4<B: ByteOrder>(&mut self) -> Result<Vec4> {
+		Ok(Vec4::new(self.read_f32::<B>()?, self.read_f32::<B>()?, self.read_f32::<B>()?, self.read_f32::<B>()?))
 	}
 
-	/// Reads a little endian 3D vector
+	/// Reads a quaternion stored as `x, y, z, w`
 	#[inline]
-	fn read_vec3_le(&mut self) -> Result<Vec3> {
-		let mut x = [0; 4];
-		let mut y = x;
-		let mut z = y;
+	fn read_quat<B: ByteOrder>(&mut self) -> Result<Rotor3> {
+		Ok(Rotor3::from_quaternion_array(*self.read_vec4::<B>()?.as_array()))
+	}
+
+	/// Reads a column major 3x3 matrix
+	#[inline]
+	fn read_mat3<B: ByteOrder>(&mut self) -> Result<Mat3> {
+		Ok(Mat3::new(self.read_vec3::<B>()?, self.read_vec3::<B>()?, self.read_vec3::<B>()?))
+	}
+
+	/// Reads a column major 4x4 matrix
+	#[inline]
+	fn read_mat4<B: ByteOrder>(&mut self) -> Result<Mat4> {
+		Ok(Mat4::new(self.read_vec4::<B>()?, self.read_vec4::<B>()?, self.read_vec4::<B>()?, self.read_vec4::<B>()?))
+	}
 
-		self.read_exact(&mut x)?;
-		self.read_exact(&mut y)?;
-		self.read_exact(&mut z)?;
+	/// Reads a little endian 2D vector
+	#[inline]
+	fn read_vec2_le(&mut self) -> Result<Vec2> {
+		self.read_vec2::<LittleEndian>()
+	}
 
-		Ok(Vec3::new(f32::from_le_bytes(x), f32::from_le_bytes(y), f32::from_le_bytes(z)))
+	/// Reads a little endian 3D vector
+	#[inline]
+	fn read_vec3_le(&mut self) -> Result<Vec3> {
+		self.read_vec3::<LittleEndian>()
 	}
 
 	/// Reads a little endian 4D vector
 	#[inline]
 	fn read_vec4_le(&mut self) -> Result<Vec4> {
-		let mut x = [0; 4];
-		let mut y = x;
-		let mut z = y;
-		let mut w = z;
+		self.read_vec4::<LittleEndian>()
+	}
+
+	/// Reads a big endian 2D vector
+	#[inline]
+	fn read_vec2_be(&mut self) -> Result<Vec2> {
+		self.read_vec2::<BigEndian>()
+	}
 
-		self.read_exact(&mut x)?;
-		self.read_exact(&mut y)?;
-		self.read_exact(&mut z)?;
-		self.read_exact(&mut w)?;
+	/// Reads a big endian 3D vector
+	#[inline]
+	fn read_vec3_be(&mut self) -> Result<Vec3> {
+		self.read_vec3::<BigEndian>()
+	}
 
-		Ok(Vec4::new(f32::from_le_bytes(x), f32::from_le_bytes(y), f32::from_le_bytes(z),
-			f32::from_le_bytes(w)))
+	/// Reads a big endian 4D vector
+	#[inline]
+	fn read_vec4_be(&mut self) -> Result<Vec4> {
+		self.read_vec4::<BigEndian>()
 	}
 }
 
@@ -93,9 +255,110 @@ where
 {
 }
 
+/// Writing counterpart to [`ReadBinExt`]. Strings the encoding can't represent, and strings too long for
+/// their length prefix or field, are rejected with [`ErrorKind::InvalidInput`].
+pub trait WriteBinExt: Write {
+	/// Writes a null-terminated Latin-1 string
+	#[inline]
+	fn write_cstr(&mut self, s: &str) -> Result<()> {
+		self.write_cstr_with(s, Encoding::Latin1)
+	}
+
+	/// Writes a string terminated by a null code unit
+	#[inline]
+	fn write_cstr_with(&mut self, s: &str, encoding: Encoding) -> Result<()> {
+		if s.contains('\0') {
+			return Err(Error::new(ErrorKind::InvalidInput, "String contains a null"));
+		}
+
+		self.write_all(&encoding.encode(s)?)?;
+		self.write_all(&vec![0; encoding.unit_size()])
+	}
+
+	/// Writes a Pascal style Latin-1 string
+	#[inline]
+	fn write_pstr(&mut self, s: &str) -> Result<()> {
+		self.write_pstr_with(s, Encoding::Latin1)
+	}
+
+	/// Writes a string prefixed by its length in bytes as a `u8`
+	#[inline]
+	fn write_pstr_with(&mut self, s: &str, encoding: Encoding) -> Result<()> {
+		let bytes = encoding.encode(s)?;
+		let length = u8::try_from(bytes.len())
+			.map_err(|_| Error::new(ErrorKind::InvalidInput, "String is longer than 255 bytes"))?;
+
+		self.write_all(&[length])?;
+		self.write_all(&bytes)
+	}
+
+	/// Writes a Latin-1 string into a fixed width field, padding it with nulls
+	#[inline]
+	fn write_fixed_str(&mut self, s: &str, width: usize) -> Result<()> {
+		self.write_fixed_str_with(s, width, Encoding::Latin1)
+	}
+
+	/// Writes a string into a field of `width` bytes, padding it with nulls
+	#[inline]
+	fn write_fixed_str_with(&mut self, s: &str, width: usize, encoding: Encoding) -> Result<()> {
+		let mut bytes = encoding.encode(s)?;
+		if bytes.len() > width {
+			return Err(Error::new(ErrorKind::InvalidInput, format!("String is longer than {} bytes", width)));
+		}
+
+		bytes.resize(width, 0);
+		self.write_all(&bytes)
+	}
+
+	/// Writes a 2D vector
+	#[inline]
+	fn write_vec2<B: ByteOrder>(&mut self, v: &Vec2) -> Result<()> {
+		v.as_slice().iter().try_for_each(|f| self.write_f32::<B>(*f))
+	}
+
+	/// Writes a 3D vector
+	#[inline]
+	fn write_vec3<B: ByteOrder>(&mut self, v: &Vec3) -> Result<()> {
+		v.as_slice().iter().try_for_each(|f| self.write_f32::<B>(*f))
+	}
+
+	/// Writes a 4D vector
+	#[inline]
+	fn write_vec4<B: ByteOrder>(&mut self, v: &Vec4) -> Result<()> {
+		v.as_slice().iter().try_for_each(|f| self.write_f32::<B>(*f))
+	}
+
+	/// Writes a quaternion as `x, y, z, w`
+	#[inline]
+	fn write_quat<B: ByteOrder>(&mut self, q: &Rotor3) -> Result<()> {
+		q.into_quaternion_array().iter().try_for_each(|f| self.write_f32::<B>(*f))
+	}
+
+	/// Writes a 3x3 matrix in column major order
+	#[inline]
+	fn write_mat3<B: ByteOrder>(&mut self, m: &Mat3) -> Result<()> {
+		m.as_slice().iter().try_for_each(|f| self.write_f32::<B>(*f))
+	}
+
+	/// Writes a 4x4 matrix in column major order
+	#[inline]
+	fn write_mat4<B: ByteOrder>(&mut self, m: &Mat4) -> Result<()> {
+		m.as_slice().iter().try_for_each(|f| self.write_f32::<B>(*f))
+	}
+}
+
+impl<W> WriteBinExt for W
+where
+	W: Write + ?Sized,
+{
+}
+
 #[cfg(test)]
 mod tests {
-	use std::io::Read;
+	use byteorder::{
+		BE,
+		LE
+	};
 
 	use ultraviolet::vec::{
 		Vec2,
@@ -118,6 +381,7 @@ mod tests {
 	}
 
 	#[test]
+	#[allow(clippy::excessive_precision)]
 	fn test_read_vecs() {
 		let mut vec2: &[u8] = &[0x5c, 0x1f, 0x7f, 0x3c, 0xa4, 0xfb, 0xf0, 0x3d][..];
 		let mut vec3: &[u8] = &[0x5c, 0x1f, 0x7f, 0x3c, 0xa4, 0xfb, 0xf0, 0x3d, 0xd4, 0xf1, 0xb6, 0x3d][..];
@@ -127,4 +391,93 @@ mod tests {
 		assert_eq!(Vec3::new(0.0155714415, 0.117667466, 0.089328438), vec3.read_vec3_le().unwrap());
 		assert_eq!(Vec4::new(0.0155714415, 0.117667466, 0.089328438, -0.106262207), vec4.read_vec4_le().unwrap());
 	}
+
+	#[test]
+	fn test_read_vecs_be() {
+		let mut vec2: &[u8] = &[0x3c, 0x7f, 0x1f, 0x5c, 0x3d, 0xf0, 0xfb, 0xa4][..];
+		let mut vec3: &[u8] = &[0x3c, 0x7f, 0x1f, 0x5c, 0x3d, 0xf0, 0xfb, 0xa4, 0x3d, 0xb6, 0xf1, 0xd4][..];
+		let mut vec4: &[u8] = &[0x3c, 0x7f, 0x1f, 0x5c, 0x3d, 0xf0, 0xfb, 0xa4, 0x3d, 0xb6, 0xf1, 0xd4,
+			0xbd, 0xd9, 0xa0, 0][..];
+		assert_eq!(Vec2::new(0.0155714415, 0.117_667_47), vec2.read_vec2_be().unwrap());
+		assert_eq!(Vec3::new(0.0155714415, 0.117_667_47, 0.089_328_44), vec3.read_vec3_be().unwrap());
+		assert_eq!(Vec4::new(0.0155714415, 0.117_667_47, 0.089_328_44, -0.106_262_21), vec4.read_vec4_be().unwrap());
+	}
+
+	#[test]
+	fn test_write_strs() {
+		let mut buf = vec![];
+		buf.write_cstr("tést").unwrap();
+		buf.write_pstr("test").unwrap();
+		buf.write_fixed_str("ab", 4).unwrap();
+		assert_eq!(&buf[..], &b"t\xe9st\x00\x04testab\x00\x00"[..]);
+
+		let mut data = &buf[..];
+		assert_eq!(data.read_cstr().unwrap(), "tést");
+		assert_eq!(data.read_pstr().unwrap(), "test");
+		assert_eq!(data.read_fixed_str(4).unwrap(), "ab");
+
+		assert!(buf.write_cstr("日本").is_err());
+		assert!(buf.write_cstr("a\0b").is_err());
+		assert!(buf.write_pstr(&"a".repeat(256)).is_err());
+		assert!(buf.write_fixed_str("abcde", 4).is_err());
+	}
+
+	#[test]
+	fn test_encodings() {
+		let cases = [
+			(Encoding::Ascii, "bone", &b"bone"[..]),
+			(Encoding::Latin1, "tête", &b"t\xeate"[..]),
+			(Encoding::ShiftJis, "センター", &b"\x83\x5a\x83\x93\x83\x5e\x81\x5b"[..]),
+			(Encoding::Utf8, "頭", &b"\xe9\xa0\xad"[..]),
+			(Encoding::Utf16Le, "頭a", &b"\x2d\x98a\x00"[..]),
+			(Encoding::Utf16Be, "頭a", &b"\x98\x2d\x00a"[..]),
+			(Encoding::EucKr, "머리", &b"\xb8\xd3\xb8\xae"[..]),
+		];
+
+		for (encoding, text, bytes) in cases.iter() {
+			assert_eq!(encoding.encode(text).unwrap(), *bytes, "{:?}", encoding);
+			assert_eq!(encoding.decode(bytes), *text, "{:?}", encoding);
+
+			let mut buf = vec![];
+			buf.write_cstr_with(text, *encoding).unwrap();
+			buf.write_fixed_str_with(text, 16, *encoding).unwrap();
+			buf.write_pstr_with(text, *encoding).unwrap();
+			assert_eq!(buf.len(), bytes.len() * 2 + encoding.unit_size() + 17);
+
+			let mut data = &buf[..];
+			assert_eq!(data.read_cstr_with(*encoding).unwrap(), *text);
+			assert_eq!(data.read_fixed_str_with(16, *encoding).unwrap(), *text);
+			assert_eq!(data.read_pstr_with(*encoding).unwrap(), *text);
+		}
+
+		// a corrupt length fails once the data runs out rather than allocating it all first
+		assert!((&b"abc"[..]).read_str_with(u32::MAX as usize, Encoding::Utf8).is_err());
+		assert!((&b"abc"[..]).read_pstr().is_err());
+
+		assert!(Encoding::Ascii.encode("é").is_err());
+		assert!(Encoding::ShiftJis.encode("머리").is_err());
+		assert_eq!(Encoding::Utf8.decode(b"a\xff"), "a\u{FFFD}");
+		assert_eq!(Encoding::Utf16Le.decode(b"a\x00b"), "a\u{FFFD}");
+	}
+
+	#[test]
+	fn test_write_roundtrip() {
+		let q = Rotor3::from_rotation_xz(0.5);
+		let m = Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0));
+
+		let mut buf = vec![];
+		buf.write_vec3::<BE>(&Vec3::new(1.0, -2.0, 0.5)).unwrap();
+		buf.write_quat::<LE>(&q).unwrap();
+		buf.write_mat4::<BE>(&m).unwrap();
+		buf.write_mat3::<LE>(&Mat3::identity()).unwrap();
+		assert_eq!(&buf[..4], &1.0f32.to_be_bytes());
+		assert_eq!(&buf[28..32], &1.0f32.to_be_bytes());
+
+		let mut data = &buf[..];
+		assert_eq!(data.read_vec3::<BE>().unwrap(), Vec3::new(1.0, -2.0, 0.5));
+		assert_eq!(data.read_quat::<LE>().unwrap(), q);
+		assert_eq!(data.read_mat4::<BE>().unwrap(), m);
+		assert_eq!(data.read_mat3::<LE>().unwrap(), Mat3::identity());
+		assert!(data.read_vec2::<LE>().is_err());
+	}
 }
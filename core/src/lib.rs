@@ -1,6 +1,12 @@
+#[cfg(feature = "binary")]
+pub mod binary;
+
 #[cfg(feature = "bit_ext")]
 pub mod bit_ext;
 
+pub mod format;
+pub mod input;
+
 #[cfg(feature = "io_ext")]
 pub mod io_ext;
 
@@ -1,61 +1,269 @@
+//! Shared nom combinators for text formats. They work on plain `&str` input as well as on [`Span`], which
+//! tracks where it is in the source so that [`TextError`] can report the line and column of a failure.
+
+use std::{
+	fmt,
+	ops::{
+		Range,
+		RangeFrom,
+		RangeTo
+	}
+};
+
 use nom::{
+	AsBytes,
+	AsChar,
+	branch::alt,
 	bytes::complete::{
 		tag,
-		take_while
+		take_until,
+		take_while,
+		take_while1
 	},
 	character::complete::{
 		char,
+		digit1,
+		hex_digit1,
 		multispace0,
-		not_line_ending
+		multispace1,
+		not_line_ending,
+		one_of
+	},
+	combinator::{
+		all_consuming,
+		map_res,
+		opt,
+		recognize,
+		value
 	},
-	combinator::value,
-	error::ParseError,
+	Compare,
+	error::{
+		ContextError,
+		ErrorKind,
+		FromExternalError,
+		ParseError
+	},
+	FindSubstring,
 	IResult,
-	multi::count,
-	number::complete::{
-		float,
-		le_f32,
-		u8
+	InputIter,
+	InputLength,
+	InputTake,
+	InputTakeAtPosition,
+	multi::{
+		count,
+		many0
 	},
+	number::complete::float,
+	Offset,
+	ParseTo,
 	Parser,
 	sequence::{
 		delimited,
-		pair
+		pair,
+		preceded,
+		terminated,
+		tuple
 	},
+	Slice,
 };
 
-use std::str::{
-	from_utf8,
-	Utf8Error
-};
+use nom_locate::LocatedSpan;
 
-use ultraviolet::vec::{
-	Vec2,
-	Vec3,
-	Vec4
+use ultraviolet::{
+	mat::Mat4,
+	vec::{
+		Vec2,
+		Vec3,
+		Vec4
+	}
 };
 
+/// Text input that tracks its line, column and byte offset in the source
+pub type Span<'a> = LocatedSpan<&'a str>;
+
+/// Input types the combinators accept, namely `&str` and [`Span`]
+pub trait Text:
+	Clone + PartialEq + Offset + AsBytes + InputLength + InputTake + ParseTo<f32> +
+	Slice<Range<usize>> + Slice<RangeFrom<usize>> + Slice<RangeTo<usize>> +
+	InputIter<Item = char> + InputTakeAtPosition<Item = char> +
+	Compare<&'static str> + for<'b> Compare<&'b [u8]> + FindSubstring<&'static str>
+{
+	/// Returns the input as a string slice
+	fn as_str(&self) -> &str;
+}
+
+impl Text for &str {
+	fn as_str(&self) -> &str {
+		self
+	}
+}
+
+impl<'a> Text for Span<'a> {
+	fn as_str(&self) -> &str {
+		self.fragment()
+	}
+}
+
+/// What went wrong in a [`TextError`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextErrorKind {
+	/// A nom parser failed
+	Nom(ErrorKind),
+
+	/// A specific character was expected
+	Char(char),
+
+	/// Converting the parsed text failed, such as a number out of range
+	External(String),
+
+	/// The input ended early
+	Incomplete,
+}
+
+/// A parse error located in the source text. Lines and columns start at 1, with columns counted in
+/// characters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextError {
+	pub line: u32,
+	pub column: usize,
+	pub offset: usize,
+	pub kind: TextErrorKind,
+
+	/// Contexts added with [`nom::error::context`], innermost first
+	pub context: Vec<&'static str>,
+}
+
+impl TextError {
+	fn new(input: &Span, kind: TextErrorKind) -> TextError {
+		TextError {
+			line: input.location_line(),
+			column: input.get_utf8_column(),
+			offset: input.location_offset(),
+			kind,
+			context: vec![],
+		}
+	}
+}
+
+impl fmt::Display for TextError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}:{}: ", self.line, self.column)?;
+
+		match &self.kind {
+			TextErrorKind::Nom(kind) => write!(f, "{}", kind.description())?,
+			TextErrorKind::Char(c) => write!(f, "expected {:?}", c)?,
+			TextErrorKind::External(e) => write!(f, "{}", e)?,
+			TextErrorKind::Incomplete => write!(f, "unexpected end of input")?,
+		}
+
+		for c in self.context.iter() {
+			write!(f, " in {}", c)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl std::error::Error for TextError {}
+
+impl<'a> ParseError<Span<'a>> for TextError {
+	fn from_error_kind(input: Span<'a>, kind: ErrorKind) -> Self {
+		TextError::new(&input, TextErrorKind::Nom(kind))
+	}
+
+	fn append(_: Span<'a>, _: ErrorKind, other: Self) -> Self {
+		other
+	}
+
+	fn from_char(input: Span<'a>, c: char) -> Self {
+		TextError::new(&input, TextErrorKind::Char(c))
+	}
+
+	fn or(self, other: Self) -> Self {
+		// the alternative that got further is more likely the one that was meant
+		if other.offset >= self.offset { other } else { self }
+	}
+}
+
+impl<'a> ContextError<Span<'a>> for TextError {
+	fn add_context(_: Span<'a>, ctx: &'static str, mut other: Self) -> Self {
+		other.context.push(ctx);
+		other
+	}
+}
+
+impl<'a, E: fmt::Display> FromExternalError<Span<'a>, E> for TextError {
+	fn from_external_error(input: Span<'a>, _: ErrorKind, e: E) -> Self {
+		TextError::new(&input, TextErrorKind::External(e.to_string()))
+	}
+}
+
+/// Runs a parser over the whole of `input`, failing on trailing text, and locates any error
+pub fn parse_all<'a, O, F>(input: &'a str, parser: F) -> Result<O, TextError>
+where
+	F: Parser<Span<'a>, O, TextError>,
+{
+	let input = Span::new(input);
+
+	match all_consuming(parser)(input) {
+		Ok((_, o)) => Ok(o),
+		Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => Err(e),
+		Err(nom::Err::Incomplete(_)) => Err(TextError::new(&input.slice(input.len()..), TextErrorKind::Incomplete)),
+	}
+}
+
 /// Parses and discards a C-style line comment
-pub fn c_comment<'a, E>(input: &'a str) -> IResult<&'a str, (), E>
+pub fn c_comment<I, E>(input: I) -> IResult<I, (), E>
 where
-	E: ParseError<&'a str>
+	I: Text,
+	E: ParseError<I>,
 {
 	value((), pair(tag("//"), not_line_ending))(input)
 }
 
+/// Parses and discards a C-style block comment, which doesn't nest
+pub fn block_comment<I, E>(input: I) -> IResult<I, (), E>
+where
+	I: Text,
+	E: ParseError<I>,
+{
+	value((), tuple((tag("/*"), take_until("*/"), tag("*/"))))(input)
+}
+
+/// Parses and discards any whitespace and C-style comments
+pub fn skip<I, E>(input: I) -> IResult<I, (), E>
+where
+	I: Text,
+	E: ParseError<I>,
+{
+	value((), many0(alt((value((), multispace1), c_comment, block_comment))))(input)
+}
+
 /// Parses the inner contents of a pair of curly braces
-pub fn curly<'a, F, O, E>(inner: F) -> impl FnMut(&'a str) -> IResult<&'a str, O, E>
+pub fn curly<I, F, O, E>(inner: F) -> impl FnMut(I) -> IResult<I, O, E>
 where
-	E: ParseError<&'a str>,
-	F: Parser<&'a str, O, E>,
+	I: Text,
+	E: ParseError<I>,
+	F: Parser<I, O, E>,
 {
 	delimited(ws(char('{')), inner, ws(char('}')))
 }
 
+/// Parses a block such as `name { ... }`, returning the output of `inner`
+pub fn named_block<I, F, O, E>(name: &'static str, inner: F) -> impl FnMut(I) -> IResult<I, O, E>
+where
+	I: Text,
+	E: ParseError<I>,
+	F: Parser<I, O, E>,
+{
+	preceded(ws(tag(name)), curly(inner))
+}
+
 /// Parses the contents of a double-quoted string
-pub fn double_quoted<'a, E>(input: &'a str) -> IResult<&'a str, &'a str, E>
+pub fn double_quoted<I, E>(input: I) -> IResult<I, I, E>
 where
-	E: ParseError<&'a str>
+	I: Text,
+	E: ParseError<I>,
 {
 	delimited(
 		char('"'),
@@ -64,50 +272,233 @@ where
 	)(input)
 }
 
+/// Parses a C-style identifier, made of ASCII letters, digits and underscores and not starting with a digit
+pub fn identifier<I, E>(input: I) -> IResult<I, I, E>
+where
+	I: Text,
+	E: ParseError<I>,
+{
+	recognize(pair(
+		take_while1(|c: char| c.is_ascii_alphabetic() || c == '_'),
+		take_while(|c: char| c.is_ascii_alphanumeric() || c == '_'),
+	))(input)
+}
+
+/// Parses a name that is either an identifier or double-quoted, returning it without the quotes
+pub fn quoted_identifier<I, E>(input: I) -> IResult<I, I, E>
+where
+	I: Text,
+	E: ParseError<I>,
+{
+	alt((double_quoted, identifier))(input)
+}
+
 /// Parses and discards a hash-prefixed ('#') line comment
-pub fn hash_comment<'a, E>(input: &'a str) -> IResult<&'a str, (), E>
+pub fn hash_comment<I, E>(input: I) -> IResult<I, (), E>
 where
-	E: ParseError<&'a str>
+	I: Text,
+	E: ParseError<I>,
 {
 	value((), pair(char('#'), not_line_ending))(input)
 }
 
+/// Parses a decimal integer with an optional sign
+pub fn signed_int<I, E>(input: I) -> IResult<I, i64, E>
+where
+	I: Text,
+	E: ParseError<I> + FromExternalError<I, std::num::ParseIntError>,
+{
+	map_res(recognize(pair(opt(one_of("+-")), digit1)), |s: I| s.as_str().parse())(input)
+}
+
+/// Parses a `0x` or `0X` prefixed hexadecimal integer
+pub fn hex_int<I, E>(input: I) -> IResult<I, u64, E>
+where
+	I: Text,
+	E: ParseError<I> + FromExternalError<I, std::num::ParseIntError>,
+{
+	map_res(preceded(alt((tag("0x"), tag("0X"))), hex_digit1), |s: I| u64::from_str_radix(s.as_str(), 16))(input)
+}
+
+/// Parses a list of `n` whitespace-delimited floats
+pub fn floatsws<I, E>(n: usize) -> impl FnMut(I) -> IResult<I, Vec<f32>, E>
+where
+	I: Text,
+	<I as InputIter>::IterElem: Clone,
+	E: ParseError<I>,
+{
+	count(ws(float), n)
+}
+
+/// Parses a [`Vec2`] of whitespace-delimited floats
+pub fn vec2ws<I, E>(input: I) -> IResult<I, Vec2, E>
+where
+	I: Text,
+	<I as InputIter>::IterElem: Clone,
+	E: ParseError<I>,
+{
+	let (input, f) = floatsws(2)(input)?;
+
+	Ok((input, Vec2::new(f[0], f[1])))
+}
+
 /// Parses a [`Vec3`] of whitespace-delimited floats
-pub fn vec3ws<'a, E>(input: &'a str) -> IResult<&'a str, Vec3, E>
+pub fn vec3ws<I, E>(input: I) -> IResult<I, Vec3, E>
 where
-	E: ParseError<&'a str>
+	I: Text,
+	<I as InputIter>::IterElem: Clone,
+	E: ParseError<I>,
 {
-	let (input, f3vec) = count(ws(float), 3)(input)?;
+	let (input, f3vec) = floatsws(3)(input)?;
 
 	Ok((input, Vec3::new(f3vec[0], f3vec[1], f3vec[2])))
 }
 
+/// Parses a [`Vec4`] of whitespace-delimited floats
+pub fn vec4ws<I, E>(input: I) -> IResult<I, Vec4, E>
+where
+	I: Text,
+	<I as InputIter>::IterElem: Clone,
+	E: ParseError<I>,
+{
+	let (input, f) = floatsws(4)(input)?;
+
+	Ok((input, Vec4::new(f[0], f[1], f[2], f[3])))
+}
+
+/// Parses a [`Mat4`] of 16 whitespace-delimited floats, in column major order
+pub fn mat4ws<I, E>(input: I) -> IResult<I, Mat4, E>
+where
+	I: Text,
+	<I as InputIter>::IterElem: Clone,
+	E: ParseError<I>,
+{
+	let (input, c) = count(vec4ws, 4)(input)?;
+
+	Ok((input, Mat4::new(c[0], c[1], c[2], c[3])))
+}
+
+/// Parses a list of `n` floats separated by `sep`, such as the comma-delimited lists of DirectX .x files
+pub fn floats_sep<I, E>(n: usize, sep: char) -> impl FnMut(I) -> IResult<I, Vec<f32>, E>
+where
+	I: Text,
+	<I as InputIter>::IterElem: Clone,
+	E: ParseError<I>,
+{
+	move |input: I| {
+		let (mut input, first) = ws(float)(input)?;
+		let mut out = Vec::with_capacity(n);
+		out.push(first);
+
+		for _ in 1..n {
+			let (rest, f) = preceded(char(sep), ws(float))(input)?;
+			out.push(f);
+			input = rest;
+		}
+
+		Ok((input, out))
+	}
+}
+
 /// A combinator that takes a parser `inner` and produces a parser that also consumes both leading and
 /// trailing whitespace, returning the output of `inner`.
 ///
 /// From https://github.com/Geal/nom/blob/master/doc/nom_recipes.md with minor edits
-pub fn ws<'a, F, O, E>(inner: F) -> impl FnMut(&'a str) -> IResult<&'a str, O, E>
+pub fn ws<I, F, O, E>(inner: F) -> impl FnMut(I) -> IResult<I, O, E>
 where
-	E: ParseError<&'a str>,
-	F: Parser<&'a str, O, E>,
+	I: InputTakeAtPosition,
+	<I as InputTakeAtPosition>::Item: AsChar + Clone,
+	E: ParseError<I>,
+	F: Parser<I, O, E>,
 {
 	delimited(multispace0, inner, multispace0)
 }
 
+/// Like [`ws`], but also consumes C-style comments
+pub fn wsc<I, F, O, E>(inner: F) -> impl FnMut(I) -> IResult<I, O, E>
+where
+	I: Text,
+	E: ParseError<I>,
+	F: Parser<I, O, E>,
+{
+	delimited(skip, inner, skip)
+}
+
+/// Parses `inner` followed by a terminating `end` character, with optional whitespace before it
+pub fn terminated_by<I, F, O, E>(inner: F, end: char) -> impl FnMut(I) -> IResult<I, O, E>
+where
+	I: Text,
+	E: ParseError<I>,
+	F: Parser<I, O, E>,
+{
+	terminated(inner, preceded(multispace0, char(end)))
+}
+
 #[cfg(test)]
 mod tests {
-    use ultraviolet::vec::Vec3;
-    use nom::character::complete::alphanumeric1;
-    use nom::error::Error;
+	use nom::{
+		character::complete::alphanumeric1,
+		combinator::cut,
+		error::{
+			context,
+			Error
+		},
+		multi::many1
+	};
+
+	use ultraviolet::vec::Vec3;
+
+	use super::*;
 
 	#[test]
 	fn test_double_quoted() {
-		assert_eq!(super::double_quoted::<'_, Error<&str>>("\"Hi there\""), Ok(("", "Hi there")));
-		assert_ne!(super::double_quoted::<'_, Error<&str>>("Hi there"), Ok(("", "Hi there")));
+		assert_eq!(super::double_quoted::<_, Error<&str>>("\"Hi there\""), Ok(("", "Hi there")));
+		assert_ne!(super::double_quoted::<_, Error<&str>>("Hi there"), Ok(("", "Hi there")));
 	}
 
 	#[test]
 	fn test_vec3ws() {
-        assert_eq!(super::vec3ws::<'_, Error<&str>>("0.1 2.3  4.5"), Ok(("", Vec3::new(0.1, 2.3, 4.5))));
+		assert_eq!(super::vec3ws::<_, Error<&str>>("0.1 2.3  4.5"), Ok(("", Vec3::new(0.1, 2.3, 4.5))));
+	}
+
+	#[test]
+	fn test_combinators() {
+		assert_eq!(signed_int::<_, Error<&str>>("-42 "), Ok((" ", -42)));
+		assert_eq!(hex_int::<_, Error<&str>>("0xFf"), Ok(("", 255)));
+		assert_eq!(quoted_identifier::<_, Error<&str>>("_bone01 x"), Ok((" x", "_bone01")));
+		assert_eq!(quoted_identifier::<_, Error<&str>>("\"Box 01\""), Ok(("", "Box 01")));
+		assert!(identifier::<_, Error<&str>>("1abc").is_err());
+
+		let (rest, _) = skip::<_, Error<&str>>("  /* a\n b */ // c\n  x").unwrap();
+		assert_eq!(rest, "x");
+
+		let (_, m) = mat4ws::<_, Error<&str>>("1 0 0 0  0 1 0 0  0 0 1 0  5 6 7 1").unwrap();
+		assert_eq!(m, Mat4::from_translation(Vec3::new(5.0, 6.0, 7.0)));
+		assert_eq!(floats_sep::<_, Error<&str>>(3, ',')("1.0, 2.0 ,3;"), Ok((";", vec![1.0, 2.0, 3.0])));
+
+		let block = named_block("*MESH", many1(ws(alphanumeric1::<_, Error<&str>>)));
+		assert_eq!(terminated_by(block, ';')("*MESH { a b }  ;"), Ok(("", vec!["a", "b"])));
+	}
+
+	#[test]
+	fn test_located_error() {
+		// cut commits to a vertex once its tag matched, so the error points at the bad number
+		let vertex = preceded(tag("*VERTEX"), cut(context("vertex", vec3ws)));
+		let mesh = named_block("*MESH", many0(wsc(vertex)));
+		let src = "*MESH {\n  *VERTEX 1 2 3\n  /* second */ *VERTEX 4 x 6\n}";
+
+		let e = parse_all(src, mesh).unwrap_err();
+		assert_eq!((e.line, e.column), (3, 26));
+		assert_eq!(e.to_string(), "3:26: Float in vertex");
+
+		let e = parse_all("\"abc", double_quoted).unwrap_err();
+		assert_eq!((e.line, e.column), (1, 5));
+		assert_eq!(e.kind, TextErrorKind::Char('"'));
+
+		let n = parse_all("-17", signed_int).unwrap();
+		assert_eq!(n, -17);
+
+		let e = parse_all("99999999999999999999", signed_int).unwrap_err();
+		assert!(matches!(e.kind, TextErrorKind::External(_)));
 	}
 }
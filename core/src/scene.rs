@@ -1,6 +1,25 @@
-use std::collections::HashMap;
+pub mod anim;
+pub mod bounds;
+pub mod camera;
+pub mod coords;
+pub mod light;
+pub mod material;
+pub mod mesh_ops;
+pub mod morph;
+pub mod skeleton;
+pub mod stats;
+pub mod validate;
+
+use std::collections::{
+	HashMap,
+	VecDeque
+};
 
 use ultraviolet::{
+	mat::{
+		Mat3,
+		Mat4
+	},
 	rotor::Rotor3,
 	vec::{
 		Vec2,
@@ -9,17 +28,40 @@ use ultraviolet::{
 	}
 };
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+use anim::Clip;
+use camera::Camera;
+use coords::Handedness;
+use light::Light;
+use material::{
+	AlphaMode,
+	BlendMode,
+	ShadingModel,
+	TextureRef
+};
+use morph::MorphTarget;
+use skeleton::{
+	JointWeight,
+	Skeleton
+};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum MatPropValueID {
 	Alpha,
+	Ambient,
 	Bump,
+
+	/// Diffuse color for Phong materials, base color for metallic-roughness materials
 	Diffuse,
 	Displacement,
 	Emission,
 	Metallic,
 	Normal,
+	Occlusion,
 	Reflective,
 	Roughness,
+
+	/// Phong specular exponent
+	Shininess,
 	Specular,
 }
 
@@ -32,13 +74,44 @@ pub enum MatPropValue {
 	Vector4(Vec4),
 }
 
+impl MatPropValue {
+	/// Returns the value as a single float, taking the first component of vectors
+	pub fn as_float(&self) -> Option<f32> {
+		match self {
+			MatPropValue::Float(f) => Some(*f),
+			MatPropValue::Vector2(v) => Some(v.x),
+			MatPropValue::Vector3(v) => Some(v.x),
+			MatPropValue::Vector4(v) => Some(v.x),
+			MatPropValue::Text(_) => None,
+		}
+	}
+
+	/// Returns the value as an RGB color, spreading floats across all channels
+	pub fn as_rgb(&self) -> Option<Vec3> {
+		match self {
+			MatPropValue::Float(f) => Some(Vec3::broadcast(*f)),
+			MatPropValue::Vector3(v) => Some(*v),
+			MatPropValue::Vector4(v) => Some(v.truncated()),
+			_ => None,
+		}
+	}
+}
+
 /// [`HashMap`] type alias for material properties
 pub type MaterialPropertyMap = HashMap<MatPropValueID, MatPropValue>;
 
-#[derive(Clone, Debug)]
+/// [`HashMap`] type alias for material texture slots
+pub type MaterialTextureMap = HashMap<MatPropValueID, TextureRef>;
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Material {
 	pub name: String,
 	pub properties: MaterialPropertyMap,
+	pub textures: MaterialTextureMap,
+	pub shading: ShadingModel,
+	pub alpha_mode: AlphaMode,
+	pub blend_mode: BlendMode,
+	pub double_sided: bool,
 	pub shader: Option<String>,
 }
 
@@ -57,33 +130,224 @@ pub enum ObjRef {
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum NodeData {
+	Camera(Camera),
 	Geometry(Mesh),
+	Light(Light),
 	Null,
 }
 
+/// Location of a node within a tree, as a list of child indices starting from the root
+pub type NodePath = Vec<usize>;
+
 /// Base type of the 3D environment
 #[derive(Clone, Debug, PartialEq)]
 pub struct Node {
 	pub id: ObjRef,
+
+	/// Depth-first index of the parent node, counting the root as 0
 	pub parent: Option<usize>,
 	pub children: Vec<Node>,
 	pub data: NodeData,
 	pub translation: Vec3,
 	pub rotation: Rotor3,
 	pub scale: Vec3,
+
+	/// Current weights of the node's morph targets
+	pub morph_weights: Vec<f32>,
 }
 
 impl Node {
 	pub fn new(id: ObjRef, parent: Option<usize>) -> Node {
 		Node {
-			id: id,
-			parent: parent,
+			id,
+			parent,
 			children: vec![],
 			data: NodeData::Null,
 			translation: Vec3::zero(),
 			rotation: Rotor3::identity(),
 			scale: Vec3::one(),
+			morph_weights: vec![],
+		}
+	}
+
+	/// Returns the node's transform relative to its parent
+	pub fn local_matrix(&self) -> Mat4 {
+		trs_matrix(self.translation, self.rotation, self.scale)
+	}
+
+	/// Sets the node's translation, rotation and scale from a matrix relative to its parent
+	pub fn set_local_matrix(&mut self, m: &Mat4) {
+		let (t, r, s) = decompose_matrix(m);
+		self.translation = t;
+		self.rotation = r;
+		self.scale = s;
+	}
+
+	/// Returns the descendant at the specified path, relative to this node
+	pub fn get(&self, path: &[usize]) -> Option<&Node> {
+		path.iter().try_fold(self, |node, i| node.children.get(*i))
+	}
+
+	/// Returns the descendant at the specified path mutably, relative to this node
+	pub fn get_mut(&mut self, path: &[usize]) -> Option<&mut Node> {
+		path.iter().try_fold(self, |node, i| node.children.get_mut(*i))
+	}
+
+	/// Returns the path to the first node in depth-first order with the specified ID
+	pub fn find(&self, id: &ObjRef) -> Option<NodePath> {
+		self.iter_depth_first().find(|(_, n)| n.id == *id).map(|(p, _)| p)
+	}
+
+	/// Iterates this node and its descendants in depth-first (pre-order) order
+	pub fn iter_depth_first(&self) -> DepthFirstIter<'_> {
+		DepthFirstIter {
+			stack: vec![(vec![], self)],
+		}
+	}
+
+	/// Iterates this node and its descendants in breadth-first order
+	pub fn iter_breadth_first(&self) -> BreadthFirstIter<'_> {
+		BreadthFirstIter {
+			queue: VecDeque::from([(vec![], self)]),
+		}
+	}
+
+	/// Calls `f` on this node and every descendant in depth-first order
+	pub fn visit_mut<F>(&mut self, f: &mut F)
+	where
+		F: FnMut(&mut Node),
+	{
+		f(self);
+		for child in self.children.iter_mut() {
+			child.visit_mut(f);
+		}
+	}
+
+	/// Returns the transform from the specified descendant's space into this node's parent space
+	pub fn world_matrix(&self, path: &[usize]) -> Option<Mat4> {
+		let mut node = self;
+		let mut m = self.local_matrix();
+
+		for i in path.iter() {
+			node = node.children.get(*i)?;
+			m = m * node.local_matrix();
+		}
+
+		Some(m)
+	}
+
+	/// Returns the transform from this node's parent space into the specified descendant's space
+	pub fn world_to_local_matrix(&self, path: &[usize]) -> Option<Mat4> {
+		self.world_matrix(path).map(|m| m.inversed())
+	}
+
+	/// Returns the world transform of this node and every descendant, in depth-first order
+	pub fn world_matrices(&self) -> Vec<(NodePath, Mat4)> {
+		let mut out = vec![];
+		let mut stack = vec![(vec![], self, self.local_matrix())];
+
+		while let Some((path, node, m)) = stack.pop() {
+			for (i, child) in node.children.iter().enumerate().rev() {
+				let mut child_path = path.clone();
+				child_path.push(i);
+				stack.push((child_path, child, m * child.local_matrix()));
+			}
+			out.push((path, m));
+		}
+
+		out
+	}
+
+	/// Moves the node at `path` under the node at `new_parent`, adjusting its local transform so its world
+	/// placement is unchanged. Returns the node's new path, or `None` if either path is invalid or the new
+	/// parent lies within the moved node.
+	pub fn reparent(&mut self, path: &[usize], new_parent: &[usize]) -> Option<NodePath> {
+		if path.is_empty() || new_parent.starts_with(path) {
+			return None;
+		}
+
+		let world = self.world_matrix(path)?;
+		let parent_world = self.world_matrix(new_parent)?;
+
+		// detach
+		let (idx, old_parent) = path.split_last()?;
+		let mut node = self.get_mut(old_parent)?.children.remove(*idx);
+
+		// removing the node shifts its later siblings, which may include the new parent's ancestry
+		let mut new_parent = new_parent.to_vec();
+		if new_parent.len() > old_parent.len() && new_parent.starts_with(old_parent) &&
+			new_parent[old_parent.len()] > *idx
+		{
+			new_parent[old_parent.len()] -= 1;
+		}
+
+		node.set_local_matrix(&(parent_world.inversed() * world));
+
+		let parent = self.get_mut(&new_parent)?;
+		parent.children.push(node);
+		new_parent.push(parent.children.len() - 1);
+
+		self.update_parent_indices();
+
+		Some(new_parent)
+	}
+
+	/// Rewrites every descendant's [`Node::parent`] to match the actual nesting
+	pub fn update_parent_indices(&mut self) {
+		fn walk(node: &mut Node, index: usize, next: &mut usize) {
+			for child in node.children.iter_mut() {
+				child.parent = Some(index);
+				let i = *next;
+				*next += 1;
+				walk(child, i, next);
+			}
+		}
+
+		self.parent = None;
+		let mut next = 1;
+		walk(self, 0, &mut next);
+	}
+}
+
+/// Depth-first node iterator, created by [`Node::iter_depth_first`]
+pub struct DepthFirstIter<'a> {
+	stack: Vec<(NodePath, &'a Node)>,
+}
+
+impl<'a> Iterator for DepthFirstIter<'a> {
+	type Item = (NodePath, &'a Node);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let (path, node) = self.stack.pop()?;
+
+		for (i, child) in node.children.iter().enumerate().rev() {
+			let mut child_path = path.clone();
+			child_path.push(i);
+			self.stack.push((child_path, child));
 		}
+
+		Some((path, node))
+	}
+}
+
+/// Breadth-first node iterator, created by [`Node::iter_breadth_first`]
+pub struct BreadthFirstIter<'a> {
+	queue: VecDeque<(NodePath, &'a Node)>,
+}
+
+impl<'a> Iterator for BreadthFirstIter<'a> {
+	type Item = (NodePath, &'a Node);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let (path, node) = self.queue.pop_front()?;
+
+		for (i, child) in node.children.iter().enumerate() {
+			let mut child_path = path.clone();
+			child_path.push(i);
+			self.queue.push_back((child_path, child));
+		}
+
+		Some((path, node))
 	}
 }
 
@@ -100,16 +364,24 @@ pub struct Vertex {
 	pub normal: Option<Vec3>,
 	pub uvw: Vec<Vec3>,
 	pub color: Option<Vec4>,
+
+	/// Tangent in XYZ, with the bitangent's sign in W
+	pub tangent: Option<Vec4>,
+
+	/// Skinning influences, indexing into the owning mesh's skeleton
+	pub weights: Vec<JointWeight>,
 }
 
 impl Vertex {
 	pub fn new(groups: Option<Vec<usize>>) -> Vertex {
 		Vertex {
-			groups: groups,
+			groups,
 			position: Vec4::zero(),
 			normal: None,
 			uvw: vec![],
 			color: None,
+			tangent: None,
+			weights: vec![],
 		}
 	}
 }
@@ -126,59 +398,152 @@ pub struct Mesh {
 	pub vertex_groups: Vec<VertexGroup>,
 	pub vertices: Vec<Vertex>,
 	pub faces: Vec<Face>,
+	pub morph_targets: Vec<MorphTarget>,
+
+	/// Index into [`Scene::skeletons`] of the rig deforming this mesh
+	pub skeleton: Option<usize>,
+}
+
+impl Face {
+	/// Returns the face's vertex indices
+	pub fn indices(&self) -> &[usize] {
+		match self {
+			Face::Triangle(t) => t,
+			Face::Quad(q) => q,
+			Face::Ngon(n) => n,
+		}
+	}
+
+	/// Returns the face's vertex indices mutably
+	pub fn indices_mut(&mut self) -> &mut [usize] {
+		match self {
+			Face::Triangle(t) => t,
+			Face::Quad(q) => q,
+			Face::Ngon(n) => n,
+		}
+	}
+
+	/// Builds the smallest face variant able to hold the specified indices
+	pub fn from_indices(indices: &[usize]) -> Face {
+		match indices.len() {
+			3 => Face::Triangle([indices[0], indices[1], indices[2]]),
+			4 => Face::Quad([indices[0], indices[1], indices[2], indices[3]]),
+			_ => Face::Ngon(indices.to_vec()),
+		}
+	}
 }
 
 impl Mesh {
-	/// Removes the specified vertex and any faces it's a part of
-	pub fn drop_vertex(&mut self, v: Vertex) {
-		let faces = self.find_vertex_faces(&v);
-		for i in faces.iter() {
-			self.faces.remove(*i);
-		}
-
-		/*// Remove references from possible groups
-		if let Some(groups) = v.groups {
-			for group in groups.iter() {
-				for i in self.vertex_groups[group].children.iter_mut() {
-					if v == self.vertices[*i] {
-						self.vertex_groups[group].children.remove(*i);
-					}
-				}
+	/// Removes the vertex at the specified index and any faces it's a part of
+	pub fn drop_vertex(&mut self, index: usize) {
+		self.retain_vertices(|i, _| i != index);
+	}
+
+	/// Returns a list of face indices holding the specified vertex
+	pub fn find_vertex_faces(&self, vert: &Vertex) -> Vec<usize> {
+		self.faces.iter().enumerate()
+			.filter(|(_, f)| f.indices().iter().any(|v| *vert == self.vertices[*v]))
+			.map(|(i, _)| i)
+			.collect()
+	}
+
+	/// Keeps only the vertices for which `keep` returns true, removing any faces that used a dropped vertex
+	pub fn retain_vertices<F>(&mut self, mut keep: F)
+	where
+		F: FnMut(usize, &Vertex) -> bool,
+	{
+		let mut next = 0;
+		let remap: Vec<Option<usize>> = self.vertices.iter().enumerate().map(|(i, v)| {
+			if keep(i, v) {
+				next += 1;
+				Some(next - 1)
+			} else {
+				None
 			}
-		}*/
+		}).collect();
 
-		for i in 0..self.vertices.len() {
-			if v == self.vertices[i] {
-				self.vertices.remove(i);
+		self.remap_vertices(&remap);
+	}
+
+	/// Removes vertices not referenced by any face
+	pub fn remove_unused_vertices(&mut self) {
+		let mut used = vec![false; self.vertices.len()];
+		for face in self.faces.iter() {
+			// out of range indices are left for validation to report
+			for i in face.indices().iter() {
+				if let Some(u) = used.get_mut(*i) {
+					*u = true;
+				}
 			}
 		}
+
+		self.retain_vertices(|i, _| used[i]);
 	}
 
-	/// Returns a list of face indices holding the specified vertex
-	pub fn find_vertex_faces(&self, vert: &Vertex) -> Vec<usize> {
-		let mut indices = vec![];
-
-		for i in 0..self.faces.len() {
-			match &self.faces[i] {
-				Face::Triangle(t) => for v in t.iter() {
-					if *vert == self.vertices[*v] {
-						indices.push(i);
-					}
-				},
-				Face::Quad(q) => for v in q.iter() {
-					if *vert == self.vertices[*v] {
-						indices.push(i);
-					}
-				},
-				Face::Ngon(n) => for v in n.iter() {
-					if *vert == self.vertices[*v] {
-						indices.push(i);
-					}
+	/// Rebuilds vertex group membership after vertices were duplicated, where `origin[i]` is the old vertex that
+	/// new vertex `i` was copied from. Every copy joins the groups its original was in.
+	pub fn remap_group_origins(&mut self, origin: &[usize]) {
+		let count = origin.iter().map(|o| o + 1).max().unwrap_or(0);
+		let mut copies = vec![vec![]; count];
+		for (i, o) in origin.iter().enumerate() {
+			copies[*o].push(i);
+		}
+
+		for group in self.vertex_groups.iter_mut() {
+			group.children = group.children.iter()
+				.filter_map(|c| copies.get(*c))
+				.flatten()
+				.copied()
+				.collect();
+		}
+	}
+
+	/// Moves every vertex `i` to `remap[i]`, dropping it if `None`. When several vertices map to the same
+	/// index, the first is kept. Faces using a dropped vertex are removed, and all other references are
+	/// rewritten.
+	pub fn remap_vertices(&mut self, remap: &[Option<usize>]) {
+		let count = remap.iter().flatten().map(|i| i + 1).max().unwrap_or(0);
+		let mut slots: Vec<Option<Vertex>> = vec![None; count];
+
+		for (v, new) in self.vertices.drain(..).zip(remap.iter()) {
+			if let Some(i) = new {
+				if slots[*i].is_none() {
+					slots[*i] = Some(v);
 				}
 			}
 		}
+		self.vertices = slots.into_iter().flatten().collect();
 
-		indices
+		self.faces.retain_mut(|face| {
+			for i in face.indices_mut().iter_mut() {
+				match remap.get(*i).copied().flatten() {
+					Some(j) => *i = j,
+					None => return false,
+				}
+			}
+
+			true
+		});
+
+		for group in self.vertex_groups.iter_mut() {
+			let mut children: Vec<usize> = group.children.iter()
+				.filter_map(|i| remap.get(*i).copied().flatten())
+				.collect();
+			children.dedup();
+			group.children = children;
+		}
+
+		for target in self.morph_targets.iter_mut() {
+			let mut seen = vec![false; count];
+			target.deltas.retain_mut(|d| match remap.get(d.vertex).copied().flatten() {
+				Some(j) if !seen[j] => {
+					seen[j] = true;
+					d.vertex = j;
+					true
+				},
+				_ => false,
+			});
+		}
 	}
 }
 
@@ -187,19 +552,120 @@ impl Mesh {
 pub struct Scene {
 	pub root: Node,
 	pub materials: Vec<Material>,
+	pub skeletons: Vec<Skeleton>,
+	pub animations: Vec<Clip>,
 	pub up: UpAxis,
+	pub handedness: Handedness,
+
+	/// Length of one unit in metres
+	pub unit_scale: f32,
 }
 
 impl Scene {
 	pub fn new(root: Node) -> Scene {
 		Scene {
-			root: root,
+			root,
 			materials: vec![],
+			skeletons: vec![],
+			animations: vec![],
 			up: UpAxis::Y,
+			handedness: Handedness::Right,
+			unit_scale: 1.0,
 		}
 	}
+
+	/// Returns the world transform of the first node with the specified ID
+	pub fn world_matrix(&self, id: &ObjRef) -> Option<Mat4> {
+		self.root.world_matrix(&self.root.find(id)?)
+	}
 }
 
 pub fn vec4_to_rot3(v: Vec4) -> Rotor3 {
 	Rotor3::from_quaternion_array([v.x, v.y, v.z, v.w])
 }
+
+/// Builds a transformation matrix from translation, rotation and scale, applied in reverse order
+pub fn trs_matrix(translation: Vec3, rotation: Rotor3, scale: Vec3) -> Mat4 {
+	Mat4::from_translation(translation) * rotation.into_matrix().into_homogeneous() *
+		Mat4::from_nonuniform_scale(scale)
+}
+
+/// Splits an affine transformation matrix into translation, rotation and scale.
+/// Shear is discarded, and a mirroring is expressed as a negative X scale.
+pub fn decompose_matrix(m: &Mat4) -> (Vec3, Rotor3, Vec3) {
+	let translation = m.extract_translation();
+	let basis = m.truncate();
+
+	let mut scale = Vec3::new(basis.cols[0].mag(), basis.cols[1].mag(), basis.cols[2].mag());
+	if basis.determinant() < 0.0 {
+		scale.x = -scale.x;
+	}
+
+	let rotation = if scale.x == 0.0 || scale.y == 0.0 || scale.z == 0.0 {
+		Rotor3::identity()
+	} else {
+		Mat3::new(basis.cols[0] / scale.x, basis.cols[1] / scale.y, basis.cols[2] / scale.z)
+			.into_rotor3()
+			.normalized()
+	};
+
+	(translation, rotation, scale)
+}
+
+#[cfg(test)]
+mod tests {
+	use ultraviolet::{
+		rotor::Rotor3,
+		vec::Vec3
+	};
+
+	use super::*;
+
+	fn approx(a: Vec3, b: Vec3) -> bool {
+		(a - b).mag() < 1e-4
+	}
+
+	/// root -> a -> b, root -> c
+	fn tree() -> Node {
+		let mut root = Node::new(ObjRef::Name("root".to_string()), None);
+		let mut a = Node::new(ObjRef::Name("a".to_string()), None);
+		a.translation = Vec3::new(1.0, 0.0, 0.0);
+		a.rotation = Rotor3::from_rotation_xz(std::f32::consts::FRAC_PI_2);
+		let mut b = Node::new(ObjRef::Name("b".to_string()), None);
+		b.translation = Vec3::new(0.0, 0.0, 2.0);
+		b.scale = Vec3::new(2.0, 2.0, 2.0);
+		a.children.push(b);
+		root.children.push(a);
+		root.children.push(Node::new(ObjRef::Name("c".to_string()), None));
+		root.update_parent_indices();
+		root
+	}
+
+	#[test]
+	fn test_traversal() {
+		let root = tree();
+		let dfs: Vec<NodePath> = root.iter_depth_first().map(|(p, _)| p).collect();
+		let bfs: Vec<NodePath> = root.iter_breadth_first().map(|(p, _)| p).collect();
+
+		assert_eq!(dfs, vec![vec![], vec![0], vec![0, 0], vec![1]]);
+		assert_eq!(bfs, vec![vec![], vec![0], vec![1], vec![0, 0]]);
+		assert_eq!(root.find(&ObjRef::Name("b".to_string())), Some(vec![0, 0]));
+		assert_eq!(root.get(&[0, 0]).unwrap().parent, Some(1));
+		assert_eq!(root.get(&[1]).unwrap().parent, Some(0));
+	}
+
+	#[test]
+	fn test_reparent_keeps_world() {
+		let mut root = tree();
+		let before = root.world_matrix(&[0, 0]).unwrap();
+		let path = root.reparent(&[0, 0], &[1]).unwrap();
+		let after = root.world_matrix(&path).unwrap();
+
+		assert_eq!(path, vec![1, 0]);
+		assert!(approx(before.transform_point3(Vec3::one()), after.transform_point3(Vec3::one())));
+		assert_eq!(root.reparent(&[0], &[0]), None);
+
+		let local = root.world_to_local_matrix(&path).unwrap();
+		assert!(approx(local.transform_point3(after.transform_point3(Vec3::unit_y())), Vec3::unit_y()));
+	}
+}
@@ -0,0 +1,396 @@
+use ultraviolet::{
+	rotor::Rotor3,
+	vec::Vec3
+};
+
+use super::{
+	Node,
+	ObjRef,
+	skeleton::Skeleton
+};
+
+/// Values that can be blended between keyframes
+pub trait Animatable: Clone {
+	/// Component-wise sum
+	fn add(&self, other: &Self) -> Self;
+
+	/// Component-wise scale
+	fn scale(&self, s: f32) -> Self;
+
+	/// Linear interpolation, `t` being in the range of 0 to 1
+	fn lerp(&self, other: &Self, t: f32) -> Self {
+		self.scale(1.0 - t).add(&other.scale(t))
+	}
+
+	/// Fixes up a value produced by blending, such as renormalising a rotation
+	fn finish(self) -> Self {
+		self
+	}
+}
+
+impl Animatable for f32 {
+	fn add(&self, other: &Self) -> Self {
+		self + other
+	}
+
+	fn scale(&self, s: f32) -> Self {
+		self * s
+	}
+}
+
+impl Animatable for Vec3 {
+	fn add(&self, other: &Self) -> Self {
+		*self + *other
+	}
+
+	fn scale(&self, s: f32) -> Self {
+		*self * s
+	}
+}
+
+impl Animatable for Rotor3 {
+	fn add(&self, other: &Self) -> Self {
+		*self + *other
+	}
+
+	fn scale(&self, s: f32) -> Self {
+		*self * s
+	}
+
+	/// Spherical linear interpolation along the shortest arc
+	fn lerp(&self, other: &Self, t: f32) -> Self {
+		let mut end = *other;
+		let mut dot = self.dot(end);
+
+		if dot < 0.0 {
+			end *= -1.0;
+			dot = -dot;
+		}
+
+		// nearly parallel, so fall back to a normalised lerp
+		if dot > 0.9995 {
+			return (*self * (1.0 - t) + end * t).normalized();
+		}
+
+		let theta = dot.min(1.0).acos();
+		let sin_theta = theta.sin();
+		let a = ((1.0 - t) * theta).sin() / sin_theta;
+		let b = (t * theta).sin() / sin_theta;
+
+		(*self * a + end * b).normalized()
+	}
+
+	fn finish(self) -> Self {
+		self.normalized()
+	}
+}
+
+impl Animatable for Vec<f32> {
+	fn add(&self, other: &Self) -> Self {
+		let mut out = self.clone();
+		out.resize(self.len().max(other.len()), 0.0);
+
+		for (o, v) in out.iter_mut().zip(other.iter()) {
+			*o += v;
+		}
+
+		out
+	}
+
+	fn scale(&self, s: f32) -> Self {
+		self.iter().map(|v| v * s).collect()
+	}
+}
+
+/// How values between two keyframes are computed
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Interpolation {
+	/// Holds the previous keyframe's value
+	Step,
+
+	/// Straight line between keyframes, or a shortest arc slerp for rotations
+	#[default]
+	Linear,
+
+	/// Cubic Hermite spline using each keyframe's tangents
+	Hermite,
+}
+
+/// A value at a point in time
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keyframe<T> {
+	/// Time in seconds
+	pub time: f32,
+	pub value: T,
+
+	/// Incoming tangent, used by [`Interpolation::Hermite`]. Missing tangents are treated as zero.
+	pub in_tangent: Option<T>,
+
+	/// Outgoing tangent, used by [`Interpolation::Hermite`]. Missing tangents are treated as zero.
+	pub out_tangent: Option<T>,
+}
+
+impl<T> Keyframe<T> {
+	pub fn new(time: f32, value: T) -> Keyframe<T> {
+		Keyframe {
+			time,
+			value,
+			in_tangent: None,
+			out_tangent: None,
+		}
+	}
+}
+
+/// A keyframe track with its interpolation mode
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Track<T> {
+	pub interpolation: Interpolation,
+
+	/// Keyframes, sorted by time
+	pub keys: Vec<Keyframe<T>>,
+}
+
+impl<T> Track<T>
+where
+	T: Animatable,
+{
+	pub fn new(interpolation: Interpolation) -> Track<T> {
+		Track {
+			interpolation,
+			keys: vec![],
+		}
+	}
+
+	/// Returns the time of the last keyframe
+	pub fn duration(&self) -> f32 {
+		self.keys.last().map(|k| k.time).unwrap_or(0.0)
+	}
+
+	/// Evaluates the track at time `t`, clamping to the first and last keyframes
+	pub fn sample(&self, t: f32) -> Option<T> {
+		let first = self.keys.first()?;
+		let last = self.keys.last()?;
+
+		if t <= first.time {
+			return Some(first.value.clone());
+		}
+		if t >= last.time {
+			return Some(last.value.clone());
+		}
+
+		// index of the first keyframe after t
+		let next = self.keys.partition_point(|k| k.time <= t);
+		let k0 = &self.keys[next - 1];
+		let k1 = &self.keys[next];
+		let dt = k1.time - k0.time;
+		if dt <= 0.0 {
+			return Some(k1.value.clone());
+		}
+		let s = (t - k0.time) / dt;
+
+		Some(match self.interpolation {
+			Interpolation::Step => k0.value.clone(),
+			Interpolation::Linear => k0.value.lerp(&k1.value, s),
+			Interpolation::Hermite => {
+				let s2 = s * s;
+				let s3 = s2 * s;
+
+				let mut v = k0.value.scale(2.0 * s3 - 3.0 * s2 + 1.0)
+					.add(&k1.value.scale(-2.0 * s3 + 3.0 * s2));
+				if let Some(m0) = &k0.out_tangent {
+					v = v.add(&m0.scale((s3 - 2.0 * s2 + s) * dt));
+				}
+				if let Some(m1) = &k1.in_tangent {
+					v = v.add(&m1.scale((s3 - s2) * dt));
+				}
+
+				v.finish()
+			},
+		})
+	}
+}
+
+/// Animated data of a [`Channel`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChannelData {
+	Translation(Track<Vec3>),
+	Rotation(Track<Rotor3>),
+	Scale(Track<Vec3>),
+
+	/// Weights of the target mesh's morph targets
+	MorphWeights(Track<Vec<f32>>),
+}
+
+/// A value produced by sampling a [`Channel`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChannelValue {
+	Translation(Vec3),
+	Rotation(Rotor3),
+	Scale(Vec3),
+	MorphWeights(Vec<f32>),
+}
+
+/// Animates one property of a node or bone
+#[derive(Clone, Debug, PartialEq)]
+pub struct Channel {
+	/// ID of the targeted [`Node`] or [`Bone`](super::skeleton::Bone)
+	pub target: ObjRef,
+	pub data: ChannelData,
+}
+
+impl Channel {
+	pub fn new(target: ObjRef, data: ChannelData) -> Channel {
+		Channel {
+			target,
+			data,
+		}
+	}
+
+	/// Returns the time of the channel's last keyframe
+	pub fn duration(&self) -> f32 {
+		match &self.data {
+			ChannelData::Translation(k) => k.duration(),
+			ChannelData::Rotation(k) => k.duration(),
+			ChannelData::Scale(k) => k.duration(),
+			ChannelData::MorphWeights(k) => k.duration(),
+		}
+	}
+
+	/// Evaluates the channel at time `t`
+	pub fn sample(&self, t: f32) -> Option<ChannelValue> {
+		match &self.data {
+			ChannelData::Translation(k) => k.sample(t).map(ChannelValue::Translation),
+			ChannelData::Rotation(k) => k.sample(t).map(ChannelValue::Rotation),
+			ChannelData::Scale(k) => k.sample(t).map(ChannelValue::Scale),
+			ChannelData::MorphWeights(k) => k.sample(t).map(ChannelValue::MorphWeights),
+		}
+	}
+}
+
+/// A pose, being every channel's value at one point in time
+pub type Pose = Vec<(ObjRef, ChannelValue)>;
+
+/// A named animation, such as a walk cycle
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clip {
+	pub name: String,
+	pub channels: Vec<Channel>,
+	pub looping: bool,
+}
+
+impl Clip {
+	pub fn new(name: &str) -> Clip {
+		Clip {
+			name: name.to_owned(),
+			channels: vec![],
+			looping: false,
+		}
+	}
+
+	/// Returns the time of the clip's last keyframe
+	pub fn duration(&self) -> f32 {
+		self.channels.iter().map(|c| c.duration()).fold(0.0, f32::max)
+	}
+
+	/// Evaluates every channel at time `t`, wrapping around if the clip loops
+	pub fn sample(&self, t: f32) -> Pose {
+		let duration = self.duration();
+		let t = if self.looping && duration > 0.0 {
+			t.rem_euclid(duration)
+		} else {
+			t
+		};
+
+		self.channels.iter()
+			.filter_map(|c| c.sample(t).map(|v| (c.target.clone(), v)))
+			.collect()
+	}
+
+	/// Samples the clip at time `t` and writes the result into the node tree
+	pub fn apply(&self, t: f32, root: &mut Node) {
+		for (target, value) in self.sample(t).iter() {
+			apply_to_node(root, target, value);
+		}
+	}
+
+	/// Samples the clip at time `t` and writes the result into the skeleton's bones
+	pub fn apply_to_skeleton(&self, t: f32, skeleton: &mut Skeleton) {
+		for (target, value) in self.sample(t).iter() {
+			if let Some(i) = skeleton.find_bone(target) {
+				let bone = &mut skeleton.bones[i];
+
+				match value {
+					ChannelValue::Translation(v) => bone.translation = *v,
+					ChannelValue::Rotation(r) => bone.rotation = *r,
+					ChannelValue::Scale(v) => bone.scale = *v,
+					ChannelValue::MorphWeights(_) => (),
+				}
+			}
+		}
+	}
+}
+
+/// Writes a sampled value into the first node matching `target`
+fn apply_to_node(node: &mut Node, target: &ObjRef, value: &ChannelValue) -> bool {
+	if node.id == *target {
+		match value {
+			ChannelValue::Translation(v) => node.translation = *v,
+			ChannelValue::Rotation(r) => node.rotation = *r,
+			ChannelValue::Scale(v) => node.scale = *v,
+			ChannelValue::MorphWeights(w) => node.morph_weights = w.clone(),
+		}
+
+		return true;
+	}
+
+	node.children.iter_mut().any(|c| apply_to_node(c, target, value))
+}
+
+#[cfg(test)]
+mod tests {
+	use ultraviolet::{
+		rotor::Rotor3,
+		vec::Vec3
+	};
+
+	use super::*;
+
+	fn track(interpolation: Interpolation) -> Track<Vec3> {
+		let mut track = Track::new(interpolation);
+		track.keys.push(Keyframe::new(0.0, Vec3::zero()));
+		track.keys.push(Keyframe::new(2.0, Vec3::new(2.0, 4.0, 0.0)));
+		track
+	}
+
+	#[test]
+	fn test_sample_track() {
+		assert_eq!(track(Interpolation::Linear).sample(1.0), Some(Vec3::new(1.0, 2.0, 0.0)));
+		assert_eq!(track(Interpolation::Step).sample(1.0), Some(Vec3::zero()));
+		assert_eq!(track(Interpolation::Hermite).sample(1.0), Some(Vec3::new(1.0, 2.0, 0.0)));
+		assert_eq!(track(Interpolation::Linear).sample(5.0), Some(Vec3::new(2.0, 4.0, 0.0)));
+	}
+
+	#[test]
+	fn test_slerp() {
+		let a = Rotor3::identity();
+		let b = Rotor3::from_rotation_xz(std::f32::consts::FRAC_PI_2);
+		let mid = a.lerp(&b, 0.5);
+		let expected = Rotor3::from_rotation_xz(std::f32::consts::FRAC_PI_4);
+
+		assert!((mid.dot(expected) - 1.0).abs() < 1e-5);
+	}
+
+	#[test]
+	fn test_apply_clip() {
+		let mut root = Node::new(ObjRef::Number(0), None);
+		root.children.push(Node::new(ObjRef::Number(1), Some(0)));
+
+		let mut clip = Clip::new("move");
+		clip.looping = true;
+		clip.channels.push(Channel::new(ObjRef::Number(1), ChannelData::Translation(track(Interpolation::Linear))));
+		clip.apply(3.0, &mut root);
+
+		assert_eq!(clip.duration(), 2.0);
+		assert_eq!(root.children[0].translation, Vec3::new(1.0, 2.0, 0.0));
+	}
+}
@@ -0,0 +1,591 @@
+use ultraviolet::{
+	mat::Mat4,
+	vec::Vec3
+};
+
+use super::{
+	Mesh,
+	Node,
+	NodeData,
+	NodePath,
+	ObjRef,
+	Scene
+};
+
+/// Axis-aligned bounding box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+	pub min: Vec3,
+	pub max: Vec3,
+}
+
+impl Default for Aabb {
+	fn default() -> Self {
+		Aabb::empty()
+	}
+}
+
+impl Aabb {
+	/// Creates a box containing nothing, which any point will extend
+	pub fn empty() -> Aabb {
+		Aabb {
+			min: Vec3::broadcast(f32::INFINITY),
+			max: Vec3::broadcast(f32::NEG_INFINITY),
+		}
+	}
+
+	pub fn from_points<'a, I>(points: I) -> Aabb
+	where
+		I: IntoIterator<Item = &'a Vec3>,
+	{
+		points.into_iter().fold(Aabb::empty(), |b, p| b.extended(*p))
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+	}
+
+	/// Returns a copy grown to include the point
+	pub fn extended(&self, p: Vec3) -> Aabb {
+		Aabb {
+			min: self.min.min_by_component(p),
+			max: self.max.max_by_component(p),
+		}
+	}
+
+	/// Returns the smallest box holding both boxes
+	pub fn union(&self, other: &Aabb) -> Aabb {
+		Aabb {
+			min: self.min.min_by_component(other.min),
+			max: self.max.max_by_component(other.max),
+		}
+	}
+
+	pub fn center(&self) -> Vec3 {
+		(self.min + self.max) * 0.5
+	}
+
+	/// Returns the box's size along each axis
+	pub fn size(&self) -> Vec3 {
+		self.max - self.min
+	}
+
+	pub fn contains(&self, p: Vec3) -> bool {
+		p.x >= self.min.x && p.y >= self.min.y && p.z >= self.min.z &&
+			p.x <= self.max.x && p.y <= self.max.y && p.z <= self.max.z
+	}
+
+	/// Returns the eight corner points
+	pub fn corners(&self) -> [Vec3; 8] {
+		let (a, b) = (self.min, self.max);
+		[
+			Vec3::new(a.x, a.y, a.z), Vec3::new(b.x, a.y, a.z), Vec3::new(a.x, b.y, a.z), Vec3::new(b.x, b.y, a.z),
+			Vec3::new(a.x, a.y, b.z), Vec3::new(b.x, a.y, b.z), Vec3::new(a.x, b.y, b.z), Vec3::new(b.x, b.y, b.z),
+		]
+	}
+
+	/// Returns the box enclosing this one after transformation
+	pub fn transformed(&self, m: &Mat4) -> Aabb {
+		if self.is_empty() {
+			return *self;
+		}
+
+		Aabb::from_points(self.corners().iter().map(|p| m.transform_point3(*p)).collect::<Vec<Vec3>>().iter())
+	}
+
+	/// Returns the distance along the ray at which it enters the box, if it does
+	pub fn intersect_ray(&self, ray: &Ray) -> Option<f32> {
+		let inv = Vec3::new(1.0 / ray.direction.x, 1.0 / ray.direction.y, 1.0 / ray.direction.z);
+		let t0 = (self.min - ray.origin) * inv;
+		let t1 = (self.max - ray.origin) * inv;
+		let near = t0.min_by_component(t1);
+		let far = t0.max_by_component(t1);
+
+		let enter = near.x.max(near.y).max(near.z).max(0.0);
+		let exit = far.x.min(far.y).min(far.z);
+
+		if enter <= exit {
+			Some(enter)
+		} else {
+			None
+		}
+	}
+}
+
+/// Bounding sphere
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sphere {
+	pub center: Vec3,
+	pub radius: f32,
+}
+
+impl Sphere {
+	/// Computes a near-minimal enclosing sphere using Ritter's algorithm
+	pub fn from_points(points: &[Vec3]) -> Option<Sphere> {
+		let first = *points.first()?;
+		let farthest = |from: Vec3| -> Vec3 {
+			points.iter().copied().fold(from, |best, p| {
+				if (p - from).mag_sq() > (best - from).mag_sq() {
+					p
+				} else {
+					best
+				}
+			})
+		};
+
+		let a = farthest(first);
+		let b = farthest(a);
+		let mut sphere = Sphere {
+			center: (a + b) * 0.5,
+			radius: (b - a).mag() * 0.5,
+		};
+
+		for p in points.iter() {
+			let d = (*p - sphere.center).mag();
+			if d > sphere.radius {
+				let radius = (sphere.radius + d) * 0.5;
+				sphere.center += (*p - sphere.center) * ((radius - sphere.radius) / d);
+				sphere.radius = radius;
+			}
+		}
+
+		Some(sphere)
+	}
+}
+
+/// A half-line used for picking
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+	pub origin: Vec3,
+	pub direction: Vec3,
+}
+
+impl Ray {
+	pub fn new(origin: Vec3, direction: Vec3) -> Ray {
+		Ray {
+			origin,
+			direction,
+		}
+	}
+
+	pub fn at(&self, t: f32) -> Vec3 {
+		self.origin + self.direction * t
+	}
+
+	/// Returns the ray in the space described by the matrix
+	pub fn transformed(&self, m: &Mat4) -> Ray {
+		Ray {
+			origin: m.transform_point3(self.origin),
+			direction: m.transform_vec3(self.direction),
+		}
+	}
+}
+
+/// Where a ray struck a mesh
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+	/// Index into [`Mesh::faces`]
+	pub face: usize,
+
+	/// Distance along the ray, in units of its direction's length
+	pub t: f32,
+
+	/// Barycentric weights of the struck triangle's corners
+	pub barycentric: Vec3,
+
+	/// Vertex indices of the struck triangle, which for quads and n-gons is part of a fan
+	pub triangle: [usize; 3],
+}
+
+/// Möller-Trumbore ray/triangle intersection, returning the distance and the barycentric weights of `b`
+/// and `c`. Both sides of the triangle are hit.
+pub fn intersect_triangle(ray: &Ray, a: Vec3, b: Vec3, c: Vec3) -> Option<(f32, f32, f32)> {
+	let e1 = b - a;
+	let e2 = c - a;
+	let p = ray.direction.cross(e2);
+	let det = e1.dot(p);
+	if det.abs() < 1e-12 {
+		return None;
+	}
+
+	let inv = 1.0 / det;
+	let s = ray.origin - a;
+	let u = s.dot(p) * inv;
+	if !(0.0..=1.0).contains(&u) {
+		return None;
+	}
+
+	let q = s.cross(e1);
+	let v = ray.direction.dot(q) * inv;
+	if v < 0.0 || u + v > 1.0 {
+		return None;
+	}
+
+	let t = e2.dot(q) * inv;
+	if t >= 0.0 {
+		Some((t, u, v))
+	} else {
+		None
+	}
+}
+
+impl Mesh {
+	/// Returns every face as triangles of vertex indices, fanning quads and n-gons, with the face index.
+	/// Faces with an out of range index are skipped.
+	pub fn fan_triangles(&self) -> Vec<(usize, [usize; 3])> {
+		let mut tris = vec![];
+
+		for (f, face) in self.faces.iter().enumerate().filter(|(_, f)| self.face_in_range(f)) {
+			let idx = face.indices();
+			for k in 1..idx.len().saturating_sub(1) {
+				tris.push((f, [idx[0], idx[k], idx[k + 1]]));
+			}
+		}
+
+		tris
+	}
+
+	/// Returns the mesh's bounding box in its own space
+	pub fn aabb(&self) -> Aabb {
+		self.vertices.iter().fold(Aabb::empty(), |b, v| b.extended(v.position.truncated()))
+	}
+
+	/// Returns the mesh's bounding sphere in its own space
+	pub fn bounding_sphere(&self) -> Option<Sphere> {
+		let points: Vec<Vec3> = self.vertices.iter().map(|v| v.position.truncated()).collect();
+		Sphere::from_points(&points)
+	}
+
+	/// Returns the nearest face struck by the ray, testing every face
+	pub fn raycast(&self, ray: &Ray) -> Option<RayHit> {
+		let mut best: Option<RayHit> = None;
+
+		for (face, tri) in self.fan_triangles().iter() {
+			if let Some(hit) = self.hit_triangle(ray, *face, *tri) {
+				if best.map(|b| hit.t < b.t).unwrap_or(true) {
+					best = Some(hit);
+				}
+			}
+		}
+
+		best
+	}
+
+	fn hit_triangle(&self, ray: &Ray, face: usize, tri: [usize; 3]) -> Option<RayHit> {
+		let p = |i: usize| self.vertices[tri[i]].position.truncated();
+		let (t, u, v) = intersect_triangle(ray, p(0), p(1), p(2))?;
+
+		Some(RayHit {
+			face,
+			t,
+			barycentric: Vec3::new(1.0 - u - v, u, v),
+			triangle: tri,
+		})
+	}
+}
+
+impl Node {
+	/// Transforms every vertex in the subtree of the descendant at `path` by its full transform from this node
+	/// down, so called on the scene root the points are in world space
+	fn world_vertices<F>(&self, path: &[usize], mut f: F) -> Option<()>
+	where
+		F: FnMut(Vec3),
+	{
+		let node = self.get(path)?;
+		let parent = match path.split_last() {
+			Some((_, ancestors)) => self.world_matrix(ancestors)?,
+			None => Mat4::identity(),
+		};
+
+		for (p, m) in node.world_matrices().iter() {
+			if let Some(NodeData::Geometry(mesh)) = node.get(p).map(|n| &n.data) {
+				let world = parent * *m;
+				mesh.vertices.iter().for_each(|v| f(world.transform_point3(v.position.truncated())));
+			}
+		}
+
+		Some(())
+	}
+
+	/// Returns the bounding box of the descendant at `path` and its subtree, applying every transform from this
+	/// node down. Called on the scene root, this is in world space.
+	pub fn world_aabb(&self, path: &[usize]) -> Option<Aabb> {
+		let mut bounds = Aabb::empty();
+		self.world_vertices(path, |p| bounds = bounds.extended(p))?;
+		Some(bounds)
+	}
+
+	/// Returns the bounding sphere of the descendant at `path` and its subtree, applying every transform from this
+	/// node down. Called on the scene root, this is in world space.
+	pub fn world_bounding_sphere(&self, path: &[usize]) -> Option<Sphere> {
+		let mut points = vec![];
+		self.world_vertices(path, |p| points.push(p))?;
+		Sphere::from_points(&points)
+	}
+}
+
+impl Scene {
+	/// Returns the world-space bounding box of every mesh in the scene
+	pub fn aabb(&self) -> Aabb {
+		self.root.world_aabb(&[]).unwrap_or_default()
+	}
+
+	/// Returns the world-space bounding sphere of every mesh in the scene
+	pub fn bounding_sphere(&self) -> Option<Sphere> {
+		self.root.world_bounding_sphere(&[])
+	}
+
+	/// Returns the world-space bounding box of the first node with the specified ID and its subtree
+	pub fn node_aabb(&self, id: &ObjRef) -> Option<Aabb> {
+		self.root.world_aabb(&self.root.find(id)?)
+	}
+
+	/// Returns the world-space bounding sphere of the first node with the specified ID and its subtree
+	pub fn node_bounding_sphere(&self, id: &ObjRef) -> Option<Sphere> {
+		self.root.world_bounding_sphere(&self.root.find(id)?)
+	}
+
+	/// Returns the nearest mesh face struck by a world-space ray, and the path to its node.
+	/// The hit's distance is measured in world units of the ray direction's length.
+	pub fn pick(&self, ray: &Ray) -> Option<(NodePath, RayHit)> {
+		let mut best: Option<(NodePath, RayHit)> = None;
+
+		for (path, world) in self.root.world_matrices().iter() {
+			let mesh = match self.root.get(path).map(|n| &n.data) {
+				Some(NodeData::Geometry(mesh)) => mesh,
+				_ => continue,
+			};
+
+			// t is preserved by affine transforms, so local hits compare directly
+			let local = ray.transformed(&world.inversed());
+			if let Some(hit) = mesh.raycast(&local) {
+				if best.as_ref().map(|(_, b)| hit.t < b.t).unwrap_or(true) {
+					best = Some((path.clone(), hit));
+				}
+			}
+		}
+
+		best
+	}
+}
+
+/// A node of a [`Bvh`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum BvhNode {
+	/// Two child node indices
+	Branch(Aabb, usize, usize),
+
+	/// A range of [`Bvh::triangles`]
+	Leaf(Aabb, usize, usize),
+}
+
+impl BvhNode {
+	pub fn bounds(&self) -> &Aabb {
+		match self {
+			BvhNode::Branch(b, _, _) => b,
+			BvhNode::Leaf(b, _, _) => b,
+		}
+	}
+}
+
+/// Bounding volume hierarchy over a mesh's triangles, for fast picking on large meshes.
+/// It must be rebuilt if the mesh's vertices or faces change.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Bvh {
+	/// Triangles as face index and vertex indices, reordered so each leaf's are contiguous
+	pub triangles: Vec<(usize, [usize; 3])>,
+
+	/// Tree nodes, the root being first
+	pub nodes: Vec<BvhNode>,
+}
+
+/// Largest number of triangles in a BVH leaf
+const BVH_LEAF_SIZE: usize = 4;
+
+impl Bvh {
+	/// Builds a hierarchy by splitting along the longest axis at the median centroid
+	pub fn build(mesh: &Mesh) -> Bvh {
+		let mut bvh = Bvh {
+			triangles: mesh.fan_triangles(),
+			nodes: vec![],
+		};
+
+		if !bvh.triangles.is_empty() {
+			let centroids: Vec<Vec3> = bvh.triangles.iter()
+				.map(|(_, t)| t.iter().fold(Vec3::zero(), |c, i| c + mesh.vertices[*i].position.truncated()) / 3.0)
+				.collect();
+			let mut order: Vec<usize> = (0..bvh.triangles.len()).collect();
+			bvh.split(mesh, &centroids, &mut order, 0);
+
+			let triangles = order.iter().map(|i| bvh.triangles[*i]).collect();
+			bvh.triangles = triangles;
+		}
+
+		bvh
+	}
+
+	fn split(&mut self, mesh: &Mesh, centroids: &[Vec3], order: &mut [usize], start: usize) -> usize {
+		let bounds = order.iter().fold(Aabb::empty(), |b, i| {
+			self.triangles[*i].1.iter().fold(b, |b, v| b.extended(mesh.vertices[*v].position.truncated()))
+		});
+
+		let index = self.nodes.len();
+		if order.len() <= BVH_LEAF_SIZE {
+			self.nodes.push(BvhNode::Leaf(bounds, start, start + order.len()));
+			return index;
+		}
+
+		let size = Aabb::from_points(order.iter().map(|i| &centroids[*i])).size();
+		let axis = if size.x >= size.y && size.x >= size.z {
+			0
+		} else if size.y >= size.z {
+			1
+		} else {
+			2
+		};
+		let key = |i: &usize| match axis {
+			0 => centroids[*i].x,
+			1 => centroids[*i].y,
+			_ => centroids[*i].z,
+		};
+		order.sort_by(|a, b| key(a).total_cmp(&key(b)));
+
+		// placeholder, filled once the children exist
+		self.nodes.push(BvhNode::Leaf(bounds, 0, 0));
+		let mid = order.len() / 2;
+		let (left, right) = order.split_at_mut(mid);
+		let l = self.split(mesh, centroids, left, start);
+		let r = self.split(mesh, centroids, right, start + mid);
+		self.nodes[index] = BvhNode::Branch(bounds, l, r);
+
+		index
+	}
+
+	/// Returns the nearest face of `mesh` struck by the ray
+	pub fn raycast(&self, mesh: &Mesh, ray: &Ray) -> Option<RayHit> {
+		let mut best: Option<RayHit> = None;
+		let mut stack = vec![];
+		if !self.nodes.is_empty() {
+			stack.push(0);
+		}
+
+		while let Some(n) = stack.pop() {
+			let node = &self.nodes[n];
+			match node.bounds().intersect_ray(ray) {
+				Some(t) if best.map(|b| t <= b.t).unwrap_or(true) => (),
+				_ => continue,
+			}
+
+			match node {
+				BvhNode::Branch(_, l, r) => {
+					stack.push(*l);
+					stack.push(*r);
+				},
+				BvhNode::Leaf(_, start, end) => for (face, tri) in self.triangles[*start..*end].iter() {
+					if let Some(hit) = mesh.hit_triangle(ray, *face, *tri) {
+						if best.map(|b| hit.t < b.t).unwrap_or(true) {
+							best = Some(hit);
+						}
+					}
+				},
+			}
+		}
+
+		best
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ultraviolet::vec::{
+		Vec3,
+		Vec4
+	};
+
+	use super::*;
+	use crate::scene::{
+		Face,
+		Vertex
+	};
+
+	/// A flat grid of quads on the XY plane
+	fn grid(n: usize) -> Mesh {
+		let mut mesh = Mesh::default();
+		for y in 0..=n {
+			for x in 0..=n {
+				let mut v = Vertex::new(None);
+				v.position = Vec4::new(x as f32, y as f32, 0.0, 1.0);
+				mesh.vertices.push(v);
+			}
+		}
+		for y in 0..n {
+			for x in 0..n {
+				let i = y * (n + 1) + x;
+				mesh.faces.push(Face::Quad([i, i + 1, i + n + 2, i + n + 1]));
+			}
+		}
+		mesh
+	}
+
+	#[test]
+	fn test_bounds() {
+		let mesh = grid(4);
+		let aabb = mesh.aabb();
+		assert_eq!(aabb.min, Vec3::zero());
+		assert_eq!(aabb.max, Vec3::new(4.0, 4.0, 0.0));
+
+		let sphere = mesh.bounding_sphere().unwrap();
+		assert!(mesh.vertices.iter().all(|v| (v.position.truncated() - sphere.center).mag() <= sphere.radius + 1e-4));
+
+		// the child's bounds include its parent's transform
+		let mut root = Node::new(ObjRef::Number(0), None);
+		root.translation = Vec3::new(0.0, 0.0, 5.0);
+		let mut child = Node::new(ObjRef::Number(1), Some(0));
+		child.translation = Vec3::new(1.0, 0.0, 0.0);
+		child.data = NodeData::Geometry(mesh);
+		root.children.push(child);
+		assert_eq!(root.world_aabb(&[0]).unwrap().min, Vec3::new(1.0, 0.0, 5.0));
+		assert!(root.world_aabb(&[1]).is_none());
+
+		let scene = Scene::new(root);
+		assert_eq!(scene.node_aabb(&ObjRef::Number(1)), Some(scene.aabb()));
+		let sphere = scene.bounding_sphere().unwrap();
+		assert!((sphere.center - scene.aabb().center()).mag() < 1e-4);
+		assert!((sphere.radius - 8.0f32.sqrt()).abs() < 1e-3);
+		assert_eq!(scene.node_bounding_sphere(&ObjRef::Number(1)), Some(sphere));
+	}
+
+	#[test]
+	fn test_bvh_matches_brute_force() {
+		let mut mesh = grid(16);
+		// a face with a bad index is skipped instead of panicking
+		mesh.faces.push(Face::Triangle([0, 1, mesh.vertices.len()]));
+		assert_eq!(mesh.fan_triangles().len(), 16 * 16 * 2);
+		let bvh = Bvh::build(&mesh);
+
+		for (x, y) in [(0.5, 0.5), (3.2, 7.9), (15.9, 15.1), (20.0, 1.0)].iter() {
+			let ray = Ray::new(Vec3::new(*x, *y, 10.0), -Vec3::unit_z());
+			let brute = mesh.raycast(&ray);
+			let fast = bvh.raycast(&mesh, &ray);
+
+			assert_eq!(brute.map(|h| h.face), fast.map(|h| h.face));
+			if let Some(hit) = fast {
+				assert!((hit.t - 10.0).abs() < 1e-5);
+			}
+		}
+	}
+
+	#[test]
+	fn test_pick() {
+		let mut root = Node::new(ObjRef::Number(0), None);
+		let mut child = Node::new(ObjRef::Number(1), Some(0));
+		child.translation = Vec3::new(-1.0, -1.0, -2.0);
+		child.data = NodeData::Geometry(grid(2));
+		root.children.push(child);
+
+		let scene = Scene::new(root);
+		let (path, hit) = scene.pick(&Ray::new(Vec3::zero(), -Vec3::unit_z())).unwrap();
+		assert_eq!(path, vec![0]);
+		assert_eq!(hit.t, 2.0);
+	}
+}
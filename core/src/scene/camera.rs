@@ -0,0 +1,119 @@
+use ultraviolet::{
+	mat::Mat4,
+	projection::rh_yup::{
+		orthographic_gl,
+		perspective_gl
+	},
+	vec::Vec3
+};
+
+/// Camera lens type
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+	/// Vertical field of view in radians
+	Perspective(f32),
+
+	/// Half the width and height of the view volume
+	Orthographic(f32, f32),
+}
+
+/// A viewpoint, looking down its node's local -Z axis with +Y up
+#[derive(Clone, Debug, PartialEq)]
+pub struct Camera {
+	pub projection: Projection,
+
+	/// Width divided by height. If absent, the viewport's should be used.
+	pub aspect_ratio: Option<f32>,
+	pub near: f32,
+
+	/// Far clip plane. If absent, the camera has an infinite far plane.
+	pub far: Option<f32>,
+}
+
+impl Camera {
+	pub fn perspective(yfov: f32, aspect_ratio: Option<f32>, near: f32, far: Option<f32>) -> Camera {
+		Camera {
+			projection: Projection::Perspective(yfov),
+			aspect_ratio,
+			near,
+			far,
+		}
+	}
+
+	pub fn orthographic(xmag: f32, ymag: f32, near: f32, far: f32) -> Camera {
+		Camera {
+			projection: Projection::Orthographic(xmag, ymag),
+			aspect_ratio: Some(xmag / ymag),
+			near,
+			far: Some(far),
+		}
+	}
+
+	/// Creates a perspective camera from a horizontal field of view, as stored by fSpy and 3ds Max
+	pub fn from_horizontal_fov(xfov: f32, aspect_ratio: f32, near: f32, far: Option<f32>) -> Camera {
+		let yfov = 2.0 * ((xfov * 0.5).tan() / aspect_ratio).atan();
+		Camera::perspective(yfov, Some(aspect_ratio), near, far)
+	}
+
+	/// Returns the horizontal field of view in radians, if perspective
+	pub fn horizontal_fov(&self, default_aspect: f32) -> Option<f32> {
+		match self.projection {
+			Projection::Perspective(yfov) => {
+				let aspect = self.aspect_ratio.unwrap_or(default_aspect);
+				Some(2.0 * ((yfov * 0.5).tan() * aspect).atan())
+			},
+			Projection::Orthographic(_, _) => None,
+		}
+	}
+
+	/// Returns an OpenGL style clip space projection matrix
+	pub fn projection_matrix(&self, default_aspect: f32) -> Mat4 {
+		let aspect = self.aspect_ratio.unwrap_or(default_aspect);
+
+		match self.projection {
+			Projection::Perspective(yfov) => match self.far {
+				Some(far) => perspective_gl(yfov, aspect, self.near, far),
+				None => {
+					let f = 1.0 / (yfov * 0.5).tan();
+					let mut m = Mat4::identity();
+					m.cols[0].x = f / aspect;
+					m.cols[1].y = f;
+					m.cols[2].z = -1.0;
+					m.cols[2].w = -1.0;
+					m.cols[3].z = -2.0 * self.near;
+					m.cols[3].w = 0.0;
+					m
+				},
+			},
+			Projection::Orthographic(xmag, ymag) => orthographic_gl(-xmag, xmag, -ymag, ymag, self.near,
+				self.far.unwrap_or(self.near + 1000.0)),
+		}
+	}
+
+	/// Returns the view matrix for a camera placed by the specified world transform
+	pub fn view_matrix(world: &Mat4) -> Mat4 {
+		world.inversed()
+	}
+
+	/// Returns the world-space direction a camera placed by the specified world transform faces
+	pub fn forward(world: &Mat4) -> Vec3 {
+		world.transform_vec3(-Vec3::unit_z()).normalized()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::f32::consts::FRAC_PI_2;
+
+	use super::*;
+
+	#[test]
+	fn test_fov_roundtrip() {
+		let cam = Camera::from_horizontal_fov(FRAC_PI_2, 16.0 / 9.0, 0.1, Some(100.0));
+		assert!((cam.horizontal_fov(1.0).unwrap() - FRAC_PI_2).abs() < 1e-5);
+
+		let infinite = Camera::perspective(FRAC_PI_2, Some(1.0), 0.1, None).projection_matrix(1.0);
+		let finite = Camera::perspective(FRAC_PI_2, Some(1.0), 0.1, Some(1e9)).projection_matrix(1.0);
+		assert!((infinite.cols[3].z - finite.cols[3].z).abs() < 1e-3);
+	}
+}
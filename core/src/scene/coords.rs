@@ -0,0 +1,340 @@
+use ultraviolet::{
+	bivec::Bivec3,
+	mat::{
+		Mat3,
+		Mat4
+	},
+	rotor::Rotor3,
+	vec::{
+		Vec3,
+		Vec4
+	}
+};
+
+use super::{
+	anim::ChannelData,
+	camera::Projection,
+	Mesh,
+	NodeData,
+	Scene,
+	UpAxis
+};
+
+/// Coordinate system chirality
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Handedness {
+	/// OpenGL, glTF, Blender, 3ds Max, Maya
+	#[default]
+	Right,
+
+	/// DirectX, Unity, Unreal, MikuMikuDance
+	Left,
+}
+
+/// A change of basis between two coordinate conventions, restricted to axis swaps and mirrors
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AxisConversion {
+	pub matrix: Mat3,
+}
+
+impl AxisConversion {
+	/// Builds the conversion from one convention to another
+	pub fn new(from_up: UpAxis, from_hand: Handedness, to_up: UpAxis, to_hand: Handedness) -> AxisConversion {
+		// the target basis is orthonormal, so its inverse is its transpose
+		AxisConversion {
+			matrix: to_base(to_up, to_hand).transposed() * to_base(from_up, from_hand),
+		}
+	}
+
+	/// Does the conversion mirror the scene, requiring winding to be reversed?
+	pub fn is_mirror(&self) -> bool {
+		self.matrix.determinant() < 0.0
+	}
+
+	pub fn point(&self, v: Vec3) -> Vec3 {
+		self.matrix * v
+	}
+
+	pub fn point4(&self, v: Vec4) -> Vec4 {
+		let p = self.matrix * v.truncated();
+		Vec4::new(p.x, p.y, p.z, v.w)
+	}
+
+	/// Converts a per-axis scale, which only has its axes swapped. Mirrors flip an axis twice when conjugating,
+	/// so each component keeps its own sign.
+	pub fn scale(&self, s: Vec3) -> Vec3 {
+		let c = &self.matrix.cols;
+		let abs = |v: Vec3| Vec3::new(v.x.abs(), v.y.abs(), v.z.abs());
+		Mat3::new(abs(c[0]), abs(c[1]), abs(c[2])) * s
+	}
+
+	/// Converts a tangent, flipping the bitangent sign if the conversion mirrors
+	pub fn tangent(&self, t: Vec4) -> Vec4 {
+		let p = self.matrix * t.truncated();
+		Vec4::new(p.x, p.y, p.z, if self.is_mirror() { -t.w } else { t.w })
+	}
+
+	/// Converts a rotation. This is linear in the rotor's components, so it applies equally to tangents.
+	pub fn rotor(&self, r: Rotor3) -> Rotor3 {
+		let b = r.bv;
+		let m = [
+			[0.0, b.xy, b.xz],
+			[-b.xy, 0.0, b.yz],
+			[-b.xz, -b.yz, 0.0],
+		];
+
+		// conjugate the bivector's antisymmetric matrix form by the basis change
+		let c = &self.matrix.cols;
+		let e = |i: usize, j: usize| -> f32 {
+			let mut sum = 0.0;
+			for k in 0..3 {
+				for l in 0..3 {
+					sum += c[k][i] * m[k][l] * c[l][j];
+				}
+			}
+			sum
+		};
+
+		Rotor3::new(r.s, Bivec3::new(e(0, 1), e(0, 2), e(1, 2)))
+	}
+
+	/// Converts an affine transformation matrix
+	pub fn matrix(&self, m: &Mat4) -> Mat4 {
+		let c = self.matrix.into_homogeneous();
+		c * *m * c.transposed()
+	}
+
+	/// Converts every vertex of a mesh, reversing face winding if the conversion mirrors
+	pub fn mesh(&self, mesh: &mut Mesh) {
+		for v in mesh.vertices.iter_mut() {
+			v.position = self.point4(v.position);
+			v.normal = v.normal.map(|n| self.point(n));
+			v.tangent = v.tangent.map(|t| self.tangent(t));
+		}
+
+		for target in mesh.morph_targets.iter_mut() {
+			for d in target.deltas.iter_mut() {
+				d.position = self.point(d.position);
+				d.normal = self.point(d.normal);
+			}
+		}
+
+		if self.is_mirror() {
+			for face in mesh.faces.iter_mut() {
+				face.indices_mut().reverse();
+			}
+		}
+	}
+}
+
+/// Returns the basis taking the specified convention into right-handed Y-up
+fn to_base(up: UpAxis, hand: Handedness) -> Mat3 {
+	match (up, hand) {
+		(UpAxis::Y, Handedness::Right) => Mat3::identity(),
+		(UpAxis::Y, Handedness::Left) => Mat3::new(Vec3::unit_x(), Vec3::unit_y(), -Vec3::unit_z()),
+		// (x, y, z) -> (x, z, -y)
+		(UpAxis::Z, Handedness::Right) => Mat3::new(Vec3::unit_x(), -Vec3::unit_z(), Vec3::unit_y()),
+		// (x, y, z) -> (x, z, y)
+		(UpAxis::Z, Handedness::Left) => Mat3::new(Vec3::unit_x(), Vec3::unit_z(), Vec3::unit_y()),
+	}
+}
+
+impl Scene {
+	/// Rewrites every transform, vertex, bone and animation into the specified axis convention
+	pub fn convert_axes(&mut self, up: UpAxis, handedness: Handedness) {
+		let conv = AxisConversion::new(self.up, self.handedness, up, handedness);
+
+		self.root.visit_mut(&mut |node| {
+			node.translation = conv.point(node.translation);
+			node.rotation = conv.rotor(node.rotation);
+			node.scale = conv.scale(node.scale);
+
+			if let NodeData::Geometry(mesh) = &mut node.data {
+				conv.mesh(mesh);
+			}
+		});
+
+		for skel in self.skeletons.iter_mut() {
+			for bone in skel.bones.iter_mut() {
+				bone.translation = conv.point(bone.translation);
+				bone.rotation = conv.rotor(bone.rotation);
+				bone.scale = conv.scale(bone.scale);
+				bone.inverse_bind = conv.matrix(&bone.inverse_bind);
+			}
+		}
+
+		for clip in self.animations.iter_mut() {
+			for channel in clip.channels.iter_mut() {
+				match &mut channel.data {
+					ChannelData::Translation(track) => for k in track.keys.iter_mut() {
+						k.value = conv.point(k.value);
+						k.in_tangent = k.in_tangent.map(|t| conv.point(t));
+						k.out_tangent = k.out_tangent.map(|t| conv.point(t));
+					},
+					ChannelData::Rotation(track) => for k in track.keys.iter_mut() {
+						k.value = conv.rotor(k.value);
+						k.in_tangent = k.in_tangent.map(|t| conv.rotor(t));
+						k.out_tangent = k.out_tangent.map(|t| conv.rotor(t));
+					},
+					ChannelData::Scale(track) => for k in track.keys.iter_mut() {
+						k.value = conv.scale(k.value);
+						k.in_tangent = k.in_tangent.map(|t| conv.scale(t));
+						k.out_tangent = k.out_tangent.map(|t| conv.scale(t));
+					},
+					ChannelData::MorphWeights(_) => (),
+				}
+			}
+		}
+
+		self.up = up;
+		self.handedness = handedness;
+	}
+
+	/// Rescales every distance so that one unit spans `unit_scale` metres, for example 0.01 for centimetres
+	pub fn convert_units(&mut self, unit_scale: f32) {
+		let factor = self.unit_scale / unit_scale;
+
+		self.root.visit_mut(&mut |node| {
+			node.translation *= factor;
+
+			match &mut node.data {
+				NodeData::Geometry(mesh) => {
+					for v in mesh.vertices.iter_mut() {
+						v.position = Vec4::new(v.position.x * factor, v.position.y * factor, v.position.z * factor,
+							v.position.w);
+					}
+
+					for target in mesh.morph_targets.iter_mut() {
+						for d in target.deltas.iter_mut() {
+							d.position *= factor;
+						}
+					}
+				},
+				NodeData::Camera(cam) => {
+					cam.near *= factor;
+					cam.far = cam.far.map(|f| f * factor);
+					if let Projection::Orthographic(x, y) = cam.projection {
+						cam.projection = Projection::Orthographic(x * factor, y * factor);
+					}
+				},
+				NodeData::Light(light) => light.range = light.range.map(|r| r * factor),
+				NodeData::Null => (),
+			}
+		});
+
+		for skel in self.skeletons.iter_mut() {
+			for bone in skel.bones.iter_mut() {
+				bone.translation *= factor;
+				bone.inverse_bind.cols[3].x *= factor;
+				bone.inverse_bind.cols[3].y *= factor;
+				bone.inverse_bind.cols[3].z *= factor;
+			}
+		}
+
+		for clip in self.animations.iter_mut() {
+			for channel in clip.channels.iter_mut() {
+				if let ChannelData::Translation(track) = &mut channel.data {
+					for k in track.keys.iter_mut() {
+						k.value *= factor;
+						k.in_tangent = k.in_tangent.map(|t| t * factor);
+						k.out_tangent = k.out_tangent.map(|t| t * factor);
+					}
+				}
+			}
+		}
+
+		self.unit_scale = unit_scale;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ultraviolet::{
+		rotor::Rotor3,
+		vec::{
+			Vec3,
+			Vec4
+		}
+	};
+
+	use super::*;
+	use crate::scene::{
+		Face,
+		Node,
+		ObjRef,
+		Vertex
+	};
+
+	fn scene() -> Scene {
+		let mut mesh = Mesh::default();
+		for p in [Vec3::zero(), Vec3::unit_x(), Vec3::unit_y()].iter() {
+			let mut v = Vertex::new(None);
+			v.position = Vec4::new(p.x, p.y, p.z, 1.0);
+			v.normal = Some(Vec3::unit_z());
+			mesh.vertices.push(v);
+		}
+		mesh.faces.push(Face::Triangle([0, 1, 2]));
+
+		let mut root = Node::new(ObjRef::Number(0), None);
+		root.translation = Vec3::new(1.0, 2.0, 3.0);
+		root.rotation = Rotor3::from_rotation_xy(0.3);
+		root.data = NodeData::Geometry(mesh);
+
+		let mut scene = Scene::new(root);
+		scene.up = UpAxis::Z;
+		scene
+	}
+
+	#[test]
+	fn test_rotor_matches_matrix() {
+		let conv = AxisConversion::new(UpAxis::Z, Handedness::Right, UpAxis::Y, Handedness::Left);
+		let r = Rotor3::from_euler_angles(0.2, 0.4, 0.6);
+		let expected = conv.matrix(&r.into_matrix().into_homogeneous());
+		let actual = conv.rotor(r).into_matrix().into_homogeneous();
+
+		for v in [Vec3::unit_x(), Vec3::unit_y(), Vec3::unit_z()].iter() {
+			assert!((expected.transform_vec3(*v) - actual.transform_vec3(*v)).mag() < 1e-5);
+		}
+	}
+
+	#[test]
+	fn test_convert_axes() {
+		let mut s = scene();
+		let world = s.root.local_matrix();
+		s.convert_axes(UpAxis::Y, Handedness::Left);
+
+		assert_eq!(s.root.translation, Vec3::new(1.0, 3.0, 2.0));
+		let expected = AxisConversion::new(UpAxis::Z, Handedness::Right, UpAxis::Y, Handedness::Left).matrix(&world);
+		let p = Vec3::new(0.5, -1.0, 2.0);
+		assert!((s.root.local_matrix().transform_point3(p) - expected.transform_point3(p)).mag() < 1e-5);
+
+		if let NodeData::Geometry(mesh) = &s.root.data {
+			assert_eq!(mesh.faces[0], Face::Triangle([2, 1, 0]));
+			assert_eq!(mesh.vertices[2].position, Vec4::new(0.0, 0.0, 1.0, 1.0));
+		}
+
+		s.convert_axes(UpAxis::Z, Handedness::Right);
+		assert_eq!(s.root.translation, Vec3::new(1.0, 2.0, 3.0));
+		assert!((s.root.rotation.dot(Rotor3::from_rotation_xy(0.3)) - 1.0).abs() < 1e-5);
+	}
+
+	#[test]
+	fn test_mirrored_scale() {
+		let conv = AxisConversion::new(UpAxis::Z, Handedness::Right, UpAxis::Y, Handedness::Left);
+		let s = Vec3::new(-2.0, 3.0, 4.0);
+		let expected = conv.matrix(&Mat4::from_nonuniform_scale(s));
+
+		assert_eq!(conv.scale(s), Vec3::new(-2.0, 4.0, 3.0));
+		assert_eq!(Mat4::from_nonuniform_scale(conv.scale(s)), expected);
+	}
+
+	#[test]
+	fn test_convert_units() {
+		let mut s = scene();
+		s.unit_scale = 0.01;
+		s.convert_units(1.0);
+
+		assert_eq!(s.root.translation, Vec3::new(0.01, 0.02, 0.03));
+		assert_eq!(s.unit_scale, 1.0);
+	}
+}
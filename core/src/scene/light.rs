@@ -0,0 +1,127 @@
+use ultraviolet::{
+	mat::Mat4,
+	vec::Vec3
+};
+
+/// Light source type
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+	/// Emits in all directions from the node's origin
+	Point,
+
+	/// Emits along the node's local -Z axis within a cone, with angles in radians from the axis
+	Spot {
+		inner_cone: f32,
+		outer_cone: f32,
+	},
+
+	/// Emits parallel rays along the node's local -Z axis, like the sun
+	Directional,
+}
+
+/// Falloff over distance, as `1 / (constant + linear * d + quadratic * d²)`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attenuation {
+	pub constant: f32,
+	pub linear: f32,
+	pub quadratic: f32,
+}
+
+impl Default for Attenuation {
+	/// Physically based inverse square falloff
+	fn default() -> Self {
+		Attenuation {
+			constant: 0.0,
+			linear: 0.0,
+			quadratic: 1.0,
+		}
+	}
+}
+
+/// A light source, placed by its node's world transform
+#[derive(Clone, Debug, PartialEq)]
+pub struct Light {
+	pub kind: LightKind,
+
+	/// Linear RGB color
+	pub color: Vec3,
+	pub intensity: f32,
+	pub attenuation: Attenuation,
+
+	/// Distance beyond which the light has no influence. If absent, the range is infinite.
+	pub range: Option<f32>,
+}
+
+impl Light {
+	pub fn new(kind: LightKind) -> Light {
+		Light {
+			kind,
+			color: Vec3::one(),
+			intensity: 1.0,
+			attenuation: Attenuation::default(),
+			range: None,
+		}
+	}
+
+	/// Returns the world-space direction light travels for a light placed by the specified world transform.
+	/// Point lights have no direction.
+	pub fn direction(&self, world: &Mat4) -> Option<Vec3> {
+		match self.kind {
+			LightKind::Point => None,
+			_ => Some(world.transform_vec3(-Vec3::unit_z()).normalized()),
+		}
+	}
+
+	/// Returns the intensity multiplier at the specified distance from the light
+	pub fn falloff(&self, distance: f32) -> f32 {
+		if self.kind == LightKind::Directional {
+			return 1.0;
+		}
+
+		if let Some(range) = self.range {
+			if distance > range {
+				return 0.0;
+			}
+		}
+
+		let a = &self.attenuation;
+		let denom = a.constant + a.linear * distance + a.quadratic * distance * distance;
+		if denom > 0.0 {
+			1.0 / denom
+		} else {
+			1.0
+		}
+	}
+
+	/// Returns the spot cone multiplier for a ray at `angle` radians from the light's axis
+	pub fn cone_factor(&self, angle: f32) -> f32 {
+		match self.kind {
+			LightKind::Spot { inner_cone, outer_cone } => {
+				if angle <= inner_cone {
+					1.0
+				} else if angle >= outer_cone {
+					0.0
+				} else {
+					(outer_cone - angle) / (outer_cone - inner_cone)
+				}
+			},
+			_ => 1.0,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_falloff() {
+		let mut light = Light::new(LightKind::Spot { inner_cone: 0.2, outer_cone: 0.4 });
+		light.range = Some(10.0);
+
+		assert_eq!(light.falloff(2.0), 0.25);
+		assert_eq!(light.falloff(11.0), 0.0);
+		assert!((light.cone_factor(0.3) - 0.5).abs() < 1e-6);
+		assert_eq!(light.direction(&Mat4::identity()), Some(-Vec3::unit_z()));
+	}
+}
@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+
+use ultraviolet::vec::{
+	Vec2,
+	Vec3,
+	Vec4
+};
+
+use super::{
+	MatPropValue,
+	MatPropValueID,
+	Material
+};
+
+/// Reflectance of non-metals at normal incidence, as assumed by glTF 2.0
+pub const DIELECTRIC_SPECULAR: f32 = 0.04;
+
+/// Shading model a material's properties are expressed in
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ShadingModel {
+	/// Ambient, diffuse and specular colors with a specular exponent, as used by MTL, ASE and OGRE
+	#[default]
+	Phong,
+
+	/// glTF 2.0 style base color, metallic and roughness
+	MetallicRoughness,
+
+	/// Base color only, unaffected by lighting
+	Unlit,
+}
+
+/// How a material's alpha is interpreted
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AlphaMode {
+	/// Alpha is ignored
+	#[default]
+	Opaque,
+
+	/// Fragments below the cutoff are discarded
+	Mask(f32),
+
+	/// Alpha is used for blending
+	Blend,
+}
+
+/// How a blended material is combined with what is behind it
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BlendMode {
+	/// Standard alpha blending
+	#[default]
+	Mix,
+	Additive,
+	Multiply,
+}
+
+/// Texture coordinate wrapping outside of the 0 to 1 range
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum WrapMode {
+	#[default]
+	Repeat,
+	ClampToEdge,
+	MirroredRepeat,
+}
+
+/// Texel filtering
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FilterMode {
+	Nearest,
+	#[default]
+	Linear,
+}
+
+/// Texture sampling state
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Sampler {
+	pub wrap_u: WrapMode,
+	pub wrap_v: WrapMode,
+	pub mag_filter: FilterMode,
+	pub min_filter: FilterMode,
+
+	/// Filtering between mipmap levels, if mipmapping is enabled
+	pub mipmap_filter: Option<FilterMode>,
+}
+
+/// Texture coordinate transform, applied as scale, then rotation, then offset
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TexTransform {
+	pub offset: Vec2,
+
+	/// Counter-clockwise rotation in radians
+	pub rotation: f32,
+	pub scale: Vec2,
+}
+
+impl Default for TexTransform {
+	fn default() -> Self {
+		TexTransform {
+			offset: Vec2::zero(),
+			rotation: 0.0,
+			scale: Vec2::one(),
+		}
+	}
+}
+
+impl TexTransform {
+	/// Transforms a texture coordinate
+	pub fn apply(&self, uv: Vec2) -> Vec2 {
+		let s = uv * self.scale;
+		let (sin, cos) = self.rotation.sin_cos();
+		Vec2::new(cos * s.x - sin * s.y, sin * s.x + cos * s.y) + self.offset
+	}
+
+	pub fn is_identity(&self) -> bool {
+		*self == TexTransform::default()
+	}
+}
+
+/// A texture bound to a material slot
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureRef {
+	/// File path or embedded image name
+	pub path: String,
+
+	/// Index of the vertex UV channel to sample with
+	pub uv_set: usize,
+	pub sampler: Sampler,
+	pub transform: TexTransform,
+}
+
+impl TextureRef {
+	pub fn new(path: &str) -> TextureRef {
+		TextureRef {
+			path: path.to_owned(),
+			uv_set: 0,
+			sampler: Sampler::default(),
+			transform: TexTransform::default(),
+		}
+	}
+}
+
+impl Material {
+	pub fn new(name: &str, shading: ShadingModel) -> Material {
+		Material {
+			name: name.to_owned(),
+			properties: HashMap::new(),
+			textures: HashMap::new(),
+			shading,
+			alpha_mode: AlphaMode::Opaque,
+			blend_mode: BlendMode::Mix,
+			double_sided: false,
+			shader: None,
+		}
+	}
+
+	fn rgb(&self, id: MatPropValueID, default: Vec3) -> Vec3 {
+		self.properties.get(&id).and_then(|p| p.as_rgb()).unwrap_or(default)
+	}
+
+	fn float(&self, id: MatPropValueID, default: f32) -> f32 {
+		self.properties.get(&id).and_then(|p| p.as_float()).unwrap_or(default)
+	}
+
+	/// Returns the material's opacity, from an explicit alpha or the diffuse color's alpha channel
+	pub fn opacity(&self) -> f32 {
+		match self.properties.get(&MatPropValueID::Alpha) {
+			Some(a) => a.as_float().unwrap_or(1.0),
+			None => match self.properties.get(&MatPropValueID::Diffuse) {
+				Some(MatPropValue::Vector4(c)) => c.w,
+				_ => 1.0,
+			},
+		}
+	}
+
+	/// Converts a Phong material to metallic-roughness.
+	///
+	/// Roughness comes from the specular exponent `n`, matching the Blinn-Phong lobe to a GGX lobe with
+	/// `alpha = sqrt(2 / (n + 2))` and `roughness = sqrt(alpha)`. Metallic and base color are solved from
+	/// the diffuse and specular colors the same way as Khronos' specular-glossiness converter: specular
+	/// brighter than [`DIELECTRIC_SPECULAR`] is treated as metal. Texture slots are carried over unchanged,
+	/// the diffuse map becoming the base color map.
+	pub fn to_metallic_roughness(&self) -> Material {
+		if self.shading != ShadingModel::Phong {
+			return self.clone();
+		}
+
+		let diffuse = self.rgb(MatPropValueID::Diffuse, Vec3::one());
+		let specular = self.rgb(MatPropValueID::Specular, Vec3::zero());
+		let shininess = self.float(MatPropValueID::Shininess, 0.0).max(0.0);
+
+		let spec_strength = specular.x.max(specular.y).max(specular.z);
+		let one_minus_spec = 1.0 - spec_strength;
+		let metallic = solve_metallic(brightness(diffuse), brightness(specular), one_minus_spec);
+
+		let base_from_diffuse = diffuse * (one_minus_spec / (1.0 - DIELECTRIC_SPECULAR) /
+			(1.0 - metallic).max(f32::EPSILON));
+		let base_from_specular = (specular - Vec3::broadcast(DIELECTRIC_SPECULAR * (1.0 - metallic))) *
+			(1.0 / metallic.max(f32::EPSILON));
+		let t = metallic * metallic;
+		let base = base_from_diffuse * (1.0 - t) + base_from_specular * t;
+		let base = Vec3::new(base.x.clamp(0.0, 1.0), base.y.clamp(0.0, 1.0), base.z.clamp(0.0, 1.0));
+
+		let roughness = (2.0 / (shininess + 2.0)).sqrt().sqrt();
+
+		let mut out = self.clone();
+		out.shading = ShadingModel::MetallicRoughness;
+		out.properties.remove(&MatPropValueID::Ambient);
+		out.properties.remove(&MatPropValueID::Specular);
+		out.properties.remove(&MatPropValueID::Shininess);
+		out.properties.insert(MatPropValueID::Diffuse, MatPropValue::Vector4(Vec4::new(base.x, base.y, base.z,
+			self.opacity())));
+		out.properties.insert(MatPropValueID::Metallic, MatPropValue::Float(metallic));
+		out.properties.insert(MatPropValueID::Roughness, MatPropValue::Float(roughness));
+		out.textures.remove(&MatPropValueID::Ambient);
+		out.textures.remove(&MatPropValueID::Shininess);
+		out.textures.remove(&MatPropValueID::Specular);
+
+		out
+	}
+
+	/// Converts a metallic-roughness material to Phong.
+	///
+	/// This is the inverse of [`Material::to_metallic_roughness`]: the diffuse color is
+	/// `base * (1 - metallic)`, the specular color blends from [`DIELECTRIC_SPECULAR`] to the base color
+	/// by metallic, and the exponent is `2 / alpha² - 2` with `alpha = roughness²`, capped at 1024.
+	pub fn to_phong(&self) -> Material {
+		if self.shading != ShadingModel::MetallicRoughness {
+			return self.clone();
+		}
+
+		let base = self.rgb(MatPropValueID::Diffuse, Vec3::one());
+		let metallic = self.float(MatPropValueID::Metallic, 1.0).clamp(0.0, 1.0);
+		let roughness = self.float(MatPropValueID::Roughness, 1.0).clamp(0.0, 1.0);
+
+		let diffuse = base * (1.0 - metallic);
+		let specular = Vec3::broadcast(DIELECTRIC_SPECULAR) * (1.0 - metallic) + base * metallic;
+		let alpha = (roughness * roughness).max(f32::EPSILON);
+		let shininess = (2.0 / (alpha * alpha) - 2.0).clamp(0.0, 1024.0);
+
+		let mut out = self.clone();
+		out.shading = ShadingModel::Phong;
+		out.properties.remove(&MatPropValueID::Metallic);
+		out.properties.remove(&MatPropValueID::Roughness);
+		out.properties.insert(MatPropValueID::Diffuse, MatPropValue::Vector4(Vec4::new(diffuse.x, diffuse.y,
+			diffuse.z, self.opacity())));
+		out.properties.insert(MatPropValueID::Specular, MatPropValue::Vector3(specular));
+		out.properties.insert(MatPropValueID::Shininess, MatPropValue::Float(shininess));
+		out.textures.remove(&MatPropValueID::Metallic);
+		out.textures.remove(&MatPropValueID::Roughness);
+
+		out
+	}
+}
+
+/// Perceived brightness of a linear color
+fn brightness(c: Vec3) -> f32 {
+	(0.299 * c.x * c.x + 0.587 * c.y * c.y + 0.114 * c.z * c.z).sqrt()
+}
+
+/// Solves the metallic factor that best reproduces the specified diffuse and specular brightness
+fn solve_metallic(diffuse: f32, specular: f32, one_minus_spec: f32) -> f32 {
+	if specular < DIELECTRIC_SPECULAR {
+		return 0.0;
+	}
+
+	let a = DIELECTRIC_SPECULAR;
+	let b = diffuse * one_minus_spec / (1.0 - a) + specular - 2.0 * a;
+	let c = a - specular;
+	let d = (b * b - 4.0 * a * c).max(0.0);
+
+	((-b + d.sqrt()) / (2.0 * a)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+	use ultraviolet::vec::{
+		Vec2,
+		Vec3
+	};
+
+	use super::*;
+
+	#[test]
+	fn test_roundtrip() {
+		let mut m = Material::new("metal", ShadingModel::MetallicRoughness);
+		m.properties.insert(MatPropValueID::Diffuse, MatPropValue::Vector3(Vec3::new(0.9, 0.6, 0.2)));
+		m.properties.insert(MatPropValueID::Metallic, MatPropValue::Float(1.0));
+		m.properties.insert(MatPropValueID::Roughness, MatPropValue::Float(0.5));
+		m.textures.insert(MatPropValueID::Diffuse, TextureRef::new("gold.png"));
+
+		let phong = m.to_phong();
+		assert_eq!(phong.shading, ShadingModel::Phong);
+		assert_eq!(phong.textures.len(), 1);
+
+		let back = phong.to_metallic_roughness();
+		let get = |id| back.properties[&id].as_float().unwrap();
+		assert!((get(MatPropValueID::Metallic) - 1.0).abs() < 1e-3);
+		assert!((get(MatPropValueID::Roughness) - 0.5).abs() < 1e-3);
+		assert!((back.properties[&MatPropValueID::Diffuse].as_rgb().unwrap() - Vec3::new(0.9, 0.6, 0.2)).mag()
+			< 1e-3);
+	}
+
+	#[test]
+	fn test_dielectric() {
+		let mut m = Material::new("plastic", ShadingModel::Phong);
+		m.properties.insert(MatPropValueID::Diffuse, MatPropValue::Vector3(Vec3::new(0.5, 0.1, 0.1)));
+		m.properties.insert(MatPropValueID::Specular, MatPropValue::Float(0.02));
+
+		let pbr = m.to_metallic_roughness();
+		assert_eq!(pbr.properties[&MatPropValueID::Metallic], MatPropValue::Float(0.0));
+	}
+
+	#[test]
+	fn test_tex_transform() {
+		let t = TexTransform {
+			offset: Vec2::new(0.5, 0.0),
+			rotation: std::f32::consts::FRAC_PI_2,
+			scale: Vec2::new(2.0, 2.0),
+		};
+
+		assert!((t.apply(Vec2::new(1.0, 0.0)) - Vec2::new(0.5, 2.0)).mag() < 1e-5);
+	}
+}
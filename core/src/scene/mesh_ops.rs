@@ -0,0 +1,597 @@
+use std::collections::HashMap;
+
+use ultraviolet::vec::{
+	Vec2,
+	Vec3,
+	Vec4
+};
+
+use super::{
+	Face,
+	Mesh,
+	Vertex
+};
+
+impl Mesh {
+	/// Returns the positions of a face's corners, skipping any out of range index
+	pub fn face_positions(&self, face: &Face) -> Vec<Vec3> {
+		face.indices().iter().filter_map(|i| self.vertices.get(*i)).map(|v| v.position.truncated()).collect()
+	}
+
+	/// Do all of a face's indices refer to a vertex?
+	pub(crate) fn face_in_range(&self, face: &Face) -> bool {
+		face.indices().iter().all(|i| *i < self.vertices.len())
+	}
+
+	/// Returns a face's normal, scaled by its area, using Newell's method
+	pub fn face_area_normal(&self, face: &Face) -> Vec3 {
+		newell_normal(&self.face_positions(face))
+	}
+
+	/// Splits every quad and n-gon into triangles by ear clipping. Faces with an out of range index are kept as is.
+	pub fn triangulate(&mut self) {
+		let mut faces = Vec::with_capacity(self.faces.len());
+
+		for face in self.faces.iter() {
+			match face {
+				_ if !self.face_in_range(face) => faces.push(face.clone()),
+				Face::Triangle(_) => faces.push(face.clone()),
+				_ => {
+					let indices = face.indices();
+					for t in ear_clip(&self.face_positions(face)).iter() {
+						faces.push(Face::Triangle([indices[t[0]], indices[t[1]], indices[t[2]]]));
+					}
+				},
+			}
+		}
+
+		self.faces = faces;
+	}
+
+	/// Removes faces that repeat a vertex or have less area than `epsilon`
+	pub fn remove_degenerate_faces(&mut self, epsilon: f32) {
+		let mut faces = Vec::with_capacity(self.faces.len());
+
+		for face in self.faces.iter() {
+			// collapse repeated neighbouring corners, including the wrap-around
+			let mut indices = face.indices().to_vec();
+			indices.dedup();
+			while indices.len() > 1 && indices.first() == indices.last() {
+				indices.pop();
+			}
+
+			if indices.len() < 3 {
+				continue;
+			}
+
+			let face = Face::from_indices(&indices);
+			if self.face_area_normal(&face).mag() * 0.5 > epsilon {
+				faces.push(face);
+			}
+		}
+
+		self.faces = faces;
+	}
+
+	/// Merges vertices whose position and attributes are all within `epsilon` of each other,
+	/// then removes any faces that collapse
+	pub fn weld(&mut self, epsilon: f32) {
+		let cell_size = epsilon.max(1e-6);
+		let cell = |p: Vec4| -> (i64, i64, i64) {
+			((p.x / cell_size).floor() as i64, (p.y / cell_size).floor() as i64, (p.z / cell_size).floor() as i64)
+		};
+
+		let mut grid: HashMap<(i64, i64, i64), Vec<usize>> = HashMap::new();
+		let mut remap = vec![None; self.vertices.len()];
+		let mut next = 0;
+
+		for i in 0..self.vertices.len() {
+			let v = &self.vertices[i];
+			let (cx, cy, cz) = cell(v.position);
+			let mut found = None;
+
+			'search: for x in (cx - 1)..=(cx + 1) {
+				for y in (cy - 1)..=(cy + 1) {
+					for z in (cz - 1)..=(cz + 1) {
+						if let Some(candidates) = grid.get(&(x, y, z)) {
+							for j in candidates.iter() {
+								if vertices_match(v, &self.vertices[*j], epsilon) {
+									found = remap[*j];
+									break 'search;
+								}
+							}
+						}
+					}
+				}
+			}
+
+			remap[i] = match found {
+				Some(j) => Some(j),
+				None => {
+					grid.entry((cx, cy, cz)).or_default().push(i);
+					next += 1;
+					Some(next - 1)
+				},
+			};
+		}
+
+		self.remap_vertices(&remap);
+		self.remove_degenerate_faces(0.0);
+	}
+
+	/// Gives each face its own vertices, all sharing the face's normal. Faces with an out of range index are removed.
+	pub fn compute_flat_normals(&mut self) {
+		let mut vertices = Vec::with_capacity(self.faces.len() * 3);
+		let mut origin = Vec::with_capacity(self.faces.len() * 3);
+		let mut faces = Vec::with_capacity(self.faces.len());
+
+		for face in self.faces.iter().filter(|f| self.face_in_range(f)) {
+			let normal = self.face_area_normal(face).normalized();
+			let mut indices = vec![];
+
+			for i in face.indices().iter() {
+				let mut v = self.vertices[*i].clone();
+				v.normal = Some(normal);
+				indices.push(vertices.len());
+				vertices.push(v);
+				origin.push(*i);
+			}
+
+			faces.push(Face::from_indices(&indices));
+		}
+
+		self.vertices = vertices;
+		self.faces = faces;
+		self.remap_group_origins(&origin);
+		self.remap_morph_origins(&origin);
+	}
+
+	/// Computes area-weighted vertex normals, averaging only across faces that meet at an angle of at most
+	/// `max_angle` radians. Vertices on a sharper edge are split. Adjacency follows vertex indices, so
+	/// vertices split for UV seams should be welded first for seamless shading. Faces with an out of range index are
+	/// left untouched.
+	pub fn compute_smooth_normals(&mut self, max_angle: f32) {
+		let face_normals: Vec<Vec3> = self.faces.iter().map(|f| self.face_area_normal(f)).collect();
+		let unit_normals: Vec<Vec3> = face_normals.iter().map(|n| n.normalized()).collect();
+		let cos_limit = max_angle.cos() - 1e-6;
+
+		let valid: Vec<bool> = self.faces.iter().map(|f| self.face_in_range(f)).collect();
+		let mut vertex_faces = vec![vec![]; self.vertices.len()];
+		for (f, face) in self.faces.iter().enumerate().filter(|(f, _)| valid[*f]) {
+			for i in face.indices().iter() {
+				vertex_faces[*i].push(f);
+			}
+		}
+
+		// normals already assigned to each original vertex, with the index that carries them
+		let mut assigned: Vec<Vec<(Vec3, usize)>> = vec![vec![]; self.vertices.len()];
+		let mut origin: Vec<usize> = (0..self.vertices.len()).collect();
+
+		for f in (0..self.faces.len()).filter(|f| valid[*f]) {
+			for corner in 0..self.faces[f].indices().len() {
+				let v = self.faces[f].indices()[corner];
+
+				let mut normal = Vec3::zero();
+				for g in vertex_faces[v].iter() {
+					if unit_normals[*g].dot(unit_normals[f]) >= cos_limit {
+						normal += face_normals[*g];
+					}
+				}
+				let normal = normal.normalized();
+
+				let index = match assigned[v].iter().find(|(n, _)| n.dot(normal) > 0.9999) {
+					Some((_, i)) => *i,
+					None if assigned[v].is_empty() => {
+						self.vertices[v].normal = Some(normal);
+						assigned[v].push((normal, v));
+						v
+					},
+					None => {
+						let mut split = self.vertices[v].clone();
+						split.normal = Some(normal);
+						self.vertices.push(split);
+						origin.push(v);
+						let i = self.vertices.len() - 1;
+						assigned[v].push((normal, i));
+						i
+					},
+				};
+
+				self.faces[f].indices_mut()[corner] = index;
+			}
+		}
+
+		if origin.len() > assigned.len() {
+			self.remap_group_origins(&origin);
+			self.remap_morph_origins(&origin);
+		}
+	}
+
+	/// Generates per-vertex tangents from the first UV channel with MikkTSpace, matching the tangents baking tools
+	/// expect. The bitangent's sign is stored in W so that `bitangent = w * normal.cross(tangent)`. Vertices whose
+	/// corners receive different tangents are split, and vertices lacking a normal or UV are left untouched.
+	/// N-gons are treated as triangle fans.
+	pub fn compute_tangents(&mut self) {
+		let mut space = TangentSpace::new(self);
+		if !bevy_mikktspace::generate_tangents(&mut space) {
+			return;
+		}
+		let corners = space.corners;
+
+		// tangents already assigned to each original vertex, with the index that carries them
+		let mut assigned: Vec<Vec<(Vec4, usize)>> = vec![vec![]; self.vertices.len()];
+		let mut origin: Vec<usize> = (0..self.vertices.len()).collect();
+
+		for (f, tangents) in corners.iter().enumerate() {
+			for (corner, tangent) in tangents.iter().enumerate() {
+				let v = self.faces[f].indices()[corner];
+				let tangent = match tangent {
+					Some(t) if self.vertices[v].normal.is_some() && !self.vertices[v].uvw.is_empty() => *t,
+					_ => continue,
+				};
+
+				let same = |t: &Vec4| t.w == tangent.w && t.xyz().dot(tangent.xyz()) > 0.9999;
+				let index = match assigned[v].iter().find(|(t, _)| same(t)) {
+					Some((_, i)) => *i,
+					None if assigned[v].is_empty() => {
+						self.vertices[v].tangent = Some(tangent);
+						assigned[v].push((tangent, v));
+						v
+					},
+					None => {
+						let mut split = self.vertices[v].clone();
+						split.tangent = Some(tangent);
+						self.vertices.push(split);
+						origin.push(v);
+						let i = self.vertices.len() - 1;
+						assigned[v].push((tangent, i));
+						i
+					},
+				};
+
+				self.faces[f].indices_mut()[corner] = index;
+			}
+		}
+
+		if origin.len() > assigned.len() {
+			self.remap_group_origins(&origin);
+			self.remap_morph_origins(&origin);
+		}
+	}
+}
+
+/// A mesh as MikkTSpace sees it, with n-gons split into triangle fans
+struct TangentSpace<'a> {
+	mesh: &'a Mesh,
+
+	/// Each MikkTSpace face as its mesh face and the corners of that face it uses
+	faces: Vec<(usize, Vec<usize>)>,
+
+	/// Tangent generated for each corner of each mesh face
+	corners: Vec<Vec<Option<Vec4>>>,
+}
+
+impl<'a> TangentSpace<'a> {
+	fn new(mesh: &'a Mesh) -> TangentSpace<'a> {
+		let mut faces = vec![];
+
+		for (f, face) in mesh.faces.iter().enumerate() {
+			// faces with an out of range index are left for validation to report
+			let len = face.indices().len();
+			if face.indices().iter().any(|i| *i >= mesh.vertices.len()) {
+				continue;
+			}
+
+			match len {
+				3 | 4 => faces.push((f, (0..len).collect())),
+				_ => for k in 1..len.saturating_sub(1) {
+					faces.push((f, vec![0, k, k + 1]));
+				},
+			}
+		}
+
+		TangentSpace {
+			mesh,
+			faces,
+			corners: mesh.faces.iter().map(|f| vec![None; f.indices().len()]).collect(),
+		}
+	}
+
+	fn vertex(&self, face: usize, vert: usize) -> &Vertex {
+		let (f, corners) = &self.faces[face];
+		&self.mesh.vertices[self.mesh.faces[*f].indices()[corners[vert]]]
+	}
+}
+
+impl bevy_mikktspace::Geometry for TangentSpace<'_> {
+	fn num_faces(&self) -> usize {
+		self.faces.len()
+	}
+
+	fn num_vertices_of_face(&self, face: usize) -> usize {
+		self.faces[face].1.len()
+	}
+
+	fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+		let p = self.vertex(face, vert).position;
+		[p.x, p.y, p.z]
+	}
+
+	fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+		let n = self.vertex(face, vert).normal.unwrap_or_else(Vec3::zero);
+		[n.x, n.y, n.z]
+	}
+
+	fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+		match self.vertex(face, vert).uvw.first() {
+			Some(uv) => [uv.x, uv.y],
+			None => [0.0, 0.0],
+		}
+	}
+
+	fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+		let (f, corners) = &self.faces[face];
+		let slot = &mut self.corners[*f][corners[vert]];
+
+		// fan triangles share corners of an n-gon, so the first tangent wins
+		if slot.is_none() {
+			*slot = Some(Vec4::new(tangent[0], tangent[1], tangent[2], tangent[3]));
+		}
+	}
+}
+
+/// Are the two vertices equal within `epsilon`?
+fn vertices_match(a: &Vertex, b: &Vertex, epsilon: f32) -> bool {
+	let close3 = |a: Vec3, b: Vec3| (a - b).mag() <= epsilon;
+	let close4 = |a: Vec4, b: Vec4| (a - b).mag() <= epsilon;
+
+	close4(a.position, b.position) &&
+		match (a.normal, b.normal) {
+			(Some(x), Some(y)) => close3(x, y),
+			(None, None) => true,
+			_ => false,
+		} &&
+		match (a.color, b.color) {
+			(Some(x), Some(y)) => close4(x, y),
+			(None, None) => true,
+			_ => false,
+		} &&
+		a.uvw.len() == b.uvw.len() &&
+		a.uvw.iter().zip(b.uvw.iter()).all(|(x, y)| close3(*x, *y)) &&
+		a.groups == b.groups &&
+		a.weights == b.weights
+}
+
+/// Returns a polygon's normal scaled by twice its area
+pub fn newell_normal(points: &[Vec3]) -> Vec3 {
+	let mut n = Vec3::zero();
+
+	for i in 0..points.len() {
+		let a = points[i];
+		let b = points[(i + 1) % points.len()];
+		n.x += (a.y - b.y) * (a.z + b.z);
+		n.y += (a.z - b.z) * (a.x + b.x);
+		n.z += (a.x - b.x) * (a.y + b.y);
+	}
+
+	n
+}
+
+/// Triangulates a simple polygon by ear clipping, returning triangles as indices into `points`.
+/// Triangles keep the polygon's winding.
+pub fn ear_clip(points: &[Vec3]) -> Vec<[usize; 3]> {
+	if points.len() < 3 {
+		return vec![];
+	}
+
+	let normal = newell_normal(points);
+
+	// project onto the plane most facing the normal, keeping the winding counter-clockwise
+	let abs = Vec3::new(normal.x.abs(), normal.y.abs(), normal.z.abs());
+	let (flat, sign): (Vec<Vec2>, f32) = if abs.x >= abs.y && abs.x >= abs.z {
+		(points.iter().map(|p| Vec2::new(p.y, p.z)).collect(), normal.x.signum())
+	} else if abs.y >= abs.z {
+		(points.iter().map(|p| Vec2::new(p.z, p.x)).collect(), normal.y.signum())
+	} else {
+		(points.iter().map(|p| Vec2::new(p.x, p.y)).collect(), normal.z.signum())
+	};
+
+	let cross = |a: Vec2, b: Vec2, c: Vec2| ((b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)) * sign;
+
+	let mut remaining: Vec<usize> = (0..points.len()).collect();
+	let mut triangles = Vec::with_capacity(points.len() - 2);
+
+	while remaining.len() > 3 {
+		let n = remaining.len();
+		let mut ear = None;
+
+		for i in 0..n {
+			let (a, b, c) = (remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]);
+
+			if cross(flat[a], flat[b], flat[c]) <= 0.0 {
+				continue;
+			}
+
+			let contains_other = remaining.iter()
+				.filter(|p| **p != a && **p != b && **p != c)
+				.any(|p| {
+					let p = flat[*p];
+					cross(flat[a], flat[b], p) >= 0.0 && cross(flat[b], flat[c], p) >= 0.0 &&
+						cross(flat[c], flat[a], p) >= 0.0
+				});
+
+			if !contains_other {
+				ear = Some(i);
+				break;
+			}
+		}
+
+		// degenerate or self-intersecting input, so clip anything to guarantee progress
+		let i = ear.unwrap_or(1);
+		triangles.push([remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]]);
+		remaining.remove(i);
+	}
+
+	triangles.push([remaining[0], remaining[1], remaining[2]]);
+	triangles
+}
+
+#[cfg(test)]
+mod tests {
+	use ultraviolet::vec::{
+		Vec3,
+		Vec4
+	};
+
+	use super::*;
+	use crate::scene::VertexGroup;
+
+	fn vert(x: f32, y: f32, z: f32) -> Vertex {
+		let mut v = Vertex::new(None);
+		v.position = Vec4::new(x, y, z, 1.0);
+		v.uvw.push(Vec3::new(x, y, 0.0));
+		v
+	}
+
+	/// An L-shaped, concave hexagon on the XY plane
+	fn l_shape() -> Mesh {
+		let mut mesh = Mesh::default();
+		for (x, y) in [(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0)].iter() {
+			mesh.vertices.push(vert(*x, *y, 0.0));
+		}
+		mesh.faces.push(Face::Ngon((0..6).collect()));
+		mesh
+	}
+
+	#[test]
+	fn test_triangulate_concave() {
+		let mut mesh = l_shape();
+		let area = mesh.face_area_normal(&mesh.faces[0]);
+		mesh.triangulate();
+
+		assert_eq!(mesh.faces.len(), 4);
+		let sum = mesh.faces.iter().fold(Vec3::zero(), |n, f| n + mesh.face_area_normal(f));
+		assert!((sum - area).mag() < 1e-5);
+		assert!(mesh.faces.iter().all(|f| mesh.face_area_normal(f).z > 0.0));
+	}
+
+	#[test]
+	fn test_weld_and_compact() {
+		let mut mesh = Mesh {
+			vertices: vec![vert(0.0, 0.0, 0.0), vert(1.0, 0.0, 0.0), vert(0.0, 1.0, 0.0),
+				vert(1.0, 0.0, 0.00001), vert(1.0, 1.0, 0.0), vert(5.0, 5.0, 5.0)],
+			faces: vec![Face::Triangle([0, 1, 2]), Face::Triangle([3, 4, 2]), Face::Triangle([5, 0, 1])],
+			..Default::default()
+		};
+		mesh.weld(0.001);
+
+		assert_eq!(mesh.vertices.len(), 5);
+		assert_eq!(mesh.faces[1], Face::Triangle([1, 3, 2]));
+
+		mesh.drop_vertex(4);
+		assert_eq!(mesh.faces, vec![Face::Triangle([0, 1, 2]), Face::Triangle([1, 3, 2])]);
+		assert_eq!(mesh.vertices.len(), 4);
+
+		// a bad index drops its face instead of panicking
+		mesh.faces.push(Face::Triangle([0, 1, 9]));
+		mesh.remove_unused_vertices();
+		assert_eq!(mesh.faces, vec![Face::Triangle([0, 1, 2]), Face::Triangle([1, 3, 2])]);
+	}
+
+	#[test]
+	fn test_normals_and_tangents() {
+		let mut mesh = l_shape();
+		mesh.triangulate();
+		mesh.compute_smooth_normals(std::f32::consts::PI);
+		mesh.compute_tangents();
+
+		assert_eq!(mesh.vertices.len(), 6);
+		for v in mesh.vertices.iter() {
+			assert!((v.normal.unwrap() - Vec3::unit_z()).mag() < 1e-5);
+			assert!((v.tangent.unwrap() - Vec4::new(1.0, 0.0, 0.0, 1.0)).mag() < 1e-5);
+		}
+
+		mesh.vertex_groups.push(VertexGroup {
+			id: None,
+			children: vec![0],
+		});
+		mesh.compute_flat_normals();
+		assert_eq!(mesh.vertices.len(), 12);
+
+		// every copy of vertex 0 stays in its group
+		let children = &mesh.vertex_groups[0].children;
+		let corners = mesh.faces.iter()
+			.flat_map(|f| f.indices())
+			.filter(|i| mesh.vertices[**i].position.xyz() == Vec3::zero());
+		assert_eq!(children.len(), corners.clone().count());
+		assert!(corners.into_iter().all(|i| children.contains(i)));
+	}
+
+	#[test]
+	fn test_mirrored_tangents_split() {
+		// two triangles sharing the edge 1-2, with the second's UVs mirrored
+		let mut mesh = Mesh {
+			vertices: vec![vert(0.0, 0.0, 0.0), vert(1.0, 0.0, 0.0), vert(1.0, 1.0, 0.0), vert(2.0, 0.0, 0.0)],
+			faces: vec![Face::Triangle([0, 1, 2]), Face::Triangle([1, 3, 2])],
+			..Default::default()
+		};
+		mesh.vertices[3].uvw[0] = Vec3::new(0.0, 0.0, 0.0);
+		mesh.vertex_groups.push(VertexGroup {
+			id: None,
+			children: vec![1],
+		});
+		mesh.compute_smooth_normals(std::f32::consts::PI);
+		mesh.compute_tangents();
+
+		assert_eq!(mesh.vertices.len(), 6);
+		let w = |i: usize| mesh.vertices[i].tangent.unwrap().w;
+		let [a, b, c] = [mesh.faces[1].indices()[0], mesh.faces[1].indices()[1], mesh.faces[1].indices()[2]];
+		assert_eq!((w(0), w(a), w(b), w(c)), (1.0, -1.0, -1.0, -1.0));
+		assert_eq!(mesh.vertex_groups[0].children, vec![1, a]);
+	}
+
+	#[test]
+	fn test_hard_edges_split() {
+		// two faces folded at 90 degrees along the shared edge 1-2
+		let mut mesh = Mesh {
+			vertices: vec![vert(0.0, 0.0, 0.0), vert(1.0, 0.0, 0.0), vert(1.0, 1.0, 0.0),
+				vert(1.0, 0.0, -1.0)],
+			faces: vec![Face::Triangle([0, 1, 2]), Face::Triangle([1, 3, 2])],
+			..Default::default()
+		};
+		mesh.vertex_groups.push(VertexGroup {
+			id: None,
+			children: vec![1, 2],
+		});
+		mesh.compute_smooth_normals(0.5);
+
+		assert_eq!(mesh.vertices.len(), 6);
+		for i in mesh.faces[1].indices().iter() {
+			let p = mesh.vertices[*i].position;
+			assert_eq!(mesh.vertex_groups[0].children.contains(i), p.x == 1.0 && p.z == 0.0);
+		}
+		mesh.faces.push(Face::Triangle([0, 0, 1]));
+		mesh.remove_degenerate_faces(0.0);
+		assert_eq!(mesh.faces.len(), 2);
+	}
+
+	#[test]
+	fn test_bad_index() {
+		let mut mesh = l_shape();
+		let bad = Face::Quad([0, 1, 2, 9]);
+		mesh.faces.push(bad.clone());
+
+		assert_eq!(mesh.face_positions(&bad).len(), 3);
+		mesh.triangulate();
+		assert_eq!(mesh.faces.len(), 5);
+		assert_eq!(mesh.faces[4], bad);
+
+		mesh.compute_smooth_normals(std::f32::consts::PI);
+		assert_eq!(mesh.faces[4], bad);
+		assert!(mesh.vertices.iter().all(|v| (v.normal.unwrap() - Vec3::unit_z()).mag() < 1e-5));
+
+		mesh.compute_flat_normals();
+		assert_eq!(mesh.faces.len(), 4);
+		assert_eq!(mesh.vertices.len(), 12);
+	}
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use ultraviolet::vec::{
+	Vec3,
+	Vec4
+};
+
+use super::Mesh;
+
+/// Per-vertex offsets applied by a [`MorphTarget`] at full weight
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MorphDelta {
+	/// Index into [`Mesh::vertices`]
+	pub vertex: usize,
+	pub position: Vec3,
+	pub normal: Vec3,
+
+	/// Offset of the UV channel selected by [`MorphTarget::uv_channel`]
+	pub uvw: Vec3,
+}
+
+impl MorphDelta {
+	pub fn new(vertex: usize) -> MorphDelta {
+		MorphDelta {
+			vertex,
+			position: Vec3::zero(),
+			normal: Vec3::zero(),
+			uvw: Vec3::zero(),
+		}
+	}
+}
+
+/// A named blend shape, storing offsets only for the vertices it moves
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MorphTarget {
+	pub name: String,
+	pub uv_channel: usize,
+	pub deltas: Vec<MorphDelta>,
+}
+
+impl MorphTarget {
+	pub fn new(name: &str) -> MorphTarget {
+		MorphTarget {
+			name: name.to_owned(),
+			uv_channel: 0,
+			deltas: vec![],
+		}
+	}
+
+	/// Builds a target from the differences between two meshes with matching vertex order, such as a
+	/// base pose and a vertex animation frame. Offsets of `epsilon` or less are ignored.
+	pub fn from_difference(name: &str, base: &Mesh, target: &Mesh, epsilon: f32) -> MorphTarget {
+		let mut morph = MorphTarget::new(name);
+
+		for (i, (a, b)) in base.vertices.iter().zip(target.vertices.iter()).enumerate() {
+			let mut d = MorphDelta::new(i);
+			d.position = (b.position - a.position).truncated();
+			if let (Some(na), Some(nb)) = (a.normal, b.normal) {
+				d.normal = nb - na;
+			}
+			if let (Some(ua), Some(ub)) = (a.uvw.first(), b.uvw.first()) {
+				d.uvw = *ub - *ua;
+			}
+
+			if d.position.mag() > epsilon || d.normal.mag() > epsilon || d.uvw.mag() > epsilon {
+				morph.deltas.push(d);
+			}
+		}
+
+		morph
+	}
+}
+
+impl Mesh {
+	/// Returns the index of the morph target with the specified name, if present
+	pub fn find_morph_target(&self, name: &str) -> Option<usize> {
+		self.morph_targets.iter().position(|m| m.name == name)
+	}
+
+	/// Returns a copy of the mesh with its morph targets blended in by `weights`, which are matched to
+	/// [`Mesh::morph_targets`] by index. The result has no morph targets.
+	pub fn bake_morphs(&self, weights: &[f32]) -> Mesh {
+		let mut mesh = self.clone();
+		mesh.morph_targets.clear();
+
+		for (target, weight) in self.morph_targets.iter().zip(weights.iter()) {
+			if *weight == 0.0 {
+				continue;
+			}
+
+			for d in target.deltas.iter() {
+				let v = match mesh.vertices.get_mut(d.vertex) {
+					Some(v) => v,
+					None => continue,
+				};
+
+				let p = d.position * *weight;
+				v.position += Vec4::new(p.x, p.y, p.z, 0.0);
+				if let Some(n) = v.normal.as_mut() {
+					*n += d.normal * *weight;
+				}
+				if let Some(uv) = v.uvw.get_mut(target.uv_channel) {
+					*uv += d.uvw * *weight;
+				}
+			}
+		}
+
+		for v in mesh.vertices.iter_mut() {
+			if let Some(n) = v.normal.as_mut() {
+				if n.mag_sq() > 0.0 {
+					n.normalize();
+				}
+			}
+		}
+
+		mesh
+	}
+
+	/// Rebuilds morph deltas after vertices were duplicated, where `origin[i]` is the old vertex that new
+	/// vertex `i` was copied from
+	pub fn remap_morph_origins(&mut self, origin: &[usize]) {
+		for target in self.morph_targets.iter_mut() {
+			let old: HashMap<usize, MorphDelta> = target.deltas.drain(..).map(|d| (d.vertex, d)).collect();
+
+			target.deltas = origin.iter().enumerate()
+				.filter_map(|(i, o)| old.get(o).map(|d| MorphDelta {
+					vertex: i,
+					..*d
+				}))
+				.collect();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ultraviolet::vec::{
+		Vec3,
+		Vec4
+	};
+
+	use super::*;
+	use crate::scene::{
+		Face,
+		Vertex
+	};
+
+	fn mesh() -> Mesh {
+		let mut mesh = Mesh::default();
+		for x in 0..4 {
+			let mut v = Vertex::new(None);
+			v.position = Vec4::new(x as f32, 0.0, 0.0, 1.0);
+			v.normal = Some(Vec3::unit_z());
+			mesh.vertices.push(v);
+		}
+		mesh.faces.push(Face::Quad([0, 1, 2, 3]));
+		mesh
+	}
+
+	#[test]
+	fn test_bake() {
+		let base = mesh();
+		let mut moved = base.clone();
+		moved.vertices[2].position.y = 2.0;
+
+		let mut mesh = base.clone();
+		mesh.morph_targets.push(MorphTarget::from_difference("raise", &base, &moved, 1e-6));
+		assert_eq!(mesh.morph_targets[0].deltas.len(), 1);
+
+		let baked = mesh.bake_morphs(&[0.5]);
+		assert_eq!(baked.vertices[2].position, Vec4::new(2.0, 1.0, 0.0, 1.0));
+		assert!(baked.morph_targets.is_empty());
+	}
+
+	#[test]
+	fn test_compaction_remaps_deltas() {
+		let mut mesh = mesh();
+		let mut morph = MorphTarget::new("m");
+		morph.deltas.push(MorphDelta::new(3));
+		mesh.morph_targets.push(morph);
+
+		mesh.drop_vertex(0);
+		assert_eq!(mesh.morph_targets[0].deltas[0].vertex, 2);
+
+		mesh.faces.push(Face::Triangle([0, 1, 2]));
+		mesh.compute_flat_normals();
+		assert_eq!(mesh.morph_targets[0].deltas[0].vertex, 2);
+	}
+}
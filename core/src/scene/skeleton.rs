@@ -0,0 +1,184 @@
+use ultraviolet::{
+	mat::Mat4,
+	rotor::Rotor3,
+	vec::Vec3
+};
+
+use super::{
+	ObjRef,
+	trs_matrix
+};
+
+/// A single joint of a [`Skeleton`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bone {
+	pub id: ObjRef,
+	pub parent: Option<usize>,
+	pub translation: Vec3,
+	pub rotation: Rotor3,
+	pub scale: Vec3,
+
+	/// Transforms a mesh-space position into the bone's space at bind time
+	pub inverse_bind: Mat4,
+}
+
+impl Bone {
+	pub fn new(id: ObjRef, parent: Option<usize>) -> Bone {
+		Bone {
+			id,
+			parent,
+			translation: Vec3::zero(),
+			rotation: Rotor3::identity(),
+			scale: Vec3::one(),
+			inverse_bind: Mat4::identity(),
+		}
+	}
+
+	/// Returns the bone's transform relative to its parent
+	pub fn local_matrix(&self) -> Mat4 {
+		trs_matrix(self.translation, self.rotation, self.scale)
+	}
+}
+
+/// A bone hierarchy shared by one or more skinned meshes
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Skeleton {
+	pub id: Option<ObjRef>,
+
+	/// Bones, ordered so that every parent precedes its children
+	pub bones: Vec<Bone>,
+}
+
+impl Skeleton {
+	/// Appends a bone, returning its index
+	pub fn add_bone(&mut self, bone: Bone) -> usize {
+		self.bones.push(bone);
+		self.bones.len() - 1
+	}
+
+	/// Returns the index of the bone with the specified ID, if present
+	pub fn find_bone(&self, id: &ObjRef) -> Option<usize> {
+		self.bones.iter().position(|b| b.id == *id)
+	}
+
+	/// Returns the indices of the bones directly parented to the specified bone
+	pub fn children(&self, bone: usize) -> Vec<usize> {
+		self.bones.iter().enumerate()
+			.filter(|(_, b)| b.parent == Some(bone))
+			.map(|(i, _)| i)
+			.collect()
+	}
+
+	/// Returns the skeleton-space transform of every bone in its current pose
+	pub fn world_matrices(&self) -> Vec<Mat4> {
+		let mut world: Vec<Mat4> = Vec::with_capacity(self.bones.len());
+
+		for bone in self.bones.iter() {
+			let local = bone.local_matrix();
+
+			world.push(match bone.parent {
+				Some(p) if p < world.len() => world[p] * local,
+				_ => local,
+			});
+		}
+
+		world
+	}
+
+	/// Recomputes every bone's inverse bind matrix from the current pose
+	pub fn compute_inverse_bind_matrices(&mut self) {
+		let world = self.world_matrices();
+
+		for (bone, m) in self.bones.iter_mut().zip(world.iter()) {
+			bone.inverse_bind = m.inversed();
+		}
+	}
+
+	/// Returns the matrices that take bind pose vertices into the current pose
+	pub fn skinning_matrices(&self) -> Vec<Mat4> {
+		self.world_matrices().iter().zip(self.bones.iter())
+			.map(|(m, b)| *m * b.inverse_bind)
+			.collect()
+	}
+
+	/// Does every bone's parent precede it?
+	pub fn is_sorted(&self) -> bool {
+		self.bones.iter().enumerate().all(|(i, b)| match b.parent {
+			Some(p) => p < i,
+			None => true,
+		})
+	}
+}
+
+/// A joint influence on a vertex
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JointWeight {
+	/// Index into [`Skeleton::bones`]
+	pub joint: usize,
+	pub weight: f32,
+}
+
+impl JointWeight {
+	pub fn new(joint: usize, weight: f32) -> JointWeight {
+		JointWeight {
+			joint,
+			weight,
+		}
+	}
+}
+
+/// Scales the weights so they add up to 1, dropping any that are zero or negative
+pub fn normalize_weights(weights: &mut Vec<JointWeight>) {
+	weights.retain(|w| w.weight > 0.0);
+
+	let total: f32 = weights.iter().map(|w| w.weight).sum();
+	if total > 0.0 {
+		for w in weights.iter_mut() {
+			w.weight /= total;
+		}
+	}
+}
+
+/// Keeps only the `max` strongest influences, then renormalises them
+pub fn limit_weights(weights: &mut Vec<JointWeight>, max: usize) {
+	weights.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+	weights.truncate(max);
+	normalize_weights(weights);
+}
+
+#[cfg(test)]
+mod tests {
+	use ultraviolet::vec::Vec3;
+
+	use super::*;
+
+	#[test]
+	fn test_world_matrices() {
+		let mut skel = Skeleton::default();
+		let mut root = Bone::new(ObjRef::Name("root".to_string()), None);
+		root.translation = Vec3::new(0.0, 1.0, 0.0);
+		let mut arm = Bone::new(ObjRef::Name("arm".to_string()), Some(skel.add_bone(root)));
+		arm.translation = Vec3::new(2.0, 0.0, 0.0);
+		skel.add_bone(arm);
+
+		let world = skel.world_matrices();
+		assert_eq!(world[1].extract_translation(), Vec3::new(2.0, 1.0, 0.0));
+		assert_eq!(skel.find_bone(&ObjRef::Name("arm".to_string())), Some(1));
+
+		skel.compute_inverse_bind_matrices();
+		for m in skel.skinning_matrices().iter() {
+			assert_eq!(m.transform_point3(Vec3::one()), Vec3::one());
+		}
+	}
+
+	#[test]
+	fn test_limit_weights() {
+		let mut w = vec![JointWeight::new(0, 0.1), JointWeight::new(1, 0.6), JointWeight::new(2, 0.3),
+			JointWeight::new(3, 0.0)];
+		limit_weights(&mut w, 2);
+
+		assert_eq!(w.len(), 2);
+		assert_eq!(w[0].joint, 1);
+		assert!((w[0].weight + w[1].weight - 1.0).abs() < 1e-6);
+	}
+}
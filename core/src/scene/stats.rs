@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use ultraviolet::vec::Vec2;
+
+use super::{
+	Face,
+	Mesh
+};
+
+/// Grid resolution used for UV coverage in [`Mesh::stats`]
+const UV_GRID_SIZE: usize = 128;
+
+/// A summary of a mesh's topology, for checking ripped models
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshStats {
+	pub vertices: usize,
+	pub faces: usize,
+	pub triangles: usize,
+	pub quads: usize,
+	pub ngons: usize,
+
+	/// Faces with repeated vertex indices
+	pub degenerate_faces: usize,
+
+	/// Vertices not referenced by any face
+	pub unused_vertices: usize,
+
+	/// Unique edges
+	pub edges: usize,
+
+	/// Edges used by only one face
+	pub boundary_edges: usize,
+
+	/// Edges shared by more than two faces
+	pub non_manifold_edges: usize,
+
+	/// Fraction of the first UV channel's unit square covered by faces, if the mesh has UVs
+	pub uv_coverage: Option<f32>,
+
+	/// Fraction of the first UV channel's unit square covered by more than one face
+	pub uv_overlap: Option<f32>,
+}
+
+impl Mesh {
+	/// Gathers topology statistics
+	pub fn stats(&self) -> MeshStats {
+		let mut stats = MeshStats {
+			vertices: self.vertices.len(),
+			faces: self.faces.len(),
+			..MeshStats::default()
+		};
+
+		let mut used = vec![false; self.vertices.len()];
+		let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+
+		for face in self.faces.iter() {
+			match face {
+				Face::Triangle(_) => stats.triangles += 1,
+				Face::Quad(_) => stats.quads += 1,
+				Face::Ngon(_) => stats.ngons += 1,
+			}
+
+			let idx = face.indices();
+			let mut sorted = idx.to_vec();
+			sorted.sort_unstable();
+			sorted.dedup();
+			if sorted.len() < idx.len() || idx.len() < 3 {
+				stats.degenerate_faces += 1;
+			}
+
+			for (k, a) in idx.iter().enumerate() {
+				if let Some(u) = used.get_mut(*a) {
+					*u = true;
+				}
+
+				let b = idx[(k + 1) % idx.len()];
+				if *a != b {
+					*edges.entry((*a.min(&b), *a.max(&b))).or_insert(0) += 1;
+				}
+			}
+		}
+
+		stats.unused_vertices = used.iter().filter(|u| !**u).count();
+		stats.edges = edges.len();
+		stats.boundary_edges = edges.values().filter(|n| **n == 1).count();
+		stats.non_manifold_edges = edges.values().filter(|n| **n > 2).count();
+
+		if let Some((coverage, overlap)) = self.uv_coverage(0, UV_GRID_SIZE) {
+			stats.uv_coverage = Some(coverage);
+			stats.uv_overlap = Some(overlap);
+		}
+
+		stats
+	}
+
+	/// Rasterizes the faces of a UV channel onto a `size` by `size` grid over the unit square, returning
+	/// the fraction of cells covered and the fraction covered more than once. UVs outside the unit square
+	/// are wrapped. Returns `None` if no vertex has the channel.
+	pub fn uv_coverage(&self, channel: usize, size: usize) -> Option<(f32, f32)> {
+		if size == 0 || !self.vertices.iter().any(|v| v.uvw.len() > channel) {
+			return None;
+		}
+
+		let mut hits = vec![0u8; size * size];
+
+		for (_, tri) in self.fan_triangles().iter() {
+			let uv: Vec<Vec2> = match tri.iter()
+				.map(|i| self.vertices.get(*i).and_then(|v| v.uvw.get(channel)).map(|t| Vec2::new(t.x, t.y)))
+				.collect::<Option<Vec<Vec2>>>()
+			{
+				Some(uv) => uv,
+				None => continue,
+			};
+
+			// shift the triangle so its first corner lies in the unit square, then wrap each cell
+			let shift = Vec2::new(uv[0].x.floor(), uv[0].y.floor());
+			let (a, b, c) = ((uv[0] - shift) * size as f32, (uv[1] - shift) * size as f32,
+				(uv[2] - shift) * size as f32);
+			rasterize(a, b, c, |x, y| {
+				let cell = &mut hits[y.rem_euclid(size as isize) as usize * size + x.rem_euclid(size as isize) as usize];
+				*cell = cell.saturating_add(1);
+			});
+		}
+
+		let total = (size * size) as f32;
+		Some((
+			hits.iter().filter(|h| **h > 0).count() as f32 / total,
+			hits.iter().filter(|h| **h > 1).count() as f32 / total,
+		))
+	}
+}
+
+/// Calls `f` for every grid cell whose center lies inside the triangle, in either winding
+fn rasterize<F: FnMut(isize, isize)>(a: Vec2, b: Vec2, c: Vec2, mut f: F) {
+	let edge = |p: Vec2, q: Vec2, r: Vec2| (q.x - p.x) * (r.y - p.y) - (q.y - p.y) * (r.x - p.x);
+	let area = edge(a, b, c);
+	if area == 0.0 {
+		return;
+	}
+
+	let x0 = a.x.min(b.x).min(c.x).floor() as isize;
+	let x1 = a.x.max(b.x).max(c.x).ceil() as isize;
+	let y0 = a.y.min(b.y).min(c.y).floor() as isize;
+	let y1 = a.y.max(b.y).max(c.y).ceil() as isize;
+
+	for y in y0..y1 {
+		for x in x0..x1 {
+			let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+			let (w0, w1, w2) = (edge(b, c, p), edge(c, a, p), edge(a, b, p));
+			let inside = if area > 0.0 {
+				w0 >= 0.0 && w1 >= 0.0 && w2 >= 0.0
+			} else {
+				w0 <= 0.0 && w1 <= 0.0 && w2 <= 0.0
+			};
+
+			if inside {
+				f(x, y);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ultraviolet::vec::{
+		Vec3,
+		Vec4
+	};
+
+	use super::*;
+	use crate::scene::Vertex;
+
+	#[test]
+	fn test_stats() {
+		let mut mesh = Mesh::default();
+		for (x, y) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.5, 2.0)].iter() {
+			let mut v = Vertex::new(None);
+			v.position = Vec4::new(*x, *y, 0.0, 1.0);
+			v.uvw.push(Vec3::new(*x, *y * 0.5, 0.0));
+			mesh.vertices.push(v);
+		}
+		mesh.faces.push(Face::Quad([0, 1, 2, 3]));
+		mesh.faces.push(Face::Triangle([3, 2, 4]));
+		mesh.faces.push(Face::Triangle([2, 3, 4]));
+		mesh.faces.push(Face::Triangle([2, 3, 4]));
+
+		let stats = mesh.stats();
+		assert_eq!(stats.vertices, 5);
+		assert_eq!(stats.quads, 1);
+		assert_eq!(stats.triangles, 3);
+		assert_eq!(stats.unused_vertices, 0);
+		assert_eq!(stats.edges, 6);
+		assert_eq!(stats.boundary_edges, 3);
+		assert_eq!(stats.non_manifold_edges, 3);
+
+		// the quad covers the lower half, the stacked triangles a quarter of the upper half
+		let coverage = stats.uv_coverage.unwrap();
+		assert!((coverage - 0.75).abs() < 0.02);
+		assert!((stats.uv_overlap.unwrap() - 0.25).abs() < 0.02);
+	}
+}
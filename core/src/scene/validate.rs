@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+
+use ultraviolet::{
+	rotor::Rotor3,
+	vec::Vec3
+};
+
+use super::{
+	Mesh,
+	NodeData,
+	NodePath,
+	Scene
+};
+
+/// How serious a [`Diagnostic`] is
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+	/// Suspicious but usable data
+	Warning,
+
+	/// Data an exporter can't handle
+	Error,
+}
+
+/// A problem found by [`Scene::validate`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Issue {
+	/// [`Node::parent`](super::Node::parent) disagrees with the node's position in the tree
+	ParentMismatch {
+		expected: Option<usize>,
+		found: Option<usize>,
+	},
+
+	/// The node's translation, rotation or scale is NaN or infinite
+	NonFiniteTransform,
+
+	/// [`Mesh::skeleton`] doesn't index [`Scene::skeletons`]
+	SkeletonOutOfRange(usize),
+
+	/// A face refers to a vertex past the end of [`Mesh::vertices`]
+	FaceIndexOutOfRange {
+		face: usize,
+		index: usize,
+	},
+
+	/// A face has fewer than three vertices or repeats one
+	DegenerateFace(usize),
+
+	/// A vertex refers to a group past the end of [`Mesh::vertex_groups`]
+	VertexGroupOutOfRange {
+		vertex: usize,
+		group: usize,
+	},
+
+	/// A vertex group lists a vertex past the end of [`Mesh::vertices`]
+	GroupVertexOutOfRange {
+		group: usize,
+		vertex: usize,
+	},
+
+	/// A vertex position is NaN or infinite
+	NonFinitePosition(usize),
+
+	/// A vertex normal, tangent, UV or color is NaN or infinite
+	NonFiniteAttribute(usize),
+
+	/// A vertex weight refers to a bone the mesh's skeleton lacks, or the mesh has no skeleton
+	JointOutOfRange {
+		vertex: usize,
+		joint: usize,
+	},
+
+	/// A morph target offsets a vertex past the end of [`Mesh::vertices`]
+	MorphVertexOutOfRange {
+		target: usize,
+		vertex: usize,
+	},
+
+	/// A bone's parent doesn't precede it in [`Skeleton::bones`](super::skeleton::Skeleton::bones)
+	BoneOrder {
+		skeleton: usize,
+		bone: usize,
+	},
+}
+
+impl Issue {
+	pub fn severity(&self) -> Severity {
+		match self {
+			Issue::ParentMismatch { .. } |
+			Issue::DegenerateFace(_) |
+			Issue::NonFiniteAttribute(_) => Severity::Warning,
+			_ => Severity::Error,
+		}
+	}
+}
+
+/// A validation finding
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+	pub severity: Severity,
+
+	/// Path to the offending node, or `None` for scene-wide data such as skeletons
+	pub path: Option<NodePath>,
+	pub issue: Issue,
+}
+
+impl Diagnostic {
+	fn new(path: Option<NodePath>, issue: Issue) -> Diagnostic {
+		Diagnostic {
+			severity: issue.severity(),
+			path,
+			issue,
+		}
+	}
+}
+
+fn finite3(v: Vec3) -> bool {
+	v.x.is_finite() && v.y.is_finite() && v.z.is_finite()
+}
+
+fn finite_rotor(r: Rotor3) -> bool {
+	r.s.is_finite() && r.bv.xy.is_finite() && r.bv.xz.is_finite() && r.bv.yz.is_finite()
+}
+
+/// Checks a mesh, where `bones` is the bone count of its skeleton if it has a valid one
+fn check_mesh(mesh: &Mesh, bones: Option<usize>, skeletons: usize, mut report: impl FnMut(Issue)) {
+	let vertex_count = mesh.vertices.len();
+
+	if let Some(s) = mesh.skeleton {
+		if s >= skeletons {
+			report(Issue::SkeletonOutOfRange(s));
+		}
+	}
+
+	for (f, face) in mesh.faces.iter().enumerate() {
+		let idx = face.indices();
+		if let Some(i) = idx.iter().find(|i| **i >= vertex_count) {
+			report(Issue::FaceIndexOutOfRange {
+				face: f,
+				index: *i,
+			});
+		} else if idx.len() < 3 || (1..idx.len()).any(|k| idx[..k].contains(&idx[k])) {
+			report(Issue::DegenerateFace(f));
+		}
+	}
+
+	for (i, v) in mesh.vertices.iter().enumerate() {
+		if !v.position.x.is_finite() || !v.position.y.is_finite() || !v.position.z.is_finite() ||
+			!v.position.w.is_finite()
+		{
+			report(Issue::NonFinitePosition(i));
+		} else if !v.normal.map(finite3).unwrap_or(true) ||
+			!v.tangent.map(|t| finite3(t.truncated()) && t.w.is_finite()).unwrap_or(true) ||
+			!v.color.map(|c| finite3(c.truncated()) && c.w.is_finite()).unwrap_or(true) ||
+			!v.uvw.iter().all(|uv| finite3(*uv))
+		{
+			report(Issue::NonFiniteAttribute(i));
+		}
+
+		if let Some(groups) = v.groups.as_ref() {
+			for g in groups.iter().filter(|g| **g >= mesh.vertex_groups.len()) {
+				report(Issue::VertexGroupOutOfRange {
+					vertex: i,
+					group: *g,
+				});
+			}
+		}
+
+		for w in v.weights.iter().filter(|w| bones.map(|n| w.joint >= n).unwrap_or(true)) {
+			report(Issue::JointOutOfRange {
+				vertex: i,
+				joint: w.joint,
+			});
+		}
+	}
+
+	for (g, group) in mesh.vertex_groups.iter().enumerate() {
+		for v in group.children.iter().filter(|v| **v >= vertex_count) {
+			report(Issue::GroupVertexOutOfRange {
+				group: g,
+				vertex: *v,
+			});
+		}
+	}
+
+	for (t, target) in mesh.morph_targets.iter().enumerate() {
+		for d in target.deltas.iter().filter(|d| d.vertex >= vertex_count) {
+			report(Issue::MorphVertexOutOfRange {
+				target: t,
+				vertex: d.vertex,
+			});
+		}
+	}
+}
+
+/// Removes or resets everything [`check_mesh`] complains about
+fn repair_mesh(mesh: &mut Mesh, bones: Option<usize>, skeletons: usize) {
+	let vertex_count = mesh.vertices.len();
+	let group_count = mesh.vertex_groups.len();
+
+	if mesh.skeleton.map(|s| s >= skeletons).unwrap_or(false) {
+		mesh.skeleton = None;
+	}
+
+	mesh.faces.retain(|f| {
+		let idx = f.indices();
+		idx.len() >= 3 && idx.iter().all(|i| *i < vertex_count) &&
+			!(1..idx.len()).any(|k| idx[..k].contains(&idx[k]))
+	});
+
+	for group in mesh.vertex_groups.iter_mut() {
+		group.children.retain(|v| *v < vertex_count);
+	}
+
+	for target in mesh.morph_targets.iter_mut() {
+		target.deltas.retain(|d| d.vertex < vertex_count);
+	}
+
+	for v in mesh.vertices.iter_mut() {
+		if let Some(groups) = v.groups.as_mut() {
+			groups.retain(|g| *g < group_count);
+		}
+
+		v.weights.retain(|w| bones.map(|n| w.joint < n).unwrap_or(false));
+
+		if !v.normal.map(finite3).unwrap_or(true) {
+			v.normal = None;
+		}
+		if !v.tangent.map(|t| finite3(t.truncated()) && t.w.is_finite()).unwrap_or(true) {
+			v.tangent = None;
+		}
+		if !v.color.map(|c| finite3(c.truncated()) && c.w.is_finite()).unwrap_or(true) {
+			v.color = None;
+		}
+		for uv in v.uvw.iter_mut().filter(|uv| !finite3(**uv)) {
+			*uv = Vec3::zero();
+		}
+	}
+
+	// dropping a vertex also drops its faces and remaps groups and morphs
+	mesh.retain_vertices(|_, v| v.position.x.is_finite() && v.position.y.is_finite() &&
+		v.position.z.is_finite() && v.position.w.is_finite());
+}
+
+impl Scene {
+	/// Checks the scene for data that would trip up an exporter
+	pub fn validate(&self) -> Vec<Diagnostic> {
+		let mut out = vec![];
+
+		for (s, skel) in self.skeletons.iter().enumerate() {
+			for (b, bone) in skel.bones.iter().enumerate() {
+				if bone.parent.map(|p| p >= b).unwrap_or(false) {
+					out.push(Diagnostic::new(None, Issue::BoneOrder {
+						skeleton: s,
+						bone: b,
+					}));
+				}
+			}
+		}
+
+		// node indices follow depth-first order, as used by Node::update_parent_indices
+		let mut indices: HashMap<NodePath, usize> = HashMap::new();
+		for (i, (path, node)) in self.root.iter_depth_first().enumerate() {
+			let expected = if path.is_empty() {
+				None
+			} else {
+				indices.get(&path[..path.len() - 1]).copied()
+			};
+
+			if node.parent != expected {
+				out.push(Diagnostic::new(Some(path.clone()), Issue::ParentMismatch {
+					expected,
+					found: node.parent,
+				}));
+			}
+
+			if !finite3(node.translation) || !finite_rotor(node.rotation) || !finite3(node.scale) {
+				out.push(Diagnostic::new(Some(path.clone()), Issue::NonFiniteTransform));
+			}
+
+			if let NodeData::Geometry(mesh) = &node.data {
+				let bones = mesh.skeleton.and_then(|s| self.skeletons.get(s)).map(|s| s.bones.len());
+				check_mesh(mesh, bones, self.skeletons.len(), |issue| {
+					out.push(Diagnostic::new(Some(path.clone()), issue))
+				});
+			}
+
+			indices.insert(path, i);
+		}
+
+		out
+	}
+
+	/// Validates the scene and fixes what it can: bad faces, references and morph offsets are removed,
+	/// vertices with non-finite positions are dropped, other non-finite attributes are cleared, bad
+	/// transforms are reset and parent indices are rebuilt. Skeleton bone order is left alone.
+	/// Returns the diagnostics found before repair.
+	pub fn repair(&mut self) -> Vec<Diagnostic> {
+		let found = self.validate();
+		if found.is_empty() {
+			return found;
+		}
+
+		let bone_counts: Vec<usize> = self.skeletons.iter().map(|s| s.bones.len()).collect();
+		self.root.visit_mut(&mut |node| {
+			if !finite3(node.translation) || !finite_rotor(node.rotation) || !finite3(node.scale) {
+				node.translation = Vec3::zero();
+				node.rotation = Rotor3::identity();
+				node.scale = Vec3::one();
+			}
+
+			if let NodeData::Geometry(mesh) = &mut node.data {
+				let bones = mesh.skeleton.and_then(|s| bone_counts.get(s)).copied();
+				repair_mesh(mesh, bones, bone_counts.len());
+			}
+		});
+
+		self.root.update_parent_indices();
+
+		found
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ultraviolet::vec::Vec4;
+
+	use super::*;
+	use crate::scene::{
+		Face,
+		Node,
+		ObjRef,
+		Vertex
+	};
+
+	fn scene() -> Scene {
+		let mut mesh = Mesh::default();
+		for x in 0..4 {
+			let mut v = Vertex::new(Some(vec![0]));
+			v.position = Vec4::new(x as f32, (x % 2) as f32, 0.0, 1.0);
+			mesh.vertices.push(v);
+		}
+		mesh.faces.push(Face::Triangle([0, 1, 2]));
+		mesh.faces.push(Face::Triangle([1, 2, 3]));
+
+		let mut root = Node::new(ObjRef::Number(0), None);
+		let mut child = Node::new(ObjRef::Number(1), Some(0));
+		child.data = NodeData::Geometry(mesh);
+		root.children.push(child);
+		Scene::new(root)
+	}
+
+	#[test]
+	fn test_validate() {
+		let mut s = scene();
+		assert_eq!(s.validate().len(), 4);
+		assert!(s.validate().iter().all(|d| matches!(d.issue, Issue::VertexGroupOutOfRange { group: 0, .. })));
+
+		if let NodeData::Geometry(mesh) = &mut s.root.children[0].data {
+			for v in mesh.vertices.iter_mut() {
+				v.groups = None;
+			}
+			mesh.faces.push(Face::Triangle([2, 3, 9]));
+			mesh.vertices[3].position.x = f32::NAN;
+		}
+		s.root.children[0].parent = Some(5);
+
+		let found = s.validate();
+		assert_eq!(found.len(), 3);
+		assert!(found.iter().all(|d| d.path == Some(vec![0]) && d.severity == Severity::Error ||
+			d.issue == Issue::ParentMismatch {
+				expected: Some(0),
+				found: Some(5),
+			}));
+
+		assert_eq!(s.repair().len(), 3);
+		assert!(s.validate().is_empty());
+		if let NodeData::Geometry(mesh) = &s.root.children[0].data {
+			assert_eq!(mesh.vertices.len(), 3);
+			assert_eq!(mesh.faces, vec![Face::Triangle([0, 1, 2])]);
+		}
+	}
+}
@@ -1,5 +1,19 @@
-use crate::scale5to8;
+pub mod bc;
+pub mod block;
+pub mod etc;
+pub mod ops;
+pub mod quantize;
+pub mod swizzle;
 
+use std::{
+	collections::HashMap,
+	str::FromStr
+};
+
+use thiserror::Error;
+
+/// A straight (non-premultiplied) RGBA color with channels nominally in `0.0..=1.0`. Packing into
+/// integer formats clamps and rounds to the nearest representable value.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Color {
 	pub red: f32,
@@ -9,127 +23,694 @@ pub struct Color {
 }
 
 impl Color {
-	pub fn from_rgba5551(color: u16) -> Color {
+	pub const TRANSPARENT: Color = Color::new(0.0, 0.0, 0.0, 0.0);
+	pub const BLACK: Color = Color::new(0.0, 0.0, 0.0, 1.0);
+	pub const WHITE: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+
+	pub const fn new(red: f32, green: f32, blue: f32, alpha: f32) -> Color {
 		Color {
-			red: (scale5to8((color & 31) as u8) as f32) / 255.0,
-			green: (scale5to8(((color >> 5) & 31) as u8) as f32) / 255.0,
-			blue: (scale5to8(((color >> 10) & 31) as u8) as f32) / 255.0,
-			alpha: (!scale5to8(((color >> 15) & 31) as u8) as f32) / 255.0,
+			red,
+			green,
+			blue,
+			alpha,
 		}
 	}
 
-	pub fn to_bgr888(&self) -> u32 {
-		((self.blue * 255.0) as u32) << 24 | ((self.green * 255.0) as u32) << 16 |
-			((self.red * 255.0) as u32) << 8
+	/// Creates an opaque color from 8 bit channels
+	pub fn from_rgb8(r: u8, g: u8, b: u8) -> Color {
+		Color::from_rgba8([r, g, b, 255])
+	}
+
+	/// Creates a color from 8 bit channels in `[r, g, b, a]` order
+	pub fn from_rgba8(c: [u8; 4]) -> Color {
+		Color::new(unorm(c[0] as u32, 8), unorm(c[1] as u32, 8), unorm(c[2] as u32, 8), unorm(c[3] as u32, 8))
+	}
+
+	/// Returns 8 bit channels in `[r, g, b, a]` order
+	pub fn to_rgba8(&self) -> [u8; 4] {
+		[
+			to_unorm(self.red, 8) as u8,
+			to_unorm(self.green, 8) as u8,
+			to_unorm(self.blue, 8) as u8,
+			to_unorm(self.alpha, 8) as u8,
+		]
+	}
+
+	/// Unpacks an opaque color with blue in bits 0-4, green in 5-10 and red in 11-15
+	pub fn from_rgb565(c: u16) -> Color {
+		let c = c as u32;
+		Color::new(unorm(c >> 11, 5), unorm((c >> 5) & 63, 6), unorm(c & 31, 5), 1.0)
+	}
+
+	pub fn to_rgb565(&self) -> u16 {
+		(to_unorm(self.red, 5) << 11 | to_unorm(self.green, 6) << 5 | to_unorm(self.blue, 5)) as u16
 	}
 
+	/// Unpacks a color with red in bits 0-4, green in 5-9, blue in 10-14 and alpha in 15
+	pub fn from_rgba5551(c: u16) -> Color {
+		let c = c as u32;
+		Color::new(unorm(c & 31, 5), unorm((c >> 5) & 31, 5), unorm((c >> 10) & 31, 5), unorm(c >> 15, 1))
+	}
+
+	pub fn to_rgba5551(&self) -> u16 {
+		(to_unorm(self.red, 5) | to_unorm(self.green, 5) << 5 | to_unorm(self.blue, 5) << 10 |
+			to_unorm(self.alpha, 1) << 15) as u16
+	}
+
+	/// Unpacks a color with alpha in bits 0-3, blue in 4-7, green in 8-11 and red in 12-15
+	pub fn from_rgba4444(c: u16) -> Color {
+		let c = c as u32;
+		Color::new(unorm(c >> 12, 4), unorm((c >> 8) & 15, 4), unorm((c >> 4) & 15, 4), unorm(c & 15, 4))
+	}
+
+	pub fn to_rgba4444(&self) -> u16 {
+		(to_unorm(self.red, 4) << 12 | to_unorm(self.green, 4) << 8 | to_unorm(self.blue, 4) << 4 |
+			to_unorm(self.alpha, 4)) as u16
+	}
+
+	/// Unpacks an opaque color from `0xRRGGBB`
+	pub fn from_rgb888(c: u32) -> Color {
+		Color::from_rgb8((c >> 16) as u8, (c >> 8) as u8, c as u8)
+	}
+
+	/// Packs the color as `0xRRGGBB`, which is written little endian as blue, green, red
 	pub fn to_rgb888(&self) -> u32 {
-		((self.red * 255.0) as u32) << 16 | ((self.green * 255.0) as u32) << 8 |
-			(self.blue * 255.0) as u32
+		let [r, g, b, _] = self.to_rgba8();
+		(r as u32) << 16 | (g as u32) << 8 | b as u32
+	}
+
+	/// Unpacks an opaque color from `0xBBGGRR`
+	pub fn from_bgr888(c: u32) -> Color {
+		Color::from_rgb8(c as u8, (c >> 8) as u8, (c >> 16) as u8)
+	}
+
+	/// Packs the color as `0xBBGGRR`, which is written little endian as red, green, blue
+	pub fn to_bgr888(&self) -> u32 {
+		let [r, g, b, _] = self.to_rgba8();
+		(b as u32) << 16 | (g as u32) << 8 | r as u32
+	}
+
+	/// Unpacks a color from `0xRRGGBBAA`
+	pub fn from_rgba8888(c: u32) -> Color {
+		Color::from_rgba8(c.to_be_bytes())
 	}
 
 	pub fn to_rgba8888(&self) -> u32 {
-		((self.red * 255.0) as u32) << 24 | ((self.green * 255.0) as u32) << 16 |
-			((self.blue * 255.0) as u32) << 8 | (self.alpha * 255.0) as u32
+		u32::from_be_bytes(self.to_rgba8())
+	}
+
+	/// Unpacks a color from `0xAARRGGBB`
+	pub fn from_argb8888(c: u32) -> Color {
+		Color::from_rgba8888(c.rotate_left(8))
+	}
+
+	pub fn to_argb8888(&self) -> u32 {
+		self.to_rgba8888().rotate_right(8)
+	}
+
+	/// Converts sRGB encoded channels to linear light, leaving alpha as is
+	pub fn to_linear(&self) -> Color {
+		Color::new(srgb_to_linear(self.red), srgb_to_linear(self.green), srgb_to_linear(self.blue), self.alpha)
+	}
+
+	/// Converts linear light channels to sRGB encoding, leaving alpha as is
+	pub fn to_srgb(&self) -> Color {
+		Color::new(linear_to_srgb(self.red), linear_to_srgb(self.green), linear_to_srgb(self.blue), self.alpha)
 	}
 
 	/// Returns a textual hex representation in the form of `#rrggbb`
 	pub fn hex_rgb(&self) -> String {
-		let r = (self.red * 255.0) as u8;
-		let g = (self.green * 255.0) as u8;
-		let b = (self.blue * 255.0) as u8;
+		let [r, g, b, _] = self.to_rgba8();
+		format!("#{:02x}{:02x}{:02x}", r, g, b)
+	}
+
+	/// Returns a textual hex representation in the form of `#rrggbbaa`
+	pub fn hex_rgba(&self) -> String {
+		format!("#{:08x}", self.to_rgba8888())
+	}
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ColorParseError {
+	#[error("Color must start with '#'")]
+	MissingHash,
+	#[error("Expected 6 or 8 hex digits, got {0}")]
+	Length(usize),
+	#[error("Invalid hex digit: {0:?}")]
+	Digit(char),
+}
+
+impl FromStr for Color {
+	type Err = ColorParseError;
+
+	/// Parses `#rrggbb` or `#rrggbbaa`, as produced by [`Color::hex_rgb`] and [`Color::hex_rgba`]
+	fn from_str(s: &str) -> Result<Color, ColorParseError> {
+		let digits = s.strip_prefix('#').ok_or(ColorParseError::MissingHash)?;
+		let len = digits.chars().count();
+		if len != 6 && len != 8 {
+			return Err(ColorParseError::Length(len));
+		}
+
+		let mut v = 0u32;
+		for c in digits.chars() {
+			v = v << 4 | c.to_digit(16).ok_or(ColorParseError::Digit(c))?;
+		}
+
+		if len == 6 {
+			Ok(Color::from_rgb888(v))
+		} else {
+			Ok(Color::from_rgba8888(v))
+		}
+	}
+}
+
+/// Converts an sRGB encoded channel to linear light
+pub fn srgb_to_linear(c: f32) -> f32 {
+	if c <= 0.04045 {
+		c / 12.92
+	} else {
+		((c + 0.055) / 1.055).powf(2.4)
+	}
+}
+
+/// Converts a linear light channel to sRGB encoding
+pub fn linear_to_srgb(c: f32) -> f32 {
+	if c <= 0.0031308 {
+		c * 12.92
+	} else {
+		1.055 * c.powf(1.0 / 2.4) - 0.055
+	}
+}
+
+/// Layout of a texture's pixels. Packed formats list their channels from the least significant bit.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PixelFormat {
+	/// Indices into [`Texture::palette`]
+	Indexed,
+
+	/// 8 bits per channel, stored as `[r, g, b, a]`
+	Rgba8,
+
+	/// Blue in bits 0-4, green in 5-10, red in 11-15
+	Rgb565,
+
+	/// Red in bits 0-4, green in 5-9, blue in 10-14, alpha in 15, as used by the PlayStation
+	Rgba5551,
+
+	/// Alpha in bits 0-3, blue in 4-7, green in 8-11, red in 12-15
+	Rgba4444,
+
+	/// 8 bit luminance
+	L8,
+
+	/// 8 bit luminance and alpha, stored as `[l, a]`
+	La8,
+
+	/// IEEE 754 half floats per channel, stored as raw bits
+	Rgba16F,
+
+	/// 32 bit floats per channel
+	Rgba32F,
+}
+
+impl PixelFormat {
+	/// Size of a stored pixel in bytes, with indices counted as a `usize`
+	pub fn pixel_size(&self) -> usize {
+		match self {
+			PixelFormat::Indexed => std::mem::size_of::<usize>(),
+			PixelFormat::L8 => 1,
+			PixelFormat::La8 | PixelFormat::Rgb565 | PixelFormat::Rgba5551 | PixelFormat::Rgba4444 => 2,
+			PixelFormat::Rgba8 => 4,
+			PixelFormat::Rgba16F => 8,
+			PixelFormat::Rgba32F => 16,
+		}
+	}
+
+	pub fn has_alpha(&self) -> bool {
+		!matches!(self, PixelFormat::Rgb565 | PixelFormat::L8)
+	}
+}
+
+/// A texture's pixels in one of the [`PixelFormat`]s
+#[derive(Clone, Debug, PartialEq)]
+pub enum PixelData {
+	Indexed(Vec<usize>),
+	Rgba8(Vec<[u8; 4]>),
+	Rgb565(Vec<u16>),
+	Rgba5551(Vec<u16>),
+	Rgba4444(Vec<u16>),
+	L8(Vec<u8>),
+	La8(Vec<[u8; 2]>),
+	Rgba16F(Vec<[u16; 4]>),
+	Rgba32F(Vec<[f32; 4]>),
+}
+
+impl Default for PixelData {
+	fn default() -> Self {
+		PixelData::Indexed(vec![])
+	}
+}
+
+impl PixelData {
+	/// Creates storage for `len` transparent black pixels, or index 0 pixels
+	pub fn new(format: PixelFormat, len: usize) -> PixelData {
+		match format {
+			PixelFormat::Indexed => PixelData::Indexed(vec![0; len]),
+			PixelFormat::Rgba8 => PixelData::Rgba8(vec![[0; 4]; len]),
+			PixelFormat::Rgb565 => PixelData::Rgb565(vec![0; len]),
+			PixelFormat::Rgba5551 => PixelData::Rgba5551(vec![0; len]),
+			PixelFormat::Rgba4444 => PixelData::Rgba4444(vec![0; len]),
+			PixelFormat::L8 => PixelData::L8(vec![0; len]),
+			PixelFormat::La8 => PixelData::La8(vec![[0; 2]; len]),
+			PixelFormat::Rgba16F => PixelData::Rgba16F(vec![[0; 4]; len]),
+			PixelFormat::Rgba32F => PixelData::Rgba32F(vec![[0.0; 4]; len]),
+		}
+	}
+
+	pub fn format(&self) -> PixelFormat {
+		match self {
+			PixelData::Indexed(_) => PixelFormat::Indexed,
+			PixelData::Rgba8(_) => PixelFormat::Rgba8,
+			PixelData::Rgb565(_) => PixelFormat::Rgb565,
+			PixelData::Rgba5551(_) => PixelFormat::Rgba5551,
+			PixelData::Rgba4444(_) => PixelFormat::Rgba4444,
+			PixelData::L8(_) => PixelFormat::L8,
+			PixelData::La8(_) => PixelFormat::La8,
+			PixelData::Rgba16F(_) => PixelFormat::Rgba16F,
+			PixelData::Rgba32F(_) => PixelFormat::Rgba32F,
+		}
+	}
+
+	/// Returns the number of pixels
+	pub fn len(&self) -> usize {
+		match self {
+			PixelData::Indexed(p) => p.len(),
+			PixelData::Rgba8(p) => p.len(),
+			PixelData::Rgb565(p) => p.len(),
+			PixelData::Rgba5551(p) => p.len(),
+			PixelData::Rgba4444(p) => p.len(),
+			PixelData::L8(p) => p.len(),
+			PixelData::La8(p) => p.len(),
+			PixelData::Rgba16F(p) => p.len(),
+			PixelData::Rgba32F(p) => p.len(),
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Decodes the pixel at the specified index, looking indices up in `palette`.
+	/// Indices missing from the palette decode as transparent black.
+	pub fn get(&self, index: usize, palette: &[Color]) -> Color {
+		match self {
+			PixelData::Indexed(p) => palette.get(p[index]).copied().unwrap_or(Color::TRANSPARENT),
+			PixelData::Rgba8(p) => Color::from_rgba8(p[index]),
+			PixelData::Rgb565(p) => Color::from_rgb565(p[index]),
+			PixelData::Rgba5551(p) => Color::from_rgba5551(p[index]),
+			PixelData::Rgba4444(p) => Color::from_rgba4444(p[index]),
+			PixelData::L8(p) => {
+				let l = unorm(p[index] as u32, 8);
+				Color::new(l, l, l, 1.0)
+			},
+			PixelData::La8(p) => {
+				let l = unorm(p[index][0] as u32, 8);
+				Color::new(l, l, l, unorm(p[index][1] as u32, 8))
+			},
+			PixelData::Rgba16F(p) => {
+				let [r, g, b, a] = p[index];
+				Color::new(f16_to_f32(r), f16_to_f32(g), f16_to_f32(b), f16_to_f32(a))
+			},
+			PixelData::Rgba32F(p) => {
+				let [r, g, b, a] = p[index];
+				Color::new(r, g, b, a)
+			},
+		}
+	}
 
-		format!("#{:x}{:x}{:x}", r, g, b)
+	/// Encodes a color into the pixel at the specified index, rounding to the nearest representable value.
+	/// Luminance formats take the Rec. 709 luma. Indexed storage is left untouched, as it needs a palette.
+	pub fn set(&mut self, index: usize, c: &Color) {
+		match self {
+			PixelData::Indexed(_) => (),
+			PixelData::Rgba8(p) => p[index] = c.to_rgba8(),
+			PixelData::Rgb565(p) => p[index] = c.to_rgb565(),
+			PixelData::Rgba5551(p) => p[index] = c.to_rgba5551(),
+			PixelData::Rgba4444(p) => p[index] = c.to_rgba4444(),
+			PixelData::L8(p) => p[index] = to_unorm(luma(c), 8) as u8,
+			PixelData::La8(p) => p[index] = [to_unorm(luma(c), 8) as u8, to_unorm(c.alpha, 8) as u8],
+			PixelData::Rgba16F(p) => p[index] = [
+				f32_to_f16(c.red),
+				f32_to_f16(c.green),
+				f32_to_f16(c.blue),
+				f32_to_f16(c.alpha),
+			],
+			PixelData::Rgba32F(p) => p[index] = [c.red, c.green, c.blue, c.alpha],
+		}
+	}
+}
+
+/// Expands an unsigned normalized integer of the specified bit width to a float
+fn unorm(v: u32, bits: u32) -> f32 {
+	v as f32 / ((1u32 << bits) - 1) as f32
+}
+
+/// Packs a float into an unsigned normalized integer of the specified bit width
+fn to_unorm(f: f32, bits: u32) -> u32 {
+	let max = ((1u32 << bits) - 1) as f32;
+	(f.clamp(0.0, 1.0) * max).round() as u32
+}
+
+/// Rec. 709 luma
+fn luma(c: &Color) -> f32 {
+	0.2126 * c.red + 0.7152 * c.green + 0.0722 * c.blue
+}
+
+/// Converts IEEE 754 half float bits to a float
+pub fn f16_to_f32(h: u16) -> f32 {
+	let sign = ((h & 0x8000) as u32) << 16;
+	let exp = ((h >> 10) & 0x1F) as u32;
+	let man = (h & 0x3FF) as u32;
+
+	match exp {
+		0 => {
+			let v = man as f32 / 16777216.0;
+			if sign != 0 { -v } else { v }
+		},
+		31 => f32::from_bits(sign | 0x7F80_0000 | man << 13),
+		_ => f32::from_bits(sign | (exp + 112) << 23 | man << 13),
+	}
+}
+
+/// Converts a float to IEEE 754 half float bits, rounding to nearest even
+pub fn f32_to_f16(f: f32) -> u16 {
+	let x = f.to_bits();
+	let sign = ((x >> 16) & 0x8000) as u16;
+	let exp = ((x >> 23) & 0xFF) as i32;
+	let man = x & 0x7F_FFFF;
+
+	if exp == 255 {
+		return sign | 0x7C00 | if man != 0 { 0x200 } else { 0 };
+	}
+
+	let e = exp - 127 + 15;
+	if e >= 31 {
+		return sign | 0x7C00;
+	}
+
+	let (h, rem, halfway) = if e <= 0 {
+		if e < -10 {
+			return sign;
+		}
+
+		// subnormal, with the implicit leading bit made explicit
+		let man = man | 0x80_0000;
+		let shift = (14 - e) as u32;
+		(man >> shift, man & ((1 << shift) - 1), 1 << (shift - 1))
+	} else {
+		((e as u32) << 10 | man >> 13, man & 0x1FFF, 0x1000)
+	};
+
+	// a carry out of the mantissa correctly bumps the exponent, up to infinity
+	if rem > halfway || (rem == halfway && h & 1 == 1) {
+		sign | (h + 1) as u16
+	} else {
+		sign | h as u16
 	}
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Texture {
+	/// Colors looked up by [`PixelData::Indexed`], otherwise unused
 	pub palette: Vec<Color>,
-	pub indices: Vec<usize>,
+	pub data: PixelData,
 	pub width: usize,
 	pub height: usize,
 }
 
 impl Texture {
+	/// Creates an empty palettized texture
 	pub fn new(width: usize, height: usize) -> Texture {
 		Texture {
 			palette: vec![],
-			indices: vec![],
-			width: width,
-			height: height,
+			data: PixelData::Indexed(vec![]),
+			width,
+			height,
+		}
+	}
+
+	/// Creates a texture of the specified format, filled with transparent black or index 0
+	pub fn with_format(width: usize, height: usize, format: PixelFormat) -> Texture {
+		Texture {
+			palette: vec![],
+			data: PixelData::new(format, width * height),
+			width,
+			height,
+		}
+	}
+
+	pub fn format(&self) -> PixelFormat {
+		self.data.format()
+	}
+
+	/// Returns the palette indices, if the texture is palettized
+	pub fn indices(&self) -> Option<&[usize]> {
+		match &self.data {
+			PixelData::Indexed(i) => Some(i),
+			_ => None,
+		}
+	}
+
+	/// Returns the palette indices mutably, if the texture is palettized
+	pub fn indices_mut(&mut self) -> Option<&mut Vec<usize>> {
+		match &mut self.data {
+			PixelData::Indexed(i) => Some(i),
+			_ => None,
+		}
+	}
+
+	/// Returns the color at the specified coordinates
+	pub fn get_pixel(&self, x: usize, y: usize) -> Color {
+		self.data.get(y * self.width + x, &self.palette)
+	}
+
+	/// Sets the color at the specified coordinates. On palettized textures the color is added to the
+	/// palette if it isn't already present.
+	pub fn set_pixel(&mut self, x: usize, y: usize, c: &Color) {
+		let i = y * self.width + x;
+
+		if let PixelData::Indexed(indices) = &mut self.data {
+			indices[i] = match find_palette_index(&self.palette, c) {
+				Some(p) => p,
+				None => {
+					self.palette.push(*c);
+					self.palette.len() - 1
+				},
+			};
+		} else {
+			self.data.set(i, c);
 		}
 	}
 
 	/// Returns the (X, Y) coordinates of every instance of a specified palette index
 	pub fn find_indices(&self, index: usize) -> Vec<(usize, usize)> {
-		let mut indices = vec![];
+		let mut found = vec![];
 
-		for y in 0..self.height {
-			for x in 0..self.width {
-				if self.indices[(y * self.width) + x] == index {
-					indices.push((x, y));
+		if let Some(indices) = self.indices() {
+			for y in 0..self.height {
+				for x in 0..self.width {
+					if indices[(y * self.width) + x] == index {
+						found.push((x, y));
+					}
 				}
 			}
 		}
 
-		indices
+		found
 	}
 
 	/// Optimise the palette, removing duplicate entries, and adjusting indices accordingly.
+	/// Does nothing to direct color textures.
 	pub fn optimize(&mut self) {
+		let indices = match &mut self.data {
+			PixelData::Indexed(i) => i,
+			_ => return,
+		};
+
+		let mut seen: HashMap<[u32; 4], usize> = HashMap::new();
 		let mut opt_pal = vec![];
 
-		let mut opt_idx = vec![0; self.width * self.height];
+		// old palette index to new
+		let remap: Vec<usize> = self.palette.iter().map(|c| {
+			*seen.entry(color_key(c)).or_insert_with(|| {
+				opt_pal.push(*c);
+				opt_pal.len() - 1
+			})
+		}).collect();
 
-		for c1 in self.palette.iter() {
-			let mut duplicate = None;
+		for i in indices.iter_mut() {
+			*i = remap.get(*i).copied().unwrap_or(*i);
+		}
 
-			// check for duplicates
-			for c2 in opt_pal.iter() {
-				if *c1 == *c2 {
-					duplicate = Some(*c2);
-					break;
-				}
-			}
+		self.palette = opt_pal;
+	}
 
-			if let Some(c2) = duplicate {
-				// get the existing color's palette index and assign it
-				if let Some(i) = find_palette_index(&opt_pal, &c2) {
-					for (x, y) in self.find_indices(i).iter() {
-						opt_idx[(y * self.width) + x] = i;
-					}
-				}
-			} else {
-				// update the index as the new palette's current length
-				let i = opt_pal.len();
+	/// Decodes every pixel to a color
+	pub fn pixels(&self) -> Vec<Color> {
+		(0..self.data.len()).map(|i| self.data.get(i, &self.palette)).collect()
+	}
 
-				if let Some(j) = find_palette_index(&self.palette, c1) {
-					for (x, y) in self.find_indices(j).iter() {
-						opt_idx[(y * self.width) + x] = i;
-					}
-					opt_pal.push(*c1);
-				}
+	/// Returns a copy of the texture stored in another format. Widening conversions, such as RGBA5551 to
+	/// RGBA8, are exact and can be reversed without loss; narrowing ones round to the nearest value.
+	/// Converting to [`PixelFormat::Indexed`] builds a palette of every distinct color.
+	pub fn convert(&self, format: PixelFormat) -> Texture {
+		if format == self.format() {
+			return self.clone();
+		}
+
+		let len = self.data.len();
+		let mut tex = Texture {
+			palette: vec![],
+			data: PixelData::new(format, len),
+			width: self.width,
+			height: self.height,
+		};
+
+		if let PixelData::Indexed(indices) = &mut tex.data {
+			let mut seen: HashMap<[u32; 4], usize> = HashMap::new();
+
+			for (i, idx) in indices.iter_mut().enumerate() {
+				let c = self.data.get(i, &self.palette);
+				*idx = *seen.entry(color_key(&c)).or_insert_with(|| {
+					tex.palette.push(c);
+					tex.palette.len() - 1
+				});
+			}
+		} else {
+			for i in 0..len {
+				tex.data.set(i, &self.data.get(i, &self.palette));
 			}
 		}
 
-		self.palette = opt_pal;
-		self.indices = opt_idx;
+		tex
 	}
+}
 
-	/// Uses the palette and indices to build a pixel array
-	pub fn pixels(&self) -> Vec<Color> {
-		self.indices.iter().map(|i| self.palette[*i]).collect()
-	}
+/// Hashable identity of a color, exact to the bit
+fn color_key(c: &Color) -> [u32; 4] {
+	[c.red.to_bits(), c.green.to_bits(), c.blue.to_bits(), c.alpha.to_bits()]
 }
 
 /// Returns the index of the specified color, if present
-pub fn find_palette_index<'a, 'b>(palette: &'a [Color], color: &'b Color) -> Option<usize> {
-	for i in 0..palette.len() {
-		if *color == palette[i] {
-			return Some(i);
+pub fn find_palette_index(palette: &[Color], color: &Color) -> Option<usize> {
+	palette.iter().position(|c| *c == *color)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_color_packing() {
+		let c = Color::from_rgb8(0x0A, 0x0B, 0x0C);
+		assert_eq!(c.to_rgb888(), 0x0A0B0C);
+		assert_eq!(c.to_bgr888(), 0x0C0B0A);
+		assert_eq!(c.to_rgba8888(), 0x0A0B0CFF);
+		assert_eq!(c.to_argb8888(), 0xFF0A0B0C);
+		assert_eq!(Color::from_bgr888(0x0C0B0A), c);
+		assert_eq!(Color::from_argb8888(0xFF0A0B0C), c);
+
+		// out of range channels clamp instead of spilling into their neighbours
+		assert_eq!(Color::new(1.5, -0.5, 0.0, 1.0).to_rgb888(), 0xFF0000);
+
+		for v in 0..=u16::MAX {
+			assert_eq!(Color::from_rgba5551(v).to_rgba5551(), v);
+			assert_eq!(Color::from_rgb565(v).to_rgb565(), v);
+			assert_eq!(Color::from_rgba4444(v).to_rgba4444(), v);
+		}
+
+		assert_eq!(Color::from_rgba5551(0x8000).alpha, 1.0);
+		assert_eq!(Color::from_rgba5551(0x7FFF), Color::new(1.0, 1.0, 1.0, 0.0));
+	}
+
+	#[test]
+	fn test_color_hex() {
+		let c = Color::from_rgba8([0x0A, 0x0B, 0x0C, 0x80]);
+		assert_eq!(c.hex_rgb(), "#0a0b0c");
+		assert_eq!(c.hex_rgba(), "#0a0b0c80");
+		assert_eq!(c.hex_rgba().parse::<Color>(), Ok(c));
+		assert_eq!("#0A0B0C".parse::<Color>(), Ok(Color::from_rgb8(10, 11, 12)));
+
+		assert_eq!("0a0b0c".parse::<Color>(), Err(ColorParseError::MissingHash));
+		assert_eq!("#abc".parse::<Color>(), Err(ColorParseError::Length(3)));
+		assert_eq!("#+a0b0c".parse::<Color>(), Err(ColorParseError::Digit('+')));
+		assert_eq!("#0a0b0g".parse::<Color>(), Err(ColorParseError::Digit('g')));
+	}
+
+	#[test]
+	fn test_srgb() {
+		for i in 0..=255 {
+			let c = Color::from_rgb8(i, i, i);
+			assert_eq!(c.to_linear().to_srgb().to_rgba8(), c.to_rgba8());
 		}
+
+		assert!((srgb_to_linear(0.5) - 0.214).abs() < 1e-3);
+		assert_eq!(Color::WHITE.to_linear(), Color::WHITE);
+	}
+
+	#[test]
+	fn test_half_float() {
+		for f in [0.0, -0.0, 1.0, -2.5, 0.333, 65504.0, 6.1e-5, 5.96e-8].iter() {
+			let h = f32_to_f16(*f);
+			assert_eq!(f32_to_f16(f16_to_f32(h)), h);
+			assert!((f16_to_f32(h) - f).abs() <= f.abs() * 1e-3);
+		}
+
+		assert_eq!(f32_to_f16(1e6), 0x7C00);
+		assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
 	}
 
-	None
+	#[test]
+	fn test_widening_is_lossless() {
+		let mut tex = Texture::with_format(256, 256, PixelFormat::Rgba5551);
+		if let PixelData::Rgba5551(p) = &mut tex.data {
+			for (i, c) in p.iter_mut().enumerate() {
+				*c = i as u16;
+			}
+		}
+
+		for format in [PixelFormat::Rgba8, PixelFormat::Rgba16F, PixelFormat::Rgba32F, PixelFormat::Indexed].iter() {
+			let wide = tex.convert(*format);
+			assert_eq!(wide.convert(PixelFormat::Rgba5551), tex);
+		}
+
+		let mut tex = Texture::with_format(256, 256, PixelFormat::Rgb565);
+		if let PixelData::Rgb565(p) = &mut tex.data {
+			for (i, c) in p.iter_mut().enumerate() {
+				*c = (i as u16).wrapping_mul(257);
+			}
+		}
+		assert_eq!(tex.convert(PixelFormat::Rgba8).convert(PixelFormat::Rgb565), tex);
+	}
+
+	#[test]
+	fn test_indexed() {
+		let mut tex = Texture::with_format(2, 2, PixelFormat::Indexed);
+		let red = Color {
+			red: 1.0,
+			green: 0.0,
+			blue: 0.0,
+			alpha: 1.0,
+		};
+		tex.palette = vec![red, red];
+		tex.set_pixel(1, 1, &red);
+		if let Some(i) = tex.indices_mut() {
+			i[2] = 1;
+		}
+		tex.optimize();
+
+		assert_eq!(tex.palette.len(), 1);
+		assert_eq!(tex.indices(), Some(&[0, 0, 0, 0][..]));
+		assert_eq!(tex.convert(PixelFormat::La8).get_pixel(1, 0).alpha, 1.0);
+	}
 }
@@ -0,0 +1,841 @@
+//! BCn block codecs. Every function works on a single 4x4 block, with pixels in rows.
+
+/// Reads bit fields from a 128 bit block, least significant bit first
+struct BitReader {
+	bits: u128,
+	pos: u32,
+}
+
+impl BitReader {
+	fn new(b: &[u8]) -> BitReader {
+		let mut bytes = [0; 16];
+		bytes.copy_from_slice(&b[..16]);
+
+		BitReader {
+			bits: u128::from_le_bytes(bytes),
+			pos: 0,
+		}
+	}
+
+	fn read(&mut self, count: u32) -> u32 {
+		let v = ((self.bits >> self.pos) as u32) & ((1u64 << count) - 1) as u32;
+		self.pos += count;
+		v
+	}
+}
+
+fn expand565(c: u16) -> [u8; 4] {
+	let (r, g, b) = ((c >> 11) as u8, ((c >> 5) & 63) as u8, (c & 31) as u8);
+	[r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2, 255]
+}
+
+/// Returns the interpolation weight of `i` `n`ths in 256ths, truncated as Mesa does
+fn weight(i: u32, n: u32) -> u32 {
+	i * 255 / n
+}
+
+/// Blends two colors by `w` 256ths of the way from `a` to `b`, plus `round`
+fn mix(a: &[u8; 4], b: &[u8; 4], w: u32, round: u32) -> [u8; 4] {
+	let m = |i: usize| ((a[i] as u32 * (256 - w) + b[i] as u32 * w + round) >> 8) as u8;
+	[m(0), m(1), m(2), 255]
+}
+
+/// Returns the four colors a BC1 block's endpoints select from. `opaque` forces the four color mode, as
+/// BC2 and BC3 do.
+fn color_palette(c0: u16, c1: u16, opaque: bool) -> [[u8; 4]; 4] {
+	let (e0, e1) = (expand565(c0), expand565(c1));
+
+	if c0 > c1 || opaque {
+		[e0, e1, mix(&e0, &e1, weight(1, 3), 0), mix(&e0, &e1, weight(2, 3), 0)]
+	} else {
+		[e0, e1, mix(&e0, &e1, 128, 128), [0; 4]]
+	}
+}
+
+fn decode_color(b: &[u8], opaque: bool) -> [[u8; 4]; 16] {
+	let palette = color_palette(u16::from_le_bytes([b[0], b[1]]), u16::from_le_bytes([b[2], b[3]]), opaque);
+
+	let indices = u32::from_le_bytes([b[4], b[5], b[6], b[7]]);
+	let mut out = [[0; 4]; 16];
+	for (i, p) in out.iter_mut().enumerate() {
+		*p = palette[((indices >> (i * 2)) & 3) as usize];
+	}
+	out
+}
+
+/// Decodes an 8 byte BC3 alpha or BC4 channel block
+fn decode_alpha(b: &[u8]) -> [u8; 16] {
+	let (a0, a1) = (b[0] as u32, b[1] as u32);
+	let mut values = [a0, a1, 0, 0, 0, 0, 0, 255];
+	if a0 > a1 {
+		for (i, v) in values.iter_mut().enumerate().skip(2) {
+			*v = (a0 * (256 - weight(i as u32 - 1, 7)) + a1 * weight(i as u32 - 1, 7)) >> 8;
+		}
+	} else {
+		for (i, v) in values.iter_mut().enumerate().skip(2).take(4) {
+			*v = (a0 * (256 - weight(i as u32 - 1, 5)) + a1 * weight(i as u32 - 1, 5)) >> 8;
+		}
+	}
+
+	let mut bits = [0; 8];
+	bits[..6].copy_from_slice(&b[2..8]);
+	let indices = u64::from_le_bytes(bits);
+
+	let mut out = [0; 16];
+	for (i, a) in out.iter_mut().enumerate() {
+		*a = values[((indices >> (i * 3)) & 7) as usize] as u8;
+	}
+	out
+}
+
+pub fn decode_bc1(b: &[u8]) -> [[u8; 4]; 16] {
+	decode_color(b, false)
+}
+
+pub fn decode_bc2(b: &[u8]) -> [[u8; 4]; 16] {
+	let mut out = decode_color(&b[8..], true);
+	let alpha = u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]);
+	for (i, p) in out.iter_mut().enumerate() {
+		p[3] = ((alpha >> (i * 4)) & 15) as u8 * 17;
+	}
+	out
+}
+
+pub fn decode_bc3(b: &[u8]) -> [[u8; 4]; 16] {
+	let mut out = decode_color(&b[8..], true);
+	for (p, a) in out.iter_mut().zip(decode_alpha(b).iter()) {
+		p[3] = *a;
+	}
+	out
+}
+
+pub fn decode_bc4(b: &[u8]) -> [u8; 16] {
+	decode_alpha(b)
+}
+
+/// Decodes a BC5 block into red and green, with blue zero and alpha opaque
+pub fn decode_bc5(b: &[u8]) -> [[u8; 4]; 16] {
+	let (r, g) = (decode_alpha(b), decode_alpha(&b[8..]));
+	let mut out = [[0, 0, 0, 255]; 16];
+	for (i, p) in out.iter_mut().enumerate() {
+		p[0] = r[i];
+		p[1] = g[i];
+	}
+	out
+}
+
+/// Subset of each pixel for two subset partitions, one bit per pixel
+const PARTITIONS2: [u16; 64] = [
+	0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80,
+	0xC800, 0xFFEC, 0xFE80, 0xE800, 0xFFE8, 0xFF00, 0xFFF0, 0xF000,
+	0xF710, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE,
+	0x088C, 0x3110, 0x6666, 0x366C, 0x17E8, 0x0FF0, 0x718E, 0x399C,
+	0xAAAA, 0xF0F0, 0x5A5A, 0x33CC, 0x3C3C, 0x55AA, 0x9696, 0xA55A,
+	0x73CE, 0x13C8, 0x324C, 0x3BDC, 0x6996, 0xC33C, 0x9966, 0x0660,
+	0x0272, 0x04E4, 0x4E40, 0x2720, 0xC936, 0x936C, 0x39C6, 0x639C,
+	0x9336, 0x9CC6, 0x817E, 0xE718, 0xCCF0, 0x0FCC, 0x7744, 0xEE22,
+];
+
+/// Subset of each pixel for three subset partitions, two bits per pixel
+const PARTITIONS3: [u32; 64] = [
+	0xAA685050, 0x6A5A5040, 0x5A5A4200, 0x5450A0A8, 0xA5A50000, 0xA0A05050, 0x5555A0A0, 0x5A5A5050,
+	0xAA550000, 0xAA555500, 0xAAAA5500, 0x90909090, 0x94949494, 0xA4A4A4A4, 0xA9A59450, 0x2A0A4250,
+	0xA5945040, 0x0A425054, 0xA5A5A500, 0x55A0A0A0, 0xA8A85454, 0x6A6A4040, 0xA4A45000, 0x1A1A0500,
+	0x0050A4A4, 0xAAA59090, 0x14696914, 0x69691400, 0xA08585A0, 0xAA821414, 0x50A4A450, 0x6A5A0200,
+	0xA9A58000, 0x5090A0A8, 0xA8A09050, 0x24242424, 0x00AA5500, 0x24924924, 0x24499224, 0x50A50A50,
+	0x500AA550, 0xAAAA4444, 0x66660000, 0xA5A0A5A0, 0x50A050A0, 0x69286928, 0x44AAAA44, 0x66666600,
+	0xAA444444, 0x54A854A8, 0x95809580, 0x96969600, 0xA85454A8, 0x80959580, 0xAA141414, 0x96960000,
+	0xAAAA1414, 0xA05050A0, 0xA0A5A5A0, 0x96000000, 0x40804080, 0xA9A8A9A8, 0xAAAAAA44, 0x2A4A5254,
+];
+
+/// Anchor pixel of the second subset of two subset partitions
+const ANCHOR2: [u8; 64] = [
+	15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+	15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+	15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+	6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// Anchor pixels of the second and third subsets of three subset partitions
+const ANCHOR3: [[u8; 2]; 64] = [
+	[3, 15], [3, 8], [15, 8], [15, 3], [8, 15], [3, 15], [15, 3], [15, 8],
+	[8, 15], [8, 15], [6, 15], [6, 15], [6, 15], [5, 15], [3, 15], [3, 8],
+	[3, 15], [3, 8], [8, 15], [15, 3], [3, 15], [3, 8], [6, 15], [10, 8],
+	[5, 3], [8, 15], [8, 6], [6, 10], [8, 15], [5, 15], [15, 10], [15, 8],
+	[8, 15], [15, 3], [3, 15], [5, 10], [6, 10], [10, 8], [8, 9], [15, 10],
+	[15, 6], [3, 15], [15, 8], [5, 15], [15, 3], [15, 6], [15, 6], [15, 8],
+	[3, 15], [15, 3], [5, 15], [5, 15], [5, 15], [8, 15], [5, 15], [10, 15],
+	[5, 15], [10, 15], [8, 15], [13, 15], [15, 3], [12, 15], [3, 15], [3, 8],
+];
+
+const WEIGHTS2: [u32; 4] = [0, 21, 43, 64];
+const WEIGHTS3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+fn weights(bits: u32) -> &'static [u32] {
+	match bits {
+		2 => &WEIGHTS2,
+		3 => &WEIGHTS3,
+		_ => &WEIGHTS4,
+	}
+}
+
+/// Returns the subset of a pixel
+fn subset(subsets: u32, partition: usize, pixel: usize) -> usize {
+	match subsets {
+		2 => ((PARTITIONS2[partition] >> pixel) & 1) as usize,
+		3 => ((PARTITIONS3[partition] >> (pixel * 2)) & 3) as usize,
+		_ => 0,
+	}
+}
+
+/// Is the pixel the first of its subset, which stores one index bit fewer?
+fn is_anchor(subsets: u32, partition: usize, pixel: usize) -> bool {
+	pixel == 0 || match subsets {
+		2 => ANCHOR2[partition] as usize == pixel,
+		3 => ANCHOR3[partition].contains(&(pixel as u8)),
+		_ => false,
+	}
+}
+
+/// BC7 mode layout
+struct Bc7Mode {
+	subsets: u32,
+	partition_bits: u32,
+	rotation_bits: u32,
+	index_selection_bits: u32,
+	color_bits: u32,
+	alpha_bits: u32,
+
+	/// P-bits per endpoint
+	endpoint_pbits: bool,
+
+	/// P-bits per subset
+	shared_pbits: bool,
+	index_bits: u32,
+	index2_bits: u32,
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+	Bc7Mode { subsets: 3, partition_bits: 4, rotation_bits: 0, index_selection_bits: 0, color_bits: 4, alpha_bits: 0,
+		endpoint_pbits: true, shared_pbits: false, index_bits: 3, index2_bits: 0 },
+	Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 6, alpha_bits: 0,
+		endpoint_pbits: false, shared_pbits: true, index_bits: 3, index2_bits: 0 },
+	Bc7Mode { subsets: 3, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 0,
+		endpoint_pbits: false, shared_pbits: false, index_bits: 2, index2_bits: 0 },
+	Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 0,
+		endpoint_pbits: true, shared_pbits: false, index_bits: 2, index2_bits: 0 },
+	Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 1, color_bits: 5, alpha_bits: 6,
+		endpoint_pbits: false, shared_pbits: false, index_bits: 2, index2_bits: 3 },
+	Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 0, color_bits: 7, alpha_bits: 8,
+		endpoint_pbits: false, shared_pbits: false, index_bits: 2, index2_bits: 2 },
+	Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 7,
+		endpoint_pbits: true, shared_pbits: false, index_bits: 4, index2_bits: 0 },
+	Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 5,
+		endpoint_pbits: true, shared_pbits: false, index_bits: 2, index2_bits: 0 },
+];
+
+/// Expands an n bit value to 8 bits by replicating its high bits
+fn expand_bits(v: u32, bits: u32) -> u32 {
+	if bits >= 8 {
+		v
+	} else {
+		(v << (8 - bits)) | (v >> (2 * bits - 8))
+	}
+}
+
+/// Decodes a BC7 block. Reserved mode blocks decode as transparent black.
+pub fn decode_bc7(b: &[u8]) -> [[u8; 4]; 16] {
+	let mut r = BitReader::new(b);
+
+	let mode_index = match (0..8).find(|_| r.read(1) == 1) {
+		Some(m) => m,
+		None => return [[0; 4]; 16],
+	};
+	let mode = &BC7_MODES[mode_index];
+
+	let partition = r.read(mode.partition_bits) as usize;
+	let rotation = r.read(mode.rotation_bits);
+	let index_selection = r.read(mode.index_selection_bits);
+
+	// endpoints[subset * 2 + n][channel]
+	let count = mode.subsets as usize * 2;
+	let mut endpoints = [[0u32; 4]; 6];
+	for channel in 0..3 {
+		for e in endpoints.iter_mut().take(count) {
+			e[channel] = r.read(mode.color_bits);
+		}
+	}
+	for e in endpoints.iter_mut().take(count) {
+		e[3] = if mode.alpha_bits > 0 { r.read(mode.alpha_bits) } else { 255 };
+	}
+
+	let (mut color_bits, mut alpha_bits) = (mode.color_bits, mode.alpha_bits);
+	if mode.endpoint_pbits || mode.shared_pbits {
+		let mut pbits = [0; 6];
+		if mode.endpoint_pbits {
+			for p in pbits.iter_mut().take(count) {
+				*p = r.read(1);
+			}
+		} else {
+			for s in 0..mode.subsets as usize {
+				let p = r.read(1);
+				pbits[s * 2] = p;
+				pbits[s * 2 + 1] = p;
+			}
+		}
+
+		for (e, p) in endpoints.iter_mut().zip(pbits.iter()).take(count) {
+			for (c, v) in e.iter_mut().enumerate() {
+				if c < 3 || mode.alpha_bits > 0 {
+					*v = *v << 1 | p;
+				}
+			}
+		}
+
+		color_bits += 1;
+		if alpha_bits > 0 {
+			alpha_bits += 1;
+		}
+	}
+
+	for e in endpoints.iter_mut().take(count) {
+		for (c, v) in e.iter_mut().enumerate() {
+			if c < 3 {
+				*v = expand_bits(*v, color_bits);
+			} else if alpha_bits > 0 {
+				*v = expand_bits(*v, alpha_bits);
+			}
+		}
+	}
+
+	let mut indices = [0u32; 16];
+	for (i, idx) in indices.iter_mut().enumerate() {
+		let anchor = is_anchor(mode.subsets, partition, i);
+		*idx = r.read(mode.index_bits - anchor as u32);
+	}
+
+	let mut indices2 = [0u32; 16];
+	if mode.index2_bits > 0 {
+		for (i, idx) in indices2.iter_mut().enumerate() {
+			*idx = r.read(mode.index2_bits - (i == 0) as u32);
+		}
+	}
+
+	let mut out = [[0u8; 4]; 16];
+	for (i, p) in out.iter_mut().enumerate() {
+		let s = subset(mode.subsets, partition, i);
+		let (e0, e1) = (&endpoints[s * 2], &endpoints[s * 2 + 1]);
+
+		// mode 4's selection bit swaps which index set drives color and alpha
+		let (color_idx, color_w, alpha_idx, alpha_w) = if mode.index2_bits == 0 {
+			(indices[i], mode.index_bits, indices[i], mode.index_bits)
+		} else if index_selection == 0 {
+			(indices[i], mode.index_bits, indices2[i], mode.index2_bits)
+		} else {
+			(indices2[i], mode.index2_bits, indices[i], mode.index_bits)
+		};
+
+		let lerp = |c: usize, idx: u32, bits: u32| {
+			let w = weights(bits)[idx as usize];
+			(((64 - w) * e0[c] + w * e1[c] + 32) >> 6) as u8
+		};
+
+		*p = [
+			lerp(0, color_idx, color_w),
+			lerp(1, color_idx, color_w),
+			lerp(2, color_idx, color_w),
+			lerp(3, alpha_idx, alpha_w),
+		];
+
+		match rotation {
+			1 => p.swap(0, 3),
+			2 => p.swap(1, 3),
+			3 => p.swap(2, 3),
+			_ => (),
+		}
+	}
+
+	out
+}
+
+// BC6H endpoint fields, as endpoint * 3 + channel
+const RW: u8 = 0;
+const GW: u8 = 1;
+const BW: u8 = 2;
+const RX: u8 = 3;
+const GX: u8 = 4;
+const BX: u8 = 5;
+const RY: u8 = 6;
+const GY: u8 = 7;
+const BY: u8 = 8;
+const RZ: u8 = 9;
+const GZ: u8 = 10;
+const BZ: u8 = 11;
+
+/// BC6H mode layout
+struct Bc6hMode {
+	/// Mode value, two bits for modes 0 and 1 and five otherwise
+	value: u32,
+	regions: u32,
+	transformed: bool,
+
+	/// Bits of the base endpoint
+	endpoint_bits: u32,
+
+	/// Bits of the other endpoints, per channel
+	delta_bits: [u32; 3],
+
+	/// Fields as (field, first bit, bit count) in stored order; a negative count stores the bits reversed
+	layout: &'static [(u8, u8, i8)],
+}
+
+const BC6H_MODES: [Bc6hMode; 14] = [
+	Bc6hMode { value: 0b00, regions: 2, transformed: true, endpoint_bits: 10, delta_bits: [5, 5, 5], layout: &[
+		(GY, 4, 1), (BY, 4, 1), (BZ, 4, 1), (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 5), (GZ, 4, 1),
+		(GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1),
+		(RZ, 0, 5), (BZ, 3, 1),
+	] },
+	Bc6hMode { value: 0b01, regions: 2, transformed: true, endpoint_bits: 7, delta_bits: [6, 6, 6], layout: &[
+		(GY, 5, 1), (GZ, 4, 1), (GZ, 5, 1), (RW, 0, 7), (BZ, 0, 1), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 7), (BY, 5, 1),
+		(BZ, 2, 1), (GY, 4, 1), (BW, 0, 7), (BZ, 3, 1), (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 6),
+		(GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 6), (RZ, 0, 6),
+	] },
+	Bc6hMode { value: 0b00010, regions: 2, transformed: true, endpoint_bits: 11, delta_bits: [5, 4, 4], layout: &[
+		(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 5), (RW, 10, 1), (GY, 0, 4), (GX, 0, 4), (GW, 10, 1),
+		(BZ, 0, 1), (GZ, 0, 4), (BX, 0, 4), (BW, 10, 1), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5),
+		(BZ, 3, 1),
+	] },
+	Bc6hMode { value: 0b00110, regions: 2, transformed: true, endpoint_bits: 11, delta_bits: [4, 5, 4], layout: &[
+		(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, 1), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5),
+		(GW, 10, 1), (GZ, 0, 4), (BX, 0, 4), (BW, 10, 1), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 4), (BZ, 0, 1), (BZ, 2, 1),
+		(RZ, 0, 4), (GY, 4, 1), (BZ, 3, 1),
+	] },
+	Bc6hMode { value: 0b01010, regions: 2, transformed: true, endpoint_bits: 11, delta_bits: [4, 4, 5], layout: &[
+		(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, 1), (BY, 4, 1), (GY, 0, 4), (GX, 0, 4),
+		(GW, 10, 1), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BW, 10, 1), (BY, 0, 4), (RY, 0, 4), (BZ, 1, 1), (BZ, 2, 1),
+		(RZ, 0, 4), (BZ, 4, 1), (BZ, 3, 1),
+	] },
+	Bc6hMode { value: 0b01110, regions: 2, transformed: true, endpoint_bits: 9, delta_bits: [5, 5, 5], layout: &[
+		(RW, 0, 9), (BY, 4, 1), (GW, 0, 9), (GY, 4, 1), (BW, 0, 9), (BZ, 4, 1), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4),
+		(GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5),
+		(BZ, 3, 1),
+	] },
+	Bc6hMode { value: 0b10010, regions: 2, transformed: true, endpoint_bits: 8, delta_bits: [6, 5, 5], layout: &[
+		(RW, 0, 8), (GZ, 4, 1), (BY, 4, 1), (GW, 0, 8), (BZ, 2, 1), (GY, 4, 1), (BW, 0, 8), (BZ, 3, 1), (BZ, 4, 1),
+		(RX, 0, 6), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 6),
+		(RZ, 0, 6),
+	] },
+	Bc6hMode { value: 0b10110, regions: 2, transformed: true, endpoint_bits: 8, delta_bits: [5, 6, 5], layout: &[
+		(RW, 0, 8), (BZ, 0, 1), (BY, 4, 1), (GW, 0, 8), (GY, 5, 1), (GY, 4, 1), (BW, 0, 8), (GZ, 5, 1), (BZ, 4, 1),
+		(RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5),
+		(BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1),
+	] },
+	Bc6hMode { value: 0b11010, regions: 2, transformed: true, endpoint_bits: 8, delta_bits: [5, 5, 6], layout: &[
+		(RW, 0, 8), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 8), (BY, 5, 1), (GY, 4, 1), (BW, 0, 8), (BZ, 5, 1), (BZ, 4, 1),
+		(RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 5),
+		(BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1),
+	] },
+	Bc6hMode { value: 0b11110, regions: 2, transformed: false, endpoint_bits: 6, delta_bits: [6, 6, 6], layout: &[
+		(RW, 0, 6), (GZ, 4, 1), (BZ, 0, 1), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 6), (GY, 5, 1), (BY, 5, 1), (BZ, 2, 1),
+		(GY, 4, 1), (BW, 0, 6), (GZ, 5, 1), (BZ, 3, 1), (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 6),
+		(GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 6), (RZ, 0, 6),
+	] },
+	Bc6hMode { value: 0b00011, regions: 1, transformed: false, endpoint_bits: 10, delta_bits: [10, 10, 10], layout: &[
+		(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 10), (GX, 0, 10), (BX, 0, 10),
+	] },
+	Bc6hMode { value: 0b00111, regions: 1, transformed: true, endpoint_bits: 11, delta_bits: [9, 9, 9], layout: &[
+		(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 9), (RW, 10, 1), (GX, 0, 9), (GW, 10, 1), (BX, 0, 9),
+		(BW, 10, 1),
+	] },
+	Bc6hMode { value: 0b01011, regions: 1, transformed: true, endpoint_bits: 12, delta_bits: [8, 8, 8], layout: &[
+		(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 8), (RW, 10, -2), (GX, 0, 8), (GW, 10, -2), (BX, 0, 8),
+		(BW, 10, -2),
+	] },
+	Bc6hMode { value: 0b01111, regions: 1, transformed: true, endpoint_bits: 16, delta_bits: [4, 4, 4], layout: &[
+		(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, -6), (GX, 0, 4), (GW, 10, -6), (BX, 0, 4),
+		(BW, 10, -6),
+	] },
+];
+
+fn sign_extend(v: i32, bits: u32) -> i32 {
+	let shift = 32 - bits;
+	(v << shift) >> shift
+}
+
+fn bc6h_unquantize(v: i32, bits: u32, signed: bool) -> i32 {
+	if signed {
+		if bits >= 16 {
+			return v;
+		}
+
+		let (neg, c) = (v < 0, v.abs());
+		let u = if c == 0 {
+			0
+		} else if c >= (1 << (bits - 1)) - 1 {
+			0x7FFF
+		} else {
+			((c << 15) + 0x4000) >> (bits - 1)
+		};
+		if neg { -u } else { u }
+	} else if bits >= 15 {
+		v
+	} else if v == 0 {
+		0
+	} else if v == (1 << bits) - 1 {
+		0xFFFF
+	} else {
+		((v << 16) + 0x8000) >> bits
+	}
+}
+
+/// Scales an interpolated value to half float bits
+fn bc6h_finish(v: i32, signed: bool) -> u16 {
+	if signed {
+		if v < 0 {
+			((((-v) * 31) >> 5) as u16) | 0x8000
+		} else {
+			((v * 31) >> 5) as u16
+		}
+	} else {
+		((v * 31) >> 6) as u16
+	}
+}
+
+/// Decodes a BC6H block to half float RGBA with opaque alpha. Reserved mode blocks decode as zero.
+pub fn decode_bc6h(b: &[u8], signed: bool) -> [[u16; 4]; 16] {
+	const ONE: u16 = 0x3C00;
+	let mut r = BitReader::new(b);
+
+	let mut value = r.read(2);
+	if value > 1 {
+		value |= r.read(3) << 2;
+	}
+	let mode = match BC6H_MODES.iter().find(|m| m.value == value) {
+		Some(m) => m,
+		None => return [[0, 0, 0, ONE]; 16],
+	};
+
+	let mut fields = [0i32; 12];
+	for (field, first, count) in mode.layout.iter() {
+		let n = count.unsigned_abs() as u32;
+		for k in 0..n {
+			let bit = if *count < 0 { *first as u32 + n - 1 - k } else { *first as u32 + k };
+			fields[*field as usize] |= (r.read(1) as i32) << bit;
+		}
+	}
+
+	let partition = if mode.regions == 2 { r.read(5) as usize } else { 0 };
+	let endpoints = mode.regions as usize * 2;
+	let eb = mode.endpoint_bits;
+
+	if signed {
+		for f in fields.iter_mut().take(3) {
+			*f = sign_extend(*f, eb);
+		}
+	}
+	if signed || mode.transformed {
+		for e in 1..endpoints {
+			for c in 0..3 {
+				fields[e * 3 + c] = sign_extend(fields[e * 3 + c], mode.delta_bits[c]);
+			}
+		}
+	}
+	if mode.transformed {
+		for e in 1..endpoints {
+			for c in 0..3 {
+				let v = (fields[c] + fields[e * 3 + c]) & ((1 << eb) - 1);
+				fields[e * 3 + c] = if signed { sign_extend(v, eb) } else { v };
+			}
+		}
+	}
+	for f in fields.iter_mut().take(endpoints * 3) {
+		*f = bc6h_unquantize(*f, eb, signed);
+	}
+
+	let index_bits = if mode.regions == 2 { 3 } else { 4 };
+	let mut out = [[0, 0, 0, ONE]; 16];
+	for (i, p) in out.iter_mut().enumerate() {
+		let anchor = is_anchor(mode.regions, partition, i);
+		let w = weights(index_bits)[r.read(index_bits - anchor as u32) as usize] as i32;
+		let s = subset(mode.regions, partition, i);
+
+		for c in 0..3 {
+			let (e0, e1) = (fields[s * 6 + c], fields[s * 6 + 3 + c]);
+			p[c] = bc6h_finish(((64 - w) * e0 + w * e1 + 32) >> 6, signed);
+		}
+	}
+
+	out
+}
+
+fn to565(p: &[f32; 3]) -> u16 {
+	let q = |v: f32, max: f32| (v.clamp(0.0, 255.0) * max / 255.0).round() as u16;
+	q(p[0], 31.0) << 11 | q(p[1], 63.0) << 5 | q(p[2], 31.0)
+}
+
+fn dist_sq(a: &[u8; 4], b: &[u8; 4]) -> u32 {
+	(0..3).map(|i| (a[i] as i32 - b[i] as i32).pow(2) as u32).sum()
+}
+
+/// Encodes a BC1 color block, fitting endpoints along the colors' principal axis.
+/// With `punchthrough`, pixels with alpha below 128 are made transparent.
+pub fn encode_bc1(px: &[[u8; 4]; 16], punchthrough: bool) -> [u8; 8] {
+	let transparent = |p: &[u8; 4]| punchthrough && p[3] < 128;
+	let opaque: Vec<[f32; 3]> = px.iter()
+		.filter(|p| !transparent(p))
+		.map(|p| [p[0] as f32, p[1] as f32, p[2] as f32])
+		.collect();
+
+	let three_color = opaque.len() < 16;
+	let (mut c0, mut c1) = (0, 0);
+
+	if !opaque.is_empty() {
+		let n = opaque.len() as f32;
+		let mean = [0, 1, 2].map(|c| opaque.iter().map(|p| p[c]).sum::<f32>() / n);
+
+		// covariance, then the principal axis by power iteration
+		let mut cov = [[0f32; 3]; 3];
+		for p in opaque.iter() {
+			for i in 0..3 {
+				for j in 0..3 {
+					cov[i][j] += (p[i] - mean[i]) * (p[j] - mean[j]);
+				}
+			}
+		}
+		let mut axis = [1f32, 1.0, 1.0];
+		for _ in 0..8 {
+			let next = [0, 1, 2].map(|i| (0..3).map(|j| cov[i][j] * axis[j]).sum::<f32>());
+			let len = next.iter().map(|v| v * v).sum::<f32>().sqrt();
+			if len < 1e-6 {
+				break;
+			}
+			axis = next.map(|v| v / len);
+		}
+
+		let proj = |p: &[f32; 3]| (0..3).map(|i| (p[i] - mean[i]) * axis[i]).sum::<f32>();
+		let (lo, hi) = opaque.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), p| {
+			(lo.min(proj(p)), hi.max(proj(p)))
+		});
+		c0 = to565(&[0, 1, 2].map(|i| mean[i] + axis[i] * hi));
+		c1 = to565(&[0, 1, 2].map(|i| mean[i] + axis[i] * lo));
+	}
+
+	// the endpoint order selects the mode
+	if (three_color && c0 > c1) || (!three_color && c0 < c1) {
+		std::mem::swap(&mut c0, &mut c1);
+	}
+
+	let palette = color_palette(c0, c1, false);
+	let choices = if c0 > c1 { 4 } else { 3 };
+	let mut indices = 0u32;
+	for (i, p) in px.iter().enumerate() {
+		let idx = if transparent(p) {
+			3
+		} else {
+			(0..choices).min_by_key(|k| dist_sq(&palette[*k as usize], p)).unwrap_or(0)
+		};
+		indices |= idx << (i * 2);
+	}
+
+	let mut block = [0u8; 8];
+	block[..2].copy_from_slice(&c0.to_le_bytes());
+	block[2..4].copy_from_slice(&c1.to_le_bytes());
+	block[4..].copy_from_slice(&indices.to_le_bytes());
+	block
+}
+
+/// Encodes a BC3 alpha or BC4 channel block using the eight value mode
+pub fn encode_bc4(values: &[u8; 16]) -> [u8; 8] {
+	let (lo, hi) = values.iter().fold((255u8, 0u8), |(lo, hi), v| (lo.min(*v), hi.max(*v)));
+
+	let mut block = [0u8; 8];
+	block[0] = hi;
+	block[1] = lo;
+
+	if hi > lo {
+		let palette: Vec<u32> = (0..8).map(|i| match i {
+			0 => hi as u32,
+			1 => lo as u32,
+			_ => ((8 - i) * hi as u32 + (i - 1) * lo as u32) / 7,
+		}).collect();
+
+		let mut indices = 0u64;
+		for (i, v) in values.iter().enumerate() {
+			let idx = (0..8u64).min_by_key(|k| (palette[*k as usize] as i32 - *v as i32).abs()).unwrap_or(0);
+			indices |= idx << (i * 3);
+		}
+		block[2..].copy_from_slice(&indices.to_le_bytes()[..6]);
+	}
+
+	block
+}
+
+pub fn encode_bc2(px: &[[u8; 4]; 16]) -> [u8; 16] {
+	let mut alpha = 0u64;
+	for (i, p) in px.iter().enumerate() {
+		alpha |= (((p[3] as u32 * 15 + 127) / 255) as u64) << (i * 4);
+	}
+
+	let mut block = [0u8; 16];
+	block[..8].copy_from_slice(&alpha.to_le_bytes());
+	block[8..].copy_from_slice(&encode_bc1(px, false));
+	block
+}
+
+pub fn encode_bc3(px: &[[u8; 4]; 16]) -> [u8; 16] {
+	let mut block = [0u8; 16];
+	block[..8].copy_from_slice(&encode_bc4(&px.map(|p| p[3])));
+	block[8..].copy_from_slice(&encode_bc1(px, false));
+	block
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Writes bit fields least significant bit first, for building test blocks
+	struct BitWriter {
+		bits: u128,
+		pos: u32,
+	}
+
+	impl BitWriter {
+		fn new() -> BitWriter {
+			BitWriter {
+				bits: 0,
+				pos: 0,
+			}
+		}
+
+		fn write(&mut self, v: u32, count: u32) {
+			self.bits |= ((v as u128) & ((1u128 << count) - 1)) << self.pos;
+			self.pos += count;
+		}
+
+		fn bytes(&self) -> [u8; 16] {
+			self.bits.to_le_bytes()
+		}
+	}
+
+	#[test]
+	fn test_anchors() {
+		// every anchor must be the first pixel of its subset it can be, and belong to it
+		for p in 0..64 {
+			assert_eq!(subset(2, p, 0), 0);
+			assert_eq!(subset(2, p, ANCHOR2[p] as usize), 1, "partition {}", p);
+			assert_eq!(subset(3, p, 0), 0);
+			assert_eq!(subset(3, p, ANCHOR3[p][0] as usize), 1, "partition {}", p);
+			assert_eq!(subset(3, p, ANCHOR3[p][1] as usize), 2, "partition {}", p);
+		}
+	}
+
+	#[test]
+	fn test_bc1() {
+		// red and blue endpoints, with the first row using every index
+		let block = [0x00, 0xF8, 0x1F, 0x00, 0b11_10_01_00, 0, 0, 0];
+		let px = decode_bc1(&block);
+		assert_eq!(px[0], [255, 0, 0, 255]);
+		assert_eq!(px[1], [0, 0, 255, 255]);
+		assert_eq!(px[2], [170, 0, 84, 255]);
+		assert_eq!(px[3], [85, 0, 169, 255]);
+		assert_eq!(px[4], px[0]);
+
+		// swapped endpoints select the three color mode
+		let block = [0x1F, 0x00, 0x00, 0xF8, 0b11_10_01_00, 0, 0, 0];
+		let px = decode_bc1(&block);
+		assert_eq!(px[2], [128, 0, 128, 255]);
+		assert_eq!(px[3], [0, 0, 0, 0]);
+	}
+
+	#[test]
+	fn test_bc3_alpha() {
+		// 8 value mode, then 6 value mode with explicit 0 and 255
+		let px = decode_bc4(&[200, 60, 0b10_001_000, 0b0000_0110, 0, 0, 0, 0]);
+		assert_eq!(&px[..4], &[200, 60, 180, 160]);
+
+		let px = decode_bc4(&[60, 200, 0b110_111, 0, 0, 0, 0, 0]);
+		assert_eq!(&px[..3], &[255, 0, 60]);
+	}
+
+	#[test]
+	fn test_bc7_mode6() {
+		let mut w = BitWriter::new();
+		w.write(1 << 6, 7);
+		// R0 R1 G0 G1 B0 B1 A0 A1
+		for v in [127, 0, 0, 64, 0, 0, 127, 127].iter() {
+			w.write(*v, 7);
+		}
+		// p-bits, then the anchor's 3 bit index and the rest 4 bits
+		w.write(1, 1);
+		w.write(0, 1);
+		w.write(0, 3);
+		w.write(15, 4);
+
+		let px = decode_bc7(&w.bytes());
+		assert_eq!(px[0], [255, 1, 1, 255]);
+		assert_eq!(px[1], [0, 128, 0, 254]);
+		assert_eq!(px[2], px[0]);
+	}
+
+	#[test]
+	fn test_bc7_mode1_partition() {
+		let mut w = BitWriter::new();
+		w.write(1 << 1, 2);
+		w.write(13, 6);
+		// subset 0 black, subset 1 white, in 6 bit endpoints
+		for _ in 0..3 {
+			for v in [0, 0, 63, 63].iter() {
+				w.write(*v, 6);
+			}
+		}
+		w.write(0, 1);
+		w.write(1, 1);
+
+		// partition 13 splits the top and bottom halves
+		let px = decode_bc7(&w.bytes());
+		assert_eq!(px[0], [0, 0, 0, 255]);
+		assert_eq!(px[15], [255, 255, 255, 255]);
+	}
+
+	#[test]
+	fn test_bc6h_mode10() {
+		let mut w = BitWriter::new();
+		w.write(0b00011, 5);
+		for v in [1023, 0, 512, 0, 1023, 512].iter() {
+			w.write(*v, 10);
+		}
+		// pixel 1 uses the second endpoint
+		w.write(0, 3);
+		w.write(15, 4);
+
+		let px = decode_bc6h(&w.bytes(), false);
+		assert_eq!(px[0], [0x7BFF, 0, bc6h_finish(bc6h_unquantize(512, 10, false), false), 0x3C00]);
+		assert_eq!(px[1][0], 0);
+		assert_eq!(px[1][1], 0x7BFF);
+
+		// reserved modes decode as black
+		assert_eq!(decode_bc6h(&[0b10011; 16], false)[0], [0, 0, 0, 0x3C00]);
+	}
+
+	#[test]
+	fn test_bc6h_transformed() {
+		// mode 11: 11 bit base endpoint with a 9 bit signed delta
+		let mut w = BitWriter::new();
+		w.write(0b00111, 5);
+		w.write(100, 10);
+		w.write(100, 10);
+		w.write(100, 10);
+		for _ in 0..3 {
+			w.write((-4i32) as u32, 9);
+			w.write(0, 1);
+		}
+		w.write(0, 3);
+		w.write(15, 4);
+
+		let px = decode_bc6h(&w.bytes(), false);
+		let expect = |v| bc6h_finish(bc6h_unquantize(v, 11, false), false);
+		assert_eq!(px[0][0], expect(100));
+		assert_eq!(px[1][0], expect(96));
+	}
+}
@@ -0,0 +1,319 @@
+use thiserror::Error;
+
+use super::{
+	bc,
+	etc,
+	PixelData,
+	Texture
+};
+
+/// Block-compressed texture encodings, all of which store 4x4 pixel blocks
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum BlockFormat {
+	/// DXT1, RGB with optional 1 bit alpha
+	Bc1,
+
+	/// DXT2/3, RGB with explicit 4 bit alpha
+	Bc2,
+
+	/// DXT4/5, RGB with interpolated alpha
+	Bc3,
+
+	/// ATI1/RGTC1, a single channel, decoded as luminance
+	Bc4,
+
+	/// ATI2/3Dc/RGTC2, two channels, decoded as red and green
+	Bc5,
+
+	/// Unsigned half float RGB
+	Bc6hUf16,
+
+	/// Signed half float RGB
+	Bc6hSf16,
+
+	/// High quality RGBA
+	Bc7,
+	Etc1,
+	Etc2Rgb,
+
+	/// ETC2 RGB with EAC alpha
+	Etc2Rgba,
+
+	/// ETC2 RGB with punch-through 1 bit alpha
+	Etc2RgbA1,
+
+	/// A single unsigned 11 bit channel, decoded as red
+	EacR11,
+
+	/// A single signed 11 bit channel, decoded as red
+	EacR11Snorm,
+
+	/// Two unsigned 11 bit channels, decoded as red and green
+	EacRg11,
+
+	/// Two signed 11 bit channels, decoded as red and green
+	EacRg11Snorm,
+}
+
+impl BlockFormat {
+	/// Size of a 4x4 block in bytes
+	pub fn block_size(&self) -> usize {
+		match self {
+			BlockFormat::Bc1 | BlockFormat::Bc4 | BlockFormat::Etc1 | BlockFormat::Etc2Rgb | BlockFormat::Etc2RgbA1 |
+				BlockFormat::EacR11 | BlockFormat::EacR11Snorm => 8,
+			_ => 16,
+		}
+	}
+
+	/// Size of an image of the specified dimensions in bytes, padded to whole blocks
+	pub fn data_size(&self, width: usize, height: usize) -> usize {
+		width.div_ceil(4) * height.div_ceil(4) * self.block_size()
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum BlockError {
+	#[error("Expected {expected} bytes of block data, got {actual}")]
+	Size {
+		expected: usize,
+		actual: usize,
+	},
+	#[error("Encoding to {0:?} is not supported")]
+	Unsupported(BlockFormat),
+}
+
+/// Decoded pixels of a block, in rows
+enum Block {
+	Rgba8([[u8; 4]; 16]),
+	L8([u8; 16]),
+	Rgba16F([[u16; 4]; 16]),
+	Rgba32F([[f32; 4]; 16]),
+}
+
+/// Decodes one or two 11 bit EAC channels into red and green
+fn eac11(b: &[u8], channels: usize, signed: bool) -> Block {
+	let mut out = [[0.0, 0.0, 0.0, 1.0]; 16];
+	for c in 0..channels {
+		for (p, v) in out.iter_mut().zip(etc::decode_eac11(&b[c * 8..], signed).iter()) {
+			p[c] = *v;
+		}
+	}
+	Block::Rgba32F(out)
+}
+
+fn decode_block(format: BlockFormat, b: &[u8]) -> Block {
+	match format {
+		BlockFormat::Bc1 => Block::Rgba8(bc::decode_bc1(b)),
+		BlockFormat::Bc2 => Block::Rgba8(bc::decode_bc2(b)),
+		BlockFormat::Bc3 => Block::Rgba8(bc::decode_bc3(b)),
+		BlockFormat::Bc4 => Block::L8(bc::decode_bc4(b)),
+		BlockFormat::Bc5 => Block::Rgba8(bc::decode_bc5(b)),
+		BlockFormat::Bc6hUf16 => Block::Rgba16F(bc::decode_bc6h(b, false)),
+		BlockFormat::Bc6hSf16 => Block::Rgba16F(bc::decode_bc6h(b, true)),
+		BlockFormat::Bc7 => Block::Rgba8(bc::decode_bc7(b)),
+		BlockFormat::Etc1 => Block::Rgba8(etc::decode_etc2_rgb(b, false)),
+		BlockFormat::Etc2Rgb => Block::Rgba8(etc::decode_etc2_rgb(b, true)),
+		BlockFormat::Etc2Rgba => Block::Rgba8(etc::decode_etc2_rgba(b)),
+		BlockFormat::Etc2RgbA1 => Block::Rgba8(etc::decode_etc2_rgb_a1(b)),
+		BlockFormat::EacR11 => eac11(b, 1, false),
+		BlockFormat::EacR11Snorm => eac11(b, 1, true),
+		BlockFormat::EacRg11 => eac11(b, 2, false),
+		BlockFormat::EacRg11Snorm => eac11(b, 2, true),
+	}
+}
+
+/// Decodes a block-compressed image into a texture. BC4 decodes to [`PixelData::L8`], BC6H to
+/// [`PixelData::Rgba16F`], the 11 bit EAC formats to [`PixelData::Rgba32F`] and the rest to [`PixelData::Rgba8`]. Pixels past the image's edge in partial
+/// blocks are discarded.
+pub fn decode(data: &[u8], width: usize, height: usize, format: BlockFormat) -> Result<Texture, BlockError> {
+	let expected = format.data_size(width, height);
+	if data.len() < expected {
+		return Err(BlockError::Size {
+			expected,
+			actual: data.len(),
+		});
+	}
+
+	let len = width * height;
+	let mut pixels = match format {
+		BlockFormat::Bc4 => PixelData::L8(vec![0; len]),
+		BlockFormat::Bc6hUf16 | BlockFormat::Bc6hSf16 => PixelData::Rgba16F(vec![[0; 4]; len]),
+		BlockFormat::EacR11 | BlockFormat::EacR11Snorm | BlockFormat::EacRg11 | BlockFormat::EacRg11Snorm => {
+			PixelData::Rgba32F(vec![[0.0; 4]; len])
+		},
+		_ => PixelData::Rgba8(vec![[0; 4]; len]),
+	};
+
+	let blocks_x = width.div_ceil(4);
+	for (n, b) in data[..expected].chunks_exact(format.block_size()).enumerate() {
+		let (bx, by) = ((n % blocks_x) * 4, (n / blocks_x) * 4);
+		let block = decode_block(format, b);
+
+		for y in 0..4.min(height - by) {
+			for x in 0..4.min(width - bx) {
+				let (i, j) = ((by + y) * width + bx + x, y * 4 + x);
+				match (&mut pixels, &block) {
+					(PixelData::Rgba8(p), Block::Rgba8(b)) => p[i] = b[j],
+					(PixelData::L8(p), Block::L8(b)) => p[i] = b[j],
+					(PixelData::Rgba16F(p), Block::Rgba16F(b)) => p[i] = b[j],
+					(PixelData::Rgba32F(p), Block::Rgba32F(b)) => p[i] = b[j],
+					_ => (),
+				}
+			}
+		}
+	}
+
+	Ok(Texture {
+		palette: vec![],
+		data: pixels,
+		width,
+		height,
+	})
+}
+
+/// Encodes a texture of any pixel format. BC1 through BC5 are supported; BC1 switches blocks with
+/// transparent pixels to its 1 bit alpha mode. Partial blocks are padded by repeating edge pixels.
+pub fn encode(tex: &Texture, format: BlockFormat) -> Result<Vec<u8>, BlockError> {
+	let rgba = tex.convert(super::PixelFormat::Rgba8);
+	let pixels = match &rgba.data {
+		PixelData::Rgba8(p) => p,
+		_ => unreachable!(),
+	};
+
+	let mut out = Vec::with_capacity(format.data_size(tex.width, tex.height));
+	for by in (0..tex.height).step_by(4) {
+		for bx in (0..tex.width).step_by(4) {
+			let mut block = [[0u8; 4]; 16];
+			for (j, p) in block.iter_mut().enumerate() {
+				let x = (bx + j % 4).min(tex.width - 1);
+				let y = (by + j / 4).min(tex.height - 1);
+				*p = pixels[y * tex.width + x];
+			}
+
+			match format {
+				BlockFormat::Bc1 => out.extend_from_slice(&bc::encode_bc1(&block, true)),
+				BlockFormat::Bc2 => out.extend_from_slice(&bc::encode_bc2(&block)),
+				BlockFormat::Bc3 => out.extend_from_slice(&bc::encode_bc3(&block)),
+				BlockFormat::Bc4 => {
+					let luma = block.map(|p| {
+						((p[0] as u32 * 54 + p[1] as u32 * 183 + p[2] as u32 * 19 + 128) >> 8) as u8
+					});
+					out.extend_from_slice(&bc::encode_bc4(&luma));
+				},
+				BlockFormat::Bc5 => {
+					out.extend_from_slice(&bc::encode_bc4(&block.map(|p| p[0])));
+					out.extend_from_slice(&bc::encode_bc4(&block.map(|p| p[1])));
+				},
+				_ => return Err(BlockError::Unsupported(format)),
+			}
+		}
+	}
+
+	Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs::read;
+
+	use super::*;
+	use crate::texture::{
+		Color,
+		PixelFormat
+	};
+
+	/// Every format, named as its reference image in `test_data/blocks`
+	const REFERENCES: [(&str, BlockFormat); 16] = [
+		("bc1", BlockFormat::Bc1),
+		("bc2", BlockFormat::Bc2),
+		("bc3", BlockFormat::Bc3),
+		("bc4", BlockFormat::Bc4),
+		("bc5", BlockFormat::Bc5),
+		("bc6h_uf16", BlockFormat::Bc6hUf16),
+		("bc6h_sf16", BlockFormat::Bc6hSf16),
+		("bc7", BlockFormat::Bc7),
+		("etc1", BlockFormat::Etc1),
+		("etc2_rgb", BlockFormat::Etc2Rgb),
+		("etc2_rgba", BlockFormat::Etc2Rgba),
+		("etc2_rgb_a1", BlockFormat::Etc2RgbA1),
+		("eac_r11", BlockFormat::EacR11),
+		("eac_r11_snorm", BlockFormat::EacR11Snorm),
+		("eac_rg11", BlockFormat::EacRg11),
+		("eac_rg11_snorm", BlockFormat::EacRg11Snorm),
+	];
+
+	fn gradient(w: usize, h: usize) -> Texture {
+		let mut tex = Texture::with_format(w, h, PixelFormat::Rgba8);
+		for y in 0..h {
+			for x in 0..w {
+				tex.set_pixel(x, y, &Color {
+					red: x as f32 / w as f32,
+					green: y as f32 / h as f32,
+					blue: 0.5,
+					alpha: 1.0,
+				});
+			}
+		}
+		tex
+	}
+
+	#[test]
+	fn test_roundtrip() {
+		let tex = gradient(42, 26);
+
+		for format in [BlockFormat::Bc1, BlockFormat::Bc2, BlockFormat::Bc3, BlockFormat::Bc5].iter() {
+			let data = encode(&tex, *format).unwrap();
+			assert_eq!(data.len(), format.data_size(42, 26));
+
+			let out = decode(&data, 42, 26, *format).unwrap();
+			for (a, b) in tex.pixels().iter().zip(out.pixels().iter()) {
+				assert!((a.red - b.red).abs() < 0.05 && (a.green - b.green).abs() < 0.05, "{:?}", format);
+			}
+		}
+
+		let data = encode(&tex, BlockFormat::Bc4).unwrap();
+		assert_eq!(decode(&data, 42, 26, BlockFormat::Bc4).unwrap().format(), PixelFormat::L8);
+		assert!(encode(&tex, BlockFormat::Bc7).is_err());
+		assert!(decode(&data[1..], 42, 26, BlockFormat::Bc4).is_err());
+	}
+
+	#[test]
+	fn test_reference_images() {
+		for (name, format) in REFERENCES.iter() {
+			let data = read(format!("test_data/blocks/{}.bin", name)).unwrap();
+			let expected = read(format!("test_data/blocks/{}.out", name)).unwrap();
+			let tex = decode(&data, 16, 16, *format).unwrap();
+
+			match &tex.data {
+				// the reference reads single channel images back as red
+				PixelData::L8(p) => {
+					for (i, (l, e)) in p.iter().zip(expected.chunks_exact(4)).enumerate() {
+						assert_eq!(*l, e[0], "{} pixel {}", name, i);
+					}
+				},
+				PixelData::Rgba8(p) => {
+					for (i, (c, e)) in p.iter().zip(expected.chunks_exact(4)).enumerate() {
+						assert_eq!(&c[..], e, "{} pixel {}", name, i);
+					}
+				},
+				PixelData::Rgba16F(p) => {
+					for (i, (c, e)) in p.iter().zip(expected.chunks_exact(8)).enumerate() {
+						for (j, h) in c.iter().enumerate().take(3) {
+							assert_eq!(*h, u16::from_le_bytes([e[j * 2], e[j * 2 + 1]]), "{} pixel {}", name, i);
+						}
+					}
+				},
+				// the reference widens 11 bit values to 16 bits before normalizing them, so allow for rounding
+				PixelData::Rgba32F(p) => {
+					for (i, (c, e)) in p.iter().zip(expected.chunks_exact(16)).enumerate() {
+						for (j, f) in c.iter().enumerate() {
+							let e = f32::from_le_bytes([e[j * 4], e[j * 4 + 1], e[j * 4 + 2], e[j * 4 + 3]]);
+							assert!((f - e).abs() < 1e-4, "{} pixel {}: {} != {}", name, i, f, e);
+						}
+					}
+				},
+				_ => unreachable!(),
+			}
+		}
+	}
+}
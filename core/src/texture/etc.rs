@@ -0,0 +1,283 @@
+//! Ericsson texture compression block decoders. Blocks are big endian, and their pixel indices run down
+//! columns; the decoded pixels are returned in rows.
+
+/// ETC1 intensity modifiers, as the small and large magnitude for each table
+const ETC1_MODIFIERS: [[i32; 2]; 8] = [
+	[2, 8],
+	[5, 17],
+	[9, 29],
+	[13, 42],
+	[18, 60],
+	[24, 80],
+	[33, 106],
+	[47, 183],
+];
+
+/// ETC2 T and H mode distances
+const ETC2_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+/// EAC alpha modifiers
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+	[-3, -6, -9, -15, 2, 5, 8, 14],
+	[-3, -7, -10, -13, 2, 6, 9, 12],
+	[-2, -5, -8, -13, 1, 4, 7, 12],
+	[-2, -4, -6, -13, 1, 3, 5, 12],
+	[-3, -6, -8, -12, 2, 5, 7, 11],
+	[-3, -7, -9, -11, 2, 6, 8, 10],
+	[-4, -7, -8, -11, 3, 6, 7, 10],
+	[-3, -5, -8, -11, 2, 4, 7, 10],
+	[-2, -6, -8, -10, 1, 5, 7, 9],
+	[-2, -5, -8, -10, 1, 4, 7, 9],
+	[-2, -4, -8, -10, 1, 3, 7, 9],
+	[-2, -5, -7, -10, 1, 4, 6, 9],
+	[-3, -4, -7, -10, 2, 3, 6, 9],
+	[-1, -2, -3, -10, 0, 1, 2, 9],
+	[-4, -6, -8, -9, 3, 5, 7, 8],
+	[-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+fn bits(v: u64, hi: u32, lo: u32) -> i32 {
+	((v >> lo) & ((1 << (hi - lo + 1)) - 1)) as i32
+}
+
+fn clamp8(v: i32) -> u8 {
+	v.clamp(0, 255) as u8
+}
+
+fn extend4(v: i32) -> i32 {
+	v << 4 | v
+}
+
+fn extend5(v: i32) -> i32 {
+	v << 3 | v >> 2
+}
+
+/// Returns the 2 bit index of the pixel at column `x` and row `y`
+fn pixel_index(v: u64, x: usize, y: usize) -> usize {
+	let i = x * 4 + y;
+	(((v >> (i + 16)) & 1) << 1 | ((v >> i) & 1)) as usize
+}
+
+/// Decodes an ETC1 block, or an ETC2 RGB block if `etc2` is set, which reinterprets differential blocks
+/// whose base colors overflow as the T, H and planar modes
+pub fn decode_etc2_rgb(b: &[u8], etc2: bool) -> [[u8; 4]; 16] {
+	decode_rgb(b, etc2, false)
+}
+
+/// Decodes an ETC2 RGB block with punch-through alpha. The individual mode bit marks the block opaque instead,
+/// and in other blocks pixel index 2 is transparent black, while differential mode's small modifiers become 0.
+pub fn decode_etc2_rgb_a1(b: &[u8]) -> [[u8; 4]; 16] {
+	decode_rgb(b, true, true)
+}
+
+fn decode_rgb(b: &[u8], etc2: bool, punchthrough: bool) -> [[u8; 4]; 16] {
+	let v = u64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]);
+	let opaque = !punchthrough || (v >> 33) & 1 == 1;
+	let diff = punchthrough || (v >> 33) & 1 == 1;
+	let flip = (v >> 32) & 1 == 1;
+
+	let (base1, base2) = if diff {
+		let c1 = [bits(v, 63, 59), bits(v, 55, 51), bits(v, 47, 43)];
+		let d = [bits(v, 58, 56), bits(v, 50, 48), bits(v, 42, 40)].map(|d| (d << 29) >> 29);
+		let c2 = [c1[0] + d[0], c1[1] + d[1], c1[2] + d[2]];
+
+		if etc2 {
+			if !(0..32).contains(&c2[0]) {
+				return decode_t(v, opaque);
+			} else if !(0..32).contains(&c2[1]) {
+				return decode_h(v, opaque);
+			} else if !(0..32).contains(&c2[2]) {
+				return decode_planar(v);
+			}
+		}
+
+		(c1.map(extend5), c2.map(extend5))
+	} else {
+		(
+			[bits(v, 63, 60), bits(v, 55, 52), bits(v, 47, 44)].map(extend4),
+			[bits(v, 59, 56), bits(v, 51, 48), bits(v, 43, 40)].map(extend4),
+		)
+	};
+
+	let tables = [bits(v, 39, 37) as usize, bits(v, 36, 34) as usize];
+
+	let mut out = [[0u8; 4]; 16];
+	for y in 0..4 {
+		for x in 0..4 {
+			let second = if flip { y >= 2 } else { x >= 2 };
+			let (base, table) = if second { (base2, tables[1]) } else { (base1, tables[0]) };
+
+			let index = pixel_index(v, x, y);
+			if !opaque && index == 2 {
+				continue;
+			}
+
+			let m = ETC1_MODIFIERS[table];
+			let small = if opaque { m[0] } else { 0 };
+			let modifier = [small, m[1], -small, -m[1]][index];
+			out[y * 4 + x] = [clamp8(base[0] + modifier), clamp8(base[1] + modifier), clamp8(base[2] + modifier), 255];
+		}
+	}
+
+	out
+}
+
+/// Fills a block from four colors, leaving index 2 transparent black unless `opaque`
+fn paint(v: u64, colors: [[i32; 3]; 4], opaque: bool) -> [[u8; 4]; 16] {
+	let mut out = [[0u8; 4]; 16];
+	for y in 0..4 {
+		for x in 0..4 {
+			let index = pixel_index(v, x, y);
+			if opaque || index != 2 {
+				let c = colors[index];
+				out[y * 4 + x] = [clamp8(c[0]), clamp8(c[1]), clamp8(c[2]), 255];
+			}
+		}
+	}
+	out
+}
+
+fn decode_t(v: u64, opaque: bool) -> [[u8; 4]; 16] {
+	let c1 = [bits(v, 60, 59) << 2 | bits(v, 57, 56), bits(v, 55, 52), bits(v, 51, 48)].map(extend4);
+	let c2 = [bits(v, 47, 44), bits(v, 43, 40), bits(v, 39, 36)].map(extend4);
+	let d = ETC2_DISTANCES[(bits(v, 35, 34) << 1 | bits(v, 32, 32)) as usize];
+
+	paint(v, [c1, c2.map(|c| c + d), c2, c2.map(|c| c - d)], opaque)
+}
+
+fn decode_h(v: u64, opaque: bool) -> [[u8; 4]; 16] {
+	let r1 = bits(v, 62, 59);
+	let g1 = bits(v, 58, 56) << 1 | bits(v, 52, 52);
+	let b1 = bits(v, 51, 51) << 3 | bits(v, 49, 47);
+	let (r2, g2, b2) = (bits(v, 46, 43), bits(v, 42, 39), bits(v, 38, 35));
+
+	// the base colors' order stores the distance's low bit
+	let order = ((r1 << 8 | g1 << 4 | b1) >= (r2 << 8 | g2 << 4 | b2)) as i32;
+	let d = ETC2_DISTANCES[(bits(v, 34, 34) << 2 | bits(v, 32, 32) << 1 | order) as usize];
+
+	let c1 = [r1, g1, b1].map(extend4);
+	let c2 = [r2, g2, b2].map(extend4);
+	paint(v, [c1.map(|c| c + d), c1.map(|c| c - d), c2.map(|c| c + d), c2.map(|c| c - d)], opaque)
+}
+
+fn decode_planar(v: u64) -> [[u8; 4]; 16] {
+	let e6 = |c: i32| c << 2 | c >> 4;
+	let e7 = |c: i32| c << 1 | c >> 6;
+
+	let o = [
+		e6(bits(v, 62, 57)),
+		e7(bits(v, 56, 56) << 6 | bits(v, 54, 49)),
+		e6(bits(v, 48, 48) << 5 | bits(v, 44, 43) << 3 | bits(v, 41, 39)),
+	];
+	let h = [e6(bits(v, 38, 34) << 1 | bits(v, 32, 32)), e7(bits(v, 31, 25)), e6(bits(v, 24, 19))];
+	let vert = [e6(bits(v, 18, 13)), e7(bits(v, 12, 6)), e6(bits(v, 5, 0))];
+
+	let mut out = [[0u8; 4]; 16];
+	for y in 0..4 {
+		for x in 0..4 {
+			let c = |k: usize| clamp8((x as i32 * (h[k] - o[k]) + y as i32 * (vert[k] - o[k]) + 4 * o[k] + 2) >> 2);
+			out[y * 4 + x] = [c(0), c(1), c(2), 255];
+		}
+	}
+	out
+}
+
+/// Decodes an 8 byte EAC alpha block
+pub fn decode_eac(b: &[u8]) -> [u8; 16] {
+	let v = u64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]);
+	let base = bits(v, 63, 56);
+	let multiplier = bits(v, 55, 52);
+	let table = &EAC_MODIFIERS[bits(v, 51, 48) as usize];
+
+	let mut out = [0u8; 16];
+	for x in 0..4 {
+		for y in 0..4 {
+			let i = (x * 4 + y) as u32;
+			let idx = bits(v, 47 - i * 3, 45 - i * 3) as usize;
+			out[y * 4 + x] = clamp8(base + table[idx] * multiplier);
+		}
+	}
+	out
+}
+
+/// Decodes an 8 byte 11 bit EAC block, as used by the R11 and RG11 formats, into values from 0 to 1, or from -1 to 1
+/// if `signed`
+pub fn decode_eac11(b: &[u8], signed: bool) -> [f32; 16] {
+	let v = u64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]);
+	let multiplier = bits(v, 55, 52);
+	let table = &EAC_MODIFIERS[bits(v, 51, 48) as usize];
+
+	// signed bases are two's complement, with -128 treated as -127
+	let base = if signed {
+		(bits(v, 63, 56) as i8 as i32).max(-127) * 8
+	} else {
+		bits(v, 63, 56) * 8 + 4
+	};
+
+	let mut out = [0.0; 16];
+	for x in 0..4 {
+		for y in 0..4 {
+			let i = (x * 4 + y) as u32;
+			let m = table[bits(v, 47 - i * 3, 45 - i * 3) as usize];
+
+			// a zero multiplier leaves the modifiers unscaled, at an eighth of their usual step
+			let value = base + if multiplier == 0 { m } else { m * multiplier * 8 };
+			out[y * 4 + x] = if signed {
+				value.clamp(-1023, 1023) as f32 / 1023.0
+			} else {
+				value.clamp(0, 2047) as f32 / 2047.0
+			};
+		}
+	}
+	out
+}
+
+/// Decodes an ETC2 RGBA block, an EAC alpha block followed by an ETC2 RGB block
+pub fn decode_etc2_rgba(b: &[u8]) -> [[u8; 4]; 16] {
+	let mut out = decode_etc2_rgb(&b[8..], true);
+	for (p, a) in out.iter_mut().zip(decode_eac(b).iter()) {
+		p[3] = *a;
+	}
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_etc1() {
+		// individual mode, both halves 0x88 grey, tables 0 and 7, flipped into top and bottom halves
+		let mut v: u64 = 0x88 << 56 | 0x88 << 48 | 0x88 << 40 | 0b000_111 << 34 | 1 << 32;
+		// the pixel at column 1, row 3 uses index 3, a large negative
+		v |= 1 << (16 + 7) | 1 << 7;
+		let px = decode_etc2_rgb(&v.to_be_bytes(), false);
+
+		assert_eq!(px[0], [138, 138, 138, 255]);
+		assert_eq!(px[13], [0, 0, 0, 255]);
+		assert_eq!(px[12], [183, 183, 183, 255]);
+	}
+
+	#[test]
+	fn test_etc2_planar() {
+		// a blue base color of 31 plus 1 overflows, selecting planar mode
+		let overflow: u64 = 1 << 33 | 0b11111 << 43 | 0b001 << 40;
+		// red is 0 at the origin, 63 at the horizontal corner and 0 at the vertical one
+		let v = overflow | 31 << 34 | 1 << 32;
+		let px = decode_etc2_rgb(&v.to_be_bytes(), true);
+
+		assert_eq!(px[0][0], 0);
+		assert_eq!(px[3][0], 191);
+		assert_eq!(px[12][0], 0);
+		assert_eq!(px[0][3], 255);
+	}
+
+	#[test]
+	fn test_eac() {
+		// base 128, multiplier 2, table 0; the first pixel uses index 4, +2
+		let v: u64 = 128 << 56 | 2 << 52 | 0b100 << 45;
+		let px = decode_eac(&v.to_be_bytes());
+		assert_eq!(px[0], 132);
+		assert_eq!(px[1], 128 - 6);
+	}
+}
@@ -0,0 +1,396 @@
+use std::f32::consts::PI;
+
+use super::{
+	Color,
+	PixelData,
+	PixelFormat,
+	Texture
+};
+
+/// Resampling filter
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Filter {
+	/// Nearest pixel, keeping the texture's format and palette
+	Nearest,
+
+	/// Linear interpolation, widened to a tent when shrinking
+	#[default]
+	Bilinear,
+
+	/// Windowed sinc with three lobes, sharper than bilinear but may ring at hard edges
+	Lanczos3,
+}
+
+impl Filter {
+	/// Kernel radius in source pixels at a 1:1 scale
+	fn support(&self) -> f32 {
+		match self {
+			Filter::Nearest => 0.5,
+			Filter::Bilinear => 1.0,
+			Filter::Lanczos3 => 3.0,
+		}
+	}
+
+	fn weight(&self, x: f32) -> f32 {
+		let x = x.abs();
+
+		match self {
+			Filter::Nearest => if x <= 0.5 { 1.0 } else { 0.0 },
+			Filter::Bilinear => (1.0 - x).max(0.0),
+			Filter::Lanczos3 => if x < 1e-6 {
+				1.0
+			} else if x < 3.0 {
+				let px = PI * x;
+				3.0 * px.sin() * (px / 3.0).sin() / (px * px)
+			} else {
+				0.0
+			},
+		}
+	}
+}
+
+/// Quarter turns
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rotation {
+	Clockwise90,
+	Half,
+	CounterClockwise90,
+}
+
+impl PixelData {
+	/// Builds new storage of the same format from the pixels at the specified source indices
+	pub fn gather(&self, map: &[usize]) -> PixelData {
+		match self {
+			PixelData::Indexed(p) => PixelData::Indexed(map.iter().map(|i| p[*i]).collect()),
+			PixelData::Rgba8(p) => PixelData::Rgba8(map.iter().map(|i| p[*i]).collect()),
+			PixelData::Rgb565(p) => PixelData::Rgb565(map.iter().map(|i| p[*i]).collect()),
+			PixelData::Rgba5551(p) => PixelData::Rgba5551(map.iter().map(|i| p[*i]).collect()),
+			PixelData::Rgba4444(p) => PixelData::Rgba4444(map.iter().map(|i| p[*i]).collect()),
+			PixelData::L8(p) => PixelData::L8(map.iter().map(|i| p[*i]).collect()),
+			PixelData::La8(p) => PixelData::La8(map.iter().map(|i| p[*i]).collect()),
+			PixelData::Rgba16F(p) => PixelData::Rgba16F(map.iter().map(|i| p[*i]).collect()),
+			PixelData::Rgba32F(p) => PixelData::Rgba32F(map.iter().map(|i| p[*i]).collect()),
+		}
+	}
+
+	/// Copies a stored pixel from storage of the same format, returning false if the formats differ
+	pub fn copy_pixel(&mut self, index: usize, src: &PixelData, src_index: usize) -> bool {
+		match (self, src) {
+			(PixelData::Indexed(d), PixelData::Indexed(s)) => d[index] = s[src_index],
+			(PixelData::Rgba8(d), PixelData::Rgba8(s)) => d[index] = s[src_index],
+			(PixelData::Rgb565(d), PixelData::Rgb565(s)) => d[index] = s[src_index],
+			(PixelData::Rgba5551(d), PixelData::Rgba5551(s)) => d[index] = s[src_index],
+			(PixelData::Rgba4444(d), PixelData::Rgba4444(s)) => d[index] = s[src_index],
+			(PixelData::L8(d), PixelData::L8(s)) => d[index] = s[src_index],
+			(PixelData::La8(d), PixelData::La8(s)) => d[index] = s[src_index],
+			(PixelData::Rgba16F(d), PixelData::Rgba16F(s)) => d[index] = s[src_index],
+			(PixelData::Rgba32F(d), PixelData::Rgba32F(s)) => d[index] = s[src_index],
+			_ => return false,
+		}
+
+		true
+	}
+}
+
+impl Texture {
+	/// Rearranges pixels into a texture of the specified size, where `map` gives each new pixel's source
+	fn remapped(&self, width: usize, height: usize, map: &[usize]) -> Texture {
+		Texture {
+			palette: self.palette.clone(),
+			data: self.data.gather(map),
+			width,
+			height,
+		}
+	}
+
+	/// Returns the specified region, clipped to the texture's bounds
+	pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Texture {
+		let width = width.min(self.width.saturating_sub(x));
+		let height = height.min(self.height.saturating_sub(y));
+		let map: Vec<usize> = (0..height)
+			.flat_map(|j| (0..width).map(move |i| (y + j) * self.width + x + i))
+			.collect();
+
+		self.remapped(width, height, &map)
+	}
+
+	/// Copies `src` onto this texture with its top left corner at the specified coordinates, clipping
+	/// anything outside. Pixels are copied as-is when both textures share a format and palette; otherwise
+	/// they are converted, and new colors are added to a palettized destination's palette.
+	pub fn blit(&mut self, src: &Texture, x: isize, y: isize) {
+		let raw = src.format() == self.format() &&
+			(src.format() != PixelFormat::Indexed || src.palette == self.palette);
+
+		for sy in 0..src.height {
+			let dy = y + sy as isize;
+			if dy < 0 || dy as usize >= self.height {
+				continue;
+			}
+
+			for sx in 0..src.width {
+				let dx = x + sx as isize;
+				if dx < 0 || dx as usize >= self.width {
+					continue;
+				}
+
+				let (si, di) = (sy * src.width + sx, dy as usize * self.width + dx as usize);
+				if !raw || !self.data.copy_pixel(di, &src.data, si) {
+					self.set_pixel(dx as usize, dy as usize, &src.data.get(si, &src.palette));
+				}
+			}
+		}
+	}
+
+	/// Mirrors the texture left to right
+	pub fn flip_horizontal(&self) -> Texture {
+		let map: Vec<usize> = (0..self.height)
+			.flat_map(|y| (0..self.width).rev().map(move |x| y * self.width + x))
+			.collect();
+
+		self.remapped(self.width, self.height, &map)
+	}
+
+	/// Mirrors the texture top to bottom
+	pub fn flip_vertical(&self) -> Texture {
+		let map: Vec<usize> = (0..self.height).rev()
+			.flat_map(|y| (0..self.width).map(move |x| y * self.width + x))
+			.collect();
+
+		self.remapped(self.width, self.height, &map)
+	}
+
+	/// Rotates the texture by quarter turns
+	pub fn rotate(&self, rotation: Rotation) -> Texture {
+		let (w, h) = (self.width, self.height);
+
+		match rotation {
+			Rotation::Half => {
+				let map: Vec<usize> = (0..w * h).rev().collect();
+				self.remapped(w, h, &map)
+			},
+			Rotation::Clockwise90 => {
+				// new pixel (x, y) comes from (y, h - 1 - x)
+				let map: Vec<usize> = (0..w)
+					.flat_map(|y| (0..h).map(move |x| (h - 1 - x) * w + y))
+					.collect();
+				self.remapped(h, w, &map)
+			},
+			Rotation::CounterClockwise90 => {
+				// new pixel (x, y) comes from (w - 1 - y, x)
+				let map: Vec<usize> = (0..w)
+					.flat_map(|y| (0..h).map(move |x| x * w + (w - 1 - y)))
+					.collect();
+				self.remapped(h, w, &map)
+			},
+		}
+	}
+
+	/// Scales the texture to the specified size.
+	///
+	/// [`Filter::Nearest`] keeps the format and palette. Other filters work on premultiplied alpha and keep
+	/// direct formats, but palettized textures come back as RGBA8 since filtering creates new colors;
+	/// [`Texture::quantize`] can palettize them again. A texture without exactly `width * height` pixels, such as
+	/// one fresh from [`Texture::new`], resizes to a blank texture.
+	pub fn resize(&self, width: usize, height: usize, filter: Filter) -> Texture {
+		// nothing to sample, or pixels missing, so the result is blank
+		if self.width == 0 || self.height == 0 || self.data.len() != self.width * self.height {
+			let mut tex = Texture::with_format(width, height, self.format());
+			tex.palette = self.palette.clone();
+			return tex;
+		}
+
+		if filter == Filter::Nearest {
+			let map: Vec<usize> = (0..height)
+				.flat_map(|y| (0..width).map(move |x| (y * self.height / height) * self.width + x * self.width / width))
+				.collect();
+			return self.remapped(width, height, &map);
+		}
+
+		let mut pixels: Vec<[f32; 4]> = self.pixels().iter()
+			.map(|c| [c.red * c.alpha, c.green * c.alpha, c.blue * c.alpha, c.alpha])
+			.collect();
+		pixels = resample(&pixels, self.width, self.height, width, 1, filter);
+		pixels = resample(&pixels, self.height, width, height, width, filter);
+
+		let format = match self.format() {
+			PixelFormat::Indexed => PixelFormat::Rgba8,
+			f => f,
+		};
+
+		let mut tex = Texture::with_format(width, height, format);
+		for (i, p) in pixels.iter().enumerate() {
+			let a = p[3];
+			let c = if a > 0.0 {
+				Color {
+					red: p[0] / a,
+					green: p[1] / a,
+					blue: p[2] / a,
+					alpha: a,
+				}
+			} else {
+				Color {
+					red: 0.0,
+					green: 0.0,
+					blue: 0.0,
+					alpha: 0.0,
+				}
+			};
+			tex.data.set(i, &c);
+		}
+
+		tex
+	}
+
+	/// Returns successively halved copies of the texture down to 1x1, starting with the first reduction
+	pub fn mipmaps(&self, filter: Filter) -> Vec<Texture> {
+		let mut chain: Vec<Texture> = vec![];
+
+		let (mut w, mut h) = (self.width, self.height);
+		while w > 1 || h > 1 {
+			w = (w / 2).max(1);
+			h = (h / 2).max(1);
+
+			// each level is built from the previous one, as a GPU would
+			let level = chain.last().unwrap_or(self).resize(w, h, filter);
+			chain.push(level);
+		}
+
+		chain
+	}
+}
+
+/// Resamples one axis of an image. The axis is `src_len` pixels long and is being scaled to `dst_len`;
+/// consecutive pixels along it are `stride` apart, and lines along the other axis follow each other.
+/// With a stride of 1 this resizes rows; with a stride of the row width it resizes columns.
+fn resample(src: &[[f32; 4]], src_len: usize, lines: usize, dst_len: usize, stride: usize, filter: Filter)
+	-> Vec<[f32; 4]>
+{
+	let scale = src_len as f32 / dst_len as f32;
+	// widen the kernel when shrinking so every source pixel contributes
+	let width = scale.max(1.0);
+	let support = filter.support() * width;
+
+	let weights: Vec<Vec<(usize, f32)>> = (0..dst_len).map(|i| {
+		let center = (i as f32 + 0.5) * scale - 0.5;
+		let lo = (center - support).floor() as isize;
+		let hi = (center + support).ceil() as isize;
+
+		let mut w: Vec<(usize, f32)> = (lo..=hi)
+			.map(|j| (j.clamp(0, src_len as isize - 1) as usize, filter.weight((j as f32 - center) / width)))
+			.filter(|(_, w)| *w != 0.0)
+			.collect();
+
+		let total: f32 = w.iter().map(|(_, w)| *w).sum();
+		if total != 0.0 {
+			for (_, x) in w.iter_mut() {
+				*x /= total;
+			}
+		}
+		w
+	}).collect();
+
+	// rows are resized in place along each line; columns are lines of the other axis
+	let (line_step, out_stride, out_line_step) = if stride == 1 {
+		(src_len, 1, dst_len)
+	} else {
+		(1, stride, 1)
+	};
+
+	let mut out = vec![[0.0; 4]; dst_len * lines];
+	for line in 0..lines {
+		for (i, w) in weights.iter().enumerate() {
+			let mut p = [0.0; 4];
+			for (j, x) in w.iter() {
+				let s = &src[line * line_step + j * stride];
+				for k in 0..4 {
+					p[k] += s[k] * x;
+				}
+			}
+			out[line * out_line_step + i * out_stride] = p;
+		}
+	}
+
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn numbered(w: usize, h: usize) -> Texture {
+		let mut tex = Texture::with_format(w, h, PixelFormat::Indexed);
+		tex.palette = (0..w * h).map(|i| Color {
+			red: i as f32 / (w * h) as f32,
+			green: 0.0,
+			blue: 0.0,
+			alpha: 1.0,
+		}).collect();
+		if let Some(i) = tex.indices_mut() {
+			for (n, v) in i.iter_mut().enumerate() {
+				*v = n;
+			}
+		}
+		tex
+	}
+
+	#[test]
+	fn test_rearrange() {
+		let tex = numbered(3, 2);
+
+		assert_eq!(tex.crop(1, 1, 5, 5).indices(), Some(&[4, 5][..]));
+		assert_eq!(tex.flip_horizontal().indices(), Some(&[2, 1, 0, 5, 4, 3][..]));
+		assert_eq!(tex.flip_vertical().indices(), Some(&[3, 4, 5, 0, 1, 2][..]));
+
+		let cw = tex.rotate(Rotation::Clockwise90);
+		assert_eq!((cw.width, cw.height), (2, 3));
+		assert_eq!(cw.indices(), Some(&[3, 0, 4, 1, 5, 2][..]));
+		assert_eq!(cw.rotate(Rotation::CounterClockwise90), tex);
+		assert_eq!(tex.rotate(Rotation::Half).rotate(Rotation::Half), tex);
+	}
+
+	#[test]
+	fn test_blit() {
+		let mut dst = numbered(3, 2);
+		let src = numbered(2, 2).convert(PixelFormat::Rgba8);
+		dst.blit(&src, 2, -1);
+
+		assert_eq!(dst.get_pixel(2, 0), src.get_pixel(0, 1));
+		assert_eq!(dst.get_pixel(1, 0), numbered(3, 2).get_pixel(1, 0));
+	}
+
+	#[test]
+	fn test_resize() {
+		let mut tex = Texture::with_format(8, 8, PixelFormat::Rgba32F);
+		for y in 0..8 {
+			for x in 0..8 {
+				let v = if (x + y) % 2 == 0 { 1.0 } else { 0.0 };
+				tex.set_pixel(x, y, &Color {
+					red: v,
+					green: v,
+					blue: v,
+					alpha: 1.0,
+				});
+			}
+		}
+
+		// a checkerboard averages to grey
+		for filter in [Filter::Bilinear, Filter::Lanczos3].iter() {
+			let small = tex.resize(2, 2, *filter);
+			assert_eq!(small.format(), PixelFormat::Rgba32F);
+			assert!(small.pixels().iter().all(|c| (c.red - 0.5).abs() < 0.05), "{:?}", filter);
+		}
+
+		let chain = tex.mipmaps(Filter::Bilinear);
+		assert_eq!(chain.iter().map(|t| t.width).collect::<Vec<usize>>(), vec![4, 2, 1]);
+		assert_eq!(tex.resize(16, 16, Filter::Nearest).get_pixel(3, 3), tex.get_pixel(1, 1));
+
+		let empty = Texture::with_format(0, 4, PixelFormat::Rgba8);
+		let unfilled = Texture::new(4, 4);
+		for filter in [Filter::Nearest, Filter::Bilinear, Filter::Lanczos3].iter() {
+			for tex in [&empty, &unfilled] {
+				let out = tex.resize(2, 2, *filter);
+				assert_eq!((out.width, out.height, out.pixels().len()), (2, 2, 4));
+			}
+		}
+	}
+}
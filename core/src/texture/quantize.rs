@@ -0,0 +1,486 @@
+use super::{
+	Color,
+	PixelData,
+	Texture
+};
+
+/// Palette generation algorithm
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PaletteMethod {
+	/// Heckbert's median cut, repeatedly halving the color box with the widest channel
+	MedianCut,
+
+	/// Gervautz-Purgathofer octree, merging the least used leaves
+	Octree,
+
+	/// Lloyd's k-means, refining a median cut palette for the specified number of passes
+	KMeans(usize),
+}
+
+/// How colors missing from the palette are approximated
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Dither {
+	/// Nearest palette color
+	#[default]
+	None,
+
+	/// Error diffusion to neighbouring pixels
+	FloydSteinberg,
+
+	/// 4x4 Bayer matrix threshold
+	Ordered,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QuantizeOptions {
+	/// Palette size, including the transparent index if any, for example 16 for 4bpp
+	pub colors: usize,
+	pub method: PaletteMethod,
+	pub dither: Dither,
+
+	/// If set, index 0 is reserved for transparent black and pixels with alpha below this cutoff are
+	/// mapped to it, like the PlayStation's 0x0000 color with the STP bit clear. The other palette
+	/// entries are then opaque.
+	pub transparent: Option<f32>,
+}
+
+impl QuantizeOptions {
+	pub fn new(colors: usize) -> QuantizeOptions {
+		QuantizeOptions {
+			colors,
+			method: PaletteMethod::MedianCut,
+			dither: Dither::None,
+			transparent: None,
+		}
+	}
+}
+
+type Rgba = [f32; 4];
+
+fn rgba(c: &Color) -> Rgba {
+	[c.red, c.green, c.blue, c.alpha]
+}
+
+fn color(v: Rgba) -> Color {
+	Color {
+		red: v[0],
+		green: v[1],
+		blue: v[2],
+		alpha: v[3],
+	}
+}
+
+fn dist_sq(a: &Rgba, b: &Rgba) -> f32 {
+	a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+fn mean(points: &[Rgba]) -> Rgba {
+	let mut sum = [0f64; 4];
+	for p in points.iter() {
+		for k in 0..4 {
+			sum[k] += p[k] as f64;
+		}
+	}
+
+	let n = points.len().max(1) as f64;
+	[(sum[0] / n) as f32, (sum[1] / n) as f32, (sum[2] / n) as f32, (sum[3] / n) as f32]
+}
+
+/// Returns the index of the palette color nearest to `c`
+pub fn nearest_color(palette: &[Color], c: &Color) -> usize {
+	let c = rgba(c);
+
+	palette.iter().enumerate()
+		.map(|(i, p)| (i, dist_sq(&rgba(p), &c)))
+		.min_by(|a, b| a.1.total_cmp(&b.1))
+		.map(|(i, _)| i)
+		.unwrap_or(0)
+}
+
+/// Builds a palette of at most `colors` entries representing the specified pixels
+pub fn generate_palette(pixels: &[Color], colors: usize, method: PaletteMethod) -> Vec<Color> {
+	let points: Vec<Rgba> = pixels.iter().map(rgba).collect();
+	if colors == 0 || points.is_empty() {
+		return vec![];
+	}
+
+	let palette = match method {
+		PaletteMethod::MedianCut => median_cut(points, colors),
+		PaletteMethod::Octree => octree(&points, colors),
+		PaletteMethod::KMeans(passes) => {
+			let seeds = median_cut(points.clone(), colors);
+			k_means(&points, seeds, passes)
+		},
+	};
+
+	palette.into_iter().map(color).collect()
+}
+
+fn median_cut(points: Vec<Rgba>, colors: usize) -> Vec<Rgba> {
+	// the channel with the widest range in a box, and that range
+	fn widest(points: &[Rgba]) -> (usize, f32) {
+		(0..4).map(|k| {
+			let (lo, hi) = points.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), p| {
+				(lo.min(p[k]), hi.max(p[k]))
+			});
+			(k, hi - lo)
+		}).max_by(|a, b| a.1.total_cmp(&b.1)).unwrap_or((0, 0.0))
+	}
+
+	let mut boxes = vec![points];
+
+	while boxes.len() < colors {
+		let next = boxes.iter().enumerate()
+			.map(|(i, b)| (i, widest(b).1))
+			.filter(|(_, range)| *range > 0.0)
+			.max_by(|a, b| a.1.total_cmp(&b.1));
+
+		let i = match next {
+			Some((i, _)) => i,
+			None => break,
+		};
+
+		let mut b = boxes.swap_remove(i);
+		let (k, _) = widest(&b);
+		b.sort_by(|x, y| x[k].total_cmp(&y[k]));
+		let upper = b.split_off(b.len() / 2);
+		boxes.push(b);
+		boxes.push(upper);
+	}
+
+	boxes.iter().map(|b| mean(b)).collect()
+}
+
+#[derive(Default)]
+struct OctNode {
+	children: [Option<usize>; 8],
+	sum: [f64; 4],
+	count: usize,
+	leaf: bool,
+}
+
+/// Levels of RGB subdivision, one bit per channel each
+const OCTREE_DEPTH: usize = 8;
+
+fn octree(points: &[Rgba], colors: usize) -> Vec<Rgba> {
+	let mut nodes = vec![OctNode::default()];
+	// nodes with children, per depth
+	let mut branches: Vec<Vec<usize>> = vec![vec![]; OCTREE_DEPTH];
+	let mut leaves = 0;
+
+	for p in points.iter() {
+		let bytes = [
+			(p[0].clamp(0.0, 1.0) * 255.0).round() as u8,
+			(p[1].clamp(0.0, 1.0) * 255.0).round() as u8,
+			(p[2].clamp(0.0, 1.0) * 255.0).round() as u8,
+		];
+
+		let mut n = 0;
+		for (depth, level) in branches.iter_mut().enumerate() {
+			if nodes[n].leaf {
+				break;
+			}
+
+			let shift = 7 - depth;
+			let child = ((bytes[0] >> shift) & 1) << 2 | ((bytes[1] >> shift) & 1) << 1 | ((bytes[2] >> shift) & 1);
+			n = match nodes[n].children[child as usize] {
+				Some(c) => c,
+				None => {
+					if nodes[n].children.iter().all(|c| c.is_none()) {
+						level.push(n);
+					}
+
+					let c = nodes.len();
+					nodes.push(OctNode {
+						leaf: depth + 1 == OCTREE_DEPTH,
+						..OctNode::default()
+					});
+					if depth + 1 == OCTREE_DEPTH {
+						leaves += 1;
+					}
+					nodes[n].children[child as usize] = Some(c);
+					c
+				},
+			};
+		}
+
+		add(&mut nodes[n].sum, &[p[0] as f64, p[1] as f64, p[2] as f64, p[3] as f64]);
+		nodes[n].count += 1;
+	}
+
+	// fold the least used deepest branches into leaves until the palette fits
+	while leaves > colors {
+		let depth = match branches.iter().rposition(|b| !b.is_empty()) {
+			Some(d) => d,
+			None => break,
+		};
+
+		let count = |n: usize, nodes: &[OctNode]| subtree_count(nodes, n);
+		let (pos, _) = branches[depth].iter().enumerate()
+			.min_by_key(|(_, n)| count(**n, &nodes))
+			.unwrap();
+		let n = branches[depth].swap_remove(pos);
+
+		let children: Vec<usize> = nodes[n].children.iter().flatten().copied().collect();
+		for c in children.iter() {
+			let child = std::mem::take(&mut nodes[*c]);
+			add(&mut nodes[n].sum, &child.sum);
+			nodes[n].count += child.count;
+		}
+
+		nodes[n].children = [None; 8];
+		nodes[n].leaf = true;
+		leaves = leaves + 1 - children.len();
+	}
+
+	nodes.iter()
+		.filter(|n| n.leaf && n.count > 0)
+		.map(|n| {
+			let c = n.count as f64;
+			[(n.sum[0] / c) as f32, (n.sum[1] / c) as f32, (n.sum[2] / c) as f32, (n.sum[3] / c) as f32]
+		})
+		.collect()
+}
+
+fn add(sum: &mut [f64; 4], v: &[f64; 4]) {
+	for (s, v) in sum.iter_mut().zip(v.iter()) {
+		*s += v;
+	}
+}
+
+fn subtree_count(nodes: &[OctNode], n: usize) -> usize {
+	nodes[n].count + nodes[n].children.iter().flatten().map(|c| subtree_count(nodes, *c)).sum::<usize>()
+}
+
+fn k_means(points: &[Rgba], mut centroids: Vec<Rgba>, passes: usize) -> Vec<Rgba> {
+	for _ in 0..passes {
+		let mut sums = vec![[0f64; 4]; centroids.len()];
+		let mut counts = vec![0usize; centroids.len()];
+
+		for p in points.iter() {
+			let i = centroids.iter().enumerate()
+				.map(|(i, c)| (i, dist_sq(c, p)))
+				.min_by(|a, b| a.1.total_cmp(&b.1))
+				.map(|(i, _)| i)
+				.unwrap_or(0);
+
+			for k in 0..4 {
+				sums[i][k] += p[k] as f64;
+			}
+			counts[i] += 1;
+		}
+
+		let mut moved = false;
+		for (i, c) in centroids.iter_mut().enumerate() {
+			// empty clusters keep their centroid
+			if counts[i] == 0 {
+				continue;
+			}
+
+			let n = counts[i] as f64;
+			let new = [(sums[i][0] / n) as f32, (sums[i][1] / n) as f32, (sums[i][2] / n) as f32,
+				(sums[i][3] / n) as f32];
+			moved |= new != *c;
+			*c = new;
+		}
+
+		if !moved {
+			break;
+		}
+	}
+
+	centroids
+}
+
+/// 4x4 Bayer threshold matrix
+const BAYER4: [[u8; 4]; 4] = [
+	[0, 8, 2, 10],
+	[12, 4, 14, 6],
+	[3, 11, 1, 9],
+	[15, 7, 13, 5],
+];
+
+/// Maps a texture's pixels onto a palette, returning a palettized texture using it.
+/// If `transparent` is set, palette index 0 is assumed transparent and pixels with alpha below the cutoff
+/// map to it, while the rest only consider the other entries.
+pub fn remap(tex: &Texture, palette: &[Color], dither: Dither, transparent: Option<f32>) -> Texture {
+	let (w, h) = (tex.width, tex.height);
+	let first = if transparent.is_some() { 1 } else { 0 };
+	let choices = &palette[first.min(palette.len())..];
+
+	let mut pixels: Vec<Rgba> = tex.pixels().iter().map(rgba).collect();
+	let mut indices = vec![0; pixels.len()];
+
+	// ordered dither spread, roughly the spacing between palette colors if spread evenly over RGB
+	let spread = (1.0 / ((choices.len().max(2) as f32).cbrt() - 1.0)).min(1.0);
+
+	for y in 0..h {
+		for x in 0..w {
+			let i = y * w + x;
+			let mut p = pixels[i];
+
+			if let Some(cutoff) = transparent {
+				if p[3] < cutoff {
+					indices[i] = 0;
+					continue;
+				}
+				p[3] = 1.0;
+			}
+
+			// only the transparent entry, if any, is left to use
+			if choices.is_empty() {
+				indices[i] = 0;
+				continue;
+			}
+
+			if dither == Dither::Ordered {
+				let t = (BAYER4[y % 4][x % 4] as f32 + 0.5) / 16.0 - 0.5;
+				for v in p.iter_mut().take(3) {
+					*v += t * spread;
+				}
+			}
+
+			let chosen = nearest_color(choices, &color(p));
+			indices[i] = chosen + first;
+
+			if dither == Dither::FloydSteinberg {
+				if let Some(c) = choices.get(chosen) {
+					let q = rgba(c);
+					let err = [p[0] - q[0], p[1] - q[1], p[2] - q[2], p[3] - q[3]];
+					let mut spread_to = |dx: isize, dy: usize, f: f32| {
+						let (nx, ny) = (x as isize + dx, y + dy);
+						if nx >= 0 && (nx as usize) < w && ny < h {
+							let n = &mut pixels[ny * w + nx as usize];
+							for k in 0..4 {
+								n[k] += err[k] * f;
+							}
+						}
+					};
+
+					spread_to(1, 0, 7.0 / 16.0);
+					spread_to(-1, 1, 3.0 / 16.0);
+					spread_to(0, 1, 5.0 / 16.0);
+					spread_to(1, 1, 1.0 / 16.0);
+				}
+			}
+		}
+	}
+
+	Texture {
+		palette: palette.to_vec(),
+		data: PixelData::Indexed(indices),
+		width: w,
+		height: h,
+	}
+}
+
+impl Texture {
+	/// Reduces the texture to a palettized one with at most `options.colors` palette entries
+	pub fn quantize(&self, options: &QuantizeOptions) -> Texture {
+		let pixels = self.pixels();
+
+		let palette = match options.transparent {
+			Some(cutoff) => {
+				let opaque: Vec<Color> = pixels.iter()
+					.filter(|c| c.alpha >= cutoff)
+					.map(|c| Color {
+						alpha: 1.0,
+						..*c
+					})
+					.collect();
+
+				let mut palette = vec![Color {
+					red: 0.0,
+					green: 0.0,
+					blue: 0.0,
+					alpha: 0.0,
+				}];
+				palette.extend(generate_palette(&opaque, options.colors.saturating_sub(1), options.method));
+				palette
+			},
+			None => generate_palette(&pixels, options.colors, options.method),
+		};
+
+		remap(self, &palette, options.dither, options.transparent)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::texture::PixelFormat;
+
+	/// A horizontal gradient from black to white, with a transparent first column
+	fn gradient() -> Texture {
+		let mut tex = Texture::with_format(64, 4, PixelFormat::Rgba8);
+		for y in 0..4 {
+			for x in 0..64 {
+				let v = x as f32 / 63.0;
+				tex.set_pixel(x, y, &Color {
+					red: v,
+					green: v * 0.5,
+					blue: 1.0 - v,
+					alpha: if x == 0 { 0.0 } else { 1.0 },
+				});
+			}
+		}
+		tex
+	}
+
+	#[test]
+	fn test_methods() {
+		let tex = gradient();
+
+		for method in [PaletteMethod::MedianCut, PaletteMethod::Octree, PaletteMethod::KMeans(8)].iter() {
+			let mut options = QuantizeOptions::new(16);
+			options.method = *method;
+			options.transparent = Some(0.5);
+
+			let out = tex.quantize(&options);
+			assert!(out.palette.len() <= 16 && out.palette.len() > 8, "{:?}: {}", method, out.palette.len());
+			assert_eq!(out.palette[0].alpha, 0.0);
+
+			let indices = out.indices().unwrap();
+			assert_eq!(indices[0], 0);
+			assert!(indices[1..64].iter().all(|i| *i != 0));
+
+			// every opaque pixel should be close to its original
+			for x in 1..64 {
+				let (a, b) = (tex.get_pixel(x, 0), out.get_pixel(x, 0));
+				assert!((a.red - b.red).abs() < 0.1 && (a.blue - b.blue).abs() < 0.1, "{:?} at {}", method, x);
+			}
+		}
+	}
+
+	#[test]
+	fn test_dither_preserves_average() {
+		let mut tex = Texture::with_format(32, 32, PixelFormat::Rgba8);
+		let grey = Color {
+			red: 0.25,
+			green: 0.25,
+			blue: 0.25,
+			alpha: 1.0,
+		};
+		for y in 0..32 {
+			for x in 0..32 {
+				tex.set_pixel(x, y, &grey);
+			}
+		}
+
+		let black = Color {
+			red: 0.0,
+			..grey
+		};
+		let palette = [Color { green: 0.0, blue: 0.0, ..black }, Color { red: 1.0, green: 1.0, blue: 1.0, ..grey }];
+
+		for dither in [Dither::FloydSteinberg, Dither::Ordered].iter() {
+			let out = remap(&tex, &palette, *dither, None);
+			let avg = out.pixels().iter().map(|c| c.red).sum::<f32>() / 1024.0;
+			assert!((avg - 0.25).abs() < 0.05, "{:?}: {}", dither, avg);
+		}
+
+		// a palette holding only the transparent entry maps everything to it
+		let out = remap(&tex, &palette[..1], Dither::FloydSteinberg, Some(0.5));
+		assert!(matches!(out.data, PixelData::Indexed(ref i) if i.iter().all(|i| *i == 0)));
+	}
+}
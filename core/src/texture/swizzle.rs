@@ -0,0 +1,447 @@
+//! Conversion between linear pixel data and console memory layouts. These work on raw bytes, before
+//! decoding into a [`Texture`](super::Texture). Unswizzling reads missing source bytes as zero, and
+//! layouts built from tiles expect the swizzled data to be padded out to whole tiles.
+
+/// Copies `count` elements of `size` bytes, where `map` gives the source element of each output element
+fn remap<F>(src: &[u8], count: usize, size: usize, out_len: usize, map: F) -> Vec<u8>
+where
+	F: Fn(usize) -> usize,
+{
+	let mut out = vec![0; out_len];
+	for i in 0..count {
+		let (s, d) = (map(i) * size, i * size);
+		for k in 0..size {
+			out[d + k] = src.get(s + k).copied().unwrap_or(0);
+		}
+	}
+	out
+}
+
+/// Copies `count` elements of `size` bytes, where `map` gives the destination element of each source element
+fn scatter<F>(src: &[u8], count: usize, size: usize, out_len: usize, map: F) -> Vec<u8>
+where
+	F: Fn(usize) -> usize,
+{
+	let mut out = vec![0; out_len];
+	for i in 0..count {
+		let (s, d) = (i * size, map(i) * size);
+		for k in 0..size {
+			if let Some(b) = out.get_mut(d + k) {
+				*b = src.get(s + k).copied().unwrap_or(0);
+			}
+		}
+	}
+	out
+}
+
+fn align(v: usize, to: usize) -> usize {
+	v.div_ceil(to) * to
+}
+
+/// Layout of bytes in row-major tiles of `tile_w` bytes by `tile_h` rows, themselves in row-major order
+struct Tiles {
+	row_bytes: usize,
+	height: usize,
+	tile_w: usize,
+	tile_h: usize,
+}
+
+impl Tiles {
+	fn padded_len(&self) -> usize {
+		align(self.row_bytes, self.tile_w) * align(self.height, self.tile_h)
+	}
+
+	/// Returns the tiled offset of the byte at the specified linear offset
+	fn offset(&self, linear: usize) -> usize {
+		let (x, y) = (linear % self.row_bytes, linear / self.row_bytes);
+		let tiles_x = align(self.row_bytes, self.tile_w) / self.tile_w;
+		let tile = (y / self.tile_h) * tiles_x + x / self.tile_w;
+
+		(tile * self.tile_h + y % self.tile_h) * self.tile_w + x % self.tile_w
+	}
+
+	fn untile(&self, data: &[u8]) -> Vec<u8> {
+		let len = self.row_bytes * self.height;
+		remap(data, len, 1, len, |i| self.offset(i))
+	}
+
+	fn tile(&self, data: &[u8]) -> Vec<u8> {
+		scatter(data, self.row_bytes * self.height, 1, self.padded_len(), |i| self.offset(i))
+	}
+}
+
+fn psp_tiles(width: usize, height: usize, bits_per_pixel: usize) -> Tiles {
+	Tiles {
+		row_bytes: width * bits_per_pixel / 8,
+		height,
+		tile_w: 16,
+		tile_h: 8,
+	}
+}
+
+/// Reorders PSP GE swizzled data, stored as 16 byte by 8 row blocks, into linear rows
+pub fn psp_unswizzle(data: &[u8], width: usize, height: usize, bits_per_pixel: usize) -> Vec<u8> {
+	psp_tiles(width, height, bits_per_pixel).untile(data)
+}
+
+/// Reorders linear rows into PSP GE swizzled blocks
+pub fn psp_swizzle(data: &[u8], width: usize, height: usize, bits_per_pixel: usize) -> Vec<u8> {
+	psp_tiles(width, height, bits_per_pixel).tile(data)
+}
+
+/// Returns the Z-order position of a pixel, interleaving X and Y bits starting with X. When one dimension
+/// is larger, its excess high bits follow the interleaved ones. Dimensions are rounded up to powers of two.
+pub fn morton_index(x: usize, y: usize, width: usize, height: usize) -> usize {
+	let (mut w, mut h) = (width.next_power_of_two(), height.next_power_of_two());
+	let (mut x, mut y) = (x, y);
+	let (mut index, mut bit) = (0, 0);
+
+	while w > 1 || h > 1 {
+		if w > 1 {
+			index |= (x & 1) << bit;
+			bit += 1;
+			x >>= 1;
+			w >>= 1;
+		}
+		if h > 1 {
+			index |= (y & 1) << bit;
+			bit += 1;
+			y >>= 1;
+			h >>= 1;
+		}
+	}
+
+	index
+}
+
+/// Reorders Morton (Z-order) data, as used by PS3 and PS Vita textures, into linear rows
+pub fn morton_unswizzle(data: &[u8], width: usize, height: usize, bytes_per_pixel: usize) -> Vec<u8> {
+	let count = width * height;
+	remap(data, count, bytes_per_pixel, count * bytes_per_pixel, |i| morton_index(i % width, i / width, width, height))
+}
+
+/// Reorders linear rows into Morton (Z-order) data
+pub fn morton_swizzle(data: &[u8], width: usize, height: usize, bytes_per_pixel: usize) -> Vec<u8> {
+	let padded = width.next_power_of_two() * height.next_power_of_two();
+	scatter(data, width * height, bytes_per_pixel, padded * bytes_per_pixel, |i| {
+		morton_index(i % width, i / width, width, height)
+	})
+}
+
+/// Returns the element offset of block (`x`, `y`) in an Xbox 360 tiled surface `width` blocks wide,
+/// with `texel_pitch` bytes per block, following `XGAddress2DTiledOffset`
+pub fn xbox360_tiled_offset(x: usize, y: usize, width: usize, texel_pitch: usize) -> usize {
+	let aligned_width = align(width, 32);
+	let log_bpp = (texel_pitch >> 2) + ((texel_pitch >> 1) >> (texel_pitch >> 2));
+
+	let macro_offset = ((x >> 5) + (y >> 5) * (aligned_width >> 5)) << (log_bpp + 7);
+	let micro_offset = ((x & 7) + ((y & 6) << 2)) << log_bpp;
+	let offset = macro_offset + ((micro_offset & !15) << 1) + (micro_offset & 15) + ((y & 8) << (3 + log_bpp)) +
+		((y & 1) << 4);
+
+	(((offset & !511) << 3) + ((offset & 448) << 2) + (offset & 63) + ((y & 16) << 7) +
+		(((((y & 8) >> 2) + (x >> 3)) & 3) << 6)) >> log_bpp
+}
+
+/// Reorders Xbox 360 tiled data into linear rows. Dimensions are in pixels and `block` is the size of a
+/// compression block, 4 for DXT formats and 1 otherwise. Endianness isn't changed; see [`swap_bytes16`].
+pub fn xbox360_untile(data: &[u8], width: usize, height: usize, block: usize, bytes_per_block: usize) -> Vec<u8> {
+	let (w, h) = (width.div_ceil(block), height.div_ceil(block));
+	remap(data, w * h, bytes_per_block, w * h * bytes_per_block, |i| {
+		xbox360_tiled_offset(i % w, i / w, w, bytes_per_block)
+	})
+}
+
+/// Reorders linear rows into Xbox 360 tiled data, padded to whole 4 KiB macro tiles
+pub fn xbox360_tile(data: &[u8], width: usize, height: usize, block: usize, bytes_per_block: usize) -> Vec<u8> {
+	let (w, h) = (width.div_ceil(block), height.div_ceil(block));
+	let end = (0..h).flat_map(|y| (0..w).map(move |x| (x, y)))
+		.map(|(x, y)| xbox360_tiled_offset(x, y, w, bytes_per_block) + 1)
+		.max()
+		.unwrap_or(0);
+	let padded = align(end * bytes_per_block, 4096);
+	scatter(data, w * h, bytes_per_block, padded, |i| {
+		xbox360_tiled_offset(i % w, i / w, w, bytes_per_block)
+	})
+}
+
+/// Swaps the bytes of every 16 bit word, as Xbox 360 texture data is big endian
+pub fn swap_bytes16(data: &mut [u8]) {
+	for w in data.chunks_exact_mut(2) {
+		w.swap(0, 1);
+	}
+}
+
+fn gc_tiles(width: usize, height: usize, bits_per_pixel: usize) -> Tiles {
+	// every tile is 32 bytes; 8x8 pixels at 4 bits, 8x4 at 8 bits and 4x4 at 16 bits
+	let (tile_w, tile_h) = match bits_per_pixel {
+		4 => (4, 8),
+		_ => (8, 4),
+	};
+
+	Tiles {
+		row_bytes: width * bits_per_pixel / 8,
+		height,
+		tile_w,
+		tile_h,
+	}
+}
+
+/// Reorders GameCube/Wii tiled data of 4 (I4, C4), 8 (I8, IA4, C8) or 16 (IA8, RGB565, RGB5A3, C14X2)
+/// bits per pixel into linear rows. RGBA8 and CMPR have their own layouts.
+pub fn gc_untile(data: &[u8], width: usize, height: usize, bits_per_pixel: usize) -> Vec<u8> {
+	gc_tiles(width, height, bits_per_pixel).untile(data)
+}
+
+/// Reorders linear rows into GameCube/Wii tiles
+pub fn gc_tile(data: &[u8], width: usize, height: usize, bits_per_pixel: usize) -> Vec<u8> {
+	gc_tiles(width, height, bits_per_pixel).tile(data)
+}
+
+/// Returns the offsets of a GameCube/Wii RGBA8 pixel's alpha-red and green-blue byte pairs
+fn gc_rgba8_offset(x: usize, y: usize, width: usize) -> (usize, usize) {
+	let tile = (y / 4) * width.div_ceil(4) + x / 4;
+	let base = tile * 64 + ((y % 4) * 4 + x % 4) * 2;
+	(base, base + 32)
+}
+
+/// Converts GameCube/Wii RGBA8 tiles, which split each 4x4 tile into alpha-red and green-blue halves, to
+/// linear `[r, g, b, a]` pixels
+pub fn gc_untile_rgba8(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+	let byte = |i: usize| data.get(i).copied().unwrap_or(0);
+	let mut out = vec![0; width * height * 4];
+
+	for y in 0..height {
+		for x in 0..width {
+			let (ar, gb) = gc_rgba8_offset(x, y, width);
+			let i = (y * width + x) * 4;
+			out[i..i + 4].copy_from_slice(&[byte(ar + 1), byte(gb), byte(gb + 1), byte(ar)]);
+		}
+	}
+
+	out
+}
+
+/// Converts linear `[r, g, b, a]` pixels to GameCube/Wii RGBA8 tiles
+pub fn gc_tile_rgba8(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+	let mut out = vec![0; width.div_ceil(4) * height.div_ceil(4) * 64];
+
+	for y in 0..height {
+		for x in 0..width {
+			let (ar, gb) = gc_rgba8_offset(x, y, width);
+			let p = &data[(y * width + x) * 4..][..4];
+			out[ar] = p[3];
+			out[ar + 1] = p[0];
+			out[gb] = p[1];
+			out[gb + 1] = p[2];
+		}
+	}
+
+	out
+}
+
+/// Converts GameCube/Wii CMPR data to standard BC1 blocks in row-major order. CMPR stores 2x2 groups of
+/// big endian DXT1 blocks per 8x8 tile, with each row's indices in reverse order.
+pub fn gc_cmpr_to_bc1(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+	let (bw, bh) = (width.div_ceil(4), height.div_ceil(4));
+	let tiles_x = width.div_ceil(8);
+	let mut out = vec![0; bw * bh * 8];
+
+	for by in 0..bh {
+		for bx in 0..bw {
+			let tile = (by / 2) * tiles_x + bx / 2;
+			let src = tile * 32 + ((by % 2) * 2 + bx % 2) * 8;
+			let block = match data.get(src..src + 8) {
+				Some(b) => b,
+				None => continue,
+			};
+
+			let dst = &mut out[(by * bw + bx) * 8..][..8];
+			dst[..4].copy_from_slice(&[block[1], block[0], block[3], block[2]]);
+			for (d, s) in dst[4..].iter_mut().zip(block[4..].iter()) {
+				*d = (s & 3) << 6 | (s & 12) << 2 | (s & 48) >> 2 | (s & 192) >> 6;
+			}
+		}
+	}
+
+	out
+}
+
+/// Returns the byte offset of a pixel in PS2 GS PSMT8 swizzled data. `width` must be a multiple of 16
+/// and `height` a multiple of 4.
+pub fn ps2_psmt8_offset(x: usize, y: usize, width: usize) -> usize {
+	let block = (y & !0xF) * width + (x & !0xF) * 2;
+	let swap = (((y + 2) >> 2) & 1) * 4;
+	let pos_y = (((y & !3) >> 1) + (y & 1)) & 7;
+	let column = pos_y * width * 2 + ((x + swap) & 7) * 4;
+	let byte = ((y >> 1) & 1) + ((x >> 2) & 2);
+
+	block + column + byte
+}
+
+/// Reorders PS2 GS PSMT8 swizzled indices, as uploaded in PSMCT32 layout, into linear rows
+pub fn ps2_unswizzle8(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+	let count = width * height;
+	remap(data, count, 1, count, |i| ps2_psmt8_offset(i % width, i / width, width))
+}
+
+/// Reorders linear 8 bit indices into PS2 GS PSMT8 swizzled data
+pub fn ps2_swizzle8(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+	let count = width * height;
+	scatter(data, count, 1, count, |i| ps2_psmt8_offset(i % width, i / width, width))
+}
+
+/// Returns the byte offset and nibble shift of a pixel in PS2 GS PSMT4 swizzled data. Pages of 128x128
+/// pixels are laid out as PSMCT32 pages. `width` must be a multiple of 32 and `height` of 16.
+pub fn ps2_psmt4_offset(x: usize, y: usize, width: usize, height: usize) -> (usize, u32) {
+	let (page_x, page_y) = (x & !0x7F, y & !0x7F);
+	let pages_horz = width.div_ceil(128);
+	let pages_vert = height.div_ceil(128);
+	let page = (page_y / 128) * pages_horz + page_x / 128;
+	let page32_y = (page / pages_vert) * 32;
+	let page32_x = (page % pages_vert) * 64;
+	let page_offset = page32_y * height * 2 + page32_x * 4;
+
+	let (loc_x, loc_y) = (x & 0x7F, y & 0x7F);
+	let block = ((loc_x & !0x1F) >> 1) * height + (loc_y & !0xF) * 2;
+	let swap = (((y + 2) >> 2) & 1) * 4;
+	let pos_y = (((y & !3) >> 1) + (y & 1)) & 7;
+	let column = pos_y * height * 2 + ((x + swap) & 7) * 4;
+	let byte = (x >> 3) & 3;
+
+	(page_offset + block + column + byte, (((y >> 1) & 1) * 4) as u32)
+}
+
+/// Reorders PS2 GS PSMT4 swizzled indices into linear rows. Both hold two pixels per byte, the first in
+/// the low nibble.
+pub fn ps2_unswizzle4(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+	let mut out = vec![0; (width * height).div_ceil(2)];
+
+	for y in 0..height {
+		for x in 0..width {
+			let (offset, shift) = ps2_psmt4_offset(x, y, width, height);
+			let v = (data.get(offset).copied().unwrap_or(0) >> shift) & 0xF;
+			let i = y * width + x;
+			out[i / 2] |= v << ((i & 1) * 4);
+		}
+	}
+
+	out
+}
+
+/// Reorders linear 4 bit indices into PS2 GS PSMT4 swizzled data
+pub fn ps2_swizzle4(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+	let mut out = vec![0; (width * height).div_ceil(2)];
+
+	for y in 0..height {
+		for x in 0..width {
+			let i = y * width + x;
+			let v = (data.get(i / 2).copied().unwrap_or(0) >> ((i & 1) * 4)) & 0xF;
+			let (offset, shift) = ps2_psmt4_offset(x, y, width, height);
+			if let Some(b) = out.get_mut(offset) {
+				*b |= v << shift;
+			}
+		}
+	}
+
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sequence(len: usize) -> Vec<u8> {
+		(0..len).map(|i| (i * 7 + i / 256) as u8).collect()
+	}
+
+	#[test]
+	fn test_psp() {
+		let data = sequence(64 * 16);
+		let swizzled = psp_swizzle(&data, 32, 16, 16);
+		// the second row's first byte starts the second line of the first block
+		assert_eq!(swizzled[16], data[64]);
+		assert_eq!(psp_unswizzle(&swizzled, 32, 16, 16), data);
+	}
+
+	#[test]
+	fn test_morton() {
+		assert_eq!(morton_index(1, 0, 4, 4), 1);
+		assert_eq!(morton_index(0, 1, 4, 4), 2);
+		assert_eq!(morton_index(3, 3, 4, 4), 15);
+		assert_eq!(morton_index(4, 1, 8, 2), 10);
+
+		let data = sequence(16 * 8 * 2);
+		assert_eq!(morton_unswizzle(&morton_swizzle(&data, 16, 8, 2), 16, 8, 2), data);
+	}
+
+	#[test]
+	fn test_xbox360() {
+		// every offset of a 64x64 surface must be used exactly once
+		for pitch in [1, 2, 4, 8, 16].iter() {
+			let mut seen = vec![false; 4096];
+			for y in 0..64 {
+				for x in 0..64 {
+					let o = xbox360_tiled_offset(x, y, 64, *pitch);
+					assert!(!seen[o], "pitch {} at {}, {}", pitch, x, y);
+					seen[o] = true;
+				}
+			}
+		}
+
+		let data = sequence(64 * 40 * 4);
+		let tiled = xbox360_tile(&data, 64, 40, 1, 4);
+		assert_eq!(tiled.len(), 64 * 64 * 4);
+		assert_eq!(xbox360_tile(&data[..32 * 32], 32, 32, 1, 1).len(), 4096);
+		assert_eq!(xbox360_untile(&tiled, 64, 40, 1, 4), data);
+	}
+
+	#[test]
+	fn test_gc() {
+		for bpp in [4, 8, 16].iter() {
+			let data = sequence(16 * 8 * bpp / 8);
+			assert_eq!(gc_untile(&gc_tile(&data, 16, 8, *bpp), 16, 8, *bpp), data);
+		}
+
+		// I8 tiles are 8x4
+		let data = sequence(16 * 8);
+		assert_eq!(gc_tile(&data, 16, 8, 8)[8], data[16]);
+
+		let data = sequence(8 * 4 * 4);
+		let tiled = gc_tile_rgba8(&data, 8, 4);
+		assert_eq!(&tiled[..2], &[data[3], data[0]]);
+		assert_eq!(gc_untile_rgba8(&tiled, 8, 4), data);
+
+		let cmpr = [0x12, 0x34, 0x56, 0x78, 0b00_01_10_11, 0, 0, 0];
+		let bc1 = gc_cmpr_to_bc1(&cmpr, 4, 4);
+		assert_eq!(bc1, vec![0x34, 0x12, 0x78, 0x56, 0b11_10_01_00, 0, 0, 0]);
+	}
+
+	#[test]
+	fn test_ps2() {
+		let data = sequence(128 * 64);
+		let swizzled = ps2_swizzle8(&data, 128, 64);
+		assert_eq!(ps2_unswizzle8(&swizzled, 128, 64), data);
+
+		let mut seen = vec![false; 128 * 64];
+		for y in 0..64 {
+			for x in 0..128 {
+				let o = ps2_psmt8_offset(x, y, 128);
+				assert!(!seen[o]);
+				seen[o] = true;
+			}
+		}
+
+		let mut seen = vec![[false; 2]; 128 * 128 / 2];
+		for y in 0..128 {
+			for x in 0..128 {
+				let (o, shift) = ps2_psmt4_offset(x, y, 128, 128);
+				assert!(!seen[o][shift as usize / 4]);
+				seen[o][shift as usize / 4] = true;
+			}
+		}
+
+		let data = sequence(128 * 128 / 2);
+		assert_eq!(ps2_unswizzle4(&ps2_swizzle4(&data, 128, 128), 128, 128), data);
+	}
+}
@@ -0,0 +1,154 @@
+/*
+ * Generates the block compressed reference images in this directory. Each format gets 16 blocks of seeded
+ * random data forming a 16x16 image, written to <name>.bin, which Mesa then decodes through OpenGL into
+ * <name>.out: RGBA8 for most formats, RGBA half floats for BC6H and RGBA floats for the 11 bit EAC formats.
+ *
+ * Build and run from this directory with Mesa's software renderer:
+ *     cc reference.c -o /tmp/reference -lEGL -lOpenGL && LIBGL_ALWAYS_SOFTWARE=1 /tmp/reference
+ */
+
+#include <EGL/egl.h>
+#include <EGL/eglext.h>
+#include <GL/gl.h>
+#include <GL/glext.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#define SIZE 16
+#define BLOCKS ((SIZE / 4) * (SIZE / 4))
+
+enum kind { BYTES, HALFS, FLOATS };
+
+struct format {
+	const char *name;
+	GLenum internal;
+	int block_size;
+	enum kind out;
+};
+
+static const struct format formats[] = {
+	{ "bc1", GL_COMPRESSED_RGBA_S3TC_DXT1_EXT, 8, BYTES },
+	{ "bc2", GL_COMPRESSED_RGBA_S3TC_DXT3_EXT, 16, BYTES },
+	{ "bc3", GL_COMPRESSED_RGBA_S3TC_DXT5_EXT, 16, BYTES },
+	{ "bc4", GL_COMPRESSED_RED_RGTC1, 8, BYTES },
+	{ "bc5", GL_COMPRESSED_RG_RGTC2, 16, BYTES },
+	{ "bc6h_uf16", GL_COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT, 16, HALFS },
+	{ "bc6h_sf16", GL_COMPRESSED_RGB_BPTC_SIGNED_FLOAT, 16, HALFS },
+	{ "bc7", GL_COMPRESSED_RGBA_BPTC_UNORM, 16, BYTES },
+	/* ETC2 decodes valid ETC1 blocks identically */
+	{ "etc1", GL_COMPRESSED_RGB8_ETC2, 8, BYTES },
+	{ "etc2_rgb", GL_COMPRESSED_RGB8_ETC2, 8, BYTES },
+	{ "etc2_rgba", GL_COMPRESSED_RGBA8_ETC2_EAC, 16, BYTES },
+	{ "etc2_rgb_a1", GL_COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2, 8, BYTES },
+	{ "eac_r11", GL_COMPRESSED_R11_EAC, 8, FLOATS },
+	{ "eac_r11_snorm", GL_COMPRESSED_SIGNED_R11_EAC, 8, FLOATS },
+	{ "eac_rg11", GL_COMPRESSED_RG11_EAC, 16, FLOATS },
+	{ "eac_rg11_snorm", GL_COMPRESSED_SIGNED_RG11_EAC, 16, FLOATS },
+};
+
+/* BC6H mode bits, all 14 modes and two reserved ones */
+static const uint8_t bc6h_modes[BLOCKS] = {
+	0x00, 0x01, 0x02, 0x06, 0x0A, 0x0E, 0x12, 0x16, 0x1A, 0x1E, 0x03, 0x07, 0x0B, 0x0F, 0x13, 0x1F,
+};
+
+static uint64_t state = 0x9E3779B97F4A7C15;
+
+static uint8_t next_byte(void) {
+	state ^= state << 13;
+	state ^= state >> 7;
+	state ^= state << 17;
+	return state >> 56;
+}
+
+/* Does an ETC differential block's second base color leave the 5 bit range, making it invalid ETC1? */
+static int etc1_overflows(const uint8_t *b) {
+	if (!(b[3] & 2))
+		return 0;
+
+	for (int c = 0; c < 3; c++) {
+		int base = b[c] >> 3;
+		int delta = (int8_t)(b[c] << 5) >> 5;
+		if (base + delta < 0 || base + delta > 31)
+			return 1;
+	}
+
+	return 0;
+}
+
+static void generate(const struct format *f, uint8_t *data) {
+	for (int i = 0; i < BLOCKS; i++) {
+		uint8_t *b = data + i * f->block_size;
+
+		do {
+			for (int j = 0; j < f->block_size; j++)
+				b[j] = next_byte();
+		} while (!strcmp(f->name, "etc1") && etc1_overflows(b));
+
+		if (!strcmp(f->name, "bc7")) {
+			/* two blocks of each mode, selected by the lowest set bit */
+			int mode = i % 8;
+			b[0] = (b[0] & ~((2 << mode) - 1)) | 1 << mode;
+		} else if (!strncmp(f->name, "bc6h", 4)) {
+			uint8_t mask = bc6h_modes[i] < 2 ? 3 : 0x1F;
+			b[0] = (b[0] & ~mask) | bc6h_modes[i];
+		}
+	}
+}
+
+static void write_file(const char *name, const char *ext, const void *data, size_t len) {
+	char path[64];
+	snprintf(path, sizeof(path), "%s.%s", name, ext);
+
+	FILE *f = fopen(path, "wb");
+	if (!f || fwrite(data, 1, len, f) != len) {
+		perror(path);
+		exit(1);
+	}
+	fclose(f);
+}
+
+int main(void) {
+	PFNEGLGETPLATFORMDISPLAYEXTPROC get_display = (void *)eglGetProcAddress("eglGetPlatformDisplayEXT");
+	EGLDisplay display = get_display(EGL_PLATFORM_SURFACELESS_MESA, EGL_DEFAULT_DISPLAY, NULL);
+	if (!eglInitialize(display, NULL, NULL) || !eglBindAPI(EGL_OPENGL_API))
+		return 1;
+
+	EGLContext ctx = eglCreateContext(display, EGL_NO_CONFIG_KHR, EGL_NO_CONTEXT, NULL);
+	if (!eglMakeCurrent(display, EGL_NO_SURFACE, EGL_NO_SURFACE, ctx))
+		return 1;
+
+	PFNGLCOMPRESSEDTEXIMAGE2DPROC compressed_tex_image =
+		(void *)eglGetProcAddress("glCompressedTexImage2D");
+	printf("Decoding with %s, %s\n", glGetString(GL_RENDERER), glGetString(GL_VERSION));
+
+	for (size_t i = 0; i < sizeof(formats) / sizeof(formats[0]); i++) {
+		const struct format *f = &formats[i];
+		uint8_t data[BLOCKS * 16];
+		float out[SIZE * SIZE * 4];
+		int len = BLOCKS * f->block_size;
+		generate(f, data);
+
+		GLuint tex;
+		glGenTextures(1, &tex);
+		glBindTexture(GL_TEXTURE_2D, tex);
+		glPixelStorei(GL_PACK_ALIGNMENT, 1);
+		compressed_tex_image(GL_TEXTURE_2D, 0, f->internal, SIZE, SIZE, 0, len, data);
+
+		GLenum type = f->out == BYTES ? GL_UNSIGNED_BYTE : f->out == HALFS ? GL_HALF_FLOAT : GL_FLOAT;
+		size_t out_len = SIZE * SIZE * 4 * (f->out == BYTES ? 1 : f->out == HALFS ? 2 : 4);
+		glGetTexImage(GL_TEXTURE_2D, 0, GL_RGBA, type, out);
+
+		if (glGetError() != GL_NO_ERROR) {
+			fprintf(stderr, "%s: GL error\n", f->name);
+			return 1;
+		}
+
+		write_file(f->name, "bin", data, len);
+		write_file(f->name, "out", out, out_len);
+		glDeleteTextures(1, &tex);
+	}
+
+	return 0;
+}
@@ -2,7 +2,7 @@ use bitflags::bitflags;
 
 use rgk_processors_core::{
 	Bus,
-	Device
+	DeviceBase
 };
 
 pub static OPCODES: Vec<&str> = vec![
@@ -326,13 +326,17 @@ impl CPU {
 	}
 }
 
-impl Device for CPU {
-	fn read(&self, address: usize, length: usize) -> Vec<u8> {
-		self.bus.read(address, length)
+impl DeviceBase for CPU {
+	fn get_u8(&self, address: usize) -> u8 {
+		self.bus.get_u8(address)
 	}
 
-	fn write(&mut self, address: usize, data: &[u8]) {
-		self.bus.write(address, data);
+	fn put_u8(&mut self, address: usize, data: u8) {
+		self.bus.put_u8(address, data);
+	}
+
+	fn peek_u8(&self, address: usize) -> u8 {
+		self.bus.peek_u8(address)
 	}
 }
 
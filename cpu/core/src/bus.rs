@@ -0,0 +1,467 @@
+use std::{
+	cell::{
+		Cell,
+		RefCell
+	},
+	fmt::{
+		Debug,
+		Display,
+		Formatter,
+		self
+	},
+	rc::Rc
+};
+
+use crate::{
+	DeviceBase,
+	hexdump
+};
+
+/// Shared handle to a device mapped on a [`Bus`]
+pub type DeviceRef = Rc<RefCell<dyn DeviceBase>>;
+
+/// Index of a [`Mapping`] within its [`Bus`]
+pub type MappingId = usize;
+
+/// Plain memory, usable as RAM or, mapped read-only, as ROM
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Memory {
+	data: Vec<u8>,
+}
+
+impl Memory {
+	/// Creates zeroed memory of the specified size
+	pub fn new(size: usize) -> Memory {
+		Memory {
+			data: vec![0; size],
+		}
+	}
+
+	/// Wraps the memory into a handle for [`Mapping::new`]
+	pub fn shared(self) -> Rc<RefCell<Memory>> {
+		Rc::new(RefCell::new(self))
+	}
+
+	pub fn as_slice(&self) -> &[u8] {
+		&self.data
+	}
+
+	pub fn as_mut_slice(&mut self) -> &mut [u8] {
+		&mut self.data
+	}
+
+	pub fn len(&self) -> usize {
+		self.data.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.data.is_empty()
+	}
+}
+
+impl From<Vec<u8>> for Memory {
+	fn from(data: Vec<u8>) -> Memory {
+		Memory {
+			data,
+		}
+	}
+}
+
+impl DeviceBase for Memory {
+	/// Addresses past the end wrap around, as a chip ignores the address lines it lacks
+	fn get_u8(&self, address: usize) -> u8 {
+		match self.data.len() {
+			0 => 0,
+			len => self.data[address % len],
+		}
+	}
+
+	fn put_u8(&mut self, address: usize, data: u8) {
+		let len = self.data.len();
+		if len > 0 {
+			self.data[address % len] = data;
+		}
+	}
+
+	fn read_into(&self, address: usize, buf: &mut [u8]) {
+		match self.data.get(address..address + buf.len()) {
+			Some(src) => buf.copy_from_slice(src),
+			None => for (i, b) in buf.iter_mut().enumerate() {
+				*b = self.get_u8(address + i);
+			},
+		}
+	}
+}
+
+/// Direction of a bus access
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AccessKind {
+	Read,
+	Write,
+}
+
+/// A bus access recorded while [`Bus::set_logging`] is enabled
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BusAccess {
+	pub kind: AccessKind,
+	pub address: usize,
+	pub value: u8,
+}
+
+/// Which accesses a [`Mapping`] responds to. Other accesses fall through to the mappings beneath it.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Access {
+	#[default]
+	ReadWrite,
+
+	/// ROM, or ROM overlaying RAM that still receives writes
+	ReadOnly,
+
+	/// Latches and registers that can't be read back
+	WriteOnly,
+}
+
+impl Access {
+	const fn allows(&self, write: bool) -> bool {
+		match self {
+			Access::ReadWrite => true,
+			Access::ReadOnly => !write,
+			Access::WriteOnly => write,
+		}
+	}
+}
+
+/// A range of bus addresses routed to a device
+#[derive(Clone)]
+pub struct Mapping {
+	/// First address of the range
+	pub start: usize,
+
+	/// Address past the end of the range
+	pub end: usize,
+
+	/// Applied to the offset into the range, so a mask of 0x7FF mirrors 2 KiB across the whole range
+	pub mask: usize,
+	pub access: Access,
+
+	/// Size of a bank, or 0 if the mapping isn't banked
+	pub bank_size: usize,
+
+	/// Selected bank, offsetting device addresses by `bank * bank_size`
+	pub bank: usize,
+
+	/// Data bits the device doesn't drive, which read back the open bus value instead
+	pub undriven: u8,
+
+	/// Disabled mappings are skipped, uncovering whatever lies beneath
+	pub enabled: bool,
+	pub device: DeviceRef,
+}
+
+impl Mapping {
+	/// Maps `device` to the addresses from `start` up to but excluding `end`
+	pub fn new(start: usize, end: usize, device: DeviceRef) -> Mapping {
+		Mapping {
+			start,
+			end,
+			mask: usize::MAX,
+			access: Access::ReadWrite,
+			bank_size: 0,
+			bank: 0,
+			undriven: 0,
+			enabled: true,
+			device,
+		}
+	}
+
+	/// Repeats the first `size` bytes of the device across the range. Panics if `size` isn't a power of two, since
+	/// the address is masked rather than wrapped.
+	pub fn mirror(mut self, size: usize) -> Mapping {
+		assert!(size.is_power_of_two(), "mirror size {:#x} isn't a power of two", size);
+		self.mask = size - 1;
+		self
+	}
+
+	pub fn access(mut self, access: Access) -> Mapping {
+		self.access = access;
+		self
+	}
+
+	/// Shows one `bank_size` window of the device at a time, mirrored across the range if it's larger. Panics if
+	/// `bank_size` isn't a power of two.
+	pub fn banked(mut self, bank_size: usize, bank: usize) -> Mapping {
+		self.bank_size = bank_size;
+		self.bank = bank;
+		self.mirror(bank_size)
+	}
+
+	pub fn undriven(mut self, bits: u8) -> Mapping {
+		self.undriven = bits;
+		self
+	}
+
+	pub const fn contains(&self, address: usize) -> bool {
+		address >= self.start && address < self.end
+	}
+
+	/// Translates a bus address within the range into a device address
+	pub const fn device_address(&self, address: usize) -> usize {
+		self.bank * self.bank_size + ((address - self.start) & self.mask)
+	}
+}
+
+impl Debug for Mapping {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Mapping")
+			.field("start", &self.start)
+			.field("end", &self.end)
+			.field("mask", &self.mask)
+			.field("access", &self.access)
+			.field("bank_size", &self.bank_size)
+			.field("bank", &self.bank)
+			.field("undriven", &self.undriven)
+			.field("enabled", &self.enabled)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Single-threaded memory bus, routing each access to the topmost enabled [`Mapping`] covering the address
+/// that allows it. Mappings added later lie on top, so ROM and I/O can overlay RAM. Reads nothing answers
+/// return the open bus value, the last value the bus carried.
+#[derive(Debug, Default)]
+pub struct Bus {
+	mappings: Vec<Mapping>,
+	open_bus: Cell<u8>,
+	logging: bool,
+	log: RefCell<Vec<BusAccess>>,
+}
+
+impl Bus {
+	/// Creates a bus with RAM of the specified size mapped from address 0
+	pub fn new(ram_size: usize) -> Bus {
+		let mut bus = Bus::default();
+		bus.map(Mapping::new(0, ram_size, Memory::new(ram_size).shared()));
+		bus
+	}
+
+	/// Adds a mapping on top of the existing ones
+	pub fn map(&mut self, mapping: Mapping) -> MappingId {
+		self.mappings.push(mapping);
+		self.mappings.len() - 1
+	}
+
+	pub fn mappings(&self) -> &[Mapping] {
+		&self.mappings
+	}
+
+	pub fn mapping(&self, id: MappingId) -> &Mapping {
+		&self.mappings[id]
+	}
+
+	pub fn mapping_mut(&mut self, id: MappingId) -> &mut Mapping {
+		&mut self.mappings[id]
+	}
+
+	/// Switches the bank a mapping shows
+	pub fn set_bank(&mut self, id: MappingId, bank: usize) {
+		self.mappings[id].bank = bank;
+	}
+
+	pub fn set_enabled(&mut self, id: MappingId, enabled: bool) {
+		self.mappings[id].enabled = enabled;
+	}
+
+	/// Returns the last value carried by the bus
+	pub fn open_bus(&self) -> u8 {
+		self.open_bus.get()
+	}
+
+	/// Records every access for debuggers until disabled. Peeks aren't recorded.
+	pub fn set_logging(&mut self, logging: bool) {
+		self.logging = logging;
+		if !logging {
+			self.log.borrow_mut().clear();
+		}
+	}
+
+	/// Moves the recorded accesses into `out`, oldest first
+	pub fn drain_log(&self, out: &mut Vec<BusAccess>) {
+		out.extend(self.log.borrow_mut().drain(..));
+	}
+
+	fn record(&self, kind: AccessKind, address: usize, value: u8) {
+		if self.logging {
+			self.log.borrow_mut().push(BusAccess {
+				kind,
+				address,
+				value,
+			});
+		}
+	}
+
+	/// Returns the address past the end of the highest mapping
+	pub fn size(&self) -> usize {
+		self.mappings.iter().map(|m| m.end).max().unwrap_or(0)
+	}
+
+	/// Finds the mapping answering an access, along with the device address
+	fn route(&self, address: usize, write: bool) -> Option<(&Mapping, usize)> {
+		self.mappings.iter().rev()
+			.find(|m| m.enabled && m.contains(address) && m.access.allows(write))
+			.map(|m| (m, m.device_address(address)))
+	}
+
+	fn read_with<F>(&self, address: usize, read: F) -> u8
+	where
+		F: FnOnce(&dyn DeviceBase, usize) -> u8,
+	{
+		match self.route(address, false) {
+			Some((m, offset)) => {
+				let value = read(&*m.device.borrow(), offset);
+				(value & !m.undriven) | (self.open_bus.get() & m.undriven)
+			},
+			None => self.open_bus.get(),
+		}
+	}
+}
+
+impl DeviceBase for Bus {
+	fn get_u8(&self, address: usize) -> u8 {
+		let value = self.read_with(address, |d, a| d.get_u8(a));
+		self.open_bus.set(value);
+		self.record(AccessKind::Read, address, value);
+		value
+	}
+
+	fn put_u8(&mut self, address: usize, data: u8) {
+		self.open_bus.set(data);
+		self.record(AccessKind::Write, address, data);
+
+		if let Some((m, offset)) = self.route(address, true) {
+			m.device.borrow_mut().put_u8(offset, data);
+		}
+	}
+
+	fn peek_u8(&self, address: usize) -> u8 {
+		self.read_with(address, |d, a| d.peek_u8(a))
+	}
+}
+
+impl Display for Bus {
+	/// Writes the address space as a 16-column hexdump with ASCII view, without read side effects
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let size = self.size();
+		let data: Vec<u8> = (0..size).map(|a| self.peek_u8(a)).collect();
+
+		match size {
+			0..=255 => write!(f, "{}", hexdump(&data, 1)),
+			256..=65535 => write!(f, "{}", hexdump(&data, 2)),
+			65536..=4294967295 => write!(f, "{}", hexdump(&data, 4)),
+			_ => write!(f, "{}", hexdump(&data, 8)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Counts reads of its registers, like a status register cleared on read
+	#[derive(Default)]
+	struct Registers {
+		regs: [u8; 8],
+		reads: Cell<usize>,
+	}
+
+	impl DeviceBase for Registers {
+		fn get_u8(&self, address: usize) -> u8 {
+			self.reads.set(self.reads.get() + 1);
+			self.regs[address]
+		}
+
+		fn put_u8(&mut self, address: usize, data: u8) {
+			self.regs[address] = data;
+		}
+
+		fn peek_u8(&self, address: usize) -> u8 {
+			self.regs[address]
+		}
+	}
+
+	#[test]
+	fn test_mirroring_and_io() {
+		let mut bus = Bus::default();
+		let ram = Memory::new(0x800).shared();
+		let ppu = Rc::new(RefCell::new(Registers::default()));
+
+		bus.map(Mapping::new(0, 0x2000, ram.clone()).mirror(0x800));
+		bus.map(Mapping::new(0x2000, 0x4000, ppu.clone()).mirror(8));
+
+		bus.put_u8(0x0001, 0x42);
+		assert_eq!(bus.get_u8(0x0801), 0x42);
+		assert_eq!(bus.get_u8(0x1801), 0x42);
+		bus.put_u16_le(0x07FF, 0xBEEF);
+		assert_eq!(ram.borrow().as_slice()[0x7FF], 0xEF);
+		assert_eq!(ram.borrow().as_slice()[0], 0xBE);
+
+		bus.put_u8(0x3FFE, 7);
+		assert_eq!(ppu.borrow().regs[6], 7);
+		assert_eq!(bus.get_u8(0x2006), 7);
+		assert_eq!(bus.peek_u8(0x2006), 7);
+		assert_eq!(ppu.borrow().reads.get(), 1);
+	}
+
+	#[test]
+	fn test_overlays_and_banks() {
+		let mut bus = Bus::new(0x10000);
+		let rom = Memory::from((0..4).flat_map(|b| vec![b as u8; 0x2000]).collect::<Vec<u8>>()).shared();
+
+		// ROM over RAM, as on the C64: reads hit the ROM, writes reach the RAM beneath
+		let basic = bus.map(Mapping::new(0xA000, 0xC000, rom.clone()).access(Access::ReadOnly).banked(0x2000, 1));
+		bus.put_u8(0xA000, 0x55);
+		assert_eq!(bus.get_u8(0xA000), 1);
+		assert_eq!(rom.borrow().as_slice()[0x2000], 1);
+
+		bus.set_bank(basic, 3);
+		assert_eq!(bus.get_u8(0xBFFF), 3);
+
+		bus.set_enabled(basic, false);
+		assert_eq!(bus.get_u8(0xA000), 0x55);
+	}
+
+	#[test]
+	#[should_panic(expected = "isn't a power of two")]
+	fn test_bad_mirror() {
+		let _ = Mapping::new(0, 0x2000, Memory::new(0x600).shared()).mirror(0x600);
+	}
+
+	#[test]
+	#[should_panic(expected = "isn't a power of two")]
+	fn test_bad_bank() {
+		let _ = Mapping::new(0x8000, 0xC000, Memory::new(0x6000).shared()).banked(0x3000, 1);
+	}
+
+	#[test]
+	fn test_open_bus() {
+		let mut bus = Bus::default();
+		let port = Memory::from(vec![0x01]).shared();
+		let latch = Memory::new(1).shared();
+
+		bus.map(Mapping::new(0x4016, 0x4017, port).undriven(0xE0));
+		bus.map(Mapping::new(0x4014, 0x4015, latch.clone()).access(Access::WriteOnly));
+
+		assert_eq!(bus.get_u8(0x5000), 0);
+		bus.put_u8(0x4014, 0x40);
+		assert_eq!(latch.borrow().as_slice()[0], 0x40);
+		assert_eq!(bus.get_u8(0x4014), 0x40);
+		assert_eq!(bus.get_u8(0x4016), 0x41);
+		assert_eq!(bus.open_bus(), 0x41);
+		assert_eq!(bus.read(0x4016, 2), vec![0x41, 0x41]);
+		assert_eq!(bus.size(), 0x4017);
+
+		// the last row of the dump is only 7 bytes long
+		let dump = bus.to_string();
+		assert_eq!(dump.lines().count(), 0x402);
+		assert_eq!(dump.lines().last().unwrap().split('\t').nth(1).unwrap().trim().split(' ').count(), 7);
+	}
+}
@@ -0,0 +1,723 @@
+use indexmap::IndexMap;
+
+use std::{
+	collections::VecDeque,
+	fmt::Write,
+	ops::Range
+};
+
+use crate::{
+	AccessKind,
+	BusAccess,
+	Device,
+	Processor
+};
+
+/// Index of a [`Breakpoint`] within its [`Debugger`]
+pub type BreakpointId = usize;
+
+/// How an instruction affects the call stack, so stepping can treat a subroutine as a single instruction
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Flow {
+	Normal,
+
+	/// Enters a subroutine, including software interrupts
+	Call,
+
+	/// Leaves a subroutine or interrupt handler
+	Return,
+}
+
+/// Processor state exposed to a [`Debugger`]
+pub trait Debuggable: Device + Processor {
+	/// Names of the registers, in the order [`Debuggable::register`] indexes them
+	fn register_names(&self) -> &'static [&'static str];
+
+	/// Gets a register value by its index into [`Debuggable::register_names`]
+	fn register(&self, index: usize) -> u64;
+
+	/// Gets the address of the next instruction
+	fn pc(&self) -> usize;
+
+	/// Whether the last instruction has finished, so the next clock starts a new one
+	fn at_boundary(&self) -> bool;
+
+	/// Classifies the instruction at the program counter. Conditional calls and returns that won't be taken are
+	/// [`Flow::Normal`].
+	fn flow(&self) -> Flow;
+
+	/// Gets a register value by its name, ignoring case
+	fn register_by_name(&self, name: &str) -> Option<u64> {
+		self.register_names().iter().position(|n| n.eq_ignore_ascii_case(name)).map(|i| self.register(i))
+	}
+}
+
+/// Comparison applied by a [`Condition`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compare {
+	Eq,
+	Ne,
+	Lt,
+	Le,
+	Gt,
+	Ge,
+}
+
+impl Compare {
+	pub const fn test(&self, lhs: u64, rhs: u64) -> bool {
+		match self {
+			Compare::Eq => lhs == rhs,
+			Compare::Ne => lhs != rhs,
+			Compare::Lt => lhs < rhs,
+			Compare::Le => lhs <= rhs,
+			Compare::Gt => lhs > rhs,
+			Compare::Ge => lhs >= rhs,
+		}
+	}
+}
+
+/// Compares a register against a value
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Condition {
+	pub register: String,
+	pub compare: Compare,
+	pub value: u64,
+}
+
+impl Condition {
+	pub fn new(register: &str, compare: Compare, value: u64) -> Condition {
+		Condition {
+			register: register.to_owned(),
+			compare,
+			value,
+		}
+	}
+
+	/// Checks the condition against the processor. Registers the processor lacks never match.
+	pub fn holds<P: Debuggable>(&self, cpu: &P) -> bool {
+		matches!(cpu.register_by_name(&self.register), Some(v) if self.compare.test(v, self.value))
+	}
+}
+
+/// What a [`Breakpoint`] watches
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Trigger {
+	/// Stops before the instruction at the address executes
+	Execute(usize),
+
+	/// Stops after an address in the range is read, a watchpoint
+	Read(Range<usize>),
+
+	/// Stops after an address in the range is written, a watchpoint
+	Write(Range<usize>),
+}
+
+impl Trigger {
+	const fn watches_memory(&self) -> bool {
+		matches!(self, Trigger::Read(_) | Trigger::Write(_))
+	}
+
+	fn matches(&self, access: &BusAccess) -> bool {
+		match (self, access.kind) {
+			(Trigger::Read(r), AccessKind::Read) | (Trigger::Write(r), AccessKind::Write) => r.contains(&access.address),
+			_ => false,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Breakpoint {
+	pub trigger: Trigger,
+
+	/// All of these must hold for the breakpoint to trigger
+	pub conditions: Vec<Condition>,
+	pub enabled: bool,
+
+	/// Number of times the breakpoint has triggered
+	pub hits: usize,
+}
+
+impl Breakpoint {
+	pub fn new(trigger: Trigger) -> Breakpoint {
+		Breakpoint {
+			trigger,
+			conditions: vec![],
+			enabled: true,
+			hits: 0,
+		}
+	}
+
+	pub fn execute(address: usize) -> Breakpoint {
+		Breakpoint::new(Trigger::Execute(address))
+	}
+
+	pub fn read(addresses: Range<usize>) -> Breakpoint {
+		Breakpoint::new(Trigger::Read(addresses))
+	}
+
+	pub fn write(addresses: Range<usize>) -> Breakpoint {
+		Breakpoint::new(Trigger::Write(addresses))
+	}
+
+	/// Adds a condition that must also hold for the breakpoint to trigger
+	pub fn when(mut self, condition: Condition) -> Breakpoint {
+		self.conditions.push(condition);
+		self
+	}
+
+	fn triggers<P: Debuggable>(&self, cpu: &P) -> bool {
+		self.enabled && self.conditions.iter().all(|c| c.holds(cpu))
+	}
+}
+
+/// Why the [`Debugger`] stopped
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Stop {
+	/// The requested step finished
+	Step,
+
+	/// A breakpoint triggered, along with the access that triggered it for read and write breakpoints
+	Breakpoint(BreakpointId, Option<BusAccess>),
+
+	/// The cycle counter reached [`Debugger::cycle_limit`]
+	CycleLimit,
+}
+
+/// Processor state at the start of an instruction
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TraceEntry {
+	/// Cycle counter value when the instruction started
+	pub cycle: u64,
+	pub pc: usize,
+
+	/// Register values, ordered as in [`Debuggable::register_names`]
+	pub registers: Vec<u64>,
+}
+
+/// Drives a processor one cycle, instruction or subroutine at a time, stopping at breakpoints and keeping a trace
+/// of the most recent instructions
+pub struct Debugger<P: Debuggable> {
+	cpu: P,
+	breakpoints: IndexMap<BreakpointId, Breakpoint>,
+	next_id: BreakpointId,
+	trace: VecDeque<TraceEntry>,
+	trace_capacity: usize,
+	accesses: Vec<BusAccess>,
+	logging: bool,
+
+	/// Address of the execute breakpoint last stopped at, which doesn't trigger again when resuming
+	resume: Option<usize>,
+	cycles: u64,
+	instructions: u64,
+
+	/// Stops any run once the cycle counter reaches this. Without one, runs only stop at breakpoints.
+	pub cycle_limit: Option<u64>,
+}
+
+impl<P: Debuggable> Debugger<P> {
+	/// Attaches a debugger to the processor, with tracing disabled
+	pub fn new(cpu: P) -> Debugger<P> {
+		Debugger {
+			cpu,
+			breakpoints: IndexMap::new(),
+			next_id: 0,
+			trace: VecDeque::new(),
+			trace_capacity: 0,
+			accesses: vec![],
+			logging: false,
+			resume: None,
+			cycles: 0,
+			instructions: 0,
+			cycle_limit: None,
+		}
+	}
+
+	pub fn cpu(&self) -> &P {
+		&self.cpu
+	}
+
+	pub fn cpu_mut(&mut self) -> &mut P {
+		&mut self.cpu
+	}
+
+	/// Detaches the debugger, returning the processor
+	pub fn into_inner(self) -> P {
+		self.cpu
+	}
+
+	/// Gets the number of cycles clocked through the debugger
+	pub fn cycles(&self) -> u64 {
+		self.cycles
+	}
+
+	/// Gets the number of instructions started through the debugger
+	pub fn instructions(&self) -> u64 {
+		self.instructions
+	}
+
+	pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> BreakpointId {
+		let id = self.next_id;
+		self.next_id += 1;
+		self.breakpoints.insert(id, breakpoint);
+		id
+	}
+
+	pub fn remove_breakpoint(&mut self, id: BreakpointId) -> Option<Breakpoint> {
+		self.breakpoints.shift_remove(&id)
+	}
+
+	pub fn breakpoint(&self, id: BreakpointId) -> Option<&Breakpoint> {
+		self.breakpoints.get(&id)
+	}
+
+	pub fn breakpoint_mut(&mut self, id: BreakpointId) -> Option<&mut Breakpoint> {
+		self.breakpoints.get_mut(&id)
+	}
+
+	/// Iterates over the breakpoints in the order they were added
+	pub fn breakpoints(&self) -> impl Iterator<Item = (BreakpointId, &Breakpoint)> {
+		self.breakpoints.iter().map(|(id, b)| (*id, b))
+	}
+
+	/// Keeps the last `capacity` instructions in the trace. 0 disables tracing.
+	pub fn set_trace_capacity(&mut self, capacity: usize) {
+		self.trace_capacity = capacity;
+		while self.trace.len() > capacity {
+			self.trace.pop_front();
+		}
+	}
+
+	/// Iterates over the traced instructions, oldest first
+	pub fn trace(&self) -> impl Iterator<Item = &TraceEntry> {
+		self.trace.iter()
+	}
+
+	pub fn clear_trace(&mut self) {
+		self.trace.clear();
+	}
+
+	/// Formats the trace one instruction per line, with its cycle, address and registers
+	pub fn dump_trace(&self) -> String {
+		let names = self.cpu.register_names();
+		let mut out = String::new();
+
+		for entry in self.trace.iter() {
+			let _ = write!(out, "{:>10}  {:08X} ", entry.cycle, entry.pc);
+			for (name, value) in names.iter().zip(entry.registers.iter()) {
+				let _ = write!(out, " {}=${:X}", name, value);
+			}
+			out.push('\n');
+		}
+
+		out
+	}
+
+	/// Clocks the processor once
+	pub fn step_cycle(&mut self) -> Stop {
+		self.sync_logging();
+		self.clock().unwrap_or(Stop::Step)
+	}
+
+	/// Executes one instruction, entering subroutines. If an instruction is underway, finishes it instead.
+	pub fn step_into(&mut self) -> Stop {
+		self.sync_logging();
+		self.instruction(false).unwrap_or(Stop::Step)
+	}
+
+	/// Executes one instruction, running subroutines it calls to completion
+	pub fn step_over(&mut self) -> Stop {
+		self.sync_logging();
+
+		let call = self.cpu.at_boundary() && self.cpu.flow() == Flow::Call;
+		match self.instruction(false) {
+			Some(stop) => stop,
+			None if call => self.finish_frame(true),
+			None => Stop::Step,
+		}
+	}
+
+	/// Runs until the current subroutine returns
+	pub fn step_out(&mut self) -> Stop {
+		self.sync_logging();
+		self.finish_frame(false)
+	}
+
+	/// Runs until a breakpoint triggers or the cycle limit is reached
+	pub fn run(&mut self) -> Stop {
+		self.sync_logging();
+
+		loop {
+			if let Some(stop) = self.instruction(true) {
+				return stop;
+			}
+		}
+	}
+
+	/// Only has the bus record accesses while a breakpoint needs them
+	fn sync_logging(&mut self) {
+		let logging = self.breakpoints.values().any(|b| b.enabled && b.trigger.watches_memory());
+		if logging != self.logging {
+			self.cpu.get_bus().borrow_mut().set_logging(logging);
+			self.logging = logging;
+		}
+	}
+
+	fn record(&mut self) {
+		if self.trace_capacity == 0 {
+			return;
+		}
+
+		let mut entry = if self.trace.len() >= self.trace_capacity {
+			self.trace.pop_front().unwrap_or_default()
+		} else {
+			TraceEntry::default()
+		};
+
+		entry.cycle = self.cycles;
+		entry.pc = self.cpu.pc();
+		entry.registers.clear();
+		entry.registers.extend((0..self.cpu.register_names().len()).map(|i| self.cpu.register(i)));
+		self.trace.push_back(entry);
+	}
+
+	fn clock(&mut self) -> Option<Stop> {
+		if self.cpu.at_boundary() {
+			self.resume = None;
+			self.record();
+			self.instructions += 1;
+		}
+
+		self.cpu.clock();
+		self.cycles += 1;
+
+		if !self.logging {
+			return None;
+		}
+
+		self.cpu.get_bus().borrow().drain_log(&mut self.accesses);
+
+		let mut stop = None;
+		for access in self.accesses.drain(..) {
+			if stop.is_some() {
+				continue;
+			}
+
+			for (id, b) in self.breakpoints.iter_mut() {
+				if b.trigger.matches(&access) && b.triggers(&self.cpu) {
+					b.hits += 1;
+					stop = Some(Stop::Breakpoint(*id, Some(access)));
+					break;
+				}
+			}
+		}
+
+		stop
+	}
+
+	fn check_execute(&mut self) -> Option<Stop> {
+		if !self.cpu.at_boundary() {
+			return None;
+		}
+
+		let pc = self.cpu.pc();
+		if self.resume == Some(pc) {
+			return None;
+		}
+
+		for (id, b) in self.breakpoints.iter_mut() {
+			if b.trigger == Trigger::Execute(pc) && b.triggers(&self.cpu) {
+				b.hits += 1;
+				self.resume = Some(pc);
+				return Some(Stop::Breakpoint(*id, None));
+			}
+		}
+
+		None
+	}
+
+	fn limit_reached(&self) -> bool {
+		matches!(self.cycle_limit, Some(limit) if self.cycles >= limit)
+	}
+
+	/// Clocks until the next instruction boundary, optionally checking execute breakpoints first. Steps skip the
+	/// check, so they always execute the instruction they start on.
+	fn instruction(&mut self, check: bool) -> Option<Stop> {
+		if check {
+			if let Some(stop) = self.check_execute() {
+				return Some(stop);
+			}
+		}
+
+		loop {
+			if self.limit_reached() {
+				return Some(Stop::CycleLimit);
+			}
+			if let Some(stop) = self.clock() {
+				return Some(stop);
+			}
+			if self.cpu.at_boundary() {
+				return None;
+			}
+		}
+	}
+
+	/// Runs instructions until a return leaves the current frame, counting nested calls along the way
+	fn finish_frame(&mut self, mut check: bool) -> Stop {
+		let mut depth = 0usize;
+
+		loop {
+			let flow = if self.cpu.at_boundary() {
+				self.cpu.flow()
+			} else {
+				Flow::Normal
+			};
+
+			if let Some(stop) = self.instruction(check) {
+				return stop;
+			}
+			check = true;
+
+			match flow {
+				Flow::Call => depth += 1,
+				Flow::Return if depth == 0 => return Stop::Step,
+				Flow::Return => depth -= 1,
+				Flow::Normal => (),
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		cell::RefCell,
+		rc::Rc
+	};
+
+	use crate::{
+		Bus,
+		DeviceBase
+	};
+
+	use super::*;
+
+	/// Toy processor: LDA #imm, STA abs, LDA abs, CALL abs, RET, INC A and JMP abs
+	struct Toy {
+		bus: Rc<RefCell<Bus>>,
+		a: u8,
+		sp: usize,
+		pc: usize,
+		cycles: u8,
+	}
+
+	impl DeviceBase for Toy {
+		fn get_u8(&self, address: usize) -> u8 {
+			self.bus.borrow().get_u8(address)
+		}
+
+		fn put_u8(&mut self, address: usize, data: u8) {
+			self.bus.borrow_mut().put_u8(address, data);
+		}
+
+		fn peek_u8(&self, address: usize) -> u8 {
+			self.bus.borrow().peek_u8(address)
+		}
+	}
+
+	impl Device for Toy {
+		fn get_bus(&self) -> Rc<RefCell<Bus>> {
+			Rc::clone(&self.bus)
+		}
+	}
+
+	impl Processor for Toy {
+		fn clock(&mut self) {
+			if self.cycles == 0 {
+				let opcode = self.get_u8(self.pc);
+				let arg = self.get_u8(self.pc + 1);
+
+				self.cycles = match opcode {
+					1 => {
+						self.a = arg;
+						self.pc += 2;
+						2
+					},
+					2 => {
+						self.put_u8(arg as usize, self.a);
+						self.pc += 2;
+						3
+					},
+					3 => {
+						self.a = self.get_u8(arg as usize);
+						self.pc += 2;
+						3
+					},
+					4 => {
+						self.put_u8(self.sp, self.pc as u8 + 2);
+						self.sp -= 1;
+						self.pc = arg as usize;
+						4
+					},
+					5 => {
+						self.sp += 1;
+						self.pc = self.get_u8(self.sp) as usize;
+						4
+					},
+					6 => {
+						self.a += 1;
+						self.pc += 1;
+						2
+					},
+					_ => {
+						self.pc = arg as usize;
+						2
+					},
+				};
+			}
+
+			self.cycles -= 1;
+		}
+
+		fn get_ptr(&self, offset: usize) -> usize {
+			self.get_u8(offset) as usize
+		}
+
+		fn get_ptr_size(&self) -> usize {
+			1
+		}
+
+		fn reset(&mut self) {
+			self.pc = 0;
+		}
+	}
+
+	impl Debuggable for Toy {
+		fn register_names(&self) -> &'static [&'static str] {
+			&["A", "SP", "PC"]
+		}
+
+		fn register(&self, index: usize) -> u64 {
+			match index {
+				0 => self.a as u64,
+				1 => self.sp as u64,
+				_ => self.pc as u64,
+			}
+		}
+
+		fn pc(&self) -> usize {
+			self.pc
+		}
+
+		fn at_boundary(&self) -> bool {
+			self.cycles == 0
+		}
+
+		fn flow(&self) -> Flow {
+			match self.peek_u8(self.pc) {
+				4 => Flow::Call,
+				5 => Flow::Return,
+				_ => Flow::Normal,
+			}
+		}
+	}
+
+	fn debugger() -> Debugger<Toy> {
+		let bus = Rc::new(RefCell::new(Bus::new(256)));
+		bus.borrow_mut().write(0, &[
+			1, 5, // LDA #5
+			4, 0x10, // CALL $10
+			2, 0x80, // STA $80
+			7, 6, // JMP $06
+		]);
+		bus.borrow_mut().write(0x10, &[
+			6, // INC A
+			4, 0x20, // CALL $20
+			5, // RET
+		]);
+		bus.borrow_mut().write(0x20, &[
+			3, 0x80, // LDA $80
+			6, // INC A
+			5, // RET
+		]);
+
+		Debugger::new(Toy {
+			bus,
+			a: 0,
+			sp: 0xF0,
+			pc: 0,
+			cycles: 0,
+		})
+	}
+
+	#[test]
+	fn test_step() {
+		let mut dbg = debugger();
+
+		assert_eq!(dbg.step_into(), Stop::Step);
+		assert_eq!((dbg.cpu().pc, dbg.cycles()), (2, 2));
+
+		assert_eq!(dbg.step_over(), Stop::Step);
+		assert_eq!((dbg.cpu().pc, dbg.cpu().a), (4, 1));
+		assert_eq!((dbg.cycles(), dbg.instructions()), (25, 8));
+
+		assert_eq!(dbg.step_into(), Stop::Step);
+		assert_eq!(dbg.cpu().pc, 6);
+		assert_eq!(dbg.cpu().peek_u8(0x80), 1);
+
+		let mut dbg = debugger();
+		dbg.step_into();
+		dbg.step_into();
+		dbg.step_into();
+		assert_eq!(dbg.cpu().pc, 0x11);
+
+		// leaves both the nested call and the current one
+		assert_eq!(dbg.step_out(), Stop::Step);
+		assert_eq!((dbg.cpu().pc, dbg.cpu().sp), (4, 0xF0));
+	}
+
+	#[test]
+	fn test_breakpoints() {
+		let mut dbg = debugger();
+		let never = dbg.add_breakpoint(Breakpoint::execute(0x22).when(Condition::new("a", Compare::Eq, 7)));
+		let inc = dbg.add_breakpoint(Breakpoint::execute(0x22).when(Condition::new("a", Compare::Eq, 0)));
+		let read = dbg.add_breakpoint(Breakpoint::read(0x80..0x81));
+		let write = dbg.add_breakpoint(Breakpoint::write(0x80..0x81));
+
+		assert_eq!(dbg.run(), Stop::Breakpoint(read, Some(BusAccess {
+			kind: AccessKind::Read,
+			address: 0x80,
+			value: 0,
+		})));
+		assert_eq!(dbg.run(), Stop::Breakpoint(inc, None));
+		assert_eq!(dbg.cpu().pc, 0x22);
+		assert_eq!(dbg.run(), Stop::Breakpoint(write, Some(BusAccess {
+			kind: AccessKind::Write,
+			address: 0x80,
+			value: 1,
+		})));
+		assert_eq!(dbg.breakpoint(never).unwrap().hits, 0);
+
+		dbg.breakpoint_mut(read).unwrap().enabled = false;
+		dbg.remove_breakpoint(write);
+		dbg.cycle_limit = Some(100);
+		assert_eq!(dbg.run(), Stop::CycleLimit);
+		assert_eq!(dbg.cycles(), 100);
+	}
+
+	#[test]
+	fn test_trace() {
+		let mut dbg = debugger();
+		dbg.set_trace_capacity(3);
+
+		for _ in 0..5 {
+			dbg.step_into();
+		}
+
+		let pcs: Vec<usize> = dbg.trace().map(|e| e.pc).collect();
+		assert_eq!(pcs, [0x10, 0x11, 0x20]);
+
+		let last = dbg.trace().last().unwrap();
+		assert_eq!((last.cycle, last.registers.as_slice()), (12, &[6, 0xEE, 0x20][..]));
+		assert!(dbg.dump_trace().ends_with("A=$6 SP=$EE PC=$20\n"));
+	}
+}
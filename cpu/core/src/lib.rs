@@ -4,17 +4,17 @@ use indexmap::IndexMap;
 use std::{
 	cell::RefCell,
 	collections::HashSet,
-	fmt::{
-		Display,
-		Formatter,
-		self
-	},
 	rc::Rc
 };
 
+pub mod bus;
+pub mod debug;
+
 #[cfg(feature = "shared")]
 pub mod shared;
 
+pub use bus::*;
+
 bitflags! {
 	#[derive(Default)]
 	pub struct RegionFlags: u8 {
@@ -252,104 +252,114 @@ pub trait Disassembler {
 	}*/
 }
 
-/// Common device operations
+/// Common device operations. Only single bytes must be implemented; wider accesses are composed from them
+/// without allocating.
 pub trait DeviceBase {
-	/// Reads data from an address on the device
-	fn read(&self, address: usize, length: usize) -> Vec<u8>;
+	/// Retrieves a single unsigned byte from the address on the device
+	fn get_u8(&self, address: usize) -> u8;
 
-	/// Writes data to an address on the device
-	fn write(&mut self, address: usize, data: &[u8]);
+	/// Writes a single byte to the address on the device
+	fn put_u8(&mut self, address: usize, data: u8);
 
-	/// Retrieves a single signed byte from the address on the device
-	fn get_i8(&self, address: usize) -> i8 {
-		i8::from_ne_bytes(self.read(address, 1).try_into().unwrap())
+	/// Retrieves a byte without side effects such as clearing status flags, for debuggers and dumps
+	fn peek_u8(&self, address: usize) -> u8 {
+		self.get_u8(address)
 	}
 
-	/// Retrieves a single unsigned byte from the address on the device
-	fn get_u8(&self, address: usize) -> u8 {
-		u8::from_ne_bytes(self.read(address, 1).try_into().unwrap())
-		/*match self.read(address, 1).try_into() {
-			Ok(b) => u8::from_ne_bytes(b),
-			Err(e) => {
-				dbg!(e.kind());
-				panic!("bhup");
-			},
-		}*/
+	/// Fills `buf` with data from an address on the device
+	fn read_into(&self, address: usize, buf: &mut [u8]) {
+		for (i, b) in buf.iter_mut().enumerate() {
+			*b = self.get_u8(address + i);
+		}
 	}
 
-	/// Writes a single byte to the address on the device
-	fn put_u8(&mut self, address: usize, data: u8) {
-		self.write(address, data.to_ne_bytes().as_slice());
+	/// Reads data from an address on the device
+	fn read(&self, address: usize, length: usize) -> Vec<u8> {
+		let mut data = vec![0; length];
+		self.read_into(address, &mut data);
+		data
+	}
+
+	/// Writes data to an address on the device
+	fn write(&mut self, address: usize, data: &[u8]) {
+		for (i, b) in data.iter().enumerate() {
+			self.put_u8(address + i, *b);
+		}
+	}
+
+	/// Retrieves a single signed byte from the address on the device
+	fn get_i8(&self, address: usize) -> i8 {
+		self.get_u8(address) as i8
 	}
 
 	/// Retrieves a little endian 16-bit signed value from the address on the device
 	fn get_i16_le(&self, address: usize) -> i16 {
-		i16::from_le_bytes(self.read(address, 2).try_into().unwrap())
+		self.get_u16_le(address) as i16
 	}
 
 	/// Retrieves a big endian 16-bit signed value from the address on the device
 	fn get_i16_be(&self, address: usize) -> i16 {
-		i16::from_be_bytes(self.read(address, 2).try_into().unwrap())
+		self.get_u16_be(address) as i16
 	}
 
 	/// Retrieves a little endian 16-bit unsigned value from the address on the device
 	fn get_u16_le(&self, address: usize) -> u16 {
-		u16::from_le_bytes(self.read(address, 2).try_into().unwrap())
+		u16::from_le_bytes([self.get_u8(address), self.get_u8(address + 1)])
 	}
 
 	/// Retrieves a big endian 16-bit unsigned value from the address on the device
 	fn get_u16_be(&self, address: usize) -> u16 {
-		u16::from_be_bytes(self.read(address, 2).try_into().unwrap())
+		u16::from_be_bytes([self.get_u8(address), self.get_u8(address + 1)])
 	}
 
 	/// Writes a big endian 16-bit unsigned value to the address on the device
 	fn put_u16_be(&mut self, address: usize, data: u16) {
-		self.write(address, data.to_be_bytes().as_slice());
+		self.write(address, &data.to_be_bytes());
 	}
 
 	/// Writes a little endian 16-bit unsigned value to the address on the device
 	fn put_u16_le(&mut self, address: usize, data: u16) {
-		self.write(address, data.to_le_bytes().as_slice());
+		self.write(address, &data.to_le_bytes());
 	}
 
 	/// Retrieves a little endian 32-bit unsigned value from the address on the device
 	fn get_u32_le(&self, address: usize) -> u32 {
-		u32::from_le_bytes(self.read(address, 4).try_into().unwrap())
+		self.get_u16_le(address) as u32 | (self.get_u16_le(address + 2) as u32) << 16
 	}
 
 	/// Retrieves a big endian 32-bit unsigned value from the address on the device
 	fn get_u32_be(&self, address: usize) -> u32 {
-		u32::from_be_bytes(self.read(address, 4).try_into().unwrap())
+		(self.get_u16_be(address) as u32) << 16 | self.get_u16_be(address + 2) as u32
 	}
 
 	/// Writes a big endian 32-bit unsigned value to the address on the device
 	fn put_u32_be(&mut self, address: usize, data: u32) {
-		self.write(address, data.to_be_bytes().as_slice());
+		self.write(address, &data.to_be_bytes());
 	}
 
 	/// Writes a little endian 32-bit unsigned value to the address on the device
 	fn put_u32_le(&mut self, address: usize, data: u32) {
-		self.write(address, data.to_le_bytes().as_slice());
+		self.write(address, &data.to_le_bytes());
 	}
 
 	/// Retrieves a little endian 64-bit unsigned value from the address on the device
 	fn get_u64_le(&self, address: usize) -> u64 {
-		u64::from_le_bytes(self.read(address, 8).try_into().unwrap())
+		self.get_u32_le(address) as u64 | (self.get_u32_le(address + 4) as u64) << 32
 	}
 
 	/// Retrieves a big endian 64-bit unsigned value from the address on the device
 	fn get_u64_be(&self, address: usize) -> u64 {
-		u64::from_be_bytes(self.read(address, 8).try_into().unwrap())
+		(self.get_u32_be(address) as u64) << 32 | self.get_u32_be(address + 4) as u64
 	}
 
 	/// Writes a big endian 64-bit unsigned value to the address on the device
 	fn put_u64_be(&mut self, address: usize, data: u64) {
-		self.write(address, data.to_be_bytes().as_slice());
+		self.write(address, &data.to_be_bytes());
 	}
 
 	/// Writes a little endian 64-bit unsigned value to the address on the device
 	fn put_u64_le(&mut self, address: usize, data: u64) {
-		self.write(address, data.to_le_bytes().as_slice());
+		self.write(address, &data.to_le_bytes());
 	}
 }
 
@@ -359,51 +369,13 @@ pub trait Device: DeviceBase {
 	fn get_bus(&self) -> Rc<RefCell<Bus>>;
 }
 
-/// Single-threaded memory bus
-#[derive(Clone, Debug)]
-pub struct Bus {
-	ram: Vec<u8>,
-}
-
-impl Bus {
-	pub fn new(ram_size: usize) -> Bus {
-		Bus {
-			ram: vec![0; ram_size],
-		}
-	}
-}
-
-impl DeviceBase for Bus {
-	fn read(&self, address: usize, length: usize) -> Vec<u8> {
-		self.ram.iter().skip(address).take(length).map(|b| *b).collect()
-	}
-
-	fn write(&mut self, address: usize, data: &[u8]) {
-		data.iter().enumerate().for_each(|(i, b)| {
-			self.ram[address + i] = *b;
-		});
-	}
-}
-
 impl Device for Bus {
 	fn get_bus(&self) -> Rc<RefCell<Bus>> {
 		unimplemented!("Bus attempted to get a reference counted pointer of itself");
 	}
 }
 
-impl Display for Bus {
-	/// Writes the RAM as a 16-column hexdump with ASCII view
-	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-		match self.ram.len() {
-			0..=255 => write!(f, "{}", hexdump(self.ram.as_ref(), 1)),
-			256..=65535 => write!(f, "{}", hexdump(self.ram.as_ref(), 2)),
-			65536..=4294967295 => write!(f, "{}", hexdump(self.ram.as_ref(), 4)),
-			_ => unreachable!(),
-		}
-	}
-}
-
-/// Outputs the provided data as a 16-column hexdump with ASCII view
+/// Outputs the provided data as a 16-column hexdump with ASCII view. A short last row is padded to line up.
 pub fn hexdump(data: &[u8], ptr_size: u8) -> String {
 	let mut s = String::new();
 
@@ -416,18 +388,20 @@ pub fn hexdump(data: &[u8], ptr_size: u8) -> String {
 			_ => panic!("Invalid pointer size given"),
 		};
 
+		let row = &data[i..(i + 16).min(data.len())];
 		let mut bytes = String::new();
 		for x in 0..16 {
-			bytes = format!("{} {:02X}", bytes, data[i + x]);
+			match row.get(x) {
+				Some(b) => bytes = format!("{} {:02X}", bytes, b),
+				None => bytes.push_str("   "),
+			}
 		}
 		bytes.push('\t');
 
 		s.push_str(addr.as_str());
 		s.push_str(bytes.as_str());
 
-		for x in 0..16 {
-			let c = data[i + x];
-
+		for &c in row.iter() {
 			// check for ASCII and Latin-1 supplement
 			s.push(match c {
 				(32..=126) | (161..=255) => c as char,
@@ -11,6 +11,10 @@ use std::{
 };
 
 use rgk_processors_core::{
+	debug::{
+		Debuggable,
+		Flow
+	},
 	Bus,
 	Device,
 	DeviceBase,
@@ -482,12 +486,16 @@ impl Display for MOS6502 {
 }
 
 impl DeviceBase for MOS6502 {
-	fn read(&self, address: usize, length: usize) -> Vec<u8> {
-		self.bus.borrow().read(address, length)
+	fn get_u8(&self, address: usize) -> u8 {
+		self.bus.borrow().get_u8(address)
+	}
+
+	fn put_u8(&mut self, address: usize, data: u8) {
+		self.bus.borrow_mut().put_u8(address, data);
 	}
 
-	fn write(&mut self, address: usize, data: &[u8]) {
-		self.bus.borrow_mut().write(address, data);
+	fn peek_u8(&self, address: usize) -> u8 {
+		self.bus.borrow().peek_u8(address)
 	}
 }
 
@@ -497,6 +505,43 @@ impl Device for MOS6502 {
 	}
 }
 
+impl Debuggable for MOS6502 {
+	fn register_names(&self) -> &'static [&'static str] {
+		&["A", "X", "Y", "P", "SP", "PC"]
+	}
+
+	fn register(&self, index: usize) -> u64 {
+		match index {
+			0 => self.regs.a as u64,
+			1 => self.regs.x as u64,
+			2 => self.regs.y as u64,
+			3 => self.regs.p.bits() as u64,
+			4 => self.regs.s as u64,
+			5 => self.regs.pc as u64,
+			_ => 0,
+		}
+	}
+
+	fn pc(&self) -> usize {
+		self.regs.pc
+	}
+
+	fn at_boundary(&self) -> bool {
+		self.cache.cycles == 0
+	}
+
+	fn flow(&self) -> Flow {
+		match self.peek_u8(self.regs.pc) {
+			// BRK, JSR
+			0x00 | 0x20 => Flow::Call,
+
+			// RTI, RTS
+			0x40 | 0x60 => Flow::Return,
+			_ => Flow::Normal,
+		}
+	}
+}
+
 impl ISA6502 for MOS6502 {
 	fn irq(&mut self) {
 		if !self.check_flag(Status::I) {
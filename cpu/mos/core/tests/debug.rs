@@ -0,0 +1,76 @@
+use std::{
+	cell::RefCell,
+	rc::Rc
+};
+
+use rgk_processors_core::{
+	debug::{
+		Breakpoint,
+		Debuggable,
+		Debugger,
+		Flow,
+		Stop
+	},
+	Bus,
+	DeviceBase
+};
+use rgk_processors_mos::MOS6502;
+
+fn debugger() -> Debugger<MOS6502> {
+	let bus = Rc::new(RefCell::new(Bus::new(0x10000)));
+	bus.borrow_mut().write(0xFFFC, &[0x00, 0x02]);
+	bus.borrow_mut().write(0x200, &[
+		0xA9, 0x05, // LDA #$05
+		0x20, 0x10, 0x02, // JSR $0210
+		0x85, 0x80, // STA $80
+		0x4C, 0x07, 0x02, // JMP $0207
+	]);
+	bus.borrow_mut().write(0x210, &[
+		0xE8, // INX
+		0x20, 0x20, 0x02, // JSR $0220
+		0x60, // RTS
+	]);
+	bus.borrow_mut().write(0x220, &[
+		0xC8, // INY
+		0x60, // RTS
+	]);
+
+	let mut dbg = Debugger::new(MOS6502::new(bus));
+
+	// finishes the reset sequence
+	assert_eq!(dbg.step_into(), Stop::Step);
+	assert_eq!(dbg.cpu().pc(), 0x200);
+	dbg
+}
+
+#[test]
+fn test_step() {
+	let mut dbg = debugger();
+
+	dbg.step_into();
+	assert_eq!((dbg.cpu().pc(), dbg.cpu().register_by_name("a")), (0x202, Some(5)));
+	assert_eq!(dbg.cpu().flow(), Flow::Call);
+
+	assert_eq!(dbg.step_over(), Stop::Step);
+	assert_eq!(dbg.cpu().pc(), 0x205);
+	assert_eq!((dbg.cpu().register_by_name("X"), dbg.cpu().register_by_name("Y")), (Some(1), Some(1)));
+
+	dbg.step_into();
+	assert_eq!(dbg.cpu().peek_u8(0x80), 5);
+}
+
+#[test]
+fn test_breakpoint_and_step_out() {
+	let mut dbg = debugger();
+	let inner = dbg.add_breakpoint(Breakpoint::execute(0x220));
+
+	assert_eq!(dbg.run(), Stop::Breakpoint(inner, None));
+	assert_eq!(dbg.cpu().register_by_name("x"), Some(1));
+
+	dbg.step_into();
+	assert_eq!(dbg.cpu().flow(), Flow::Return);
+
+	// back in the outer subroutine, at its RTS
+	assert_eq!(dbg.step_out(), Stop::Step);
+	assert_eq!(dbg.cpu().pc(), 0x214);
+}
@@ -6,7 +6,7 @@ use rgk_processors_mos::CPU;
 
 use rgk_processors_core::{
 	Bus,
-	Device
+	DeviceBase
 };
 
 const CTRL_ADDR: u16 = 8192;
@@ -71,16 +71,14 @@ impl PPU2C02 {
 	}
 }
 
-impl Device for PPU {
-	fn read(&self, address: usize, length: usize) -> Vec<u8> {
-		match address {
-			a if a >= 0 && a <= 8191 =>
-				self.bus.read(((address & 4095) >> 12) , length)
-		}
+impl DeviceBase for PPU {
+	/// The PPU's 14-bit address space mirrors above $3FFF
+	fn get_u8(&self, address: usize) -> u8 {
+		self.bus.get_u8(address & 0x3FFF)
 	}
 
-	fn write(&mut self, address: usize, data: &[u8]) {
-		self.bus.write(address, data);
+	fn put_u8(&mut self, address: usize, data: u8) {
+		self.bus.put_u8(address & 0x3FFF, data);
 	}
 }
 
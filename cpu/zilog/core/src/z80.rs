@@ -378,12 +378,16 @@ impl Z80 {
 }
 
 impl DeviceBase for Z80 {
-	fn read(&self, address: usize, length: usize) -> Vec<u8> {
-		self.bus.borrow().read(address, length)
+	fn get_u8(&self, address: usize) -> u8 {
+		self.bus.borrow().get_u8(address)
 	}
 
-	fn write(&mut self, address: usize, data: &[u8]) {
-		self.bus.borrow().write(address, data);
+	fn put_u8(&mut self, address: usize, data: u8) {
+		self.bus.borrow_mut().put_u8(address, data);
+	}
+
+	fn peek_u8(&self, address: usize) -> u8 {
+		self.bus.borrow().peek_u8(address)
 	}
 }
 
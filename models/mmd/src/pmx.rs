@@ -6,7 +6,10 @@ use byteorder::{
 	ReadBytesExt
 };
 
-use rgk_core::tag4;
+use rgk_core::{
+	io_ext,
+	tag4
+};
 
 use import::{
 	index,
@@ -78,6 +81,15 @@ pub enum Encoding {
 }
 
 impl Encoding {
+	/// Returns the shared text encoding this flag selects
+	pub fn text_encoding(&self) -> Option<io_ext::Encoding> {
+		match self {
+			Encoding::UTF16LE => Some(io_ext::Encoding::Utf16Le),
+			Encoding::UTF8 => Some(io_ext::Encoding::Utf8),
+			Encoding::Unknown => None,
+		}
+	}
+
 	#[cfg(feature = "import")]
 	fn read<R>(buf: &mut R) -> Result<Encoding, MMD2ImportError>
 	where
@@ -222,10 +234,7 @@ pub mod import {
 		ReadBytesExt
 	};
 
-	use encoding_rs::{
-		UTF_16LE,
-		UTF_8
-	};
+	use rgk_core::io_ext::ReadBinExt;
 
 	use std::io;
 	use thiserror::Error;
@@ -280,30 +289,9 @@ pub mod import {
 		R: ReadBytesExt,
 	{
 		let length = buf.read_u32::<LE>()? as usize;
-		let mut raw = vec![];
-		let mut chr = [0; 1];
-
-		for _ in 0..length {
-			buf.read_exact(&mut chr)?;
-			raw.push(chr[0]);
-		}
+		let text_encoding = encoding.text_encoding().ok_or(MMD2ImportError::Encoding(encoding as u8))?;
 
-		match encoding {
-			Encoding::UTF16LE => {
-				let mut dec = UTF_16LE.new_decoder_without_bom_handling();
-				let mut output = String::with_capacity(length);
-				let _ = dec.decode_to_string(raw.as_slice(), &mut output, true);
-				output.shrink_to_fit();
-				Ok(output)
-			},
-			Encoding::UTF8 => {
-				let mut dec = UTF_8.new_decoder_without_bom_handling();
-				let mut output = String::with_capacity(length);
-				let _ = dec.decode_to_string(raw.as_slice(), &mut output, true);
-				Ok(output)
-			},
-			_ => Err(MMD2ImportError::Encoding(encoding as u8)),
-		}
+		Ok(buf.read_str_with(length, text_encoding)?)
 	}
 
 	#[cfg(test)]
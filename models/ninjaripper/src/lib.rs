@@ -2,17 +2,13 @@ pub mod rip;
 
 use bitflags::bitflags;
 
-use std::{
-	fs,
-	io::Cursor
-};
-
 use ultraviolet::vec::{
 	Vec2,
 	Vec3
 };
 
 use rgk_core::{
+	input::Input,
 	io_ext::ReadBinExt,
 	scene::{
 		Face,
@@ -92,8 +88,8 @@ impl Default for ImportCfg {
 
 #[cfg(feature = "import")]
 pub fn read(filepath: &str, cfg: ImportCfg, ) -> Result<Scene, RipImportError> {
-	let mut data = Cursor::new(fs::read(filepath))?;
-	let model = RipModel::read(&mut data)?;
+	let input = Input::open(filepath)?;
+	let model = RipModel::read(&mut input.cursor())?;
 
 	let mut root = Node::new(ObjRef::Name(filepath.to_string()), None);
 
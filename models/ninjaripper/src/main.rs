@@ -1,8 +1,6 @@
-use std::{
-	env,
-	fs::read,
-	io::Cursor
-};
+use std::env;
+
+use rgk_core::input::Input;
 
 use rgk_models_ninjaripper::rip::{
 	RipModel,
@@ -11,8 +9,8 @@ use rgk_models_ninjaripper::rip::{
 
 fn main() -> Result<(), RipImportError> {
 	let args: Vec<String> = env::args().collect();
-	let mut data = Cursor::new(read(&args[1])?);
-	let model = RipModel::read(&mut data)?;
+	let input = Input::open(&args[1])?;
+	let model = RipModel::read(&mut input.cursor())?;
 
 	println!("{:#?}", model);
 
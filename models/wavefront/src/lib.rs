@@ -2,12 +2,13 @@ pub mod mtl;
 pub mod obj;
 pub mod refl;
 
+#[cfg(feature = "import")]
 use nom::{
 	branch::alt,
-	bytes::complete::take_while,
-	character::{
-		complete::multispace1,
-		is_space
+	bytes::complete::take_while1,
+	character::complete::{
+		multispace1,
+		space0
 	},
 	combinator::{
 		opt,
@@ -15,6 +16,7 @@ use nom::{
 	},
 	error::ParseError,
 	IResult,
+	InputIter,
 	multi::many0,
 	number::complete::float,
 	Parser,
@@ -24,15 +26,22 @@ use nom::{
 	}
 };
 
+#[cfg(feature = "import")]
 use ultraviolet::vec::Vec3;
 
-use meshio_core::parser::hash_comment;
+#[cfg(feature = "import")]
+use rgk_core::nom_ext::{
+	hash_comment,
+	Text
+};
 
 /// Parses discardable content (comment, whitespace, line feeds)
-pub(crate) fn etc<'a, F, O, E>(inner: F) -> impl FnMut(&'a str) -> IResult<&'a str, O, E>
+#[cfg(feature = "import")]
+pub(crate) fn etc<I, F, O, E>(inner: F) -> impl FnMut(I) -> IResult<I, O, E>
 where
-	E: ParseError<&'a str>,
-	F: Parser<&'a str, O, E>,
+	I: Text,
+	E: ParseError<I>,
+	F: Parser<I, O, E>,
 {
 	preceded(
 		many0(alt((
@@ -44,22 +53,35 @@ where
 }
 
 /// Parses an identifier (object/group/material name, filename)
-pub(crate) fn identifier<'a, E>(input: &'a str) -> IResult<&'a str, &'a str, E>
+#[cfg(feature = "import")]
+pub(crate) fn identifier<I, E>(input: I) -> IResult<I, I, E>
+where
+	I: Text,
+	E: ParseError<I>,
+{
+	take_while1(|c: char| !c.is_whitespace())(input)
+}
+
+/// Parses a float on the same line, after any spaces or tabs
+#[cfg(feature = "import")]
+pub(crate) fn hfloat<I, E>(input: I) -> IResult<I, f32, E>
 where
-	E: ParseError<&'a str>
+	I: Text,
+	<I as InputIter>::IterElem: Clone,
+	E: ParseError<I>,
 {
-	take_while(move |c| !is_space(c as u8))(input)
+	preceded(space0, float)(input)
 }
 
 /// Parses a 3D vector, with the last element optional, defaulting to `0`, used for UVWs
-pub(crate) fn uvw<'a, E>(input: &'a str) -> IResult<&'a str, Vec3, E>
+#[cfg(feature = "import")]
+pub(crate) fn uvw<I, E>(input: I) -> IResult<I, Vec3, E>
 where
-	E: ParseError<&'a str>
+	I: Text,
+	<I as InputIter>::IterElem: Clone,
+	E: ParseError<I>,
 {
-	let (input, f4vec) = tuple((etc(float), etc(float), opt(etc(float))))(input)?;
-	
-	Ok((input, Vec3::new(f4vec.0, f4vec.1, match f4vec.2 {
-		Some(val) => val,
-		_ => 0.0,
-	})))
+	let (input, f) = tuple((hfloat, hfloat, opt(hfloat)))(input)?;
+
+	Ok((input, Vec3::new(f.0, f.1, f.2.unwrap_or(0.0))))
 }
@@ -34,7 +34,9 @@ pub mod ast {
 	}
 }
 
+// the attribute parsers aren't assembled into a material reader yet
 #[cfg(feature = "import")]
+#[allow(dead_code)]
 pub mod import {
 	use nom::{
 		branch::alt,
@@ -58,11 +60,10 @@ pub mod import {
 
 	use crate::{
 		etc,
-		identifier,
-		uvw
+		identifier
 	};
 
-	use meshio_core::parser::vec3ws;
+	use rgk_core::nom_ext::vec3ws;
 	use super::ast::*;
 
 	#[derive(Error, Debug, PartialEq)]
@@ -84,14 +85,14 @@ pub mod import {
 	/// Parses a ['MtlFormat']
 	fn fmt(input: &str) -> IResult<&str, MtlFormat> {
 		alt((
-			map(preceded(etc(tag("spectral")), identifier), |s| MtlFormat::Spectral(s.to_string())),
-			map(preceded(etc(tag("xyz")), vec3ws), |v| MtlFormat::Xyz(v)),
-			map(vec3ws, |v| MtlFormat::Rgb(v))
+			map(preceded(etc(tag("spectral")), identifier), |s: &str| MtlFormat::Spectral(s.to_string())),
+			map(preceded(etc(tag("xyz")), vec3ws), MtlFormat::Xyz),
+			map(vec3ws, MtlFormat::Rgb)
 		))(input)
 	}
 
 	/// Parses a filename used for the material's bump map (`bump` attribute)
-	fn bump<'a>(input: &'a str) -> IResult<&'a str, &'a str> {
+	fn bump(input: &str) -> IResult<&str, &str> {
         preceded(etc(tag("bump")), etc(identifier))(input)
     }
 
@@ -100,12 +101,12 @@ pub mod import {
     }
 
 	/// Parses a filename used for the material's decal
-	fn decal<'a>(input: &'a str) -> IResult<&'a str, &'a str> {
+	fn decal(input: &str) -> IResult<&str, &str> {
         preceded(etc(tag("decal")), etc(identifier))(input)
     }
 
 	/// Parses a filename used for the material's displacement map
-	fn disp<'a>(input: &'a str) -> IResult<&'a str, &'a str> {
+	fn disp(input: &str) -> IResult<&str, &str> {
         preceded(etc(tag("disp")), etc(identifier))(input)
     }
 
@@ -130,21 +131,21 @@ pub mod import {
     }
 
 	/// Parses a filename used for the material's bump map (`map_bump` attribute)
-	fn map_bump<'a>(input: &'a str) -> IResult<&'a str, &'a str> {
+	fn map_bump(input: &str) -> IResult<&str, &str> {
         preceded(etc(tag("map_bump")), etc(identifier))(input)
     }
 
 	/// Parses a filename used for the material's diffuse map
-	fn map_d<'a>(input: &'a str) -> IResult<&'a str, &'a str> {
+	fn map_d(input: &str) -> IResult<&str, &str> {
         preceded(etc(tag("map_d")), etc(identifier))(input)
     }
 
-	fn map_kd<'a>(input: &'a str) -> IResult<&'a str, &'a str> {
+	fn map_kd(input: &str) -> IResult<&str, &str> {
         preceded(etc(tag("map_Kd")), etc(identifier))(input)
     }
 
 	/// Parses a material's name
-	fn newmtl<'a>(input: &'a str) -> IResult<&'a str, &'a str> {
+	fn newmtl(input: &str) -> IResult<&str, &str> {
         preceded(etc(tag("newmtl")), etc(identifier))(input)
     }
 
@@ -36,142 +36,239 @@ pub mod ast {
 pub mod export {
 	use std::{
 		fmt::Write as FmtWrite,
-		fs,
-		io::Write as IoWrite,
+		io
 	};
 
-	use meshio_core::{
-		IOError,
+	use thiserror::Error;
+
+	use rgk_core::{
+		format::{
+			self,
+			Asset,
+			Exporter,
+			FormatError,
+			FormatInfo
+		},
 		scene::{
 			Face,
-			Mesh,
 			NodeData,
-			Scene,
-			Vertex
+			ObjRef,
+			Scene
 		}
 	};
 
 	static BUF_ERR: &str = "Buffer unwritable";
 	static FACE_ERR: &str = "Face unwritable";
 
-	pub fn write<'a, 'b>(filepath: &'a str, scene: &'b Scene) -> Result<(), IOError> {
+	#[derive(Debug, Error)]
+	pub enum ObjExportError {
+		#[error("I/O error")]
+		IO {
+			#[from]
+			source: io::Error,
+		},
+		#[error("No geometry found")]
+		NoGeometry,
+	}
+
+	/// Writes every mesh in the scene as an object, transformed into world space
+	pub fn write<W: io::Write + ?Sized>(out: &mut W, scene: &Scene) -> Result<(), ObjExportError> {
 		let mut buf = String::new();
-		writeln!(&mut buf, "# Generated by the meshio-wavefront Rust crate").expect(BUF_ERR);
+		writeln!(&mut buf, "# Generated by the rgk-models-wavefront Rust crate").expect(BUF_ERR);
 		writeln!(&mut buf).expect(BUF_ERR);
 
-		if let NodeData::Geometry(mesh) = &scene.root.data {
+		// Wavefront indices are 1-based and shared across every object in the file
+		let mut vertex_base = 1usize;
+		let mut normal_base = 1usize;
+		let mut uv_base = 1usize;
+		let mut found = false;
+
+		for (path, world) in scene.root.world_matrices().iter() {
+			let node = match scene.root.get(path) {
+				Some(n) => n,
+				None => continue,
+			};
+
+			let mesh = match &node.data {
+				NodeData::Geometry(mesh) => mesh,
+				_ => continue,
+			};
+			found = true;
+
+			match &node.id {
+				ObjRef::Name(name) => writeln!(&mut buf, "o {}", name).expect(BUF_ERR),
+				ObjRef::Number(n) => writeln!(&mut buf, "o {}", n).expect(BUF_ERR),
+			}
+
 			// positions
 			for v in mesh.vertices.iter() {
-				writeln!(&mut buf, "v {} {} {}", v.position.x, v.position.y, v.position.z).expect("Vertex unwritable");
+				let p = world.transform_point3(v.position.truncated());
+				writeln!(&mut buf, "v {} {} {}", p.x, p.y, p.z).expect("Vertex unwritable");
 			}
 
 			writeln!(&mut buf).expect(BUF_ERR);
 
-			// normals
-			for v in mesh.vertices.iter() {
+			// normals, transformed by the inverse transpose to survive non-uniform scaling
+			let normal_matrix = world.inversed().transposed();
+			let mut normal_indices = vec![None; mesh.vertices.len()];
+			for (i, v) in mesh.vertices.iter().enumerate() {
 				if let Some(n) = v.normal {
+					let n = normal_matrix.transform_vec3(n).normalized();
 					writeln!(&mut buf, "vn {} {} {}", n.x, n.y, n.z).expect("Normal unwritable");
+					normal_indices[i] = Some(normal_base);
+					normal_base += 1;
 				}
 			}
 
 			writeln!(&mut buf).expect(BUF_ERR);
 
 			// UVs
-			for v in mesh.vertices.iter() {
-				if v.uvw.len() > 0 {
-					writeln!(&mut buf, "vt {} {}", v.uvw[0].x, v.uvw[0].y).expect("UV unwritable");
+			let mut uv_indices = vec![None; mesh.vertices.len()];
+			for (i, v) in mesh.vertices.iter().enumerate() {
+				if let Some(uv) = v.uvw.first() {
+					writeln!(&mut buf, "vt {} {}", uv.x, uv.y).expect("UV unwritable");
+					uv_indices[i] = Some(uv_base);
+					uv_base += 1;
 				}
 			}
 
 			writeln!(&mut buf).expect(BUF_ERR);
 
-			// Wavefront uses 1-based indices
-			let mut normal_count = 1usize;
-			let mut uv_count = 1usize;
 			for face in mesh.faces.iter() {
-				if let Face::Triangle(t) = face {
-					write!(&mut buf, "f ").expect(FACE_ERR);
-					for i in t.iter() {
-						write!(&mut buf, "{}", *i + 1).expect("Vertex index unwritable");
-
-						if mesh.vertices[*i].uvw.len() > 0 {
-							write!(&mut buf, "/{}", uv_count).expect("UV index unwritable");
-							uv_count += 1;
-						} else {
-							write!(&mut buf, "/").expect(FACE_ERR);
-						}
-
-						if let Some(_) = mesh.vertices[*i].normal {
-							write!(&mut buf, "/{} ", normal_count).expect("Normal index unwritable");
-							normal_count += 1;
-						} else {
-							write!(&mut buf, " ").expect(FACE_ERR);
-						}
-					}
-					writeln!(&mut buf).expect(FACE_ERR);
+				let indices = match face {
+					Face::Triangle(t) => &t[..],
+					Face::Quad(q) => &q[..],
+					Face::Ngon(n) => &n[..],
+				};
+
+				// faces referencing missing vertices can't be written
+				if indices.iter().any(|i| *i >= mesh.vertices.len()) {
+					continue;
+				}
+
+				write!(&mut buf, "f").expect(FACE_ERR);
+				for i in indices.iter() {
+					write!(&mut buf, " {}", *i + vertex_base).expect("Vertex index unwritable");
+
+					match (uv_indices[*i], normal_indices[*i]) {
+						(Some(uv), Some(n)) => write!(&mut buf, "/{}/{}", uv, n),
+						(Some(uv), None) => write!(&mut buf, "/{}", uv),
+						(None, Some(n)) => write!(&mut buf, "//{}", n),
+						(None, None) => Ok(()),
+					}.expect(FACE_ERR);
 				}
+				writeln!(&mut buf).expect(FACE_ERR);
 			}
 
-			match fs::write(filepath, buf) {
-				Ok(_) => return Ok(()),
-				_ => return Err(IOError {
-					msg: "Unable to write to file".to_string(),
+			writeln!(&mut buf).expect(BUF_ERR);
+			vertex_base += mesh.vertices.len();
+		}
+
+		if !found {
+			return Err(ObjExportError::NoGeometry);
+		}
+
+		out.write_all(buf.as_bytes())?;
+		Ok(())
+	}
+
+	/// [`Exporter`] writing a scene's meshes without their materials
+	pub struct ObjExporter;
+
+	impl Exporter for ObjExporter {
+		fn format(&self) -> &FormatInfo {
+			format::builtin("OBJ").expect("OBJ is a builtin format")
+		}
+
+		fn export(&self, asset: &Asset, out: &mut dyn io::Write) -> Result<(), FormatError> {
+			match asset {
+				Asset::Scene(scene) => write(out, scene).map_err(|e| FormatError::format("OBJ", e)),
+				_ => Err(FormatError::Unsupported {
+					format: "OBJ",
+					kind: asset.kind(),
 				}),
 			}
 		}
-
-		Err(IOError {
-			msg: "No geometry found".to_string(),
-		})
 	}
 
 	#[cfg(test)]
 	mod tests {
+		use rgk_core::scene::{
+			Mesh,
+			Node,
+			Vertex
+		};
+		use ultraviolet::{
+			Vec3,
+			Vec4
+		};
+
+		use super::*;
+
 		#[test]
 		fn test_obj_write() {
-			let test_rip = meshio_ninjaripper::read("../ninjaripper/test_data/Mesh_0015.rip",
-				meshio_ninjaripper::RipImportCfg::default());
-			super::write("Mesh_0015.obj", &test_rip.unwrap()).unwrap();
+			let mut mesh = Mesh::default();
+			for (x, y) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].iter() {
+				let mut v = Vertex::new(None);
+				v.position = Vec4::new(*x, *y, 0.0, 1.0);
+				v.normal = Some(Vec3::unit_z());
+				mesh.vertices.push(v);
+			}
+			mesh.faces = vec![Face::Quad([0, 1, 2, 3]), Face::Triangle([0, 2, 9])];
+
+			let mut quad = Node::new(ObjRef::Name("quad".to_string()), Some(0));
+			quad.translation = Vec3::new(0.0, 0.0, 2.0);
+			quad.data = NodeData::Geometry(mesh);
+
+			let mut root = Node::new(ObjRef::Number(0), None);
+			root.children.push(quad);
 
-			let mut ripcfg = meshio_ninjaripper::RipImportCfg::default();
-			ripcfg.flags |= meshio_ninjaripper::RipImportFlag::FLIP_X;
+			let mut out = vec![];
+			write(&mut out, &Scene::new(root)).unwrap();
+			let text = String::from_utf8(out).unwrap();
+			assert!(text.contains("o quad\nv 0 0 2\nv 1 0 2\n"));
+			assert!(text.contains("\nf 1//1 2//2 3//3 4//4\n\n"));
 
-			let test_rip_flipped = meshio_ninjaripper::read("../ninjaripper/test_data/Mesh_0015.rip",
-				ripcfg);
-			super::write("Mesh_0015_flipped.obj", &test_rip_flipped.unwrap()).unwrap();
+			let empty = Scene::new(Node::new(ObjRef::Number(0), None));
+			assert!(matches!(write(&mut vec![], &empty), Err(ObjExportError::NoGeometry)));
 		}
 	}
 }
 
-/*#[cfg(feature = "import")]
+#[cfg(feature = "import")]
 pub mod import {
+	use std::collections::HashMap;
+
 	use nom::{
-		branch::{
-			alt,
-			permutation,
+		branch::alt,
+		bytes::complete::{
+			tag,
+			take_while1
 		},
-		bytes::complete::tag,
 		character::complete::{
 			char,
-			u32
+			line_ending,
+			not_line_ending,
+			space0,
+			space1
 		},
 		combinator::{
+			cut,
+			eof,
+			map,
 			opt,
 			value
 		},
-		error::{
-			ErrorKind,
-			ParseError
-		},
+		error::context,
 		IResult,
 		multi::{
 			many0,
-			many1,
-			separated_list1
+			many_m_n
 		},
-		number::complete::float,
+		Parser,
 		sequence::{
 			preceded,
+			terminated,
 			tuple
 		}
 	};
@@ -183,193 +280,279 @@ pub mod import {
 		Vec4
 	};
 
+	use rgk_core::{
+		format::{
+			self,
+			Asset,
+			FormatError,
+			FormatInfo,
+			Importer
+		},
+		nom_ext::{
+			hash_comment,
+			parse_all,
+			signed_int,
+			Span,
+			TextError
+		},
+		scene::{
+			Face,
+			Mesh,
+			Node,
+			NodeData,
+			ObjRef,
+			Scene,
+			Vertex
+		}
+	};
+
 	use crate::{
 		etc,
+		hfloat,
 		identifier,
 		uvw
 	};
 
-	use super::ast::*;
-
-	use meshio_core::parser::vec3ws;
-
-	#[derive(Error, Debug, PartialEq)]
+	#[derive(Debug, Error, PartialEq)]
 	pub enum ObjImportError {
-		#[error("Material not found")]
-		Material(String),
-		#[error("Material library not found")]
-		MaterialLibrary(String),
-		#[error("Unknown import error")]
-		Unknown,
-		#[error("Vertex index out of bounds")]
-		VertexIndex(u32),
+		#[error(transparent)]
+		Syntax(#[from] TextError),
+		#[error("Line {line}: index {index} is out of range")]
+		Index {
+			line: u32,
+			index: i64,
+		},
 	}
 
-	impl<I> ParseError<I> for ObjImportError {
-		fn from_error_kind(_: I, _: ErrorKind) -> Self {
-			ObjImportError::Unknown
-		}
+	type ParseResult<'a, O> = IResult<Span<'a>, O, TextError>;
 
-		fn append(_: I, _: ErrorKind, other: Self) -> Self {
-			other
-		}
-	}
+	/// Indices of a face corner's position, UV and normal as written, 1-based or negative
+	type RawCorner = (i64, Option<i64>, Option<i64>);
 
-	/// Parses a 4D vector, of which the last element is optional and defaults to `1.0`, used for vertices
-	fn xyzw(input: &str) -> IResult<&str, Vec4> {
-		let (input, f4vec) =
-            tuple((etc(float), etc(float), etc(float), opt(etc(float))))(input)?;
-
-		Ok((input, Vec4::new(f4vec.0, f4vec.1, f4vec.2, match f4vec.3 {
-			Some(val) => val,
-			_ => 1.0,
-		})))
-	}
+	/// Indices of a face corner's position, UV and normal
+	type Corner = (usize, Option<usize>, Option<usize>);
 
-	/// Parses a face index set
-	fn indices(input: &str) -> IResult<&str, FaceIndex> {
-		let (input, f) = separated_list1(char('/'), opt(u32))(input)?;
-		Ok((input, [
-			match f[0] {
-				Some(f) => f,
-				_ => 0,
-			},
-			match f[1] {
-				Some(f) => f,
-				_ => 0,
-			},
-			match f[2] {
-				Some(f) => f,
-				_ => 0,
-			}
-		]))
+	#[derive(Clone, Debug, PartialEq)]
+	enum Statement {
+		Position(Vec4),
+		Uv(Vec3),
+		Normal(Vec3),
+		Object(String),
+		Face(Vec<RawCorner>),
+
+		/// Anything else, such as materials and smoothing groups
+		Other,
 	}
 
-	/// Parses a mesh
-	fn mesh(input: &str) -> IResult<&str, MeshInfo> {
-		let (input, data) = tuple((
-            opt(etc(usemtl)),
-            opt(etc(s)),
-            opt(many0(etc(f)))
-        ))(input)?;
-		
-		Ok((input, MeshInfo {
-			faces: data.2,
-			material: match data.0 {
-				Some(m) => Some(m.to_string()),
-				_ => None,
-			},
-			smooth: match data.1 {
-				Some(s) => s,
-				_ => false,
-			}
-		}))
+	/// An object being read, with a vertex for each distinct corner
+	struct Object {
+		name: String,
+		mesh: Mesh,
+		corners: HashMap<Corner, usize>,
 	}
 
-	/// Parses an object
-	fn obj(input: &str) -> IResult<&str, ObjectInfo> {
-		let (input, data) = tuple((
-            opt(etc(mtllib)),
-            opt(etc(alt((o, g)))),
-            permutation((
-                opt(many0(etc(v))),
-                opt(many0(etc(vn))),
-                opt(many0(etc(vt))),
-                opt(many0(etc(mesh))),
-            )),
-        ))(input)?;
-
-		println!("{:?}", data);
-		Ok((input, ObjectInfo {
-			name: match data.1 {
-				Some(n) => Some(n.0.to_string()),
-				_ => None,
-			},
-			mat_lib: match data.0 {
-				Some(ml) => Some(ml.to_string()),
-				_ => None,
-			},
-			meshes: data.2.3,
-			vertices: data.2.0,
-			normals: data.2.1,
-			uvs: data.2.2,
-			is_group: match data.1 {
-				Some(yes) => if yes.1 { true } else { false },
-				None => false
+	impl Object {
+		fn new(name: &str) -> Object {
+			Object {
+				name: name.to_string(),
+				mesh: Mesh::default(),
+				corners: HashMap::new(),
 			}
-		}))
+		}
 	}
 
-	/// Parses a face
-	fn f(input: &str) -> IResult<&str, Face> {
-		let (input, f) = preceded(etc(char('f')), alt((
-            many1(etc(indices)),
-            many1(etc(u32)),
-        )))(input)?;
-		
-		Ok((input, [f[0], f[1], f[2]]))
-	}
+	/// Parses a 4D vector, of which the last element is optional and defaults to `1.0`, used for vertices
+	fn xyzw(input: Span) -> ParseResult<Vec4> {
+		let (input, f) = tuple((hfloat, hfloat, hfloat, opt(hfloat)))(input)?;
 
-	/// Parses a group identifier, returning a boolean in addition for `is_group`
-	fn g<'a>(input: &'a str) -> IResult<&'a str, (&'a str, bool)> {
-		let (input, name) = preceded(etc(char('g')), etc(identifier))(input)?;
-		Ok((input, (name, true)))
+		Ok((input, Vec4::new(f.0, f.1, f.2, f.3.unwrap_or(1.0))))
 	}
 
-	/// Parses a poly line
-	fn l(input: &str) -> IResult<&str, Vec<u32>> {
-		preceded(etc(char('l')), many1(etc(u32)))(input)
-	}
+	/// Parses a face corner such as `1`, `1/2`, `1//3` or `1/2/3`
+	fn corner(input: Span) -> ParseResult<RawCorner> {
+		let (input, (v, vt, vn)) = tuple((
+			signed_int,
+			opt(preceded(char('/'), opt(signed_int))),
+			opt(preceded(char('/'), signed_int))
+		))(input)?;
 
-	/// Parses an .mtl file usage declaration
-	fn mtllib<'a>(input: &'a str) -> IResult<&'a str, &'a str> {
-		preceded(etc(tag("mtllib")), etc(identifier))(input)
+		Ok((input, (v, vt.flatten(), vn)))
 	}
 
-	/// Parses an object identifier, returning a boolean in addition for `is_group`
-	fn o<'a>(input: &'a str) -> IResult<&'a str, (&'a str, bool)> {
-		let (input, name) = preceded(etc(char('o')), etc(identifier))(input)?;
-		Ok((input, (name, false)))
+	/// Parses the end of a statement, being the end of its line or a comment
+	fn end(input: Span) -> ParseResult<()> {
+		preceded(space0, alt((value((), line_ending), value((), eof), hash_comment)))(input)
 	}
 
-	/// Parses a smoothing flag
-	fn s(input: &str) -> IResult<&str, bool> {
-		preceded(etc(char('s')), alt((
-            value(false, etc(alt((tag("off"), char('0'))))),
-            value(true, etc(alt((tag("on"), char('1'))))),
-        )))(input)
+	/// Parses a statement starting with `keyword`, committing to it once the keyword matched
+	fn keyword<'a, F, O>(keyword: &'static str, name: &'static str, inner: F)
+		-> impl FnMut(Span<'a>) -> ParseResult<'a, O>
+	where
+		F: Parser<Span<'a>, O, TextError>,
+	{
+		preceded(terminated(tag(keyword), space1), cut(context(name, terminated(inner, end))))
 	}
 
-	/// Parses a material usage declaration
-	fn usemtl<'a>(input: &'a str) -> IResult<&'a str, &'a str> {
-		preceded(etc(tag("usemtl")), etc(identifier))(input)
+	/// Parses a statement, returning it with its line
+	fn statement(input: Span) -> ParseResult<(u32, Statement)> {
+		let line = input.location_line();
+		let (input, s) = alt((
+			keyword("vt", "texture coordinate", map(uvw, Statement::Uv)),
+			keyword("vn", "normal", map(uvw, Statement::Normal)),
+			keyword("v", "vertex", map(xyzw, Statement::Position)),
+			keyword("f", "face", map(many_m_n(3, usize::MAX, preceded(space0, corner)), Statement::Face)),
+			// only the first of several group names is kept
+			keyword("o", "object", map(terminated(identifier, not_line_ending), |n: Span| {
+				Statement::Object(n.fragment().to_string())
+			})),
+			keyword("g", "group", map(terminated(identifier, not_line_ending), |n: Span| {
+				Statement::Object(n.fragment().to_string())
+			})),
+			value(Statement::Other, terminated(take_while1(|c: char| !c.is_whitespace()), not_line_ending))
+		))(input)?;
+
+		Ok((input, (line, s)))
 	}
 
-	/// Parses a vertex
-	fn v(input: &str) -> IResult<&str, Vec4> {
-		preceded(etc(char('v')), etc(xyzw))(input)
-	}
+	/// Resolves a 1-based index, or a negative one counting back from the last element
+	fn resolve(line: u32, index: i64, len: usize) -> Result<usize, ObjImportError> {
+		let resolved = if index < 0 { len as i64 + index } else { index - 1 };
+
+		if resolved < 0 || resolved >= len as i64 {
+			return Err(ObjImportError::Index {
+				line,
+				index,
+			});
+		}
 
-	/// Parses a normal
-	fn vn(input: &str) -> IResult<&str, Vec3> {
-		preceded(etc(tag("vn")), etc(vec3ws))(input)
+		Ok(resolved as usize)
 	}
 
-	fn vp(input: &str) -> IResult<&str, Vec3> {
-		preceded(etc(tag("vp")), etc(uvw))(input)
+	/// Reads an OBJ file into a scene, with a node for each object or group. Materials, smoothing groups,
+	/// lines and free-form geometry are skipped.
+	pub fn read(input: &str) -> Result<Scene, ObjImportError> {
+		let statements = parse_all(input, terminated(many0(etc(statement)), etc(eof)))?;
+
+		let mut positions: Vec<Vec4> = vec![];
+		let mut uvs: Vec<Vec3> = vec![];
+		let mut normals: Vec<Vec3> = vec![];
+		let mut objects = vec![Object::new("default")];
+
+		for (line, statement) in statements.into_iter() {
+			match statement {
+				Statement::Position(p) => positions.push(p),
+				Statement::Uv(uv) => uvs.push(uv),
+				Statement::Normal(n) => normals.push(n),
+				Statement::Object(name) => {
+					let current = objects.last_mut().expect("there's always an object");
+					if current.mesh.faces.is_empty() {
+						current.name = name;
+					} else {
+						objects.push(Object::new(&name));
+					}
+				},
+				Statement::Face(corners) => {
+					let object = objects.last_mut().expect("there's always an object");
+					let mut face = Vec::with_capacity(corners.len());
+
+					for (v, vt, vn) in corners.into_iter() {
+						let v = resolve(line, v, positions.len())?;
+						let vt = vt.map(|i| resolve(line, i, uvs.len())).transpose()?;
+						let vn = vn.map(|i| resolve(line, i, normals.len())).transpose()?;
+
+						let mesh = &mut object.mesh;
+						let index = *object.corners.entry((v, vt, vn)).or_insert_with(|| {
+							let mut vertex = Vertex::new(None);
+							vertex.position = positions[v];
+							vertex.uvw = vt.map(|i| vec![uvs[i]]).unwrap_or_default();
+							vertex.normal = vn.map(|i| normals[i]);
+							mesh.vertices.push(vertex);
+							mesh.vertices.len() - 1
+						});
+						face.push(index);
+					}
+
+					object.mesh.faces.push(match face.len() {
+						3 => Face::Triangle([face[0], face[1], face[2]]),
+						4 => Face::Quad([face[0], face[1], face[2], face[3]]),
+						_ => Face::Ngon(face),
+					});
+				},
+				Statement::Other => (),
+			}
+		}
+
+		let mut root = Node::new(ObjRef::Number(0), None);
+		for object in objects.into_iter().filter(|o| !o.mesh.faces.is_empty()) {
+			let mut node = Node::new(ObjRef::Name(object.name), Some(0));
+			node.data = NodeData::Geometry(object.mesh);
+			root.children.push(node);
+		}
+
+		Ok(Scene::new(root))
 	}
 
-	/// Parses a UV
-	fn vt(input: &str) -> IResult<&str, Vec3> {
-		preceded(etc(tag("vt")), etc(uvw))(input)
+	/// [`Importer`] reading a scene's meshes without their materials
+	pub struct ObjImporter;
+
+	impl Importer for ObjImporter {
+		fn format(&self) -> &FormatInfo {
+			format::builtin("OBJ").expect("OBJ is a builtin format")
+		}
+
+		fn import(&self, data: &[u8]) -> Result<Asset, FormatError> {
+			let text = String::from_utf8_lossy(data);
+			let scene = read(&text).map_err(|e| FormatError::format("OBJ", e))?;
+			Ok(Asset::Scene(Box::new(scene)))
+		}
 	}
 
 	#[cfg(test)]
 	mod tests {
+		use super::*;
+
 		#[test]
-		fn test_cube_usemtl() {
-			let cube_obj = include_str!("../test_data/cube_usemtl.obj");
-			println!("{:?}", super::obj(cube_obj).unwrap().1);
+		fn test_obj_read() {
+			let scene = read("# two objects\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0 1\nvt 0 0\nvt 1 1\nvn 0 0 1\n\
+				o quad\nusemtl red\nf 1/1/1 2/2/1 3/1/1 4/2/1 # comment\r\n\
+				g tri\nf -4//1 -3//1 -2//1\nf 1//1 2//1 3//1").unwrap();
+
+			assert_eq!(scene.root.children.len(), 2);
+			assert_eq!(scene.root.children[0].id, ObjRef::Name("quad".to_string()));
+
+			let quad = match &scene.root.children[0].data {
+				NodeData::Geometry(m) => m,
+				_ => panic!(),
+			};
+			assert_eq!(quad.faces, vec![Face::Quad([0, 1, 2, 3])]);
+			assert_eq!(quad.vertices[1].uvw, vec![Vec3::new(1.0, 1.0, 0.0)]);
+			assert_eq!(quad.vertices[3].position, Vec4::new(0.0, 1.0, 0.0, 1.0));
+
+			// identical corners share a vertex
+			let tri = match &scene.root.children[1].data {
+				NodeData::Geometry(m) => m,
+				_ => panic!(),
+			};
+			assert_eq!(tri.vertices.len(), 3);
+			assert_eq!(tri.faces, vec![Face::Triangle([0, 1, 2]); 2]);
+			assert_eq!(tri.vertices[0].normal, Some(Vec3::unit_z()));
+		}
+
+		#[test]
+		fn test_obj_errors() {
+			assert_eq!(read("v 0 0 0\nf 1 2 3\n").unwrap_err(), ObjImportError::Index {
+				line: 2,
+				index: 2,
+			});
+
+			let syntax = |src: &str| match read(src).unwrap_err() {
+				ObjImportError::Syntax(e) => (e.line, e.column, e.context),
+				e => panic!("{:?}", e),
+			};
+			assert_eq!(syntax("v 0 0 0\nv 0 x 0\n"), (2, 5, vec!["vertex"]));
+			// the third corner is missing at the end of the line
+			assert_eq!(syntax("v 0 0 0\n\n  f 1 1\n"), (3, 8, vec!["face"]));
+			assert_eq!(syntax("vn 0 0 1 junk\n"), (1, 10, vec!["normal"]));
 		}
 	}
-}*/
+}
@@ -1,32 +1,44 @@
 use byteorder::{
 	BE,
-	ReadBytesExt,
-	WriteBytesExt
+	ReadBytesExt
 };
 
+#[cfg(feature = "export")]
+use byteorder::WriteBytesExt;
+
 use std::{
 	fs::{
-		File,
-		OpenOptions,
 		read,
 		write
 	},
-	io::{
-		Read,
-		Seek,
-		SeekFrom,
-		self,
-		Write
-	}
+	io
 };
 
 use thiserror::Error;
 
-use rgk_core::tag4;
+use rgk_core::{
+	input::IntoInput,
+	tag4
+};
+
+#[cfg(all(feature = "import", feature = "export"))]
+use rgk_core::format::{
+	self,
+	FormatError,
+	FormatInfo,
+	Patcher
+};
 
 pub const MAGIC: [u8; 5] = [b'P', b'A', b'T', b'C', b'H'];
 pub const EOF_MARKER: u32 = tag4!(b"\x00EOF");
 
+/// Largest file size a patch can address
+pub const MAX_SIZE: usize = 1 << 24;
+
+/// Shortest run of a repeated byte worth storing as RLE
+#[cfg(feature = "export")]
+const MIN_RLE: usize = 8;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum RecData {
 	Uncompressed(Vec<u8>),
@@ -57,10 +69,7 @@ impl RecData {
 			RecData::Uncompressed(data) => buf.write_all(data)?,
 			RecData::RLE(count, value) => {
 				buf.write_u16::<BE>(*count)?;
-
-				for _ in 0..(*count as usize) {
-					buf.write_u8(*value)?
-				}
+				buf.write_u8(*value)?;
 			},
 		}
 
@@ -105,116 +114,104 @@ impl Record {
 pub struct IPSPatch {
 	pub magic: [u8; 5],
 	pub records: Vec<Record>,
+
+	/// Size to cut the patched file to, a common extension after the EOF marker
+	pub truncate: Option<u32>,
 }
 
 impl IPSPatch {
-	/// Constructs a new patch from the source and target files
+	/// Constructs a new patch from the source and target files, or any other input
 	#[cfg(feature = "export")]
-	pub fn new<'a>(source: &'a str, target: &'a str) -> io::Result<IPSPatch> {
-		let src = File::open(source)?;
-		let tgt = File::open(target)?;
+	pub fn new<'a, S, T>(source: S, target: T) -> io::Result<IPSPatch>
+	where
+		S: IntoInput<'a>,
+		T: IntoInput<'a>,
+	{
+		IPSPatch::diff(&source.into_input()?, &target.into_input()?)
+	}
+
+	/// Constructs a new patch turning `source` into `target`. Runs of a repeated byte are RLE encoded.
+	#[cfg(feature = "export")]
+	pub fn diff(source: &[u8], target: &[u8]) -> io::Result<IPSPatch> {
+		if target.len() > MAX_SIZE {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "IPS patches can't address past 16 MiB"));
+		}
+
 		let mut recs = vec![];
+		let mut i = 0;
+
+		while i < target.len() {
+			if i < source.len() && source[i] == target[i] {
+				i += 1;
+				continue;
+			}
+
+			let start = i;
+			while i < target.len() && (i >= source.len() || source[i] != target[i]) {
+				i += 1;
+			}
+
+			// split the changed run into RLE and uncompressed records
+			let mut literal = start;
+			let mut j = start;
+			while j < i {
+				let mut k = j + 1;
+				while k < i && target[k] == target[j] && k - j < u16::MAX as usize {
+					k += 1;
+				}
 
-		let mut diff_buf = vec![];
-
-		// we need to compare the offsets as well as the bytes
-		for ((_, sb), (ti, tb)) in src.bytes().enumerate().zip(tgt.bytes().enumerate()) {
-			let src_b = sb?;
-			let tgt_b = tb?;
-
-			if src_b != tgt_b {
-				diff_buf.push((ti, tgt_b));
-			} else {
-				// examine diff buffer before we continue
-				if diff_buf.len() > 0 {
-					let mut last_byte = None;
-					let mut rep_count = 0;
-					let mut cache: Vec<(usize, u8)> = vec![]; // to cache non-repeating byte strings
-
-					// try to use RLE compression
-					for (offs, b) in diff_buf.iter() {
-						if let Some(lb) = last_byte {
-							if *b == lb {
-								if cache.len() > 0 {
-									// handle the cached byte string before continuing
-									recs.push(Record {
-										offset: cache[0].0 as u32,
-										size: cache.len() as u16,
-										data: RecData::Uncompressed(cache.iter()
-											.map(|(_, cb)| *cb).collect()),
-									});
-
-									cache.clear();
-								}
-
-								if rep_count > 0 {
-									rep_count += 1;
-								} else {
-									rep_count = 2;
-								}
-							} else {
-								if rep_count > 0 {
-									// push an RLE record
-									recs.push(Record {
-										offset: cache[0].0 as u32,
-										size: 0,
-										data: RecData::RLE(rep_count as u16, lb),
-									});
-
-									rep_count = 0;
-									cache.push((*offs, *b)); // push the new byte
-								} else {
-									cache.push((*offs, *b)); // just another byte
-								}
-							}
-						} else {
-							cache.push((*offs, *b));
-						}
-
-						last_byte = Some(*b);
-					}
-
-					if cache.len() > 0 {
-						// take care of any data left in cache
-						recs.push(Record {
-							offset: cache[0].0 as u32,
-							size: cache.len() as u16,
-							data: RecData::Uncompressed(cache.iter().map(|(_, cb)| *cb).collect()),
-						});
-					}
-
-					diff_buf.clear();
+				if k - j >= MIN_RLE && j != EOF_MARKER as usize {
+					push_uncompressed(&mut recs, target, literal, j);
+					recs.push(Record {
+						offset: j as u32,
+						size: 0,
+						data: RecData::RLE((k - j) as u16, target[j]),
+					});
+					literal = k;
 				}
+
+				j = k;
 			}
+
+			push_uncompressed(&mut recs, target, literal, i);
 		}
 
 		Ok(IPSPatch {
 			magic: MAGIC,
 			records: recs,
+			truncate: if target.len() < source.len() { Some(target.len() as u32) } else { None },
 		})
 	}
 
 	/// Applies this patch to a file
 	pub fn apply<'a, 'b>(&self, input: &'a str, output: &'b str) -> io::Result<()> {
-		// copy the contents
-		write(output, read(input)?)?;
+		let mut data = read(input)?;
+		self.apply_to(&mut data);
+		write(output, data)
+	}
 
-		// patch the copy
-		let mut patched = OpenOptions::new().append(true).open(output)?;
+	/// Applies this patch in memory, growing `data` as needed
+	pub fn apply_to(&self, data: &mut Vec<u8>) {
 		for rec in self.records.iter() {
-			let _ = patched.seek(SeekFrom::Start(rec.offset as u64))?;
+			let offset = rec.offset as usize;
+			let end = offset + match &rec.data {
+				RecData::Uncompressed(bytes) => bytes.len(),
+				RecData::RLE(length, _) => *length as usize,
+			};
+
+			if data.len() < end {
+				data.resize(end, 0);
+			}
 
 			match &rec.data {
-				RecData::Uncompressed(data) => patched.write_all(&data)?,
-				RecData::RLE(length, value) => {
-					for _ in 0..(*length as usize) {
-						patched.write_u8(*value)?;
-					}
-				},
-			};
+				RecData::Uncompressed(bytes) => data[offset..end].copy_from_slice(bytes),
+				RecData::RLE(_, value) => data[offset..end].fill(*value),
+			}
 		}
 
-		Ok(())
+		if let Some(size) = self.truncate {
+			data.truncate(size as usize);
+		}
 	}
 
 	#[cfg(feature = "import")]
@@ -230,15 +227,19 @@ impl IPSPatch {
 
 		let mut recs = vec![];
 
-		while let Ok(offset) = buf.read_u24::<BE>() {
-			if offset != EOF_MARKER {
-				recs.push(Record::read(offset, buf)?);
+		loop {
+			let offset = buf.read_u24::<BE>()?;
+			if offset == EOF_MARKER {
+				break;
 			}
+
+			recs.push(Record::read(offset, buf)?);
 		}
 
 		Ok(IPSPatch {
 			magic: magic,
 			records: recs,
+			truncate: buf.read_u24::<BE>().ok(),
 		})
 	}
 
@@ -253,7 +254,57 @@ impl IPSPatch {
 			rec.write(buf)?;
 		}
 
-		buf.write_u24::<BE>(EOF_MARKER)
+		buf.write_u24::<BE>(EOF_MARKER)?;
+
+		if let Some(size) = self.truncate {
+			buf.write_u24::<BE>(size)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Pushes uncompressed records covering `target[start..end]`
+#[cfg(feature = "export")]
+fn push_uncompressed(recs: &mut Vec<Record>, target: &[u8], start: usize, end: usize) {
+	let mut pos = start;
+
+	while pos < end {
+		// an offset spelling "EOF" would end the patch early, so rewrite the preceding byte as well
+		let offset = if pos == EOF_MARKER as usize { pos - 1 } else { pos };
+		let size = (end - offset).min(u16::MAX as usize);
+
+		recs.push(Record {
+			offset: offset as u32,
+			size: size as u16,
+			data: RecData::Uncompressed(target[offset..offset + size].to_vec()),
+		});
+
+		pos = offset + size;
+	}
+}
+
+/// [`Patcher`] for IPS patches
+#[cfg(all(feature = "import", feature = "export"))]
+pub struct IPSPatcher;
+
+#[cfg(all(feature = "import", feature = "export"))]
+impl Patcher for IPSPatcher {
+	fn format(&self) -> &FormatInfo {
+		format::builtin("IPS").expect("IPS is a builtin format")
+	}
+
+	fn apply(&self, patch: &[u8], source: &[u8]) -> Result<Vec<u8>, FormatError> {
+		let patch = IPSPatch::read(&mut &patch[..]).map_err(|e| FormatError::format("IPS", e))?;
+		let mut data = source.to_vec();
+		patch.apply_to(&mut data);
+		Ok(data)
+	}
+
+	fn diff(&self, source: &[u8], target: &[u8]) -> Result<Vec<u8>, FormatError> {
+		let mut out = vec![];
+		IPSPatch::diff(source, target)?.write(&mut out)?;
+		Ok(out)
 	}
 }
 
@@ -285,3 +336,41 @@ fn test_write_ips() {
 	let mut f = File::create("test_data/test_write.ips").unwrap();
 	ips.write(&mut f).unwrap();
 }
+
+#[cfg(all(feature = "import", feature = "export"))]
+#[test]
+fn test_diff_roundtrip() {
+	let source: Vec<u8> = (0..=255).cycle().take(1000).collect();
+	let mut target = source.clone();
+	target[10] = 0;
+	target[500..600].fill(0xAA);
+	target.extend_from_slice(b"new data");
+
+	let ips = IPSPatch::diff(&source, &target).unwrap();
+	assert_eq!(ips.records.len(), 3);
+	assert_eq!(ips.records[1].data, RecData::RLE(100, 0xAA));
+
+	let mut out = vec![];
+	ips.write(&mut out).unwrap();
+	let read_back = IPSPatch::read(&mut out.as_slice()).unwrap();
+	assert_eq!(read_back, ips);
+
+	let mut patched = source.clone();
+	read_back.apply_to(&mut patched);
+	assert_eq!(patched, target);
+
+	// shrinking, and a change right at the offset that reads as "EOF"
+	let source = vec![0; EOF_MARKER as usize + 16];
+	let mut target = source[..EOF_MARKER as usize + 8].to_vec();
+	target[EOF_MARKER as usize] = 1;
+
+	let ips = IPSPatch::diff(&source, &target).unwrap();
+	assert_eq!(ips.records[0].offset, EOF_MARKER - 1);
+	assert_eq!(ips.truncate, Some(EOF_MARKER + 8));
+
+	let mut out = vec![];
+	ips.write(&mut out).unwrap();
+	let mut patched = source.clone();
+	IPSPatch::read(&mut out.as_slice()).unwrap().apply_to(&mut patched);
+	assert_eq!(patched, target);
+}
@@ -13,6 +13,15 @@ use rgk_core::{
 	texture::Texture
 };
 
+#[cfg(feature = "export")]
+use rgk_core::format::{
+	self,
+	Asset,
+	Exporter,
+	FormatError,
+	FormatInfo
+};
+
 pub const HEADER_SIZE: u32 = 40;
 pub const NUM_COLOR_PANES: u16 = 1;
 
@@ -223,7 +232,7 @@ impl Bitmap {
 	/// Creates a new [`Bitmap`] object with a bit depth of 24
 	#[cfg(feature = "export")]
 	pub fn new_24bpp(tex: &Texture) -> Bitmap {
-		let img_size = (((tex.width * 3) + 1) * tex.height) as u32;
+		let img_size = (row_size(tex.width) * tex.height) as u32;
 
 		Bitmap {
 			header: Header {
@@ -261,17 +270,47 @@ impl Bitmap {
 		self.header.write(buf)?;
 		self.info_header.write(buf)?;
 
-		for y in 0..(self.info_header.height as usize) {
-			for x in 0..(self.info_header.width as usize) {
-				buf.write_u24::<LE>(self.pixels[(y * (self.info_header.width as usize)) + x])?;
+		// rows are stored bottom up, each padded to a multiple of 4 bytes
+		let width = self.info_header.width as usize;
+		let padding = vec![0; row_size(width) - width * 3];
+		for y in (0..(self.info_header.height as usize)).rev() {
+			for x in 0..width {
+				buf.write_u24::<LE>(self.pixels[(y * width) + x])?;
 			}
-			buf.write_u8(0)?;
+			buf.write_all(&padding)?;
 		}
 
 		Ok(())
 	}
 }
 
+/// Size in bytes of a row of 24 bit pixels
+#[cfg(feature = "export")]
+fn row_size(width: usize) -> usize {
+	(width * 3 + 3) & !3
+}
+
+/// [`Exporter`] writing 24 bit bitmaps
+#[cfg(feature = "export")]
+pub struct BitmapExporter;
+
+#[cfg(feature = "export")]
+impl Exporter for BitmapExporter {
+	fn format(&self) -> &FormatInfo {
+		format::builtin("BMP").expect("BMP is a builtin format")
+	}
+
+	fn export(&self, asset: &Asset, mut out: &mut dyn io::Write) -> Result<(), FormatError> {
+		match asset {
+			Asset::Texture(tex) => Ok(Bitmap::new_24bpp(tex).write(&mut out)?),
+			_ => Err(FormatError::Unsupported {
+				format: "BMP",
+				kind: asset.kind(),
+			}),
+		}
+	}
+}
+
 #[cfg(feature = "import")]
 #[derive(Error, Debug)]
 pub enum BitmapImportError {
@@ -289,6 +328,7 @@ pub enum BitmapImportError {
 }
 
 #[test]
+#[ignore = "needs a screenshot in test_data"]
 fn test_read_bmp() {
 	use std::fs::read;
 
@@ -300,11 +340,16 @@ fn test_read_bmp() {
 #[test]
 fn test_write_bmp() {
 	use rgk_textures_playstation::read_tim;
-	use std::fs::File;
 
-	let tim = read_tim("test_data/3DFX.TIM").unwrap();
-	let bmp = Bitmap::new_24bpp(&tim);
-	println!("{:#?}", &bmp);
-	let mut f = File::create("test_data/3DFX.bmp").unwrap();
-	bmp.write(&mut f).unwrap();
+	// 3x2 16 bit TIM of red, green, blue over white, black, red
+	let mut data = b"\x10\x00\x00\x00\x02\x00\x00\x00\x18\x00\x00\x00\x00\x00\x00\x00\x03\x00\x02\x00".to_vec();
+	data.extend_from_slice(b"\x1f\x00\xe0\x03\x00\x7c\xff\x7f\x00\x00\x1f\x00");
+
+	let tim = read_tim(data.as_slice()).unwrap();
+	let mut out = vec![];
+	Bitmap::new_24bpp(&tim).write(&mut out).unwrap();
+
+	assert_eq!(&out[2..6], &78u32.to_le_bytes());
+	assert_eq!(&out[54..], b"\xff\xff\xff\x00\x00\x00\x00\x00\xff\x00\x00\x00\
+		\x00\x00\xff\x00\xff\x00\xff\x00\x00\x00\x00\x00");
 }
@@ -1,48 +1,120 @@
 pub mod tim;
 
-use std::fs;
-
-use rgk_core::texture::{
-	Color,
-	find_palette_index,
-	Texture
+use rgk_core::{
+	format::{
+		self,
+		Asset,
+		FormatError,
+		FormatInfo,
+		Importer
+	},
+	input::IntoInput,
+	texture::{
+		Color,
+		PixelData,
+		Texture
+	}
 };
 
 use tim::*;
 
+/// Reads a TIM texture from a path, buffer or stream
+#[cfg(feature = "import")]
+pub fn read_tim<'a, I: IntoInput<'a>>(input: I) -> Result<Texture, TIMImportError> {
+	let input = input.into_input()?;
+	Ok(to_texture(&PSXTexture::read(&mut input.as_slice())?))
+}
+
 #[cfg(feature = "import")]
-pub fn read_tim(filepath: &str) -> Result<Texture, TIMImportError> {
-	let input = fs::read(filepath)?;
-	let tex = PSXTexture::read(&mut input.as_slice())?;
+fn to_texture(tex: &PSXTexture) -> Texture {
+	let width = tex.img_header.pixel_width(tex.header.flags.bpp());
+	let mut texture = Texture::new(width, tex.img_header.height as usize);
 
-	let mut texture = Texture::new((tex.img_header.width / 2) as usize, tex.img_header.height as usize);
+	if let Some(ref palette) = tex.palette {
+		texture.palette = palette.iter().map(|c| Color::from_rgba5551(*c)).collect();
+	}
 
-	if tex.header.flags.contains(Flags::INDEXED) {
-		if let Some(ref palette) = tex.palette {
-			texture.palette = palette.iter().map(|c| Color::from_rgba5551(*c)).collect();
-		}
+	match tex.data {
+		ImageData::Indexed(ref indices) => texture.data = PixelData::Indexed(indices.iter()
+			.map(|i| *i as usize)
+			.collect()),
+		ImageData::BPP16(ref colors) => texture.data = PixelData::Rgba5551(colors.clone()),
+		ImageData::BPP24(ref colors) => texture.data = PixelData::Rgba8(colors.iter()
+			.map(|c| [*c as u8, (*c >> 8) as u8, (*c >> 16) as u8, 255])
+			.collect()),
+	}
+
+	texture.optimize();
+	texture
+}
+
+/// [`Importer`] for TIM textures
+#[cfg(feature = "import")]
+pub struct TIMImporter;
+
+#[cfg(feature = "import")]
+impl Importer for TIMImporter {
+	fn format(&self) -> &FormatInfo {
+		format::builtin("TIM").expect("TIM is a builtin format")
+	}
 
-		if let ImageData::Indexed(ref indices) = tex.data {
-			texture.indices = indices.iter().map(|i| *i as usize).collect();
+	fn import(&self, data: &[u8]) -> Result<Asset, FormatError> {
+		let tex = PSXTexture::read(&mut &data[..]).map_err(|e| FormatError::format("TIM", e))?;
+		Ok(Asset::Texture(to_texture(&tex)))
+	}
+
+	fn headers(&self, data: &[u8]) -> Result<Option<String>, FormatError> {
+		let tex = PSXTexture::read(&mut &data[..]).map_err(|e| FormatError::format("TIM", e))?;
+		let mut headers = format!("{:#?}\n", tex.header);
+		if let Some(palette) = tex.palette_header {
+			headers += &format!("{:#?}\n", palette);
 		}
-	} else if tex.header.flags.contains(Flags::BPP_16) {
-		if let ImageData::BPP16(ref colors) = tex.data {
-			for color in colors.iter() {
-				// Since these are direct color values, we'll have to build a palette.
-
-				let c = Color::from_rgba5551(*color);
-
-				if let Some(i) = find_palette_index(&texture.palette, &c) {
-					texture.indices.push(i);
-				} else {
-					let i = texture.palette.len();
-					texture.palette.push(c);
-					texture.indices.push(i);
-				}
+		headers += &format!("{:#?}", tex.img_header);
+
+		Ok(Some(headers))
+	}
+}
+
+#[cfg(all(test, feature = "import"))]
+mod tests {
+	use super::*;
+
+	/// Builds a 2 row TIM `words` 16 bit words wide, filled with counting bytes
+	fn tim(flags: u32, words: u16) -> Vec<u8> {
+		let mut data = vec![0x10, 0, 0, 0];
+		data.extend_from_slice(&flags.to_le_bytes());
+
+		if flags & 8 != 0 {
+			let colors: u16 = if flags & 3 == 0 { 16 } else { 256 };
+			for v in [12 + colors as u32 * 2, 0, colors as u32 | 1 << 16] {
+				data.extend_from_slice(&v.to_le_bytes());
 			}
+			data.extend((0..colors).flat_map(|c| c.to_le_bytes()));
 		}
+
+		for v in [12 + words as u32 * 4, 0, words as u32 | 2 << 16] {
+			data.extend_from_slice(&v.to_le_bytes());
+		}
+		data.extend((0..words * 4).map(|b| b as u8));
+		data
 	}
 
-	texture.optimize();
-	Ok(texture)
+	#[test]
+	fn test_dimensions() {
+		for (flags, width) in [(8, 12), (9, 6), (2, 3), (3, 2)] {
+			let tex = read_tim(tim(flags, 3).as_slice()).unwrap();
+			assert_eq!((tex.width, tex.height, tex.data.len()), (width, 2, width * 2), "flags {}", flags);
+		}
+
+		// 4 BPP indices are low nibble first
+		let tex = read_tim(tim(8, 3).as_slice()).unwrap();
+		assert_eq!(tex.data, PixelData::Indexed(vec![0, 0, 1, 0, 2, 0, 3, 0, 4, 0, 5, 0,
+			6, 0, 7, 0, 8, 0, 9, 0, 10, 0, 11, 0]));
+
+		// 5 pixels of 24 BPP take 15 bytes, so each row ends in a padding byte
+		let tex = read_tim(tim(3, 8).as_slice()).unwrap();
+		assert_eq!((tex.width, tex.data.len()), (5, 10));
+		assert_eq!(tex.pixels()[4].to_rgba8(), [12, 13, 14, 255]);
+		assert_eq!(tex.pixels()[5].to_rgba8(), [16, 17, 18, 255]);
+	}
 }
@@ -1,6 +1,7 @@
 use bitflags::bitflags;
 
 use byteorder::{
+	ByteOrder,
 	LE,
 	ReadBytesExt
 };
@@ -21,6 +22,18 @@ bitflags! {
 	}
 }
 
+impl Flags {
+	/// Bits per pixel given by the depth bits, which are a number rather than separate flags
+	pub fn bpp(&self) -> u32 {
+		match self.bits() & 3 {
+			0 => 4,
+			1 => 8,
+			2 => 16,
+			_ => 24,
+		}
+	}
+}
+
 #[cfg(feature = "import")]
 #[derive(Debug, Error)]
 pub enum TIMImportError {
@@ -69,6 +82,11 @@ pub struct SubHeader {
 }
 
 impl SubHeader {
+	/// Width in pixels at `bpp` bits per pixel, as `width` counts 16 bit words of VRAM
+	pub fn pixel_width(&self, bpp: u32) -> usize {
+		self.width as usize * 16 / bpp as usize
+	}
+
 	#[cfg(feature = "import")]
 	fn read<R>(buf: &mut R) -> Result<SubHeader, TIMImportError>
 	where
@@ -97,31 +115,26 @@ impl ImageData {
 	where
 		R: ReadBytesExt,
 	{
-		if header.flags.contains(Flags::INDEXED) {
-			let mut indices = vec![];
-
-			for _ in 0..((img_header.width * img_header.height) as usize) {
-				indices.push(buf.read_u8()?);
-			}
-
-			Ok(ImageData::Indexed(indices))
-		} else if header.flags.contains(Flags::BPP_16) {
-			let mut rgb = vec![];
-
-			for _ in 0..((img_header.width * img_header.height) as usize) {
-				rgb.push(buf.read_u16::<LE>()?);
-			}
-
-			Ok(ImageData::BPP16(rgb))
-		} else { // 24 BPP
-			let mut rgb = vec![];
-
-			for _ in 0..((img_header.width * img_header.height) as usize) {
-				rgb.push(buf.read_u24::<LE>()?);
-			}
-
-			Ok(ImageData::BPP24(rgb))
+		let bpp = header.flags.bpp();
+		let width = img_header.pixel_width(bpp);
+		let height = img_header.height as usize;
+
+		// rows are whole 16 bit words, so 24 BPP rows may end in a padding byte
+		let mut row = vec![0; img_header.width as usize * 2];
+		let mut rows = Vec::with_capacity(row.len() * height);
+		for _ in 0..height {
+			buf.read_exact(&mut row)?;
+			rows.extend_from_slice(&row);
 		}
+
+		Ok(match bpp {
+			4 => ImageData::Indexed(rows.iter().flat_map(|b| [b & 0xF, b >> 4]).collect()),
+			8 => ImageData::Indexed(rows),
+			16 => ImageData::BPP16(rows.chunks_exact(2).map(LE::read_u16).collect()),
+			_ => ImageData::BPP24(rows.chunks_exact(row.len())
+				.flat_map(|r| r.chunks_exact(3).take(width).map(LE::read_u24))
+				.collect()),
+		})
 	}
 }
 
@@ -143,24 +156,19 @@ impl PSXTexture {
 		let header = Header::read(buf)?;
 
 		let clut_header: Option<SubHeader>;
-		let mut clut: Option<Vec<u16>>;
+		let clut: Option<Vec<u16>>;
 
 		if header.flags.contains(Flags::INDEXED) {
-			clut_header = Some(SubHeader::read(buf)?);
-			clut = Some(vec![]);
-
-			if let Some(ref mut c) = clut {
-				// 8 BPP check must come before 4 BPP because Flags.contains() considers both present
-				if header.flags.contains(Flags::BPP_8) {
-					for _ in 0..256 {
-						c.push(buf.read_u16::<LE>()?);
-					}
-				} else { // 4 BPP
-					for _ in 0..16 {
-						c.push(buf.read_u16::<LE>()?);
-					}
-				}
+			let h = SubHeader::read(buf)?;
+			let mut c = vec![];
+
+			// every palette in the CLUT, each 16 or 256 colors wide
+			for _ in 0..(h.width as usize * h.height as usize) {
+				c.push(buf.read_u16::<LE>()?);
 			}
+
+			clut_header = Some(h);
+			clut = Some(c);
 		} else {
 			clut_header = None;
 			clut = None;
@@ -0,0 +1,133 @@
+use std::{
+	fs,
+	path::PathBuf
+};
+
+use clap::{
+	Args,
+	ValueEnum
+};
+
+use rgk_core::{
+	format::{
+		Asset,
+		FormatError,
+		Registry
+	},
+	input::Input,
+	scene::{
+		coords::Handedness,
+		NodeData,
+		Scene,
+		UpAxis
+	}
+};
+
+use crate::CliError;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Up {
+	Y,
+	Z,
+}
+
+impl From<Up> for UpAxis {
+	fn from(up: Up) -> UpAxis {
+		match up {
+			Up::Y => UpAxis::Y,
+			Up::Z => UpAxis::Z,
+		}
+	}
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Hand {
+	Left,
+	Right,
+}
+
+impl From<Hand> for Handedness {
+	fn from(hand: Hand) -> Handedness {
+		match hand {
+			Hand::Left => Handedness::Left,
+			Hand::Right => Handedness::Right,
+		}
+	}
+}
+
+#[derive(Args)]
+pub struct ConvertArgs {
+	/// File to read
+	input: PathBuf,
+
+	/// File to write, its extension choosing the format
+	output: PathBuf,
+
+	/// Input format, for files that can't be detected
+	#[clap(long)]
+	from: Option<String>,
+
+	/// Up axis of the converted scene
+	#[clap(long, value_enum)]
+	up: Option<Up>,
+
+	/// Handedness of the converted scene
+	#[clap(long, value_enum)]
+	handedness: Option<Hand>,
+
+	/// Length of one unit of the converted scene in metres, such as 0.01 for centimetres
+	#[clap(long)]
+	unit_scale: Option<f32>,
+
+	/// Splits quads and polygons into triangles
+	#[clap(long)]
+	triangulate: bool,
+}
+
+impl ConvertArgs {
+	fn transforms_scene(&self) -> bool {
+		self.up.is_some() || self.handedness.is_some() || self.unit_scale.is_some() || self.triangulate
+	}
+
+	fn transform(&self, scene: &mut Scene) {
+		if self.up.is_some() || self.handedness.is_some() {
+			let up = self.up.map(UpAxis::from).unwrap_or(scene.up);
+			let handedness = self.handedness.map(Handedness::from).unwrap_or(scene.handedness);
+			scene.convert_axes(up, handedness);
+		}
+
+		if let Some(s) = self.unit_scale {
+			scene.convert_units(s);
+		}
+
+		if self.triangulate {
+			scene.root.visit_mut(&mut |node| {
+				if let NodeData::Geometry(mesh) = &mut node.data {
+					mesh.triangulate();
+				}
+			});
+		}
+	}
+}
+
+pub fn run(reg: &Registry, args: &ConvertArgs) -> Result<(), CliError> {
+	let data = Input::open(&args.input)?;
+	let format = crate::detect(reg, &data, &args.input, args.from.as_deref())?;
+	let importer = reg.importer(format.name).ok_or(FormatError::NoImporter(format.name))?;
+
+	let mut asset = importer.import(&data)?;
+	match &mut asset {
+		Asset::Scene(scene) => args.transform(scene),
+		Asset::Texture(_) => if args.transforms_scene() {
+			eprintln!("warning: ignoring scene options for a texture");
+		},
+	}
+
+	// export to memory first so a failed export doesn't leave a truncated file behind
+	let mut out = vec![];
+	reg.export(&asset, &args.output, &mut out)?;
+	fs::write(&args.output, out)?;
+
+	println!("{} ({}) -> {}", args.input.display(), format.name, args.output.display());
+	Ok(())
+}
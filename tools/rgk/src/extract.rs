@@ -0,0 +1,100 @@
+use std::{
+	fs,
+	path::{
+		Path,
+		PathBuf
+	}
+};
+
+use clap::Args;
+
+use rgk_core::{
+	format::{
+		AssetKind,
+		FormatError,
+		Registry
+	},
+	input::Input
+};
+
+use crate::CliError;
+
+#[derive(Args)]
+pub struct ExtractArgs {
+	/// Archive to unpack
+	archive: PathBuf,
+
+	/// Directory to unpack into, defaulting to the archive's path without its extension
+	#[clap(short, long)]
+	output: Option<PathBuf>,
+
+	/// Archive format, for files that can't be detected
+	#[clap(long)]
+	from: Option<String>,
+
+	/// Lists the archive's files without unpacking them
+	#[clap(short, long)]
+	list: bool,
+}
+
+/// Joins an archive entry's name onto `dir`, rejecting names that would escape it
+fn entry_path(dir: &Path, name: &str) -> Result<PathBuf, CliError> {
+	let mut path = dir.to_path_buf();
+
+	for part in name.split('/') {
+		match part {
+			"" | "." => (),
+			".." => return Err(CliError::UnsafePath(name.to_string())),
+			_ if part.contains(['\\', ':']) => return Err(CliError::UnsafePath(name.to_string())),
+			_ => path.push(part),
+		}
+	}
+
+	Ok(path)
+}
+
+pub fn run(reg: &Registry, args: &ExtractArgs) -> Result<(), CliError> {
+	let data = Input::open(&args.archive)?;
+	let format = crate::detect(reg, &data, &args.archive, args.from.as_deref())?;
+	if format.kind != AssetKind::Archive {
+		return Err(CliError::Kind {
+			format: format.name,
+			expected: "archive",
+		});
+	}
+
+	let extractor = reg.extractor(format.name).ok_or(FormatError::NoExtractor(format.name))?;
+	let entries = extractor.extract(&data)?;
+	let dir = args.output.clone().unwrap_or_else(|| args.archive.with_extension(""));
+
+	for entry in entries.iter() {
+		println!("{:>10}  {}", entry.data.len(), entry.name);
+
+		if !args.list {
+			let path = entry_path(&dir, &entry.name)?;
+			if let Some(parent) = path.parent() {
+				fs::create_dir_all(parent)?;
+			}
+			fs::write(path, &entry.data)?;
+		}
+	}
+
+	println!("{} files", entries.len());
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_entry_path() {
+		let dir = Path::new("out");
+
+		assert_eq!(entry_path(dir, "data/map01.bin").unwrap(), Path::new("out/data/map01.bin"));
+		assert_eq!(entry_path(dir, "/./data//a.bin").unwrap(), Path::new("out/data/a.bin"));
+		assert!(entry_path(dir, "data/../../a.bin").is_err());
+		assert!(entry_path(dir, "C:/a.bin").is_err());
+		assert!(entry_path(dir, "data\\a.bin").is_err());
+	}
+}
@@ -0,0 +1,136 @@
+use std::path::PathBuf;
+
+use clap::Args;
+
+use rgk_core::{
+	format::{
+		Asset,
+		FormatError,
+		Registry
+	},
+	input::Input,
+	scene::{
+		validate::Severity,
+		NodeData,
+		Scene
+	},
+	texture::Texture
+};
+
+use crate::CliError;
+
+/// Bytes shown when a file's format is unknown
+const PREVIEW_SIZE: usize = 64;
+
+#[derive(Args)]
+pub struct InfoArgs {
+	/// File to describe
+	file: PathBuf,
+
+	/// File format, for files that can't be detected
+	#[clap(long)]
+	from: Option<String>,
+
+	/// Fails if validation finds errors
+	#[clap(long)]
+	check: bool,
+}
+
+fn hex_dump(data: &[u8]) {
+	for (i, row) in data.chunks(16).enumerate() {
+		let hex = row.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ");
+		let text: String = row.iter().map(|&b| if b.is_ascii_graphic() { b as char } else { '.' }).collect();
+		println!("  {:08x}  {:<47}  {}", i * 16, hex, text);
+	}
+}
+
+fn scene_info(scene: &Scene) {
+	println!("Up axis: {:?}, {:?} handed, 1 unit = {} m", scene.up, scene.handedness, scene.unit_scale);
+	println!("Nodes: {}", scene.root.iter_depth_first().count());
+
+	for (path, node) in scene.root.iter_depth_first() {
+		if let NodeData::Geometry(mesh) = &node.data {
+			let s = mesh.stats();
+			println!("Mesh {:?} at {:?}:", node.id, path);
+			println!("  {} vertices, {} faces ({} triangles, {} quads, {} polygons)", s.vertices, s.faces, s.triangles,
+				s.quads, s.ngons);
+			println!("  {} edges, {} boundary, {} non-manifold", s.edges, s.boundary_edges, s.non_manifold_edges);
+			println!("  {} degenerate faces, {} unused vertices", s.degenerate_faces, s.unused_vertices);
+
+			if let (Some(coverage), Some(overlap)) = (s.uv_coverage, s.uv_overlap) {
+				println!("  UV coverage {:.1}%, overlap {:.1}%", coverage * 100.0, overlap * 100.0);
+			}
+		}
+	}
+
+	println!("Materials: {}", scene.materials.len());
+	for (i, skel) in scene.skeletons.iter().enumerate() {
+		println!("Skeleton {}: {} bones", i, skel.bones.len());
+	}
+	for clip in scene.animations.iter() {
+		println!("Animation {:?}: {} channels", clip.name, clip.channels.len());
+	}
+}
+
+fn texture_info(tex: &Texture) {
+	println!("Size: {}x{}, {:?}", tex.width, tex.height, tex.format());
+	if !tex.palette.is_empty() {
+		println!("Palette: {} colors", tex.palette.len());
+	}
+}
+
+pub fn run(reg: &Registry, args: &InfoArgs) -> Result<(), CliError> {
+	let data = Input::open(&args.file)?;
+	println!("{}: {} bytes", args.file.display(), data.len());
+
+	let format = match crate::detect(reg, &data, &args.file, args.from.as_deref()) {
+		Ok(f) => f,
+		Err(FormatError::Unknown) => {
+			println!("Format: unknown");
+			hex_dump(&data[..data.len().min(PREVIEW_SIZE)]);
+			return Ok(());
+		},
+		Err(e) => return Err(e.into()),
+	};
+
+	println!("Format: {} ({}), {:?}", format.name, format.description, format.kind);
+	match format.magic.iter().find(|m| m.matches(&data)) {
+		Some(m) => println!("Magic: \"{}\" at {:#x}", m.bytes.escape_ascii(), m.offset),
+		None => println!("Magic: none, detected by extension"),
+	}
+
+	let importer = match reg.importer(format.name) {
+		Some(i) => i,
+		None => {
+			println!("No importer available, skipping validation");
+			return Ok(());
+		},
+	};
+
+	if let Some(headers) = importer.headers(&data)? {
+		println!("{}", headers);
+	}
+
+	match importer.import(&data)? {
+		Asset::Scene(scene) => {
+			scene_info(&scene);
+
+			let diagnostics = scene.validate();
+			let errors = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+			for d in diagnostics.iter() {
+				match &d.path {
+					Some(p) => println!("{:?} at node {:?}: {:?}", d.severity, p, d.issue),
+					None => println!("{:?}: {:?}", d.severity, d.issue),
+				}
+			}
+			println!("Validation: {} error(s), {} warning(s)", errors, diagnostics.len() - errors);
+
+			if args.check && errors > 0 {
+				return Err(CliError::Invalid(errors));
+			}
+		},
+		Asset::Texture(tex) => texture_info(&tex),
+	}
+
+	Ok(())
+}
@@ -0,0 +1,153 @@
+mod convert;
+mod extract;
+mod info;
+mod patch;
+
+use std::{
+	error::Error,
+	io,
+	path::Path,
+	process
+};
+
+use clap::{
+	Parser,
+	Subcommand
+};
+
+use thiserror::Error;
+
+use rgk_core::format::{
+	FormatError,
+	FormatInfo,
+	Registry
+};
+
+use rgk_archives_chronocross::drp::DRPExtractor;
+use rgk_archives_squareenix::lzss::LZSSExtractor;
+use rgk_models_wavefront::obj::{
+	export::ObjExporter,
+	import::ObjImporter
+};
+use rgk_patches_ips::IPSPatcher;
+use rgk_textures_bitmap::BitmapExporter;
+use rgk_textures_playstation::TIMImporter;
+
+/// Converts, inspects, unpacks and patches game assets
+#[derive(Parser)]
+#[clap(name = "rgk", version)]
+struct Cli {
+	#[clap(subcommand)]
+	command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+	/// Converts a model or texture to another format
+	Convert(convert::ConvertArgs),
+
+	/// Creates a patch turning one file into another
+	Diff(patch::DiffArgs),
+
+	/// Unpacks an archive
+	Extract(extract::ExtractArgs),
+
+	/// Lists known formats and what this tool can do with them
+	Formats,
+
+	/// Describes a file and checks it for problems
+	Info(info::InfoArgs),
+
+	/// Applies a patch to a file
+	Patch(patch::PatchArgs),
+}
+
+#[derive(Debug, Error)]
+pub enum CliError {
+	#[error(transparent)]
+	Format(#[from] FormatError),
+	#[error("I/O error")]
+	IO {
+		#[from]
+		source: io::Error,
+	},
+	#[error("{format} is not a {expected} format")]
+	Kind {
+		format: &'static str,
+		expected: &'static str,
+	},
+	#[error("Refusing to write outside the output directory: {0}")]
+	UnsafePath(String),
+	#[error("Validation found {0} error(s)")]
+	Invalid(usize),
+}
+
+/// Builds a registry with every handler this tool links
+fn registry() -> Registry {
+	let mut reg = Registry::new();
+
+	reg.add_importer(Box::new(ObjImporter));
+	reg.add_importer(Box::new(TIMImporter));
+	reg.add_exporter(Box::new(BitmapExporter));
+	reg.add_exporter(Box::new(ObjExporter));
+	reg.add_extractor(Box::new(DRPExtractor));
+	reg.add_extractor(Box::new(LZSSExtractor));
+	reg.add_patcher(Box::new(IPSPatcher));
+
+	reg
+}
+
+/// Finds the format of a file, by name if the user specified one
+pub fn detect<'a>(reg: &'a Registry, data: &[u8], path: &Path, name: Option<&str>) -> Result<&'a FormatInfo, FormatError> {
+	match name {
+		Some(n) => reg.format(n),
+		None => reg.detect(data, Some(path)),
+	}.ok_or(FormatError::Unknown)
+}
+
+fn formats(reg: &Registry) {
+	let mut formats = reg.formats().to_vec();
+	formats.sort_by_key(|f| f.name);
+
+	println!("{:<8} {:<8} {:<16} {:<22} DESCRIPTION", "NAME", "KIND", "EXTENSIONS", "SUPPORT");
+	for f in formats.iter() {
+		let support = [
+			("import", reg.importer(f.name).is_some()),
+			("export", reg.exporter(f.name).is_some()),
+			("extract", reg.extractor(f.name).is_some()),
+			("patch", reg.patcher(f.name).is_some()),
+		].iter().filter(|(_, s)| *s).map(|(n, _)| *n).collect::<Vec<_>>().join(",");
+
+		println!("{:<8} {:<8} {:<16} {:<22} {}", f.name, format!("{:?}", f.kind), f.extensions.join(","),
+			if support.is_empty() { "-" } else { &support }, f.description);
+	}
+}
+
+fn main() {
+	let cli = Cli::parse();
+	let reg = registry();
+
+	let result = match &cli.command {
+		Command::Convert(args) => convert::run(&reg, args),
+		Command::Diff(args) => patch::diff(&reg, args),
+		Command::Extract(args) => extract::run(&reg, args),
+		Command::Formats => {
+			formats(&reg);
+			Ok(())
+		},
+		Command::Info(args) => info::run(&reg, args),
+		Command::Patch(args) => patch::apply(&reg, args),
+	};
+
+	if let Err(e) = result {
+		eprintln!("rgk: {}", e);
+
+		let mut source = e.source();
+		while let Some(s) = source {
+			eprintln!("  caused by: {}", s);
+			source = s.source();
+		}
+
+		process::exit(1);
+	}
+}
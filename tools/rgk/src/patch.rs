@@ -0,0 +1,76 @@
+use std::{
+	fs,
+	path::PathBuf
+};
+
+use clap::Args;
+
+use rgk_core::{
+	format::{
+		AssetKind,
+		FormatError,
+		Registry
+	},
+	input::Input
+};
+
+use crate::CliError;
+
+#[derive(Args)]
+pub struct PatchArgs {
+	/// Patch to apply
+	patch: PathBuf,
+
+	/// File to patch, such as a ROM
+	source: PathBuf,
+
+	/// Patched file to write
+	output: PathBuf,
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+	/// Original file
+	source: PathBuf,
+
+	/// Modified file
+	target: PathBuf,
+
+	/// Patch to write, its extension choosing the format
+	patch: PathBuf,
+
+	/// Patch format, overriding the extension
+	#[clap(long)]
+	format: Option<String>,
+}
+
+pub fn apply(reg: &Registry, args: &PatchArgs) -> Result<(), CliError> {
+	let patch = Input::open(&args.patch)?;
+	let format = crate::detect(reg, &patch, &args.patch, None)?;
+	if format.kind != AssetKind::Patch {
+		return Err(CliError::Kind {
+			format: format.name,
+			expected: "patch",
+		});
+	}
+
+	let patcher = reg.patcher(format.name).ok_or_else(|| FormatError::NoPatcher(format.name.to_string()))?;
+	let patched = patcher.apply(&patch, &Input::open(&args.source)?)?;
+	fs::write(&args.output, &patched)?;
+
+	println!("{} + {} ({}) -> {}", args.source.display(), args.patch.display(), format.name, args.output.display());
+	Ok(())
+}
+
+pub fn diff(reg: &Registry, args: &DiffArgs) -> Result<(), CliError> {
+	let patcher = match &args.format {
+		Some(name) => reg.patcher(name).ok_or_else(|| FormatError::NoPatcher(name.clone()))?,
+		None => reg.patcher_for(&args.patch).ok_or_else(|| FormatError::NoPatcher(args.patch.display().to_string()))?,
+	};
+
+	let patch = patcher.diff(&Input::open(&args.source)?, &Input::open(&args.target)?)?;
+	fs::write(&args.patch, &patch)?;
+
+	println!("{} ({}, {} bytes)", args.patch.display(), patcher.format().name, patch.len());
+	Ok(())
+}
@@ -0,0 +1,137 @@
+use std::{
+	env,
+	fs,
+	path::{
+		Path,
+		PathBuf
+	},
+	process::{
+		self,
+		Command
+	}
+};
+
+const QUAD: &str = "o quad\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1 4//1\n";
+
+/// Creates an empty directory for one test's files
+fn scratch(name: &str) -> PathBuf {
+	let dir = env::temp_dir().join(format!("rgk-{}-{}", name, process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+/// Runs the tool in `dir`, returning its output and whether it succeeded
+fn rgk(dir: &Path, args: &[&str]) -> (String, bool) {
+	let out = Command::new(env!("CARGO_BIN_EXE_rgk")).current_dir(dir).args(args).output().unwrap();
+	(String::from_utf8(out.stdout).unwrap(), out.status.success())
+}
+
+#[test]
+fn test_convert() {
+	let dir = scratch("convert");
+	fs::write(dir.join("quad.obj"), QUAD).unwrap();
+
+	let (out, ok) = rgk(&dir, &["convert", "--triangulate", "quad.obj", "tri.obj"]);
+	assert!(ok);
+	assert_eq!(out, "quad.obj (OBJ) -> tri.obj\n");
+
+	let obj = fs::read_to_string(dir.join("tri.obj")).unwrap();
+	let faces = obj.lines().filter(|l| l.starts_with("f ")).collect::<Vec<_>>();
+	assert_eq!(faces, ["f 4//4 1//1 2//2", "f 2//2 3//3 4//4"]);
+
+	// 2x2 16 bit TIM of red, green, blue and white
+	let mut tim = b"\x10\x00\x00\x00\x02\x00\x00\x00\x14\x00\x00\x00\x00\x00\x00\x00\x02\x00\x02\x00".to_vec();
+	tim.extend_from_slice(b"\x1f\x00\xe0\x03\x00\x7c\xff\x7f");
+	fs::write(dir.join("a.tim"), tim).unwrap();
+
+	let (out, ok) = rgk(&dir, &["convert", "a.tim", "a.bmp"]);
+	assert!(ok);
+	assert_eq!(out, "a.tim (TIM) -> a.bmp\n");
+	let bmp = fs::read(dir.join("a.bmp")).unwrap();
+	assert!(bmp.starts_with(b"BM"));
+	assert_eq!((&bmp[18..22], &bmp[22..26]), (&2i32.to_le_bytes()[..], &2i32.to_le_bytes()[..]));
+	// bottom row first, as BGR padded to 4 bytes
+	assert_eq!(&bmp[54..], b"\xff\x00\x00\xff\xff\xff\x00\x00\x00\x00\xff\x00\xff\x00\x00\x00");
+
+	assert!(!rgk(&dir, &["convert", "quad.obj", "quad.bmp"]).1);
+	assert!(!rgk(&dir, &["convert", "missing.obj", "out.obj"]).1);
+}
+
+#[test]
+fn test_diff_and_patch() {
+	let dir = scratch("patch");
+	let source = (0..32).collect::<Vec<u8>>();
+	let mut target = source.clone();
+	target[16..24].copy_from_slice(b"patched!");
+	fs::write(dir.join("source.bin"), &source).unwrap();
+	fs::write(dir.join("target.bin"), &target).unwrap();
+
+	let (out, ok) = rgk(&dir, &["diff", "source.bin", "target.bin", "fix.ips"]);
+	assert!(ok);
+	assert!(out.starts_with("fix.ips (IPS, "));
+	assert!(fs::read(dir.join("fix.ips")).unwrap().starts_with(b"PATCH"));
+
+	assert!(rgk(&dir, &["patch", "fix.ips", "source.bin", "out.bin"]).1);
+	assert_eq!(fs::read(dir.join("out.bin")).unwrap(), target);
+
+	assert!(!rgk(&dir, &["patch", "source.bin", "target.bin", "out.bin"]).1);
+	assert!(!rgk(&dir, &["diff", "source.bin", "target.bin", "fix.txt"]).1);
+}
+
+#[test]
+fn test_extract() {
+	let dir = scratch("extract");
+	let mut drp = b"drp\x00\x00\x00\x00\x00\x02\x00\x00\x00".to_vec();
+	drp.extend_from_slice(b"\x00\x00\x00\x00mesh\x02\x00\x00\x00\x03\x00\x00\x00abc");
+	drp.extend_from_slice(b"\x00\x00\x00\x00tex\x00\x01\x00\x00\x00\x02\x00\x00\x00hi");
+	fs::write(dir.join("field.drp"), drp).unwrap();
+
+	let (out, ok) = rgk(&dir, &["extract", "--list", "field.drp"]);
+	assert!(ok);
+	assert_eq!(out, "         3  000_mesh.bin\n         2  001_tex.drp\n2 files\n");
+	assert!(!dir.join("field").exists());
+
+	assert!(rgk(&dir, &["extract", "field.drp"]).1);
+	assert_eq!(fs::read(dir.join("field/000_mesh.bin")).unwrap(), b"abc");
+	assert_eq!(fs::read(dir.join("field/001_tex.drp")).unwrap(), b"hi");
+
+	fs::write(dir.join("quad.obj"), QUAD).unwrap();
+	assert!(!rgk(&dir, &["extract", "quad.obj"]).1);
+}
+
+#[test]
+fn test_formats() {
+	let (out, ok) = rgk(&env::temp_dir(), &["formats"]);
+	assert!(ok);
+
+	let support = |name: &str| out.lines()
+		.find(|l| l.split_whitespace().next() == Some(name))
+		.and_then(|l| l.split_whitespace().nth(3))
+		.map(str::to_string);
+	assert_eq!(support("BMP").as_deref(), Some("export"));
+	assert_eq!(support("DRP").as_deref(), Some("extract"));
+	assert_eq!(support("IPS").as_deref(), Some("patch"));
+	assert_eq!(support("LZSS").as_deref(), Some("extract"));
+	assert_eq!(support("OBJ").as_deref(), Some("import,export"));
+	assert_eq!(support("TIM").as_deref(), Some("import"));
+	assert_eq!(support("FBX").as_deref(), Some("-"));
+}
+
+#[test]
+fn test_info() {
+	let dir = scratch("info");
+	fs::write(dir.join("quad.obj"), QUAD).unwrap();
+
+	let (out, ok) = rgk(&dir, &["info", "--check", "quad.obj"]);
+	assert!(ok);
+	assert!(out.contains("Format: OBJ (Wavefront OBJ), Scene"));
+	assert!(out.contains("4 vertices, 1 faces (0 triangles, 1 quads, 0 polygons)"));
+	assert!(out.contains("Validation: 0 error(s), 0 warning(s)"));
+
+	fs::write(dir.join("unknown.dat"), b"\x00\x01\x02\x03").unwrap();
+	let (out, ok) = rgk(&dir, &["info", "unknown.dat"]);
+	assert!(ok);
+	assert!(out.contains("Format: unknown"));
+	assert!(out.contains("00 01 02 03"));
+}